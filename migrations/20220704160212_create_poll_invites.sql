@@ -0,0 +1,14 @@
+-- Invite-only polls can only be joined through a token minted by their creator
+ALTER TABLE polls ADD COLUMN invite_only BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE poll_invites (
+    invite_id  UUID NOT NULL,
+    poll_id    UUID NOT NULL REFERENCES polls(poll_id),
+    token      TEXT NOT NULL UNIQUE,
+    max_uses   INTEGER NOT NULL,
+    uses       INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    PRIMARY KEY (invite_id)
+);
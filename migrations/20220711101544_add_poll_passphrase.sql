@@ -0,0 +1,2 @@
+-- Argon2 PHC string of the passphrase needed to view the poll, if any
+ALTER TABLE polls ADD COLUMN passphrase_hash TEXT;
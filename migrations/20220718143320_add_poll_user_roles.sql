@@ -0,0 +1,12 @@
+-- Every participant of a poll has a role, the creator starts out as its owner
+ALTER TABLE poll_users ADD PRIMARY KEY (poll_id, user_id);
+ALTER TABLE poll_users ADD COLUMN role TEXT NOT NULL DEFAULT 'participant'
+    CHECK (role IN ('owner', 'co_organizer', 'participant', 'observer'));
+
+UPDATE poll_users
+SET role = 'owner'
+FROM polls
+WHERE polls.poll_id = poll_users.poll_id AND polls.creator_id = poll_users.user_id;
+
+-- There can only be one owner per poll
+CREATE UNIQUE INDEX poll_users_single_owner ON poll_users (poll_id) WHERE role = 'owner';
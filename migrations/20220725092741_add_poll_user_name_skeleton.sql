@@ -0,0 +1,24 @@
+-- Confusable skeleton (UTS #39) of the username, two users of the same
+-- poll can't have names that look alike.
+ALTER TABLE poll_users ADD COLUMN name_skeleton TEXT;
+
+-- Names used to be restricted to [a-zA-Z0-9_], for those the skeleton
+-- only maps I, 0, 1 and m and ignores case.
+UPDATE poll_users
+SET name_skeleton = replace(lower(translate(username, 'I01', 'lOl')), 'm', 'rn');
+
+-- Lookalike names that already share a poll keep working, only the first
+-- one claims the skeleton.
+UPDATE poll_users
+SET name_skeleton = duplicates.name_skeleton || ':' || duplicates.user_id
+FROM (
+    SELECT poll_id, user_id, name_skeleton,
+           row_number() OVER (PARTITION BY poll_id, name_skeleton ORDER BY username) AS position
+    FROM poll_users
+) AS duplicates
+WHERE poll_users.poll_id = duplicates.poll_id
+  AND poll_users.user_id = duplicates.user_id
+  AND duplicates.position > 1;
+
+ALTER TABLE poll_users ALTER COLUMN name_skeleton SET NOT NULL;
+ALTER TABLE poll_users ADD CONSTRAINT poll_users_name_skeleton_key UNIQUE (poll_id, name_skeleton);
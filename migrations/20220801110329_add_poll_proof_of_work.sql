@@ -0,0 +1,3 @@
+-- Polls can make visitors solve a proof-of-work challenge before joining,
+-- even when it isn't required everywhere
+ALTER TABLE polls ADD COLUMN require_proof_of_work BOOLEAN NOT NULL DEFAULT false;
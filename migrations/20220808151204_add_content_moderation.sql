@@ -0,0 +1,13 @@
+-- Polls can replace the site's content filters with their own
+ALTER TABLE polls ADD COLUMN content_filter JSONB NULL;
+
+-- Content caught by a filter set to hold waits for an organizer to approve it
+ALTER TABLE suggestions
+    ADD COLUMN moderation_status TEXT NOT NULL DEFAULT 'visible'
+        CHECK (moderation_status IN ('visible', 'held')),
+    ADD COLUMN moderation_reason TEXT NULL;
+
+ALTER TABLE poll_users
+    ADD COLUMN moderation_status TEXT NOT NULL DEFAULT 'visible'
+        CHECK (moderation_status IN ('visible', 'held')),
+    ADD COLUMN moderation_reason TEXT NULL;
@@ -0,0 +1,20 @@
+-- Members flag suggestions and names, every report targets exactly one of them
+CREATE TABLE reports (
+    report_id        UUID NOT NULL,
+    poll_id          UUID NOT NULL REFERENCES polls(poll_id),
+    reporter_id      UUID NOT NULL REFERENCES users(user_id),
+    suggestion_id    UUID NULL REFERENCES suggestions(suggestion_id),
+    reported_user_id UUID NULL REFERENCES users(user_id),
+    reason           TEXT NOT NULL
+        CHECK (reason IN ('spam', 'offensive', 'harassment', 'impersonation', 'other')),
+    created_at       TIMESTAMPTZ NOT NULL,
+    resolved_at      TIMESTAMPTZ NULL,
+    PRIMARY KEY (report_id),
+    CHECK ((suggestion_id IS NULL) <> (reported_user_id IS NULL))
+);
+
+-- A member can only have one pending report per item
+CREATE UNIQUE INDEX reports_pending_suggestion ON reports (reporter_id, suggestion_id)
+    WHERE suggestion_id IS NOT NULL AND resolved_at IS NULL;
+CREATE UNIQUE INDEX reports_pending_user ON reports (poll_id, reporter_id, reported_user_id)
+    WHERE reported_user_id IS NOT NULL AND resolved_at IS NULL;
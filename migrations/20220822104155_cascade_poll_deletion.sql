@@ -0,0 +1,28 @@
+-- Deleting a poll takes everything that belongs to it along
+ALTER TABLE poll_users
+    DROP CONSTRAINT poll_users_poll_id_fkey,
+    ADD CONSTRAINT poll_users_poll_id_fkey
+        FOREIGN KEY (poll_id) REFERENCES polls(poll_id) ON DELETE CASCADE;
+
+ALTER TABLE suggestions
+    DROP CONSTRAINT suggestions_poll_id_fkey,
+    ADD CONSTRAINT suggestions_poll_id_fkey
+        FOREIGN KEY (poll_id) REFERENCES polls(poll_id) ON DELETE CASCADE;
+
+ALTER TABLE votes
+    DROP CONSTRAINT votes_suggestion_id_fkey,
+    ADD CONSTRAINT votes_suggestion_id_fkey
+        FOREIGN KEY (suggestion_id) REFERENCES suggestions(suggestion_id) ON DELETE CASCADE;
+
+ALTER TABLE poll_invites
+    DROP CONSTRAINT poll_invites_poll_id_fkey,
+    ADD CONSTRAINT poll_invites_poll_id_fkey
+        FOREIGN KEY (poll_id) REFERENCES polls(poll_id) ON DELETE CASCADE;
+
+ALTER TABLE reports
+    DROP CONSTRAINT reports_poll_id_fkey,
+    ADD CONSTRAINT reports_poll_id_fkey
+        FOREIGN KEY (poll_id) REFERENCES polls(poll_id) ON DELETE CASCADE,
+    DROP CONSTRAINT reports_suggestion_id_fkey,
+    ADD CONSTRAINT reports_suggestion_id_fkey
+        FOREIGN KEY (suggestion_id) REFERENCES suggestions(suggestion_id) ON DELETE CASCADE;
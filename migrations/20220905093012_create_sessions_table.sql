@@ -0,0 +1,8 @@
+-- Sessions of the Postgres session store, expired ones are deleted periodically
+CREATE TABLE sessions(
+    session_key TEXT NOT NULL PRIMARY KEY,
+    state JSONB NOT NULL,
+    expires_at timestamptz NOT NULL
+);
+
+CREATE INDEX sessions_expires_at_idx ON sessions (expires_at);
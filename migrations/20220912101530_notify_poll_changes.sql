@@ -0,0 +1,11 @@
+-- Instances caching polls forget the ones changed or deleted by any of them
+CREATE FUNCTION notify_poll_changed() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('poll_changed', OLD.poll_id::text);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER poll_changed
+AFTER UPDATE OR DELETE ON polls
+FOR EACH ROW EXECUTE FUNCTION notify_poll_changed();
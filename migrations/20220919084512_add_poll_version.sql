@@ -0,0 +1,29 @@
+-- Bumped whenever the members or suggestions of the poll change, so the poll
+-- page can tell whether a copy cached by the browser is still current
+ALTER TABLE polls ADD COLUMN version BIGINT NOT NULL DEFAULT 0;
+
+CREATE FUNCTION bump_poll_version() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        UPDATE polls SET version = version + 1 WHERE poll_id = OLD.poll_id;
+    ELSE
+        UPDATE polls SET version = version + 1 WHERE poll_id = NEW.poll_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER poll_users_changed
+AFTER INSERT OR UPDATE OR DELETE ON poll_users
+FOR EACH ROW EXECUTE FUNCTION bump_poll_version();
+
+CREATE TRIGGER suggestions_changed
+AFTER INSERT OR UPDATE OR DELETE ON suggestions
+FOR EACH ROW EXECUTE FUNCTION bump_poll_version();
+
+-- A new version doesn't change what the poll caches hold
+DROP TRIGGER poll_changed ON polls;
+CREATE TRIGGER poll_changed
+AFTER UPDATE OF creator_id, prompt, invite_only, passphrase_hash, require_proof_of_work,
+    content_filter OR DELETE ON polls
+FOR EACH ROW EXECUTE FUNCTION notify_poll_changed();
@@ -0,0 +1,9 @@
+-- Who can export the results of the poll
+ALTER TABLE polls ADD COLUMN export_access TEXT NOT NULL DEFAULT 'participants'
+    CHECK (export_access IN ('participants', 'owner'));
+
+DROP TRIGGER poll_changed ON polls;
+CREATE TRIGGER poll_changed
+AFTER UPDATE OF creator_id, prompt, invite_only, passphrase_hash, require_proof_of_work,
+    content_filter, export_access OR DELETE ON polls
+FOR EACH ROW EXECUTE FUNCTION notify_poll_changed();
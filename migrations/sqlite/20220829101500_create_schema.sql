@@ -0,0 +1,82 @@
+-- The same schema as the Postgres migrations up to cascade_poll_deletion.
+-- Ids are stored as 16 byte blobs, times as UTC text and content filters as
+-- JSON text.
+CREATE TABLE users (
+    user_id    BLOB NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE polls (
+    poll_id               BLOB NOT NULL PRIMARY KEY,
+    creator_id            BLOB NOT NULL REFERENCES users(user_id),
+    prompt                TEXT NOT NULL CHECK (length(prompt) <= 64),
+    created_at            TEXT NOT NULL,
+    invite_only           BOOLEAN NOT NULL DEFAULT false,
+    passphrase_hash       TEXT NULL,
+    require_proof_of_work BOOLEAN NOT NULL DEFAULT false,
+    content_filter        TEXT NULL
+);
+
+CREATE TABLE poll_users (
+    poll_id           BLOB NOT NULL REFERENCES polls(poll_id) ON DELETE CASCADE,
+    user_id           BLOB NOT NULL REFERENCES users(user_id),
+    username          TEXT NOT NULL,
+    role              TEXT NOT NULL DEFAULT 'participant'
+        CHECK (role IN ('owner', 'co_organizer', 'participant', 'observer')),
+    name_skeleton     TEXT NOT NULL,
+    moderation_status TEXT NOT NULL DEFAULT 'visible'
+        CHECK (moderation_status IN ('visible', 'held')),
+    moderation_reason TEXT NULL,
+    PRIMARY KEY (poll_id, user_id),
+    UNIQUE (poll_id, username),
+    UNIQUE (poll_id, name_skeleton)
+);
+
+-- There can only be one owner per poll
+CREATE UNIQUE INDEX poll_users_single_owner ON poll_users (poll_id) WHERE role = 'owner';
+
+CREATE TABLE suggestions (
+    suggestion_id     BLOB NOT NULL PRIMARY KEY,
+    poll_id           BLOB NOT NULL REFERENCES polls(poll_id) ON DELETE CASCADE,
+    creator_id        BLOB NOT NULL REFERENCES users(user_id),
+    suggestion        TEXT NOT NULL,
+    created_at        TEXT NOT NULL,
+    moderation_status TEXT NOT NULL DEFAULT 'visible'
+        CHECK (moderation_status IN ('visible', 'held')),
+    moderation_reason TEXT NULL
+);
+
+CREATE TABLE votes (
+    user_id       BLOB NOT NULL PRIMARY KEY REFERENCES users(user_id),
+    suggestion_id BLOB NOT NULL REFERENCES suggestions(suggestion_id) ON DELETE CASCADE
+);
+
+CREATE TABLE poll_invites (
+    invite_id  BLOB NOT NULL PRIMARY KEY,
+    poll_id    BLOB NOT NULL REFERENCES polls(poll_id) ON DELETE CASCADE,
+    token      TEXT NOT NULL UNIQUE,
+    max_uses   INTEGER NOT NULL,
+    uses       INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    revoked_at TEXT NULL
+);
+
+CREATE TABLE reports (
+    report_id        BLOB NOT NULL PRIMARY KEY,
+    poll_id          BLOB NOT NULL REFERENCES polls(poll_id) ON DELETE CASCADE,
+    reporter_id      BLOB NOT NULL REFERENCES users(user_id),
+    suggestion_id    BLOB NULL REFERENCES suggestions(suggestion_id) ON DELETE CASCADE,
+    reported_user_id BLOB NULL REFERENCES users(user_id),
+    reason           TEXT NOT NULL
+        CHECK (reason IN ('spam', 'offensive', 'harassment', 'impersonation', 'other')),
+    created_at       TEXT NOT NULL,
+    resolved_at      TEXT NULL,
+    CHECK ((suggestion_id IS NULL) <> (reported_user_id IS NULL))
+);
+
+-- A member can only have one pending report per item
+CREATE UNIQUE INDEX reports_pending_suggestion ON reports (reporter_id, suggestion_id)
+    WHERE suggestion_id IS NOT NULL AND resolved_at IS NULL;
+CREATE UNIQUE INDEX reports_pending_user ON reports (poll_id, reporter_id, reported_user_id)
+    WHERE reported_user_id IS NOT NULL AND resolved_at IS NULL;
@@ -0,0 +1,33 @@
+-- Bumped whenever the members or suggestions of the poll change, so the poll
+-- page can tell whether a copy cached by the browser is still current
+ALTER TABLE polls ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+
+CREATE TRIGGER poll_users_inserted AFTER INSERT ON poll_users
+BEGIN
+    UPDATE polls SET version = version + 1 WHERE poll_id = NEW.poll_id;
+END;
+
+CREATE TRIGGER poll_users_updated AFTER UPDATE ON poll_users
+BEGIN
+    UPDATE polls SET version = version + 1 WHERE poll_id = NEW.poll_id;
+END;
+
+CREATE TRIGGER poll_users_deleted AFTER DELETE ON poll_users
+BEGIN
+    UPDATE polls SET version = version + 1 WHERE poll_id = OLD.poll_id;
+END;
+
+CREATE TRIGGER suggestions_inserted AFTER INSERT ON suggestions
+BEGIN
+    UPDATE polls SET version = version + 1 WHERE poll_id = NEW.poll_id;
+END;
+
+CREATE TRIGGER suggestions_updated AFTER UPDATE ON suggestions
+BEGIN
+    UPDATE polls SET version = version + 1 WHERE poll_id = NEW.poll_id;
+END;
+
+CREATE TRIGGER suggestions_deleted AFTER DELETE ON suggestions
+BEGIN
+    UPDATE polls SET version = version + 1 WHERE poll_id = OLD.poll_id;
+END;
@@ -0,0 +1,3 @@
+-- Who can export the results of the poll
+ALTER TABLE polls ADD COLUMN export_access TEXT NOT NULL DEFAULT 'participants'
+    CHECK (export_access IN ('participants', 'owner'));
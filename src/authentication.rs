@@ -0,0 +1,72 @@
+use anyhow::Context;
+use argon2::password_hash::SaltString;
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::telemetry::spawn_blocking_with_tracing;
+
+#[tracing::instrument(name = "Compute passphrase hash", skip_all)]
+pub async fn compute_passphrase_hash(
+    passphrase: Secret<String>,
+) -> Result<Secret<String>, anyhow::Error> {
+    spawn_blocking_with_tracing(move || {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let hash = argon2_hasher()
+            .hash_password(passphrase.expose_secret().as_bytes(), &salt)
+            .context("failed to hash passphrase")?
+            .to_string();
+        Ok(Secret::new(hash))
+    })
+    .await
+    .context("failed to spawn blocking task")?
+}
+
+/// Check a candidate passphrase against a PHC string, returns `false` on mismatch.
+#[tracing::instrument(name = "Verify passphrase hash", skip_all)]
+pub async fn verify_passphrase_hash(
+    expected_hash: Secret<String>,
+    candidate: Secret<String>,
+) -> Result<bool, anyhow::Error> {
+    spawn_blocking_with_tracing(move || {
+        let expected_hash = PasswordHash::new(expected_hash.expose_secret())
+            .context("failed to parse hash in PHC string format")?;
+        Ok(argon2_hasher()
+            .verify_password(candidate.expose_secret().as_bytes(), &expected_hash)
+            .is_ok())
+    })
+    .await
+    .context("failed to spawn blocking task")?
+}
+
+fn argon2_hasher() -> Argon2<'static> {
+    Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::new(15000, 2, 1, None).expect("invalid argon2 parameters"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::{compute_passphrase_hash, verify_passphrase_hash};
+
+    #[tokio::test]
+    async fn hashed_passphrase_verifies_only_the_original() {
+        let hash = compute_passphrase_hash(Secret::new("correct horse".into()))
+            .await
+            .unwrap();
+
+        assert!(
+            verify_passphrase_hash(hash.clone(), Secret::new("correct horse".into()))
+                .await
+                .unwrap()
+        );
+        assert!(
+            !verify_passphrase_hash(hash, Secret::new("battery staple".into()))
+                .await
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::net::SocketAddr;
+use std::path::Path;
+
+use anyhow::Context;
+use argon2::PasswordHash;
+use chrono::{Duration, Utc};
+use secrecy::ExposeSecret;
+use sqlx::{Connection, Executor, PgConnection};
+
+use crate::{
+    configuration::{
+        DatabaseBackend, DatabaseSettings, SameSiteSetting, SessionStoreBackend, Settings,
+    },
+    storage::{self, Storage, StorageError},
+    tls,
+};
+
+#[derive(clap::Parser)]
+#[clap(name = "apoll", version, about = "Run and maintain an apoll server")]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug, PartialEq)]
+pub enum Command {
+    /// Run the web server, the default when no subcommand is given
+    Serve,
+    /// Create the database if it's missing and apply the pending migrations
+    Migrate {
+        /// Only list the migrations that would be applied
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Load the configuration and report anything that would stop the server
+    CheckConfig,
+    /// Delete invites that expired or were revoked
+    PurgeExpired {
+        /// Keep invites that stopped working less than this many days ago
+        #[clap(long, default_value_t = 7)]
+        grace_days: u32,
+    },
+}
+
+/// A migration embedded in the binary.
+#[derive(Debug, PartialEq)]
+pub struct PendingMigration {
+    pub version: i64,
+    pub description: String,
+}
+
+/// Applies the embedded migrations that the database doesn't have yet,
+/// creating the database first if needed. With `dry_run` nothing is changed.
+pub async fn migrate(
+    settings: &DatabaseSettings,
+    dry_run: bool,
+) -> Result<Vec<PendingMigration>, anyhow::Error> {
+    let storage = storage::connect(settings);
+    let exists = match settings.backend {
+        DatabaseBackend::Postgres => create_postgres_database(settings, dry_run).await?,
+        // SQLite creates the file on the first connection
+        DatabaseBackend::Sqlite => !dry_run || Path::new(&settings.sqlite_path).exists(),
+        // Nothing to create, nor to migrate
+        DatabaseBackend::Memory => true,
+    };
+    if !exists {
+        return Ok(storage
+            .migrator()
+            .iter()
+            .map(PendingMigration::from)
+            .collect());
+    }
+
+    let pending = pending_migrations(storage.as_ref()).await?;
+    if !dry_run {
+        storage
+            .migrate()
+            .await
+            .context("failed to migrate the database")?;
+    }
+
+    Ok(pending)
+}
+
+/// Whether the Postgres database exists, it's created unless `dry_run` is set.
+async fn create_postgres_database(
+    settings: &DatabaseSettings,
+    dry_run: bool,
+) -> Result<bool, anyhow::Error> {
+    let mut connection = PgConnection::connect_with(&settings.without_db())
+        .await
+        .context("failed to connect to Postgres")?;
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)")
+            .bind(&settings.database_name)
+            .fetch_one(&mut connection)
+            .await
+            .context("failed to look up the database")?;
+
+    if !exists && !dry_run {
+        connection
+            .execute(&*format!(
+                r#"CREATE DATABASE "{}";"#,
+                settings.database_name.replace('"', "\"\"")
+            ))
+            .await
+            .context("failed to create the database")?;
+        return Ok(true);
+    }
+
+    Ok(exists)
+}
+
+/// The embedded migrations that haven't been applied successfully.
+pub async fn pending_migrations(
+    storage: &dyn Storage,
+) -> Result<Vec<PendingMigration>, anyhow::Error> {
+    let applied = storage.applied_migrations().await?;
+
+    Ok(storage
+        .migrator()
+        .iter()
+        .filter(|m| !applied.contains(&m.version))
+        .map(PendingMigration::from)
+        .collect())
+}
+
+impl From<&sqlx::migrate::Migration> for PendingMigration {
+    fn from(migration: &sqlx::migrate::Migration) -> Self {
+        Self {
+            version: migration.version,
+            description: migration.description.to_string(),
+        }
+    }
+}
+
+/// Everything wrong with the configuration that deserializing it didn't catch.
+pub fn check_config(settings: &Settings) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if settings.application.hmac_secrets.is_empty() {
+        problems.push("application.hmac_secrets should have at least one secret".into());
+    }
+    for (i, hmac_secret) in settings.application.hmac_secrets.iter().enumerate() {
+        if hmac_secret.secret.expose_secret().len() < 64 {
+            problems.push(format!(
+                "application.hmac_secrets[{i}] should be at least 64 characters long"
+            ));
+        }
+    }
+    if let Some(tls_settings) = &settings.application.tls {
+        if let Err(e) = tls::load_certified_key(tls_settings) {
+            problems.push(format!("application.tls doesn't load: {e:#}"));
+        }
+        if tls_settings.reload_interval_seconds == 0 {
+            problems.push("application.tls.reload_interval_seconds should be at least 1".into());
+        }
+        if tls_settings.redirect_http_port == Some(settings.application.port) {
+            problems.push(
+                "application.tls.redirect_http_port should differ from application.port".into(),
+            );
+        }
+    }
+    for account in &settings.admin.accounts {
+        if let Err(e) = PasswordHash::new(account.password_hash.expose_secret()) {
+            problems.push(format!(
+                "admin account `{}` doesn't have a valid PHC password hash: {e}",
+                account.username
+            ));
+        }
+    }
+    if let Some(address) = &settings.metrics.address {
+        if address.parse::<SocketAddr>().is_err() {
+            problems.push(format!(
+                "metrics.address `{address}` isn't a socket address"
+            ));
+        }
+    }
+    if let Some(otlp) = &settings.telemetry.otlp {
+        if reqwest::Url::parse(&otlp.endpoint).is_err() {
+            problems.push(format!(
+                "telemetry.otlp.endpoint `{}` isn't a URL",
+                otlp.endpoint
+            ));
+        }
+        if !(0.0..=1.0).contains(&otlp.sample_ratio) {
+            problems.push("telemetry.otlp.sample_ratio should be between 0 and 1".into());
+        }
+    }
+    match settings.session.store {
+        SessionStoreBackend::Redis => {
+            if redis::parse_redis_url(settings.session.redis_uri.expose_secret()).is_none() {
+                problems.push("session.redis_uri isn't a Redis URL".into());
+            }
+        }
+        SessionStoreBackend::Postgres => {
+            if settings.database.backend != DatabaseBackend::Postgres {
+                problems
+                    .push("the postgres session store needs the postgres database backend".into());
+            }
+        }
+        SessionStoreBackend::Cookie | SessionStoreBackend::Memory => {}
+    }
+    if settings.session.ttl_minutes == 0 {
+        problems.push("session.ttl_minutes should be at least 1".into());
+    }
+    if settings.session.cleanup_interval_seconds == 0 {
+        problems.push("session.cleanup_interval_seconds should be at least 1".into());
+    }
+    if settings.session.same_site == SameSiteSetting::None && !settings.session.secure {
+        problems.push("browsers reject cookies with `SameSite=None` unless they're secure".into());
+    }
+
+    problems
+}
+
+/// Deletes the invites that expired or were revoked more than `grace`
+/// ago, returning how many were removed.
+pub async fn purge_expired_invites(
+    storage: &dyn Storage,
+    grace: Duration,
+) -> Result<u64, StorageError> {
+    storage.purge_expired_invites(Utc::now() - grace).await
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::{check_config, Cli, Command};
+    use crate::configuration::{DatabaseBackend, SessionStoreBackend, Settings, TlsSettings};
+
+    #[test]
+    fn serve_is_the_default_command() {
+        let cli = Cli::try_parse_from(["apoll"]).unwrap();
+        assert_eq!(cli.command, None);
+
+        let cli = Cli::try_parse_from(["apoll", "migrate", "--dry-run"]).unwrap();
+        assert_eq!(cli.command, Some(Command::Migrate { dry_run: true }));
+
+        let cli = Cli::try_parse_from(["apoll", "purge-expired"]).unwrap();
+        assert_eq!(cli.command, Some(Command::PurgeExpired { grace_days: 7 }));
+    }
+
+    #[test]
+    fn check_config_reports_invalid_values() {
+        let mut settings = Settings::new().unwrap();
+        assert!(check_config(&settings).is_empty());
+
+        settings.metrics.address = Some("not an address".into());
+        settings.session.redis_uri = secrecy::Secret::new("http://localhost".into());
+        let problems = check_config(&settings);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+    }
+
+    #[test]
+    fn tls_files_have_to_load() {
+        let mut settings = Settings::new().unwrap();
+        settings.application.tls = Some(TlsSettings {
+            certificate_path: "tests/fixtures/tls/missing.pem".into(),
+            key_path: "tests/fixtures/tls/first.key.pem".into(),
+            reload_interval_seconds: 60,
+            redirect_http_port: Some(settings.application.port),
+            hsts_max_age_seconds: None,
+        });
+
+        let problems = check_config(&settings);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].contains("missing.pem"), "{problems:?}");
+    }
+
+    #[test]
+    fn postgres_sessions_need_the_postgres_backend() {
+        let mut settings = Settings::new().unwrap();
+        settings.database.backend = DatabaseBackend::Postgres;
+        settings.session.store = SessionStoreBackend::Postgres;
+        assert!(check_config(&settings).is_empty());
+
+        settings.database.backend = DatabaseBackend::Sqlite;
+        assert_eq!(check_config(&settings).len(), 1);
+    }
+}
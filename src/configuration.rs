@@ -1,20 +1,34 @@
 use std::env;
 
+use actix_web::cookie::SameSite;
+use chrono::{DateTime, Utc};
 use config::{Config, File, FileFormat};
 use secrecy::Secret;
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
 use sqlx::ConnectOptions;
 
 #[derive(serde::Deserialize, Clone)]
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
-    pub redis_uri: Secret<String>,
+    pub session: SessionSettings,
+    pub shutdown: ShutdownSettings,
+    pub poll_cache: PollCacheSettings,
+    pub rate_limit: RateLimitSettings,
+    pub proof_of_work: ProofOfWorkSettings,
+    pub content_filter: ContentFilterSettings,
+    pub moderation: ModerationSettings,
+    pub admin: AdminSettings,
+    pub metrics: MetricsSettings,
+    pub telemetry: TelemetrySettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
 pub struct DatabaseSettings {
+    #[serde(default)]
+    pub backend: DatabaseBackend,
     pub username: String,
     pub password: String,
     #[serde(deserialize_with = "deserialize_number_from_string")]
@@ -22,6 +36,72 @@ pub struct DatabaseSettings {
     pub host: String,
     pub database_name: String,
     pub require_ssl: bool,
+    /// Apply the embedded migrations before `serve` starts listening.
+    #[serde(default)]
+    pub migrate_on_startup: bool,
+    /// The database file when the backend is SQLite, created if missing.
+    pub sqlite_path: String,
+}
+
+/// Where polls are stored, SQLite needs nothing besides the binary.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    #[default]
+    Postgres,
+    Sqlite,
+    /// Lost on restart, for development and tests.
+    Memory,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SessionSettings {
+    #[serde(default)]
+    pub store: SessionStoreBackend,
+    /// Only used by the Redis store, the rate limits then share it.
+    pub redis_uri: Secret<String>,
+    /// How long the state of an unused session is kept. Cookie sessions
+    /// can only expire with their cookie, which is kept for as long.
+    pub ttl_minutes: u32,
+    /// How often the Postgres store deletes the sessions that expired.
+    pub cleanup_interval_seconds: u64,
+    pub cookie_name: String,
+    pub same_site: SameSiteSetting,
+    /// Only send the cookie over HTTPS.
+    pub secure: bool,
+}
+
+/// Where sessions are kept, memory sessions are lost on restart and aren't
+/// shared between instances.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStoreBackend {
+    #[default]
+    Redis,
+    /// A table of the Postgres database, the database backend has to be
+    /// Postgres too.
+    Postgres,
+    /// The whole session is encrypted in the cookie, nothing is stored.
+    Cookie,
+    Memory,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSiteSetting {
+    Strict,
+    Lax,
+    None,
+}
+
+impl From<SameSiteSetting> for SameSite {
+    fn from(same_site: SameSiteSetting) -> Self {
+        match same_site {
+            SameSiteSetting::Strict => SameSite::Strict,
+            SameSiteSetting::Lax => SameSite::Lax,
+            SameSiteSetting::None => SameSite::None,
+        }
+    }
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -29,7 +109,188 @@ pub struct ApplicationSettings {
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub port: u16,
     pub host: String,
-    pub hmac_secret: Secret<String>,
+    /// The first signs new cookies and challenges, the others are still
+    /// accepted so rotating the secret doesn't log everyone out.
+    pub hmac_secrets: Vec<HmacSecret>,
+    /// Serve HTTPS instead of plain HTTP when this is set.
+    #[serde(default)]
+    pub tls: Option<TlsSettings>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct TlsSettings {
+    /// The PEM certificate chain, the server's certificate first.
+    pub certificate_path: String,
+    /// The PEM private key, in PKCS#8, RSA or SEC1 form.
+    pub key_path: String,
+    /// How often the files are checked for changes, a renewed certificate
+    /// is picked up without a restart.
+    #[serde(default = "default_tls_reload_interval")]
+    pub reload_interval_seconds: u64,
+    /// Also listen for plain HTTP on this port, only to redirect to HTTPS.
+    #[serde(default)]
+    pub redirect_http_port: Option<u16>,
+    /// Send `Strict-Transport-Security` with this max-age.
+    #[serde(default)]
+    pub hsts_max_age_seconds: Option<u64>,
+}
+
+fn default_tls_reload_interval() -> u64 {
+    60
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct HmacSecret {
+    pub secret: Secret<String>,
+    /// Cookies signed with a previous secret are rejected after this, it
+    /// doesn't apply to the first one.
+    #[serde(default)]
+    pub accepted_until: Option<DateTime<Utc>>,
+}
+
+/// What happens between SIGTERM or SIGINT and the process exiting.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ShutdownSettings {
+    /// How long requests are still served once readiness fails, so load
+    /// balancers have time to send them elsewhere.
+    pub readiness_delay_seconds: u64,
+    /// How long requests in flight are given to finish once the listener
+    /// closed, they're dropped afterwards.
+    pub drain_timeout_seconds: u64,
+}
+
+/// The polls kept in memory between requests. Instances sharing a Postgres
+/// database tell each other about the polls they change.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct PollCacheSettings {
+    /// How many polls are kept at most, 0 turns the cache off.
+    pub capacity: usize,
+    /// How long a poll is kept before it's looked up again.
+    pub ttl_seconds: u64,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct RateLimitSettings {
+    /// Prefix of the Redis keys holding the buckets.
+    pub namespace: String,
+    pub create_poll: ActionLimits,
+    pub join_poll: ActionLimits,
+    pub suggest: ActionLimits,
+    pub admin_login: ActionLimits,
+}
+
+/// Buckets that all have to allow an action, a missing one means no limit.
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct ActionLimits {
+    pub per_ip: Option<BucketSettings>,
+    pub per_session: Option<BucketSettings>,
+    pub per_poll: Option<BucketSettings>,
+}
+
+/// A token bucket holding up to `capacity` requests, refilled at a steady
+/// `refill_per_minute`.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct BucketSettings {
+    pub capacity: u32,
+    pub refill_per_minute: u32,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ProofOfWorkSettings {
+    /// Leading zero bits the hash of a solution needs, every extra bit
+    /// doubles the work.
+    pub difficulty: u8,
+    /// How long a challenge can be used after it was issued.
+    pub window_seconds: i64,
+    pub required_for_new_poll: bool,
+    /// Require it to join any poll, otherwise only polls that ask for it do.
+    pub required_for_join: bool,
+}
+
+/// Filters run on prompts, suggestions and usernames, a missing one is off.
+/// Polls can replace any of them with their own, see `ContentFilter::for_poll`.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ContentFilterSettings {
+    pub word_list: Option<WordListSettings>,
+    pub links: Option<LinkFilterSettings>,
+    pub repeated_characters: Option<RepeatedCharactersSettings>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct WordListSettings {
+    pub words: Vec<String>,
+    pub action: FilterAction,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct LinkFilterSettings {
+    pub action: FilterAction,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct RepeatedCharactersSettings {
+    /// The most times a character can appear in a row.
+    pub max: usize,
+    pub action: FilterAction,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ModerationSettings {
+    /// Reports from different members it takes to hide a suggestion or name
+    /// until an organizer reviews it.
+    pub report_threshold: u32,
+}
+
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct AdminSettings {
+    pub accounts: Vec<AdminAccount>,
+}
+
+/// An operator allowed into `/admin`, the password is stored as an argon2
+/// PHC string.
+#[derive(serde::Deserialize, Clone)]
+pub struct AdminAccount {
+    pub username: String,
+    pub password_hash: Secret<String>,
+}
+
+/// Where `/metrics` is served, it isn't served at all unless one of them is set.
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct MetricsSettings {
+    /// A `host:port` of its own, away from the public listener.
+    pub address: Option<String>,
+    /// Scrapers have to send it as a bearer token. Without an address of
+    /// its own, this serves the metrics on the application's listener.
+    pub token: Option<Secret<String>>,
+}
+
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct TelemetrySettings {
+    /// Spans are only exported when this is set.
+    pub otlp: Option<OtlpSettings>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct OtlpSettings {
+    /// The collector's OTLP/HTTP traces endpoint, like `http://localhost:4318/v1/traces`.
+    pub endpoint: String,
+    /// Share of the traces starting here that are exported. Traces continued
+    /// from a `traceparent` header follow the caller's decision instead.
+    #[serde(default = "default_sample_ratio")]
+    pub sample_ratio: f64,
+}
+
+fn default_sample_ratio() -> f64 {
+    1.0
+}
+
+/// What happens to content caught by a filter.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterAction {
+    Reject,
+    /// Keep it out of sight until an organizer approves it.
+    Hold,
 }
 
 impl DatabaseSettings {
@@ -52,6 +313,16 @@ impl DatabaseSettings {
         options.log_statements(tracing::log::LevelFilter::Trace);
         options
     }
+
+    pub fn sqlite_options(&self) -> SqliteConnectOptions {
+        let mut options = SqliteConnectOptions::new()
+            .filename(&self.sqlite_path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(std::time::Duration::from_secs(5));
+        options.log_statements(tracing::log::LevelFilter::Trace);
+        options
+    }
 }
 
 impl Settings {
@@ -61,6 +332,11 @@ impl Settings {
             .try_into()
             .expect("failed to parse APP_ENVIRONMENT");
 
+        Self::for_environment(environment)
+    }
+
+    /// The settings of `environment`, still overridden by `APP__` variables.
+    pub fn for_environment(environment: Environment) -> Result<Self, config::ConfigError> {
         let s = Config::builder()
             .add_source(File::new("configuration/base", FileFormat::Yaml).required(true))
             .add_source(
@@ -79,12 +355,25 @@ impl Settings {
     pub fn address(&self) -> String {
         format!("{}:{}", self.application.host, self.application.port)
     }
+
+    /// Redis is only used when the sessions are kept there, the rate limits
+    /// then share it.
+    pub fn redis_uri_in_use(&self) -> Option<&Secret<String>> {
+        match self.session.store {
+            SessionStoreBackend::Redis => Some(&self.session.redis_uri),
+            SessionStoreBackend::Postgres
+            | SessionStoreBackend::Cookie
+            | SessionStoreBackend::Memory => None,
+        }
+    }
 }
 
 /// The possible runtime for the application
 pub enum Environment {
     Local,
     Production,
+    /// Everything in memory, the API tests need nothing else.
+    Test,
 }
 
 impl Environment {
@@ -92,6 +381,7 @@ impl Environment {
         match self {
             Environment::Local => "local",
             Environment::Production => "production",
+            Environment::Test => "test",
         }
     }
 }
@@ -103,8 +393,9 @@ impl TryFrom<String> for Environment {
         match value.to_lowercase().as_str() {
             "local" => Ok(Self::Local),
             "production" => Ok(Self::Production),
+            "test" => Ok(Self::Test),
             other => Err(format!(
-                "{other} is not a supported environment. Use either `local` or `production`"
+                "{other} is not a supported environment. Use `local`, `production` or `test`"
             )),
         }
     }
@@ -0,0 +1,361 @@
+use crate::configuration::{
+    ContentFilterSettings, FilterAction, LinkFilterSettings, RepeatedCharactersSettings,
+    WordListSettings,
+};
+
+/// The outcome of running content through the filters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Allow,
+    /// The content can be used but only shown once an organizer approves it,
+    /// with the reason it was held.
+    Hold(String),
+    /// The content can't be used, with the reason why.
+    Reject(String),
+}
+
+/// A single check of the pipeline, returns the reason when it catches something.
+pub trait Filter: Send + Sync {
+    fn check(&self, text: &str) -> Option<String>;
+    fn action(&self) -> FilterAction;
+}
+
+/// Runs content through every filter, the strictest outcome wins.
+#[derive(Default)]
+pub struct ContentFilter {
+    filters: Vec<Box<dyn Filter>>,
+}
+
+impl ContentFilter {
+    pub fn new(settings: &ContentFilterSettings) -> Self {
+        let mut filter = Self::default();
+        if let Some(settings) = &settings.word_list {
+            filter = filter.with(WordList::new(settings));
+        }
+        if let Some(settings) = &settings.links {
+            filter = filter.with(LinkFilter::new(settings));
+        }
+        if let Some(settings) = &settings.repeated_characters {
+            filter = filter.with(RepeatedCharacters::new(settings));
+        }
+        filter
+    }
+
+    /// The site wide settings, with the filters a poll configured for itself
+    /// taking the place of the site's, `null` turning one off.
+    pub fn for_poll(
+        settings: &ContentFilterSettings,
+        poll_settings: Option<&serde_json::Value>,
+    ) -> Result<Self, serde_json::Error> {
+        Ok(Self::new(&merge_settings(settings, poll_settings)?))
+    }
+
+    pub fn with(mut self, filter: impl Filter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn check(&self, text: &str) -> Verdict {
+        let mut verdict = Verdict::Allow;
+        for filter in &self.filters {
+            if let Some(reason) = filter.check(text) {
+                match filter.action() {
+                    FilterAction::Reject => return Verdict::Reject(reason),
+                    FilterAction::Hold if verdict == Verdict::Allow => {
+                        verdict = Verdict::Hold(reason)
+                    }
+                    FilterAction::Hold => {}
+                }
+            }
+        }
+        verdict
+    }
+}
+
+pub fn merge_settings(
+    settings: &ContentFilterSettings,
+    poll_settings: Option<&serde_json::Value>,
+) -> Result<ContentFilterSettings, serde_json::Error> {
+    let mut merged = serde_json::to_value(settings)?;
+    if let (Some(merged), Some(serde_json::Value::Object(poll_settings))) =
+        (merged.as_object_mut(), poll_settings)
+    {
+        for (key, value) in poll_settings {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    serde_json::from_value(merged)
+}
+
+/// Blocks words from a list, also when written with digits or symbols in
+/// place of letters or with letters repeated ("h3ll000").
+pub struct WordList {
+    words: Vec<String>,
+    action: FilterAction,
+}
+
+impl WordList {
+    pub fn new(settings: &WordListSettings) -> Self {
+        Self {
+            words: settings
+                .words
+                .iter()
+                .map(|w| normalize_leetspeak(w))
+                .filter(|w| !w.is_empty())
+                .collect(),
+            action: settings.action,
+        }
+    }
+}
+
+impl Filter for WordList {
+    fn check(&self, text: &str) -> Option<String> {
+        let normalized = normalize_leetspeak(text);
+        normalized
+            .split(' ')
+            .any(|word| self.words.iter().any(|blocked| blocked == word))
+            .then(|| "contains a blocked word".to_string())
+    }
+
+    fn action(&self) -> FilterAction {
+        self.action
+    }
+}
+
+/// Lowercases `text`, undoes common letter substitutions, collapses repeated
+/// letters and turns everything else into single spaces between words.
+fn normalize_leetspeak(text: &str) -> String {
+    let text = text.to_lowercase();
+    let mut chars = text.chars().peekable();
+    let mut normalized = String::with_capacity(text.len());
+    while let Some(c) = chars.next() {
+        // Symbols only stand for letters inside a word, so that "spam!" is
+        // still "spam"
+        let in_word = chars
+            .peek()
+            .map(|next| next.is_alphanumeric())
+            .unwrap_or(false);
+        let c = match leetspeak_letter(c) {
+            Some(letter) if c.is_ascii_digit() || in_word => letter,
+            _ if c.is_alphanumeric() => c,
+            _ => ' ',
+        };
+        match normalized.chars().last() {
+            Some(last) if last == c => {}
+            None if c == ' ' => {}
+            _ => normalized.push(c),
+        }
+    }
+    normalized.trim_end().to_string()
+}
+
+fn leetspeak_letter(c: char) -> Option<char> {
+    match c {
+        '0' => Some('o'),
+        '1' | '!' | '|' => Some('i'),
+        '3' => Some('e'),
+        '4' | '@' => Some('a'),
+        '5' | '$' => Some('s'),
+        '7' | '+' => Some('t'),
+        '8' => Some('b'),
+        '9' => Some('g'),
+        _ => None,
+    }
+}
+
+/// Catches web addresses, with or without a scheme.
+pub struct LinkFilter {
+    action: FilterAction,
+}
+
+impl LinkFilter {
+    /// Top level domains that are commonly used in spam, anything else needs
+    /// a scheme or `www.` to be recognized, so that "node.js" isn't a link.
+    const TOP_LEVEL_DOMAINS: &'static [&'static str] = &[
+        "com", "net", "org", "info", "biz", "io", "co", "me", "ly", "gg", "xyz", "top", "app",
+        "dev", "link", "click", "site", "online", "ru", "cn", "uk", "de",
+    ];
+
+    pub fn new(settings: &LinkFilterSettings) -> Self {
+        Self {
+            action: settings.action,
+        }
+    }
+
+    fn is_link(word: &str) -> bool {
+        let word = word.to_lowercase();
+        if word.contains("://") || word.starts_with("www.") {
+            return true;
+        }
+        let host = word.split(['/', '?', '#']).next().unwrap_or_default();
+        let host = host.trim_end_matches(['.', ',', '!', ')', ':', ';']);
+        match host.rsplit_once('.') {
+            Some((name, tld)) => {
+                !name.is_empty() && !name.ends_with('.') && Self::TOP_LEVEL_DOMAINS.contains(&tld)
+            }
+            None => false,
+        }
+    }
+}
+
+impl Filter for LinkFilter {
+    fn check(&self, text: &str) -> Option<String> {
+        text.split_whitespace()
+            .any(Self::is_link)
+            .then(|| "contains a link".to_string())
+    }
+
+    fn action(&self) -> FilterAction {
+        self.action
+    }
+}
+
+/// Catches "aaaaaaaaa" and "!!!!!!!!!".
+pub struct RepeatedCharacters {
+    max: usize,
+    action: FilterAction,
+}
+
+impl RepeatedCharacters {
+    pub fn new(settings: &RepeatedCharactersSettings) -> Self {
+        Self {
+            max: settings.max,
+            action: settings.action,
+        }
+    }
+}
+
+impl Filter for RepeatedCharacters {
+    fn check(&self, text: &str) -> Option<String> {
+        let mut previous = None;
+        let mut run = 0;
+        for c in text.chars() {
+            run = if previous == Some(c) { run + 1 } else { 1 };
+            previous = Some(c);
+            if run > self.max {
+                return Some(format!(
+                    "repeats a character more than {} times in a row",
+                    self.max
+                ));
+            }
+        }
+        None
+    }
+
+    fn action(&self) -> FilterAction {
+        self.action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_settings, normalize_leetspeak, ContentFilter, LinkFilter, Verdict};
+    use crate::configuration::{
+        ContentFilterSettings, FilterAction, LinkFilterSettings, RepeatedCharactersSettings,
+        WordListSettings,
+    };
+
+    fn settings() -> ContentFilterSettings {
+        ContentFilterSettings {
+            word_list: Some(WordListSettings {
+                words: vec!["spam".into()],
+                action: FilterAction::Reject,
+            }),
+            links: Some(LinkFilterSettings {
+                action: FilterAction::Hold,
+            }),
+            repeated_characters: Some(RepeatedCharactersSettings {
+                max: 3,
+                action: FilterAction::Reject,
+            }),
+        }
+    }
+
+    #[test]
+    fn clean_content_is_allowed() {
+        let filter = ContentFilter::new(&settings());
+        assert_eq!(
+            filter.check("What should we eat on Friday?"),
+            Verdict::Allow
+        );
+        assert_eq!(filter.check("Pizza with node.js devs"), Verdict::Allow);
+    }
+
+    #[test]
+    fn blocked_words_are_caught_through_leetspeak() {
+        let filter = ContentFilter::new(&settings());
+        for text in ["spam", "buy SPAM now!", "$p4m", "5PAM", "sppaamm", "(spam)"] {
+            assert_eq!(
+                filter.check(text),
+                Verdict::Reject("contains a blocked word".into()),
+                "{text}"
+            );
+        }
+        // Only whole words are blocked
+        assert_eq!(filter.check("spammer-free zone"), Verdict::Allow);
+    }
+
+    #[test]
+    fn leetspeak_is_normalized() {
+        assert_eq!(normalize_leetspeak("H3ll0,  W0rld!!"), "helo world");
+        assert_eq!(normalize_leetspeak("  $4y sh!t"), "say shit");
+        assert_eq!(normalize_leetspeak("100 $"), "io");
+    }
+
+    #[test]
+    fn links_are_caught() {
+        for text in [
+            "https://example.com",
+            "visit www.example.org",
+            "cheap.pills.xyz/buy now",
+            "see example.com.",
+        ] {
+            assert!(
+                text.split_whitespace().any(LinkFilter::is_link),
+                "{text} has a link"
+            );
+        }
+        for text in ["node.js", "e.g. this", "a...com", "end of sentence."] {
+            assert!(
+                !text.split_whitespace().any(LinkFilter::is_link),
+                "{text} has no link"
+            );
+        }
+    }
+
+    #[test]
+    fn held_content_is_rejected_if_another_filter_rejects_it() {
+        let filter = ContentFilter::new(&settings());
+        assert_eq!(
+            filter.check("example.com"),
+            Verdict::Hold("contains a link".into())
+        );
+        assert_eq!(
+            filter.check("example.com spam"),
+            Verdict::Reject("contains a blocked word".into())
+        );
+    }
+
+    #[test]
+    fn repeated_characters_are_limited() {
+        let filter = ContentFilter::new(&settings());
+        assert_eq!(filter.check("Yesss"), Verdict::Allow);
+        assert_eq!(
+            filter.check("Yessss"),
+            Verdict::Reject("repeats a character more than 3 times in a row".into())
+        );
+    }
+
+    #[test]
+    fn poll_settings_replace_the_site_ones() {
+        let poll_settings = serde_json::json!({
+            "links": null,
+            "repeated_characters": { "max": 10, "action": "hold" },
+        });
+        let merged = merge_settings(&settings(), Some(&poll_settings)).unwrap();
+
+        assert_eq!(merged.word_list, settings().word_list);
+        assert_eq!(merged.links, None);
+        assert_eq!(merged.repeated_characters.unwrap().max, 10);
+    }
+}
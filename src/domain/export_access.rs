@@ -0,0 +1,57 @@
+use std::fmt;
+
+use super::PollRole;
+
+/// Who can export the results of a poll.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportAccess {
+    /// Every member but the observers.
+    #[default]
+    Participants,
+    Owner,
+}
+
+impl ExportAccess {
+    pub const ALL: [ExportAccess; 2] = [ExportAccess::Participants, ExportAccess::Owner];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportAccess::Participants => "participants",
+            ExportAccess::Owner => "owner",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportAccess::Participants => "Every participant",
+            ExportAccess::Owner => "Only the owner",
+        }
+    }
+
+    /// The least a member needs to be to export the results.
+    pub fn required_role(&self) -> PollRole {
+        match self {
+            ExportAccess::Participants => PollRole::Participant,
+            ExportAccess::Owner => PollRole::Owner,
+        }
+    }
+}
+
+impl fmt::Display for ExportAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl TryFrom<String> for ExportAccess {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "participants" => Ok(Self::Participants),
+            "owner" => Ok(Self::Owner),
+            other => Err(format!("{other} is not a valid export access")),
+        }
+    }
+}
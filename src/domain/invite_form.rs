@@ -0,0 +1,51 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct InviteFormData {
+    #[validate(range(min = 1, max = 720, message = "must be between 1 and 720 hours."))]
+    pub expires_in_hours: i32,
+    #[validate(range(min = 1, max = 1000, message = "must be between 1 and 1000."))]
+    pub max_uses: i32,
+}
+
+/// Invite tokens are only ever alphanumeric, anything else can't be valid and
+/// shouldn't be echoed back into a page.
+pub fn is_well_formed_invite_token(token: &str) -> bool {
+    !token.is_empty() && token.len() <= 64 && token.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use validator::Validate;
+
+    use super::InviteFormData;
+
+    #[test]
+    fn valid_invite_is_accepted() {
+        let f = InviteFormData {
+            expires_in_hours: 24,
+            max_uses: 1,
+        };
+        assert_ok!(f.validate());
+    }
+
+    #[test]
+    fn invite_without_uses_is_rejected() {
+        let f = InviteFormData {
+            expires_in_hours: 24,
+            max_uses: 0,
+        };
+        assert_err!(f.validate());
+    }
+
+    #[test]
+    fn invite_expiring_after_a_month_is_rejected() {
+        let f = InviteFormData {
+            expires_in_hours: 721,
+            max_uses: 5,
+        };
+        assert_err!(f.validate());
+    }
+}
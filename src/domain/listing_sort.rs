@@ -0,0 +1,92 @@
+use std::fmt;
+
+/// The order the poll page lists its members in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberSort {
+    Newest,
+    /// In the order they joined.
+    #[default]
+    Oldest,
+    Alphabetical,
+}
+
+impl MemberSort {
+    pub const ALL: [MemberSort; 3] = [
+        MemberSort::Newest,
+        MemberSort::Oldest,
+        MemberSort::Alphabetical,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemberSort::Newest => "newest",
+            MemberSort::Oldest => "oldest",
+            MemberSort::Alphabetical => "alphabetical",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MemberSort::Newest => "Newest",
+            MemberSort::Oldest => "Oldest",
+            MemberSort::Alphabetical => "Name",
+        }
+    }
+}
+
+impl fmt::Display for MemberSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// The order the poll page lists its suggestions in. Suggestions that tie
+/// come oldest first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionSort {
+    Newest,
+    #[default]
+    Oldest,
+    MostVotes,
+    Alphabetical,
+    /// By the name of who suggested it.
+    Author,
+}
+
+impl SuggestionSort {
+    pub const ALL: [SuggestionSort; 5] = [
+        SuggestionSort::Newest,
+        SuggestionSort::Oldest,
+        SuggestionSort::MostVotes,
+        SuggestionSort::Alphabetical,
+        SuggestionSort::Author,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuggestionSort::Newest => "newest",
+            SuggestionSort::Oldest => "oldest",
+            SuggestionSort::MostVotes => "most_votes",
+            SuggestionSort::Alphabetical => "alphabetical",
+            SuggestionSort::Author => "author",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SuggestionSort::Newest => "Newest",
+            SuggestionSort::Oldest => "Oldest",
+            SuggestionSort::MostVotes => "Most votes",
+            SuggestionSort::Alphabetical => "Alphabetical",
+            SuggestionSort::Author => "Author",
+        }
+    }
+}
+
+impl fmt::Display for SuggestionSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
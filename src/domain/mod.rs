@@ -1,3 +1,19 @@
+mod export_access;
+mod invite_form;
+mod listing_sort;
+mod moderation_status;
+mod participant_name;
 mod poll_form;
+mod poll_role;
+mod report_reason;
+mod report_target;
 
+pub use export_access::*;
+pub use invite_form::*;
+pub use listing_sort::*;
+pub use moderation_status::*;
+pub use participant_name::*;
 pub use poll_form::*;
+pub use poll_role::*;
+pub use report_reason::*;
+pub use report_target::*;
@@ -0,0 +1,23 @@
+/// Whether content caught by a content filter is waiting for an organizer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModerationStatus {
+    Visible,
+    /// Hidden from the poll until approved, with the reason it was held.
+    Held(String),
+}
+
+impl ModerationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationStatus::Visible => "visible",
+            ModerationStatus::Held(_) => "held",
+        }
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            ModerationStatus::Visible => None,
+            ModerationStatus::Held(reason) => Some(reason),
+        }
+    }
+}
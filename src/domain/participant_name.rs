@@ -0,0 +1,188 @@
+use unicode_normalization::UnicodeNormalization;
+use unicode_security::{confusable_detection::skeleton, GeneralSecurityProfile};
+use validator::ValidationError;
+
+/// A validated name a user goes by inside a poll, stored in NFC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParticipantName(String);
+
+impl ParticipantName {
+    pub const MIN_LENGTH: usize = 3;
+    pub const MAX_LENGTH: usize = 32;
+
+    pub fn parse(s: String) -> Result<Self, ValidationError> {
+        let s = s.nfc().collect::<String>();
+        validate_normalized_name(&s)?;
+        Ok(Self(s))
+    }
+
+    /// The UTS #39 skeleton of the name, lowercased and skeletonized again so
+    /// that case doesn't matter either. Two names with the same skeleton look
+    /// alike ("alice", "Alice", "aIice" and "\u{430}lice" all do) and can't be
+    /// used in the same poll.
+    pub fn skeleton(&self) -> String {
+        let lowercase = skeleton(&self.0).collect::<String>().to_lowercase();
+        skeleton(&lowercase).collect()
+    }
+
+    /// Alternatives to offer when this name is already taken in a poll,
+    /// every candidate is itself a valid name.
+    pub fn alternatives(&self) -> Vec<ParticipantName> {
+        (2..=9)
+            .flat_map(|n| [format!("{}{n}", self.0), format!("{}_{n}", self.0)])
+            .filter_map(|candidate| Self::parse(candidate).ok())
+            .collect()
+    }
+}
+
+impl AsRef<str> for ParticipantName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ParticipantName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+pub fn validate_participant_name(s: &str) -> Result<(), ValidationError> {
+    validate_normalized_name(&s.nfc().collect::<String>())
+}
+
+fn validate_normalized_name(s: &str) -> Result<(), ValidationError> {
+    let length = s.chars().count();
+    if !(ParticipantName::MIN_LENGTH..=ParticipantName::MAX_LENGTH).contains(&length) {
+        return Err(invalid("length", "length is invalid."));
+    }
+    validate_has_only_allowed_characters(s)
+}
+
+fn invalid(code: &'static str, message: &'static str) -> ValidationError {
+    let mut e = ValidationError::new(code);
+    e.message = Some(message.into());
+    e
+}
+
+fn validate_has_only_allowed_characters(s: &str) -> Result<(), ValidationError> {
+    // First character must be a letter or a number
+    if s.chars().next().is_some_and(|c| !c.is_alphanumeric()) {
+        return Err(invalid(
+            "first_character",
+            "first character must be a letter or a number.",
+        ));
+    }
+    // All of them must be allowed in identifiers by UTS #39, which keeps out
+    // invisible, obsolete and technical characters but lets combining marks
+    // through. ASCII punctuation other than underscores isn't part of a name.
+    for c in s.chars() {
+        if !c.identifier_allowed() || (c.is_ascii_punctuation() && c != '_') {
+            return Err(invalid(
+                "allowed_characters",
+                "only letters, numbers and underscores are allowed.",
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+
+    use super::ParticipantName;
+
+    #[test]
+    fn valid_name_is_accepted() {
+        assert_ok!(ParticipantName::parse("user_name1".into()));
+    }
+
+    #[test]
+    fn unicode_names_are_accepted() {
+        for name in ["pèrson", "Zoë", "Ñandú", "日本語", "Владимир", "नमस्ते"]
+        {
+            assert_ok!(ParticipantName::parse(name.into()));
+        }
+    }
+
+    #[test]
+    fn names_with_disallowed_characters_are_rejected() {
+        for name in ["user-name", "user.name", "user\u{200B}name", "user\u{2168}"] {
+            assert_err!(ParticipantName::parse(name.into()));
+        }
+    }
+
+    #[test]
+    fn first_character_has_to_be_allowed_too() {
+        // A mathematical bold letter and a roman numeral are alphanumeric
+        for name in ["\u{1D41A}lice", "\u{2168}user"] {
+            assert_err!(ParticipantName::parse(name.into()));
+        }
+    }
+
+    #[test]
+    fn names_are_normalized_to_nfc() {
+        let decomposed = ParticipantName::parse("pe\u{300}rson".into()).unwrap();
+        assert_eq!(decomposed.as_ref(), "p\u{e8}rson");
+    }
+
+    #[test]
+    fn length_is_counted_in_characters() {
+        assert_ok!(ParticipantName::parse("è".repeat(32)));
+        assert_err!(ParticipantName::parse("è".repeat(33)));
+    }
+
+    #[test]
+    fn name_too_long_is_rejected() {
+        assert_err!(ParticipantName::parse("a".repeat(33)));
+    }
+
+    #[test]
+    fn lookalike_names_share_a_skeleton() {
+        let alice = ParticipantName::parse("alice".into()).unwrap();
+        for lookalike in ["Alice", "\u{430}lice", "aIice", "a1ice"] {
+            let lookalike = ParticipantName::parse(lookalike.into()).unwrap();
+            assert_eq!(alice.skeleton(), lookalike.skeleton(), "{lookalike}");
+        }
+        let bob = ParticipantName::parse("bob".into()).unwrap();
+        assert_ne!(alice.skeleton(), bob.skeleton());
+    }
+
+    #[test]
+    fn ascii_skeleton_matches_the_migration_backfill() {
+        // Existing names were backfilled in SQL with
+        // replace(lower(translate(username, 'I01', 'lOl')), 'm', 'rn')
+        let backfill = |s: &str| {
+            let translated = s
+                .chars()
+                .map(|c| match c {
+                    'I' | '1' => 'l',
+                    '0' => 'O',
+                    c => c,
+                })
+                .collect::<String>();
+            translated.to_lowercase().replace('m', "rn")
+        };
+        let ascii = ('a'..='z').chain('A'..='Z').chain('0'..='9');
+        for c in ascii {
+            let name = ParticipantName::parse(format!("x{c}_")).unwrap();
+            assert_eq!(name.skeleton(), backfill(name.as_ref()), "{name}");
+        }
+    }
+
+    #[test]
+    fn name_starting_with_underscore_is_rejected() {
+        assert_err!(ParticipantName::parse("_user".into()));
+    }
+
+    #[test]
+    fn alternatives_are_valid_and_different() {
+        let name = ParticipantName::parse("a".repeat(31)).unwrap();
+        let alternatives = name.alternatives();
+
+        assert!(!alternatives.is_empty());
+        assert!(alternatives.iter().all(|a| a != &name));
+        assert!(alternatives.iter().all(|a| a.as_ref().len() <= 32));
+    }
+}
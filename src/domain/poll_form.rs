@@ -1,37 +1,42 @@
-use serde::Deserialize;
-use validator::{Validate, ValidationError};
+use serde::{Deserialize, Deserializer};
+use validator::Validate;
+
+use super::validate_participant_name;
 
 // TODO: implement prettier messages
 #[derive(Debug, Validate, Deserialize)]
 pub struct PollFormData {
-    #[validate(
-        length(min = 3, max = 32, message = "length is invalid."),
-        custom = "validate_has_only_allowed_characters"
-    )]
+    #[validate(custom = "validate_participant_name")]
     pub username: String,
     #[validate(length(min = 3, max = 64, message = "length is invalid."))]
     pub prompt: String,
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    pub invite_only: bool,
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    #[validate(length(min = 6, max = 128, message = "length is invalid."))]
+    pub passphrase: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    pub require_proof_of_work: bool,
+    pub pow_challenge: Option<String>,
+    pub pow_solution: Option<String>,
 }
 
-fn validate_has_only_allowed_characters(s: &str) -> Result<(), ValidationError> {
-    let mut chars = s.chars();
-    // First character must be a letter or a number
-    if let Some(c) = chars.next() {
-        if !c.is_ascii_alphanumeric() {
-            return Err(ValidationError::new(
-                "first character must be a letter or a number",
-            ));
-        }
-    }
-    // String must only contain [a-zA-Z0-9_]
-    for c in chars {
-        if !(c.is_ascii_alphanumeric() || c == '_') {
-            return Err(ValidationError::new(
-                "string contains disallowed characters",
-            ));
-        }
-    }
-    Ok(())
+/// HTML checkboxes are only submitted when checked, with the value `on`.
+pub fn deserialize_checkbox<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<String> = Option::deserialize(deserializer)?;
+    Ok(matches!(value.as_deref(), Some("on" | "true")))
+}
+
+/// Optional text inputs are submitted as an empty string when left blank.
+pub fn deserialize_optional_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<String> = Option::deserialize(deserializer)?;
+    Ok(value.filter(|s| !s.is_empty()))
 }
 
 #[cfg(test)]
@@ -45,6 +50,11 @@ mod tests {
         PollFormData {
             username: username.to_string(),
             prompt: prompt.to_string(),
+            invite_only: false,
+            passphrase: None,
+            require_proof_of_work: false,
+            pow_challenge: None,
+            pow_solution: None,
         }
     }
 
@@ -73,9 +83,15 @@ mod tests {
         assert_err!(f.validate());
     }
 
+    #[test]
+    fn usernames_with_accents_are_accepted() {
+        let f = new_form("pèrson", "What kind of question?");
+        assert_ok!(f.validate());
+    }
+
     #[test]
     fn usernames_with_special_characters_are_rejected() {
-        let test_usernames = vec!["user?name", "!user", "pèrson", "<p>user</p>"];
+        let test_usernames = vec!["user?name", "!user", "user-name", "<p>user</p>"];
         for username in test_usernames {
             let f = new_form(username, "What kind of question?");
             assert_err!(
@@ -93,6 +109,13 @@ mod tests {
         assert_err!(f.validate());
     }
 
+    #[test]
+    fn short_passphrase_is_rejected() {
+        let mut f = new_form("username", "What kind of question?");
+        f.passphrase = Some("12345".into());
+        assert_err!(f.validate());
+    }
+
     #[test]
     fn prompt_too_long_is_rejected() {
         let prompt = "a".repeat(65);
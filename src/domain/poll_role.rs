@@ -0,0 +1,96 @@
+use std::fmt;
+
+/// What a user is allowed to do in a poll, ordered from least to most privileged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PollRole {
+    Observer,
+    Participant,
+    CoOrganizer,
+    Owner,
+}
+
+impl PollRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PollRole::Observer => "observer",
+            PollRole::Participant => "participant",
+            PollRole::CoOrganizer => "co_organizer",
+            PollRole::Owner => "owner",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PollRole::Observer => "observer",
+            PollRole::Participant => "participant",
+            PollRole::CoOrganizer => "co-organizer",
+            PollRole::Owner => "owner",
+        }
+    }
+
+    pub fn can_suggest(&self) -> bool {
+        *self >= PollRole::Participant
+    }
+
+    pub fn can_manage(&self) -> bool {
+        *self >= PollRole::CoOrganizer
+    }
+}
+
+impl fmt::Display for PollRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl TryFrom<String> for PollRole {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "observer" => Ok(Self::Observer),
+            "participant" => Ok(Self::Participant),
+            "co_organizer" => Ok(Self::CoOrganizer),
+            "owner" => Ok(Self::Owner),
+            other => Err(format!("{other} is not a valid poll role")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::assert_err;
+
+    use super::PollRole;
+
+    #[test]
+    fn roles_round_trip_through_their_database_representation() {
+        for role in [
+            PollRole::Observer,
+            PollRole::Participant,
+            PollRole::CoOrganizer,
+            PollRole::Owner,
+        ] {
+            assert_eq!(PollRole::try_from(role.as_str().to_string()), Ok(role));
+        }
+    }
+
+    #[test]
+    fn unknown_role_is_rejected() {
+        assert_err!(PollRole::try_from("admin".to_string()));
+    }
+
+    #[test]
+    fn observers_cannot_suggest() {
+        assert!(!PollRole::Observer.can_suggest());
+        assert!(PollRole::Participant.can_suggest());
+    }
+
+    #[test]
+    fn only_organizers_can_manage() {
+        assert!(!PollRole::Participant.can_manage());
+        assert!(PollRole::CoOrganizer.can_manage());
+        assert!(PollRole::Owner.can_manage());
+    }
+}
@@ -0,0 +1,79 @@
+use std::fmt;
+
+/// Why a member reported a suggestion or a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Offensive,
+    Harassment,
+    Impersonation,
+    Other,
+}
+
+impl ReportReason {
+    pub const ALL: [ReportReason; 5] = [
+        ReportReason::Spam,
+        ReportReason::Offensive,
+        ReportReason::Harassment,
+        ReportReason::Impersonation,
+        ReportReason::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportReason::Spam => "spam",
+            ReportReason::Offensive => "offensive",
+            ReportReason::Harassment => "harassment",
+            ReportReason::Impersonation => "impersonation",
+            ReportReason::Other => "other",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReportReason::Spam => "Spam",
+            ReportReason::Offensive => "Offensive",
+            ReportReason::Harassment => "Harassment",
+            ReportReason::Impersonation => "Impersonation",
+            ReportReason::Other => "Something else",
+        }
+    }
+}
+
+impl fmt::Display for ReportReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl TryFrom<String> for ReportReason {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        ReportReason::ALL
+            .into_iter()
+            .find(|reason| reason.as_str() == value)
+            .ok_or_else(|| format!("{value} is not a valid report reason"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReportReason;
+
+    #[test]
+    fn reasons_round_trip_through_their_database_representation() {
+        for reason in ReportReason::ALL {
+            assert_eq!(
+                ReportReason::try_from(reason.as_str().to_string()),
+                Ok(reason)
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_reason_is_rejected() {
+        assert!(ReportReason::try_from("boring".to_string()).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use uuid::Uuid;
+
+/// What a report is about, either a suggestion or a member's name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportTarget {
+    Suggestion(Uuid),
+    Member(Uuid),
+}
+
+impl ReportTarget {
+    pub fn parse(target: &str) -> Option<Self> {
+        let (kind, id) = target.split_once(':')?;
+        let id = Uuid::parse_str(id).ok()?;
+        match kind {
+            "suggestion" => Some(ReportTarget::Suggestion(id)),
+            "member" => Some(ReportTarget::Member(id)),
+            _ => None,
+        }
+    }
+
+    /// The value of the target in the report form.
+    pub fn form_value(&self) -> String {
+        match self {
+            ReportTarget::Suggestion(id) => format!("suggestion:{id}"),
+            ReportTarget::Member(id) => format!("member:{id}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::ReportTarget;
+
+    #[test]
+    fn targets_round_trip_through_the_form() {
+        let id = Uuid::new_v4();
+        for target in [ReportTarget::Suggestion(id), ReportTarget::Member(id)] {
+            assert_eq!(ReportTarget::parse(&target.form_value()), Some(target));
+        }
+    }
+
+    #[test]
+    fn malformed_targets_are_rejected() {
+        let id = Uuid::new_v4();
+        for target in [
+            format!("poll:{id}"),
+            "suggestion:not-a-uuid".to_string(),
+            id.to_string(),
+        ] {
+            assert_eq!(ReportTarget::parse(&target), None);
+        }
+    }
+}
@@ -1,8 +1,19 @@
+pub mod authentication;
+pub mod cli;
 pub mod configuration;
+pub mod content_filter;
 pub mod domain;
+pub mod metrics;
 pub mod middleware;
+pub mod poll_cache;
+pub mod proof_of_work;
+pub mod rate_limit;
 pub mod routes;
+pub mod session_store;
+pub mod signing_keys;
 pub mod startup;
+pub mod storage;
 pub mod telemetry;
+pub mod tls;
 pub mod user_session;
 pub mod utils;
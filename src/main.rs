@@ -1,19 +1,67 @@
+use anyhow::Context;
 use apoll::{
+    cli::{check_config, migrate, purge_expired_invites, Cli, Command},
     configuration::Settings,
     startup::Application,
-    telemetry::{get_subscriber, init_subscriber},
+    storage,
+    telemetry::{get_subscriber, init_subscriber, install_otlp_exporter},
 };
+use clap::Parser;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let configuration = Settings::new().context("failed to read configuration")?;
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(configuration).await,
+        Command::Migrate { dry_run } => {
+            let migrations = migrate(&configuration.database, dry_run).await?;
+            let verb = if dry_run { "Would apply" } else { "Applied" };
+            println!("{verb} {} migration(s)", migrations.len());
+            for m in migrations {
+                println!("  {} {}", m.version, m.description);
+            }
+            Ok(())
+        }
+        Command::CheckConfig => {
+            let problems = check_config(&configuration);
+            if problems.is_empty() {
+                println!("The configuration is valid");
+                return Ok(());
+            }
+            for problem in &problems {
+                eprintln!("- {problem}");
+            }
+            anyhow::bail!("found {} problem(s) in the configuration", problems.len())
+        }
+        Command::PurgeExpired { grace_days } => {
+            let storage = storage::connect(&configuration.database);
+            let grace = chrono::Duration::days(grace_days.into());
+            let deleted = purge_expired_invites(storage.as_ref(), grace).await?;
+            println!("Deleted {deleted} expired invite(s)");
+            Ok(())
+        }
+    }
+}
+
+async fn serve(configuration: Settings) -> anyhow::Result<()> {
     // Set up tracing
-    let subscriber = get_subscriber("apoll".into(), "info".into(), std::io::stdout);
+    let tracer = configuration
+        .telemetry
+        .otlp
+        .as_ref()
+        .map(|otlp| install_otlp_exporter("apoll", otlp))
+        .transpose()?;
+    let subscriber = get_subscriber("apoll".into(), "info".into(), std::io::stdout, tracer);
     init_subscriber(subscriber);
 
-    let configuration = Settings::new().expect("failed to read configuration");
-    let application = Application::build(configuration.clone()).await?;
+    let application = Application::build(configuration).await?;
+    let result = application.run_until_stopped().await;
 
-    application.run_until_stopped().await?;
+    // Send the spans still waiting in the exporter's queue
+    opentelemetry::global::shutdown_tracer_provider();
+    result?;
 
     Ok(())
 }
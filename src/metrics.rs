@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::storage::PoolStatus;
+
+/// Everything exported on `/metrics`. Every application has its own registry,
+/// so that apps running in the same process don't share their counts.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    http_requests_in_flight: IntGauge,
+    db_pool_connections: IntGauge,
+    db_pool_idle_connections: IntGauge,
+    session_store_errors: IntCounterVec,
+    pub polls_created: IntCounter,
+    pub poll_joins: IntCounter,
+    pub suggestions: IntCounter,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("apoll".into()), None)?;
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled"),
+            &["method", "route", "status"],
+        )?;
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to handle HTTP requests",
+            ),
+            &["method", "route"],
+        )?;
+        let http_requests_in_flight =
+            IntGauge::new("http_requests_in_flight", "HTTP requests being handled")?;
+        let db_pool_connections = IntGauge::new(
+            "db_pool_connections",
+            "Connections the database pool has open",
+        )?;
+        let db_pool_idle_connections = IntGauge::new(
+            "db_pool_idle_connections",
+            "Open database connections that are not in use",
+        )?;
+        let session_store_errors = IntCounterVec::new(
+            Opts::new(
+                "session_store_errors_total",
+                "Failed operations on the session store",
+            ),
+            &["operation"],
+        )?;
+        // Start every operation at zero, so that the first error shows up as an increase
+        for operation in ["load", "save", "update", "delete"] {
+            session_store_errors.with_label_values(&[operation]);
+        }
+        let polls_created = IntCounter::new("polls_created_total", "Polls created")?;
+        let poll_joins = IntCounter::new("poll_joins_total", "Users that joined a poll")?;
+        let suggestions = IntCounter::new("suggestions_total", "Suggestions made")?;
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_request_duration.clone()))?;
+        registry.register(Box::new(http_requests_in_flight.clone()))?;
+        registry.register(Box::new(db_pool_connections.clone()))?;
+        registry.register(Box::new(db_pool_idle_connections.clone()))?;
+        registry.register(Box::new(session_store_errors.clone()))?;
+        registry.register(Box::new(polls_created.clone()))?;
+        registry.register(Box::new(poll_joins.clone()))?;
+        registry.register(Box::new(suggestions.clone()))?;
+
+        Ok(Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            http_requests_in_flight,
+            db_pool_connections,
+            db_pool_idle_connections,
+            session_store_errors,
+            polls_created,
+            poll_joins,
+            suggestions,
+        })
+    }
+
+    /// `route` is the pattern the request matched, like `/poll/{poll_id}/join`,
+    /// so that the number of series doesn't grow with the number of polls.
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, duration: Duration) {
+        self.http_requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.http_request_duration
+            .with_label_values(&[method, route])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Counts the request as in flight until the guard is dropped.
+    pub fn request_started(&self) -> InFlightGuard {
+        self.http_requests_in_flight.inc();
+        InFlightGuard(self.http_requests_in_flight.clone())
+    }
+
+    pub fn requests_in_flight(&self) -> i64 {
+        self.http_requests_in_flight.get()
+    }
+
+    /// The metrics in the Prometheus text format, with the pool statistics
+    /// taken at the time of the scrape.
+    pub fn render(&self, pool: &PoolStatus) -> Result<String, prometheus::Error> {
+        self.db_pool_connections.set(pool.connections.into());
+        self.db_pool_idle_connections
+            .set(pool.idle_connections as i64);
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+    }
+}
+
+/// Dropped along with the request it counts, even when the request is cancelled.
+pub struct InFlightGuard(IntGauge);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Counts the errors of the session store it wraps, whichever one was
+/// configured.
+#[derive(Clone)]
+pub struct MeteredSessionStore {
+    store: Arc<dyn SessionStore + Send + Sync>,
+    errors: IntCounterVec,
+}
+
+impl MeteredSessionStore {
+    pub fn new(store: impl SessionStore + Send + Sync + 'static, metrics: &Metrics) -> Self {
+        Self {
+            store: Arc::new(store),
+            errors: metrics.session_store_errors.clone(),
+        }
+    }
+
+    fn count<T, E>(&self, operation: &str, result: Result<T, E>) -> Result<T, E> {
+        if result.is_err() {
+            self.errors.with_label_values(&[operation]).inc();
+        }
+        result
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl SessionStore for MeteredSessionStore {
+    async fn load(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<Option<HashMap<String, String>>, LoadError> {
+        let result = self.store.load(session_key).await;
+        self.count("load", result)
+    }
+
+    async fn save(
+        &self,
+        session_state: HashMap<String, String>,
+        ttl: &time::Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let result = self.store.save(session_state, ttl).await;
+        self.count("save", result)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: HashMap<String, String>,
+        ttl: &time::Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let result = self.store.update(session_key, session_state, ttl).await;
+        self.count("update", result)
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        let result = self.store.delete(session_key).await;
+        self.count("delete", result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Metrics;
+    use crate::storage::PoolStatus;
+
+    #[test]
+    fn requests_are_counted_per_route_and_status() {
+        let metrics = Metrics::new().unwrap();
+        let pool = PoolStatus {
+            connections: 0,
+            idle_connections: 0,
+        };
+
+        metrics.observe_request("GET", "/poll/{poll_id}", 200, Duration::from_millis(5));
+        metrics.observe_request("GET", "/poll/{poll_id}", 200, Duration::from_millis(5));
+        metrics.polls_created.inc();
+
+        let text = metrics.render(&pool).unwrap();
+        assert!(text.contains(
+            r#"apoll_http_requests_total{method="GET",route="/poll/{poll_id}",status="200"} 2"#
+        ));
+        assert!(text.contains("apoll_polls_created_total 1"));
+        assert!(text.contains("apoll_db_pool_connections 0"));
+    }
+}
@@ -0,0 +1,62 @@
+use std::future::{ready, Ready};
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::{web, FromRequest, HttpMessage};
+use actix_web_lab::middleware::Next;
+
+use crate::configuration::AdminSettings;
+use crate::user_session::TypedSession;
+use crate::utils::redirect;
+
+/// The operator using the admin area.
+#[derive(Clone, Debug)]
+pub struct Admin {
+    pub username: String,
+}
+
+impl FromRequest for Admin {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        match req.extensions().get::<Admin>() {
+            Some(admin) => ready(Ok(admin.clone())),
+            None => ready(Err(actix_web::error::ErrorUnauthorized(
+                "could not find Admin in request",
+            ))),
+        }
+    }
+}
+
+/// Sends everyone but logged in admins to the login form. The account has to
+/// still be in the configuration, removing it logs the admin out.
+#[tracing::instrument(name = "require admin middleware", skip_all)]
+pub async fn require_admin(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let session = TypedSession::extract(req.parts_mut().0).await?;
+    let username = session
+        .get_admin()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let settings = req.app_data::<web::Data<AdminSettings>>().unwrap();
+
+    let admin = username
+        .filter(|username| settings.accounts.iter().any(|a| &a.username == username))
+        .map(|username| Admin { username });
+    match admin {
+        Some(admin) => {
+            req.extensions_mut().insert(admin);
+            next.call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        }
+        None => Ok(req
+            .into_response(redirect("/admin/login"))
+            .map_into_right_body()),
+    }
+}
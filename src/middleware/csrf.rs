@@ -0,0 +1,341 @@
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, CONTENT_TYPE, ORIGIN, REFERER};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{web, FromRequest, ResponseError};
+use actix_web_lab::middleware::Next;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+
+use crate::user_session::TypedSession;
+
+/// Name of the hidden form field carrying the token.
+pub const CSRF_FIELD: &str = "csrf_token";
+/// Header carrying the token for requests that aren't forms.
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+#[derive(thiserror::Error, Debug)]
+pub enum CsrfError {
+    #[error("the request did not come from this site")]
+    CrossOrigin,
+    #[error("the form is missing its CSRF token, reload the page and try again")]
+    MissingToken,
+    #[error("the form's CSRF token is invalid or expired, reload the page and try again")]
+    InvalidToken,
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl ResponseError for CsrfError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            CsrfError::CrossOrigin | CsrfError::MissingToken | CsrfError::InvalidToken => {
+                StatusCode::FORBIDDEN
+            }
+            CsrfError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct CsrfForm {
+    csrf_token: Option<String>,
+}
+
+/// Rejects state-changing requests that don't carry the session's
+/// synchronizer token or that come from another origin, and adds the token to
+/// every `post` form of the HTML pages it lets through.
+#[tracing::instrument(name = "csrf protection middleware", skip_all)]
+pub async fn csrf_protection(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    if !is_safe_method(req.method()) {
+        if let Err(e) = verify_request(&mut req).await {
+            tracing::warn!(error = %e, "rejected request that failed the CSRF check");
+            return Ok(req.error_response(e));
+        }
+    }
+
+    let res = next.call(req).await?;
+    if !is_html(res.headers()) {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = body::to_bytes(body)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.into().to_string()))?;
+
+    // Pages without a form to protect go out as they came
+    let html = std::str::from_utf8(&bytes)
+        .ok()
+        .filter(|html| form_tags(html).iter().any(|&(_, post)| post));
+    let Some(html) = html else {
+        let res = res.set_body(bytes).map_into_boxed_body();
+        return Ok(ServiceResponse::new(req, res));
+    };
+    let token = session_token(&req)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let res = res
+        .set_body(inject_token(html, &token))
+        .map_into_boxed_body();
+    Ok(ServiceResponse::new(req, res))
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+fn is_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("text/html"))
+        .unwrap_or(false)
+}
+
+async fn verify_request(req: &mut ServiceRequest) -> Result<(), CsrfError> {
+    // Browsers always tell where a cross site form submission comes from, the
+    // headers are only missing for clients that strip them on purpose
+    let host = req.connection_info().host().to_string();
+    let source = req
+        .headers()
+        .get(ORIGIN)
+        .or_else(|| req.headers().get(REFERER));
+    if let Some(source) = source {
+        let source = source.to_str().map_err(|_| CsrfError::CrossOrigin)?;
+        if host_of(source) != Some(host.as_str()) {
+            return Err(CsrfError::CrossOrigin);
+        }
+    }
+
+    let candidate = match req.headers().get(CSRF_HEADER) {
+        Some(header) => Some(
+            header
+                .to_str()
+                .map_err(|_| CsrfError::InvalidToken)?
+                .to_string(),
+        ),
+        None => form_token(req).await?,
+    };
+    let candidate = candidate.ok_or(CsrfError::MissingToken)?;
+
+    let session = TypedSession::extract(req.parts_mut().0)
+        .await
+        .map_err(|e| CsrfError::Unexpected(anyhow::anyhow!("{e}")))?;
+    let expected = session
+        .get_csrf_token()
+        .map_err(|e| CsrfError::Unexpected(e.into()))?
+        .ok_or(CsrfError::InvalidToken)?;
+
+    if !constant_time_eq(expected.as_bytes(), candidate.as_bytes()) {
+        return Err(CsrfError::InvalidToken);
+    }
+    Ok(())
+}
+
+/// Reads the token out of an url encoded body, then puts the body back for
+/// the handler.
+async fn form_token(req: &mut ServiceRequest) -> Result<Option<String>, CsrfError> {
+    let (http_req, payload) = req.parts_mut();
+    let bytes = web::Bytes::from_request(http_req, payload)
+        .await
+        .map_err(|e| CsrfError::Unexpected(anyhow::anyhow!("failed to read the body: {e}")))?;
+
+    let form = serde_urlencoded::from_bytes::<CsrfForm>(&bytes)
+        .map(|f| f.csrf_token)
+        .unwrap_or_default();
+
+    let (_, mut restored) = actix_http::h1::Payload::create(true);
+    restored.unread_data(bytes);
+    req.set_payload(restored.into());
+
+    Ok(form)
+}
+
+/// The token of the session the response belongs to, created on first use.
+async fn session_token(req: &actix_web::HttpRequest) -> Result<String, anyhow::Error> {
+    let session = TypedSession::extract(req)
+        .await
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    if let Some(token) = session.get_csrf_token()? {
+        return Ok(token);
+    }
+
+    let token = generate_csrf_token();
+    session.insert_csrf_token(&token)?;
+    Ok(token)
+}
+
+fn generate_csrf_token() -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(32)
+        .collect()
+}
+
+/// Adds a hidden token input at the start of every form submitted with `post`.
+fn inject_token(html: &str, token: &str) -> String {
+    let input = format!(r#"<input type="hidden" name="{CSRF_FIELD}" value="{token}" />"#);
+    let mut result = String::with_capacity(html.len() + input.len());
+    let mut copied = 0;
+    for (end, post) in form_tags(html) {
+        if post {
+            result.push_str(&html[copied..end]);
+            result.push_str(&input);
+            copied = end;
+        }
+    }
+    result.push_str(&html[copied..]);
+    result
+}
+
+/// Where each `<form>` tag of the page ends and whether the form is
+/// submitted with `post`.
+fn form_tags(html: &str) -> Vec<(usize, bool)> {
+    let mut tags = Vec::new();
+    let mut offset = 0;
+    while let Some(found) = html[offset..].find("<form") {
+        offset += found + "<form".len();
+        let tag = &html[offset..];
+        if !tag.starts_with(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/') {
+            continue;
+        }
+        match form_method_is_post(tag) {
+            Some((end, post)) => {
+                offset += end;
+                tags.push((offset, post));
+            }
+            None => break,
+        }
+    }
+    tags
+}
+
+/// Reads the attributes of a tag the way browsers do, whatever their quoting
+/// and spacing. Returns where the tag ends and whether its method is `post`,
+/// nothing if it never ends.
+fn form_method_is_post(tag: &str) -> Option<(usize, bool)> {
+    let bytes = tag.as_bytes();
+    let skip = |mut i: usize, while_: fn(u8) -> bool| {
+        while i < bytes.len() && while_(bytes[i]) {
+            i += 1;
+        }
+        i
+    };
+    let mut post = false;
+    let mut i = 0;
+    loop {
+        i = skip(i, |b| b.is_ascii_whitespace() || b == b'/');
+        if *bytes.get(i)? == b'>' {
+            return Some((i + 1, post));
+        }
+        let name_start = i;
+        i = skip(i, |b| {
+            !b.is_ascii_whitespace() && !matches!(b, b'=' | b'>' | b'/')
+        });
+        let name = &tag[name_start..i];
+        i = skip(i, |b| b.is_ascii_whitespace());
+        if bytes.get(i) != Some(&b'=') {
+            continue;
+        }
+        i = skip(i + 1, |b| b.is_ascii_whitespace());
+        let value = match *bytes.get(i)? {
+            quote @ (b'"' | b'\'') => {
+                let start = i + 1;
+                let length = tag[start..].find(char::from(quote))?;
+                i = start + length + 1;
+                &tag[start..start + length]
+            }
+            _ => {
+                let start = i;
+                i = skip(i, |b| !b.is_ascii_whitespace() && b != b'>');
+                &tag[start..i]
+            }
+        };
+        if name.eq_ignore_ascii_case("method") {
+            post = value.trim().eq_ignore_ascii_case("post");
+        }
+    }
+}
+
+/// The `host[:port]` part of an origin or URL.
+fn host_of(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let host = rest.split(['/', '?', '#']).next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{constant_time_eq, host_of, inject_token};
+
+    #[test]
+    fn token_is_injected_in_post_forms_only() {
+        let html = r#"<form action="/a" method="post"><input name="x" /></form>
+<form action="/b" method="get"></form>"#;
+        let result = inject_token(html, "abc");
+
+        assert_eq!(
+            result,
+            r#"<form action="/a" method="post"><input type="hidden" name="csrf_token" value="abc" /><input name="x" /></form>
+<form action="/b" method="get"></form>"#
+        );
+    }
+
+    #[test]
+    fn method_is_read_whatever_its_quoting_and_spacing() {
+        for form in [
+            r#"<form method='post'>"#,
+            r#"<form method=post>"#,
+            r#"<form method = "post">"#,
+            r#"<form action="/a"
+                METHOD="Post">"#,
+        ] {
+            assert_eq!(
+                inject_token(form, "abc"),
+                format!(r#"{form}<input type="hidden" name="csrf_token" value="abc" />"#)
+            );
+        }
+    }
+
+    #[test]
+    fn method_in_another_attribute_is_ignored() {
+        let html = r#"<form method="get" data-note="method='post' > here"></form><formula>"#;
+        assert_eq!(inject_token(html, "abc"), html);
+    }
+
+    #[test]
+    fn html_without_forms_is_unchanged() {
+        let html = "<p>no forms here</p>";
+        assert_eq!(inject_token(html, "abc"), html);
+    }
+
+    #[test]
+    fn host_is_extracted_from_origin_and_referer() {
+        assert_eq!(host_of("http://localhost:8000"), Some("localhost:8000"));
+        assert_eq!(
+            host_of("https://apoll.example/poll/1?invite=x"),
+            Some("apoll.example")
+        );
+        assert_eq!(host_of("null"), None);
+        assert_eq!(host_of("http://"), None);
+    }
+
+    #[test]
+    fn tokens_are_compared_in_full() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"token2"));
+    }
+}
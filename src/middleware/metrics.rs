@@ -0,0 +1,34 @@
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::web;
+use actix_web_lab::middleware::Next;
+
+use crate::metrics::Metrics;
+
+/// Counts and times every request by the route it matched.
+pub async fn record_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let metrics = match req.app_data::<web::Data<Metrics>>() {
+        Some(metrics) => metrics.clone(),
+        None => return next.call(req).await,
+    };
+    let method = req.method().to_string();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let start = Instant::now();
+    let _in_flight = metrics.request_started();
+    let result = next.call(req).await;
+    let status = match &result {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    metrics.observe_request(&method, &route, status.as_u16(), start.elapsed());
+
+    result
+}
@@ -1,3 +1,15 @@
+mod admin;
+mod csrf;
+mod metrics;
+mod poll_member;
+mod rate_limit;
+mod signing_keys;
 mod validate_poll;
 
+pub use admin::*;
+pub use csrf::*;
+pub use metrics::*;
+pub use poll_member::*;
+pub use rate_limit::*;
+pub use signing_keys::*;
 pub use validate_poll::*;
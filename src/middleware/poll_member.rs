@@ -0,0 +1,156 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, ResponseError};
+use anyhow::Context;
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use super::PollInfo;
+use crate::domain::PollRole;
+use crate::storage::{Member, Storage};
+use crate::user_session::TypedSession;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AuthorizationError {
+    #[error("You must join the poll first")]
+    NotAMember,
+    #[error("You need to be {} of the poll to do this", article(.0))]
+    InsufficientRole(PollRole),
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+fn article(role: &PollRole) -> String {
+    match role {
+        PollRole::Observer | PollRole::Owner => format!("an {role}"),
+        PollRole::Participant | PollRole::CoOrganizer => format!("a {role}"),
+    }
+}
+
+impl ResponseError for AuthorizationError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            AuthorizationError::NotAMember => StatusCode::UNAUTHORIZED,
+            AuthorizationError::InsufficientRole(_) => StatusCode::FORBIDDEN,
+            AuthorizationError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// The logged in user of the current poll, along with their role in it.
+#[derive(Debug, Clone)]
+pub struct PollMember {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: PollRole,
+}
+
+impl FromRequest for PollMember {
+    type Error = AuthorizationError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            if let Some(member) = req.extensions().get::<PollMember>() {
+                return Ok(member.clone());
+            }
+
+            let poll_info = PollInfo::extract(&req)
+                .await
+                .map_err(|e| anyhow::anyhow!("{e}"))?;
+            let session = TypedSession::extract(&req)
+                .await
+                .map_err(|e| anyhow::anyhow!("{e}"))?;
+            let user_id = session
+                .get_user_id()
+                .context("failed to retrieve user_id from session store")?
+                .ok_or(AuthorizationError::NotAMember)?;
+
+            let storage = req
+                .app_data::<web::Data<dyn Storage>>()
+                .context("missing storage")?;
+            let member: PollMember = storage
+                .find_member(&poll_info.poll_id, &user_id)
+                .await
+                .context("failed to retrieve poll member")?
+                .ok_or(AuthorizationError::NotAMember)?
+                .into();
+
+            req.extensions_mut().insert(member.clone());
+            Ok(member)
+        })
+    }
+}
+
+impl From<Member> for PollMember {
+    fn from(member: Member) -> Self {
+        PollMember {
+            user_id: member.user_id,
+            username: member.username,
+            role: member.role,
+        }
+    }
+}
+
+/// The minimum role a handler requires, see [`Authorized`].
+pub trait RequiredRole {
+    const ROLE: PollRole;
+}
+
+/// Marker types for [`Authorized`].
+pub mod require {
+    use super::RequiredRole;
+    use crate::domain::PollRole;
+
+    pub struct Participant;
+    pub struct CoOrganizer;
+    pub struct Owner;
+
+    impl RequiredRole for Participant {
+        const ROLE: PollRole = PollRole::Participant;
+    }
+
+    impl RequiredRole for CoOrganizer {
+        const ROLE: PollRole = PollRole::CoOrganizer;
+    }
+
+    impl RequiredRole for Owner {
+        const ROLE: PollRole = PollRole::Owner;
+    }
+}
+
+/// A [`PollMember`] whose role is at least `R`, e.g. `Authorized<require::Owner>`.
+pub struct Authorized<R: RequiredRole> {
+    pub member: PollMember,
+    _role: PhantomData<R>,
+}
+
+impl<R: RequiredRole> std::ops::Deref for Authorized<R> {
+    type Target = PollMember;
+
+    fn deref(&self) -> &Self::Target {
+        &self.member
+    }
+}
+
+impl<R: RequiredRole + 'static> FromRequest for Authorized<R> {
+    type Error = AuthorizationError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let member = PollMember::from_request(req, payload);
+        Box::pin(async move {
+            let member = member.await?;
+            if member.role < R::ROLE {
+                return Err(AuthorizationError::InsufficientRole(R::ROLE));
+            }
+            Ok(Authorized {
+                member,
+                _role: PhantomData,
+            })
+        })
+    }
+}
@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::StatusCode;
+use actix_web::{web, FromRequest, HttpResponse, ResponseError};
+use actix_web_lab::middleware::Next;
+
+use crate::configuration::{ActionLimits, RateLimitSettings};
+use crate::rate_limit::RateLimiter;
+use crate::user_session::TypedSession;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RateLimitError {
+    #[error("Too many requests, try again in {} seconds", retry_after_secs(.0))]
+    TooManyRequests(Duration),
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl ResponseError for RateLimitError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            RateLimitError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            RateLimitError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let RateLimitError::TooManyRequests(retry_after) = self {
+            response.insert_header((RETRY_AFTER, retry_after_secs(retry_after).to_string()));
+        }
+        response.body(self.to_string())
+    }
+}
+
+fn retry_after_secs(retry_after: &Duration) -> u64 {
+    // Round up so that clients don't come back a moment too early
+    let partial_second = retry_after.subsec_nanos() > 0;
+    retry_after.as_secs().saturating_add(partial_second.into())
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Action {
+    CreatePoll,
+    JoinPoll,
+    Suggest,
+    AdminLogin,
+}
+
+impl Action {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Action::CreatePoll => "create_poll",
+            Action::JoinPoll => "join_poll",
+            Action::Suggest => "suggest",
+            Action::AdminLogin => "admin_login",
+        }
+    }
+
+    fn limits<'a>(&self, settings: &'a RateLimitSettings) -> &'a ActionLimits {
+        match self {
+            Action::CreatePoll => &settings.create_poll,
+            Action::JoinPoll => &settings.join_poll,
+            Action::Suggest => &settings.suggest,
+            Action::AdminLogin => &settings.admin_login,
+        }
+    }
+}
+
+pub async fn limit_poll_creation(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    rate_limit(Action::CreatePoll, req, next).await
+}
+
+pub async fn limit_joins(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    rate_limit(Action::JoinPoll, req, next).await
+}
+
+pub async fn limit_suggestions(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    rate_limit(Action::Suggest, req, next).await
+}
+
+pub async fn limit_admin_logins(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    rate_limit(Action::AdminLogin, req, next).await
+}
+
+/// Takes a token from every bucket configured for `action` if none of them
+/// are empty and only then lets the request through.
+#[tracing::instrument(name = "rate limit middleware", skip(req, next))]
+async fn rate_limit<B: MessageBody>(
+    action: Action,
+    mut req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    if let Err(e) = take_tokens(action, &mut req).await {
+        if let RateLimitError::TooManyRequests(_) = e {
+            tracing::warn!(error = %e, "rejected request over the rate limit");
+        }
+        return Ok(req.error_response(e).map_into_right_body());
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+async fn take_tokens(action: Action, req: &mut ServiceRequest) -> Result<(), RateLimitError> {
+    let settings = req
+        .app_data::<web::Data<RateLimitSettings>>()
+        .ok_or_else(|| anyhow::anyhow!("rate limit settings are missing"))?
+        .clone();
+    let limiter = req
+        .app_data::<web::Data<RateLimiter>>()
+        .ok_or_else(|| anyhow::anyhow!("rate limiter is missing"))?
+        .clone();
+    let limits = action.limits(&settings);
+
+    let mut buckets = Vec::new();
+    if let Some(bucket) = limits.per_ip {
+        let ip = req
+            .peer_addr()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|| "unknown".into());
+        buckets.push((format!("{}:ip:{ip}", action.as_str()), bucket));
+    }
+    if let Some(bucket) = limits.per_session {
+        let session = TypedSession::extract(req.parts_mut().0)
+            .await
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        let client_id = session
+            .get_or_insert_client_id()
+            .map_err(|e| RateLimitError::Unexpected(e.into()))?;
+        buckets.push((format!("{}:session:{client_id}", action.as_str()), bucket));
+    }
+    if let Some(bucket) = limits.per_poll {
+        let poll_id = req.match_info().query("poll_id");
+        if !poll_id.is_empty() {
+            buckets.push((format!("{}:poll:{poll_id}", action.as_str()), bucket));
+        }
+    }
+
+    limiter
+        .check(&buckets)
+        .await
+        .map_err(RateLimitError::TooManyRequests)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::retry_after_secs;
+
+    #[test]
+    fn retry_after_is_rounded_up_to_whole_seconds() {
+        assert_eq!(retry_after_secs(&Duration::from_millis(1)), 1);
+        assert_eq!(retry_after_secs(&Duration::from_millis(1000)), 1);
+        assert_eq!(retry_after_secs(&Duration::from_millis(1001)), 2);
+        assert_eq!(retry_after_secs(&Duration::MAX), u64::MAX);
+    }
+}
@@ -0,0 +1,81 @@
+use actix_session::SessionExt;
+use actix_web::body::MessageBody;
+use actix_web::cookie::Cookie;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, COOKIE};
+use actix_web::{web, HttpMessage};
+use actix_web_lab::middleware::Next;
+use chrono::Utc;
+
+use crate::signing_keys::{Seal, SigningKeys};
+
+/// Marks requests whose session cookie was sealed with a previous key.
+#[derive(Clone, Copy)]
+struct ResealedSession;
+
+/// Replaces the cookies sealed with a previous key by the same cookies
+/// sealed with the current one, before the session and flash messages read
+/// them. Has to wrap the session middleware.
+pub async fn reseal_cookies(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let keys = match req.app_data::<web::Data<SigningKeys>>() {
+        Some(keys) => keys.clone(),
+        None => return next.call(req).await,
+    };
+
+    // Parsing the header ourselves, `HttpRequest::cookies` would cache the
+    // cookies before they were replaced
+    let now = Utc::now();
+    let mut resealed_any = false;
+    let cookies: Vec<_> = req
+        .headers()
+        .get_all(COOKIE)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| Cookie::parse_encoded(pair.trim().to_owned()).ok())
+        .map(|cookie| {
+            let resealed = keys
+                .seal_of(cookie.name())
+                .and_then(|seal| keys.reseal(&cookie, seal, now).map(|c| (seal, c)));
+            match resealed {
+                Some((seal, resealed)) => {
+                    resealed_any = true;
+                    if seal == Seal::Private {
+                        req.extensions_mut().insert(ResealedSession);
+                    }
+                    resealed
+                }
+                None => cookie,
+            }
+        })
+        .collect();
+
+    if resealed_any {
+        let header = cookies
+            .iter()
+            .map(|cookie| cookie.encoded().to_string())
+            .collect::<Vec<_>>()
+            .join("; ");
+        if let Ok(header) = HeaderValue::from_str(&header) {
+            req.headers_mut().insert(COOKIE, header);
+        }
+    }
+
+    next.call(req).await
+}
+
+/// Sends the session cookie back sealed with the current key when it was
+/// resealed by `reseal_cookies`, otherwise the browser would keep sending the
+/// old one. Has to be wrapped by the session middleware.
+pub async fn renew_resealed_session(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if req.extensions().contains::<ResealedSession>() {
+        req.get_session().renew();
+    }
+
+    next.call(req).await
+}
@@ -1,17 +1,32 @@
 use std::future::{ready, Ready};
 
-use actix_web::body::MessageBody;
+use actix_web::body::{EitherBody, MessageBody};
 use actix_web::dev::{ServiceRequest, ServiceResponse};
-use actix_web::{web, FromRequest, HttpMessage};
+use actix_web::http::header::ContentType;
+use actix_web::http::Method;
+use actix_web::{web, FromRequest, HttpMessage, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
 use actix_web_lab::middleware::Next;
+use secrecy::Secret;
 use serde::Deserialize;
-use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::domain::{is_well_formed_invite_token, ExportAccess};
+use crate::poll_cache::PollCache;
+use crate::storage::{Poll, Storage};
+use crate::user_session::TypedSession;
+
 #[derive(Deserialize, Clone)]
 pub struct PollInfo {
     pub poll_id: Uuid,
+    pub creator_id: Uuid,
     pub prompt: String,
+    pub invite_only: bool,
+    pub passphrase_hash: Option<Secret<String>>,
+    pub require_proof_of_work: bool,
+    /// The content filters this poll uses in place of the site's.
+    pub content_filter: Option<serde_json::Value>,
+    pub export_access: ExportAccess,
 }
 
 impl FromRequest for PollInfo {
@@ -33,38 +48,113 @@ impl FromRequest for PollInfo {
 
 #[tracing::instrument(name = "validate poll id middleware", skip_all)]
 pub async fn validate_poll_id(
-    req: ServiceRequest,
+    mut req: ServiceRequest,
     next: Next<impl MessageBody>,
-) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
     let poll_id = Uuid::parse_str(req.match_info().query("poll_id")).map_err(e404)?;
-    let db_pool = req.app_data::<web::Data<PgPool>>().unwrap();
+    let poll_cache = req.app_data::<web::Data<PollCache>>().cloned();
+
+    let poll_info = match poll_cache.as_ref().and_then(|cache| cache.get(&poll_id)) {
+        Some(poll_info) => poll_info,
+        None => {
+            let storage = req.app_data::<web::Data<dyn Storage>>().unwrap();
+            let poll_info: PollInfo = storage
+                .find_poll(&poll_id)
+                .await
+                .map_err(e404)?
+                .ok_or_else(|| e404(anyhow::anyhow!("could not find poll_id: {}", poll_id)))?
+                .into();
+            if let Some(cache) = &poll_cache {
+                cache.insert(poll_info.clone());
+            }
+            poll_info
+        }
+    };
+
+    // Passphrase protected polls only let through the unlock form until the
+    // passphrase has been entered in this session
+    if poll_info.passphrase_hash.is_some() && req.match_info().unprocessed() != "/unlock" {
+        let session = TypedSession::extract(req.parts_mut().0).await?;
+        let unlocked = session
+            .is_poll_unlocked(&poll_id)
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        if !unlocked {
+            let response = locked_poll_response(&mut req, &poll_info).await?;
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
 
-    let prompt = find_poll(db_pool, poll_id)
+    req.extensions_mut().insert(poll_info);
+    next.call(req)
         .await
-        .map_err(e404)?
-        .ok_or_else(|| e404(anyhow::anyhow!("could not find poll_id: {}", poll_id)))?;
+        .map(ServiceResponse::map_into_left_body)
+}
 
-    req.extensions_mut().insert(PollInfo { poll_id, prompt });
-    next.call(req).await
+async fn locked_poll_response(
+    req: &mut ServiceRequest,
+    poll_info: &PollInfo,
+) -> Result<HttpResponse, actix_web::Error> {
+    if req.method() != Method::GET {
+        return Err(actix_web::error::ErrorForbidden(
+            "this poll is protected by a passphrase",
+        ));
+    }
+
+    let flash_messages = IncomingFlashMessages::extract(req.parts_mut().0).await?;
+    let messages_html = flash_messages
+        .iter()
+        .map(|m| format!("<p><i>{}</i></p>", m.content()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let invite_input = web::Query::<LockedPollQuery>::from_query(req.query_string())
+        .ok()
+        .and_then(|q| q.0.invite)
+        .filter(|token| is_well_formed_invite_token(token))
+        .map(|token| format!(r#"<input type="hidden" name="invite" value="{token}" />"#))
+        .unwrap_or_default();
+
+    let poll_id = poll_info.poll_id;
+    Ok(HttpResponse::Unauthorized()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Apoll | Protected poll</title>
+    </head>
+    <body>
+        {messages_html}
+        <h1>This poll is protected by a passphrase</h1>
+        <form action="/poll/{poll_id}/unlock" method="post">
+            {invite_input}
+            <input type="password" placeholder="Passphrase" name="passphrase" />
+            <button type="submit">Unlock</button>
+        </form>
+    </body>
+</html>"#
+        )))
 }
 
-#[tracing::instrument(
-    name = "find poll in database"
-    skip(db_poll),
-)]
-async fn find_poll(db_poll: &PgPool, poll_id: Uuid) -> Result<Option<String>, sqlx::Error> {
-    let result = sqlx::query!(
-        r#"
-        SELECT prompt
-        FROM polls
-        WHERE poll_id = $1
-        "#,
-        poll_id
-    )
-    .fetch_optional(db_poll)
-    .await?;
-
-    Ok(result.map(|r| r.prompt))
+#[derive(Deserialize)]
+struct LockedPollQuery {
+    invite: Option<String>,
+}
+
+impl From<Poll> for PollInfo {
+    fn from(poll: Poll) -> Self {
+        PollInfo {
+            poll_id: poll.poll_id,
+            creator_id: poll.creator_id,
+            prompt: poll.prompt,
+            invite_only: poll.invite_only,
+            passphrase_hash: poll.passphrase_hash,
+            require_proof_of_work: poll.require_proof_of_work,
+            content_filter: poll.content_filter,
+            export_access: poll.export_access,
+        }
+    }
 }
 
 fn e404<T>(e: T) -> actix_web::Error
@@ -0,0 +1,155 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::configuration::PollCacheSettings;
+use crate::middleware::PollInfo;
+
+/// The polls `validate_poll_id` looked up recently, so that every request
+/// under `/poll/{poll_id}` doesn't query the database again. Entries expire
+/// after the TTL and the oldest are evicted once the cache is full.
+#[derive(Clone)]
+pub struct PollCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Arc<Mutex<Entries>>,
+}
+
+#[derive(Default)]
+struct Entries {
+    polls: HashMap<Uuid, (Instant, PollInfo)>,
+    /// When each poll was inserted, oldest first. Polls invalidated or
+    /// inserted again since are skipped when their turn comes.
+    insertions: VecDeque<(Instant, Uuid)>,
+}
+
+impl PollCache {
+    pub fn new(settings: &PollCacheSettings) -> Self {
+        Self {
+            capacity: settings.capacity,
+            ttl: Duration::from_secs(settings.ttl_seconds),
+            entries: Arc::default(),
+        }
+    }
+
+    pub fn get(&self, poll_id: &Uuid) -> Option<PollInfo> {
+        let mut entries = self.entries();
+        match entries.polls.get(poll_id) {
+            Some((inserted_at, poll_info)) if inserted_at.elapsed() < self.ttl => {
+                Some(poll_info.clone())
+            }
+            Some(_) => {
+                entries.polls.remove(poll_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, poll_info: PollInfo) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries();
+        while entries.polls.len() >= self.capacity {
+            let Some((inserted_at, poll_id)) = entries.insertions.pop_front() else {
+                break;
+            };
+            if entries
+                .polls
+                .get(&poll_id)
+                .is_some_and(|(current, _)| *current == inserted_at)
+            {
+                entries.polls.remove(&poll_id);
+            }
+        }
+
+        let now = Instant::now();
+        entries.insertions.push_back((now, poll_info.poll_id));
+        entries.polls.insert(poll_info.poll_id, (now, poll_info));
+        // Invalidated polls leave their insertion behind, don't let them pile up
+        if entries.insertions.len() > 2 * self.capacity {
+            let Entries { polls, insertions } = &mut *entries;
+            insertions.retain(|(inserted_at, poll_id)| {
+                polls
+                    .get(poll_id)
+                    .is_some_and(|(current, _)| current == inserted_at)
+            });
+        }
+    }
+
+    /// Called after the poll changed or was deleted.
+    pub fn invalidate(&self, poll_id: &Uuid) {
+        self.entries().polls.remove(poll_id);
+    }
+
+    /// Called when changes may have been missed.
+    pub fn clear(&self) {
+        let mut entries = self.entries();
+        entries.polls.clear();
+        entries.insertions.clear();
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::PollCache;
+    use crate::configuration::PollCacheSettings;
+    use crate::domain::ExportAccess;
+    use crate::middleware::PollInfo;
+
+    fn cache(capacity: usize, ttl_seconds: u64) -> PollCache {
+        PollCache::new(&PollCacheSettings {
+            capacity,
+            ttl_seconds,
+        })
+    }
+
+    fn poll() -> PollInfo {
+        PollInfo {
+            poll_id: Uuid::new_v4(),
+            creator_id: Uuid::new_v4(),
+            prompt: "Lunch?".into(),
+            invite_only: false,
+            passphrase_hash: None,
+            require_proof_of_work: false,
+            content_filter: None,
+            export_access: ExportAccess::default(),
+        }
+    }
+
+    #[test]
+    fn the_oldest_polls_are_evicted_once_full() {
+        let cache = cache(2, 60);
+        let polls = [poll(), poll(), poll()];
+        for poll in &polls {
+            cache.insert(poll.clone());
+        }
+
+        assert!(cache.get(&polls[0].poll_id).is_none());
+        assert!(cache.get(&polls[1].poll_id).is_some());
+        assert!(cache.get(&polls[2].poll_id).is_some());
+    }
+
+    #[test]
+    fn invalidated_and_expired_polls_are_looked_up_again() {
+        let cache = cache(10, 60);
+        let poll = poll();
+        cache.insert(poll.clone());
+        cache.invalidate(&poll.poll_id);
+        assert!(cache.get(&poll.poll_id).is_none());
+
+        let expired = self::cache(10, 0);
+        expired.insert(poll.clone());
+        assert!(expired.get(&poll.poll_id).is_none());
+    }
+}
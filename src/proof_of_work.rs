@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use hmac::{Hmac, Mac};
+use rand::{thread_rng, RngCore};
+use redis::aio::ConnectionManager;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+
+use crate::configuration::ProofOfWorkSettings;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Solves the challenge of the form it's in right before it's submitted.
+const SOLVER_SCRIPT: &str = r#"<script>
+document.currentScript.parentElement.addEventListener("submit", async function (event) {
+    const form = event.target;
+    const solution = form.elements["pow_solution"];
+    if (solution.value !== "") {
+        return;
+    }
+    event.preventDefault();
+
+    const challenge = form.elements["pow_challenge"].value;
+    const difficulty = parseInt(challenge.split(":")[1], 10);
+    const encoder = new TextEncoder();
+    const leadingZeroBits = function (bytes) {
+        let bits = 0;
+        for (const byte of bytes) {
+            if (byte === 0) {
+                bits += 8;
+                continue;
+            }
+            return bits + Math.clz32(byte) - 24;
+        }
+        return bits;
+    };
+    for (let n = 0; ; n++) {
+        const digest = await crypto.subtle.digest("SHA-256", encoder.encode(challenge + ":" + n));
+        if (leadingZeroBits(new Uint8Array(digest)) >= difficulty) {
+            solution.value = n;
+            break;
+        }
+    }
+    form.submit();
+});
+</script>"#;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ProofOfWorkError {
+    #[error("The anti-spam check didn't run, please make sure JavaScript is enabled.")]
+    Missing,
+    #[error("The anti-spam check expired, please submit the form again.")]
+    Expired,
+    #[error("The anti-spam check failed, please submit the form again.")]
+    Invalid,
+    #[error("The anti-spam check was already used, please submit the form again.")]
+    Replayed,
+}
+
+/// Stateless proof-of-work challenges: every challenge is signed with the
+/// HMAC secret and carries the time it was issued, so the server only has to
+/// check the signature, the age and the work done. The challenges solved
+/// are remembered until they expire so that each can only be used once.
+pub struct ProofOfWork {
+    hmac_secret: Secret<String>,
+    settings: ProofOfWorkSettings,
+    solved: SolvedChallenges,
+}
+
+impl ProofOfWork {
+    pub fn new(
+        hmac_secret: Secret<String>,
+        settings: ProofOfWorkSettings,
+        redis: Option<ConnectionManager>,
+    ) -> Self {
+        Self {
+            hmac_secret,
+            settings,
+            solved: SolvedChallenges {
+                redis,
+                fallback: Mutex::default(),
+            },
+        }
+    }
+
+    pub fn required_for_new_poll(&self) -> bool {
+        self.settings.required_for_new_poll
+    }
+
+    pub fn required_for_join(&self, poll_requires_it: bool) -> bool {
+        self.settings.required_for_join || poll_requires_it
+    }
+
+    /// Hidden inputs with a new challenge for `purpose` and the script
+    /// solving it, to put inside a form.
+    pub fn form_fields(&self, purpose: &str) -> String {
+        let challenge = self.challenge(purpose, chrono::Utc::now().timestamp());
+        format!(
+            r#"<input type="hidden" name="pow_challenge" value="{challenge}" />
+<input type="hidden" name="pow_solution" value="" />
+{SOLVER_SCRIPT}"#
+        )
+    }
+
+    /// A challenge is only accepted once and for the `purpose` it was issued
+    /// for, like joining a specific poll.
+    pub async fn verify(
+        &self,
+        purpose: &str,
+        challenge: Option<&str>,
+        solution: Option<&str>,
+    ) -> Result<(), ProofOfWorkError> {
+        let (challenge, solution) = match (challenge, solution) {
+            (Some(challenge), Some(solution)) if !solution.is_empty() => (challenge, solution),
+            _ => return Err(ProofOfWorkError::Missing),
+        };
+        let now = chrono::Utc::now().timestamp();
+        self.verify_at(purpose, challenge, solution, now)?;
+
+        // The signature is what tells challenges apart
+        let signature = challenge.rsplit_once(':').map_or(challenge, |(_, s)| s);
+        let expires_at = now + self.settings.window_seconds;
+        if !self.solved.first_use(signature, expires_at, now).await {
+            return Err(ProofOfWorkError::Replayed);
+        }
+        Ok(())
+    }
+
+    /// `v1:{difficulty}:{issued_at}:{salt}:{signature}`
+    fn challenge(&self, purpose: &str, issued_at: i64) -> String {
+        let mut salt = [0u8; 16];
+        thread_rng().fill_bytes(&mut salt);
+        let unsigned = format!(
+            "v1:{}:{issued_at}:{}",
+            self.settings.difficulty,
+            hex::encode(salt)
+        );
+        let signature = hex::encode(self.mac(purpose, &unsigned).finalize().into_bytes());
+        format!("{unsigned}:{signature}")
+    }
+
+    fn verify_at(
+        &self,
+        purpose: &str,
+        challenge: &str,
+        solution: &str,
+        now: i64,
+    ) -> Result<(), ProofOfWorkError> {
+        let (unsigned, signature) = challenge
+            .rsplit_once(':')
+            .ok_or(ProofOfWorkError::Invalid)?;
+        let signature = hex::decode(signature).map_err(|_| ProofOfWorkError::Invalid)?;
+        self.mac(purpose, unsigned)
+            .verify_slice(&signature)
+            .map_err(|_| ProofOfWorkError::Invalid)?;
+
+        // The signature is valid, so the fields were written by this server
+        let mut parts = unsigned.split(':').skip(1);
+        let difficulty = parts.next().and_then(|d| d.parse::<u8>().ok());
+        let issued_at = parts.next().and_then(|t| t.parse::<i64>().ok());
+        let (difficulty, issued_at) = difficulty.zip(issued_at).ok_or(ProofOfWorkError::Invalid)?;
+
+        if now < issued_at || now - issued_at > self.settings.window_seconds {
+            return Err(ProofOfWorkError::Expired);
+        }
+        if solution.parse::<u64>().is_err() || !has_difficulty(challenge, solution, difficulty) {
+            return Err(ProofOfWorkError::Invalid);
+        }
+        Ok(())
+    }
+
+    fn mac(&self, purpose: &str, unsigned: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(self.hmac_secret.expose_secret().as_bytes())
+            .expect("HMAC can take a key of any size");
+        mac.update(b"proof_of_work\0");
+        mac.update(purpose.as_bytes());
+        mac.update(b"\0");
+        mac.update(unsigned.as_bytes());
+        mac
+    }
+}
+
+fn has_difficulty(challenge: &str, solution: &str, difficulty: u8) -> bool {
+    let digest = Sha256::digest(format!("{challenge}:{solution}").as_bytes());
+    leading_zero_bits(&digest) >= u32::from(difficulty)
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        if *byte != 0 {
+            return bits + byte.leading_zeros();
+        }
+        bits += 8;
+    }
+    bits
+}
+
+/// The signatures of the challenges solved, shared by every instance of the
+/// application through Redis. Whenever Redis can't be reached the ones seen
+/// by this instance are used instead, like `RateLimiter` does.
+struct SolvedChallenges {
+    redis: Option<ConnectionManager>,
+    /// When each signature can be forgotten.
+    fallback: Mutex<HashMap<String, i64>>,
+}
+
+impl SolvedChallenges {
+    /// The most signatures kept in memory, the ones expiring first make room
+    /// for new ones.
+    const MAX_SIGNATURES: usize = 10_000;
+
+    /// Remembers `signature` until `expires_at`, returns whether it was the
+    /// first time it was seen.
+    async fn first_use(&self, signature: &str, expires_at: i64, now: i64) -> bool {
+        if let Some(redis) = &self.redis {
+            match first_use_in_redis(redis.clone(), signature, expires_at - now).await {
+                Ok(first) => return first,
+                Err(e) => {
+                    tracing::warn!(error = %e, "solved challenge check failed in Redis, using memory instead")
+                }
+            }
+        }
+        self.first_use_in_memory(signature, expires_at, now)
+    }
+
+    fn first_use_in_memory(&self, signature: &str, expires_at: i64, now: i64) -> bool {
+        let mut solved = self.fallback.lock().unwrap();
+        if solved.get(signature).is_some_and(|&until| until >= now) {
+            return false;
+        }
+        if solved.len() >= Self::MAX_SIGNATURES {
+            solved.retain(|_, &mut until| until >= now);
+        }
+        if solved.len() >= Self::MAX_SIGNATURES {
+            let first_to_expire = solved
+                .iter()
+                .min_by_key(|(_, &until)| until)
+                .map(|(signature, _)| signature.clone());
+            if let Some(signature) = first_to_expire {
+                solved.remove(&signature);
+            }
+        }
+        solved.insert(signature.to_string(), expires_at);
+        true
+    }
+}
+
+async fn first_use_in_redis(
+    mut redis: ConnectionManager,
+    signature: &str,
+    ttl_seconds: i64,
+) -> Result<bool, redis::RedisError> {
+    let set: Option<String> = redis::cmd("SET")
+        .arg(format!("proof_of_work:solved:{signature}"))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(ttl_seconds.max(1))
+        .query_async(&mut redis)
+        .await?;
+    Ok(set.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::assert_ok;
+    use secrecy::Secret;
+
+    use super::{has_difficulty, leading_zero_bits, ProofOfWork, ProofOfWorkError};
+    use crate::configuration::ProofOfWorkSettings;
+
+    const NOW: i64 = 1_659_000_000;
+
+    fn proof_of_work() -> ProofOfWork {
+        ProofOfWork::new(
+            Secret::new("secret".into()),
+            ProofOfWorkSettings {
+                difficulty: 8,
+                window_seconds: 60,
+                required_for_new_poll: true,
+                required_for_join: false,
+            },
+            None,
+        )
+    }
+
+    /// Does the work a browser would, the way the solver script does.
+    fn solve(challenge: &str) -> String {
+        (0..)
+            .map(|n: u64| n.to_string())
+            .find(|n| has_difficulty(challenge, n, 8))
+            .unwrap()
+    }
+
+    #[test]
+    fn solved_challenge_is_accepted() {
+        let pow = proof_of_work();
+        let challenge = pow.challenge("new_poll", NOW);
+        let solution = solve(&challenge);
+
+        assert_ok!(pow.verify_at("new_poll", &challenge, &solution, NOW + 10));
+    }
+
+    #[test]
+    fn challenge_is_bound_to_its_purpose() {
+        let pow = proof_of_work();
+        let challenge = pow.challenge("join:a", NOW);
+        let solution = solve(&challenge);
+
+        assert_eq!(
+            pow.verify_at("join:b", &challenge, &solution, NOW),
+            Err(ProofOfWorkError::Invalid)
+        );
+    }
+
+    #[test]
+    fn challenge_expires_after_the_window() {
+        let pow = proof_of_work();
+        let challenge = pow.challenge("new_poll", NOW);
+        let solution = solve(&challenge);
+
+        assert_eq!(
+            pow.verify_at("new_poll", &challenge, &solution, NOW + 61),
+            Err(ProofOfWorkError::Expired)
+        );
+    }
+
+    #[test]
+    fn tampered_challenge_is_rejected() {
+        let pow = proof_of_work();
+        let challenge = pow.challenge("new_poll", NOW);
+        // Make it easier without knowing the secret
+        let easier = challenge.replacen("v1:8:", "v1:0:", 1);
+
+        assert_eq!(
+            pow.verify_at("new_poll", &easier, "0", NOW),
+            Err(ProofOfWorkError::Invalid)
+        );
+    }
+
+    #[test]
+    fn wrong_solution_is_rejected() {
+        let pow = proof_of_work();
+        let challenge = pow.challenge("new_poll", NOW);
+        let wrong = (0..)
+            .map(|n: u64| n.to_string())
+            .find(|n| pow.verify_at("new_poll", &challenge, n, NOW).is_err())
+            .unwrap();
+
+        assert_eq!(
+            pow.verify_at("new_poll", &challenge, &wrong, NOW),
+            Err(ProofOfWorkError::Invalid)
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_solution_is_rejected() {
+        let pow = proof_of_work();
+        assert_eq!(
+            pow.verify("new_poll", Some("challenge"), Some("")).await,
+            Err(ProofOfWorkError::Missing)
+        );
+        assert_eq!(
+            pow.verify("new_poll", None, None).await,
+            Err(ProofOfWorkError::Missing)
+        );
+    }
+
+    #[tokio::test]
+    async fn solved_challenge_is_only_accepted_once() {
+        let pow = proof_of_work();
+        let challenge = pow.challenge("new_poll", chrono::Utc::now().timestamp());
+        let solution = solve(&challenge);
+
+        assert_ok!(
+            pow.verify("new_poll", Some(&challenge), Some(&solution))
+                .await
+        );
+        assert_eq!(
+            pow.verify("new_poll", Some(&challenge), Some(&solution))
+                .await,
+            Err(ProofOfWorkError::Replayed)
+        );
+    }
+
+    #[test]
+    fn solved_challenges_are_forgotten_once_expired() {
+        let pow = proof_of_work();
+
+        assert!(pow.solved.first_use_in_memory("signature", NOW + 60, NOW));
+        assert!(!pow
+            .solved
+            .first_use_in_memory("signature", NOW + 60, NOW + 60));
+        assert!(pow
+            .solved
+            .first_use_in_memory("signature", NOW + 121, NOW + 61));
+    }
+
+    #[test]
+    fn leading_zero_bits_are_counted_across_bytes() {
+        assert_eq!(leading_zero_bits(&[0xff]), 0);
+        assert_eq!(leading_zero_bits(&[0x00, 0x10]), 11);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+    }
+}
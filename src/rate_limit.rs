@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use redis::aio::ConnectionManager;
+use redis::Script;
+
+use crate::configuration::BucketSettings;
+
+/// Takes one token from each of the buckets in `KEYS`, whose capacity and
+/// refill rate are the pairs of `ARGV`. Tokens are only taken if every bucket
+/// has one. Returns whether they were and otherwise how many milliseconds
+/// until they all have a token, -1 if one of them is never refilled.
+const TAKE_TOKENS_SCRIPT: &str = r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local buckets = {}
+local retry_after = 0
+for i, key in ipairs(KEYS) do
+    local capacity = tonumber(ARGV[i * 2 - 1])
+    local rate = tonumber(ARGV[i * 2])
+    local bucket = redis.call('HMGET', key, 'tokens', 'updated_at')
+    local tokens = tonumber(bucket[1]) or capacity
+    local updated_at = tonumber(bucket[2]) or now
+    tokens = math.min(capacity, tokens + math.max(0, now - updated_at) * rate)
+    buckets[i] = {capacity, rate, tokens}
+
+    if tokens < 1 and retry_after >= 0 then
+        if rate > 0 then
+            retry_after = math.max(retry_after, math.ceil((1 - tokens) / rate))
+        else
+            retry_after = -1
+        end
+    end
+end
+if retry_after ~= 0 then
+    return {0, retry_after}
+end
+
+for i, key in ipairs(KEYS) do
+    local capacity, rate, tokens = unpack(buckets[i])
+    redis.call('HSET', key, 'tokens', tostring(tokens - 1), 'updated_at', now)
+    if rate > 0 then
+        -- Once full again the bucket is the same as a missing one
+        redis.call('PEXPIRE', key, math.ceil(capacity / rate))
+    end
+end
+return {1, 0}
+"#;
+
+/// Token buckets shared by every instance of the application through Redis.
+/// Whenever Redis can't be reached the buckets of this instance are used
+/// instead, so limits still apply, just not across instances.
+pub struct RateLimiter {
+    namespace: String,
+    redis: Option<ConnectionManager>,
+    script: Script,
+    fallback: MemoryBuckets,
+}
+
+impl RateLimiter {
+    pub fn new(namespace: String, redis: Option<ConnectionManager>) -> Self {
+        Self {
+            namespace,
+            redis,
+            script: Script::new(TAKE_TOKENS_SCRIPT),
+            fallback: MemoryBuckets::default(),
+        }
+    }
+
+    /// Takes a token from every bucket in `buckets` if none of them is empty,
+    /// otherwise takes nothing and returns how long until they all have a
+    /// token again.
+    #[tracing::instrument(name = "take rate limit tokens", skip(self))]
+    pub async fn check(&self, buckets: &[(String, BucketSettings)]) -> Result<(), Duration> {
+        if buckets.is_empty() {
+            return Ok(());
+        }
+        let buckets = buckets
+            .iter()
+            .map(|(key, settings)| (format!("{}:{key}", self.namespace), *settings))
+            .collect::<Vec<_>>();
+        if let Some(redis) = &self.redis {
+            match self.check_redis(redis.clone(), &buckets).await {
+                Ok(result) => return result,
+                Err(e) => {
+                    tracing::warn!(error = %e, "rate limit check failed in Redis, using memory instead")
+                }
+            }
+        }
+        self.fallback.check(&buckets, Instant::now())
+    }
+
+    async fn check_redis(
+        &self,
+        mut redis: ConnectionManager,
+        buckets: &[(String, BucketSettings)],
+    ) -> Result<Result<(), Duration>, redis::RedisError> {
+        let mut invocation = self.script.prepare_invoke();
+        for (key, settings) in buckets {
+            invocation
+                .key(key)
+                .arg(settings.capacity)
+                .arg(refill_per_millisecond(*settings));
+        }
+        let (allowed, retry_after_ms): (u8, i64) = invocation.invoke_async(&mut redis).await?;
+
+        Ok(match (allowed, u64::try_from(retry_after_ms)) {
+            (1, _) => Ok(()),
+            (_, Ok(retry_after_ms)) => Err(Duration::from_millis(retry_after_ms)),
+            (_, Err(_)) => Err(Duration::MAX),
+        })
+    }
+}
+
+fn refill_per_millisecond(settings: BucketSettings) -> f64 {
+    f64::from(settings.refill_per_minute) / 60_000.0
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    settings: BucketSettings,
+}
+
+impl Bucket {
+    fn refilled(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_millis() as f64;
+        let tokens = self.tokens + elapsed * refill_per_millisecond(self.settings);
+        f64::from(self.settings.capacity).min(tokens)
+    }
+}
+
+#[derive(Default)]
+struct MemoryBuckets {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl MemoryBuckets {
+    /// Buckets that went back to full are forgotten once there are this many.
+    const MAX_BUCKETS: usize = 10_000;
+
+    fn check(&self, buckets: &[(String, BucketSettings)], now: Instant) -> Result<(), Duration> {
+        let mut stored = self.buckets.lock().unwrap();
+        if stored.len() >= Self::MAX_BUCKETS {
+            stored.retain(|_, bucket| bucket.refilled(now) < f64::from(bucket.settings.capacity));
+        }
+
+        let mut retry_after = None;
+        let mut tokens = Vec::with_capacity(buckets.len());
+        for (key, settings) in buckets {
+            let available = match stored.get(key) {
+                Some(bucket) => bucket.refilled(now),
+                None => f64::from(settings.capacity),
+            };
+            if available < 1.0 {
+                let rate = refill_per_millisecond(*settings);
+                let wait = if rate > 0.0 {
+                    Duration::from_millis(((1.0 - available) / rate).ceil() as u64)
+                } else {
+                    Duration::MAX
+                };
+                retry_after = retry_after.max(Some(wait));
+            }
+            tokens.push(available);
+        }
+        if let Some(retry_after) = retry_after {
+            return Err(retry_after);
+        }
+
+        for ((key, settings), available) in buckets.iter().zip(tokens) {
+            stored.insert(
+                key.clone(),
+                Bucket {
+                    tokens: available - 1.0,
+                    updated_at: now,
+                    settings: *settings,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use claim::{assert_err, assert_ok};
+
+    use super::MemoryBuckets;
+    use crate::configuration::BucketSettings;
+
+    const SETTINGS: BucketSettings = BucketSettings {
+        capacity: 2,
+        refill_per_minute: 6,
+    };
+
+    fn bucket(key: &str) -> Vec<(String, BucketSettings)> {
+        vec![(key.into(), SETTINGS)]
+    }
+
+    #[test]
+    fn bucket_allows_up_to_its_capacity() {
+        let buckets = MemoryBuckets::default();
+        let now = Instant::now();
+
+        assert_ok!(buckets.check(&bucket("key"), now));
+        assert_ok!(buckets.check(&bucket("key"), now));
+        let retry_after = buckets.check(&bucket("key"), now).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(10));
+
+        // Other buckets are unaffected
+        assert_ok!(buckets.check(&bucket("other"), now));
+    }
+
+    #[test]
+    fn bucket_is_refilled_over_time() {
+        let buckets = MemoryBuckets::default();
+        let now = Instant::now();
+
+        assert_ok!(buckets.check(&bucket("key"), now));
+        assert_ok!(buckets.check(&bucket("key"), now));
+        assert_err!(buckets.check(&bucket("key"), now + Duration::from_secs(5)));
+        assert_ok!(buckets.check(&bucket("key"), now + Duration::from_secs(11)));
+    }
+
+    #[test]
+    fn bucket_never_holds_more_than_its_capacity() {
+        let buckets = MemoryBuckets::default();
+        let now = Instant::now();
+        let later = now + Duration::from_secs(3600);
+
+        assert_ok!(buckets.check(&bucket("key"), now));
+        assert_ok!(buckets.check(&bucket("key"), later));
+        assert_ok!(buckets.check(&bucket("key"), later));
+        assert_err!(buckets.check(&bucket("key"), later));
+    }
+
+    #[test]
+    fn denied_request_takes_no_tokens() {
+        let buckets = MemoryBuckets::default();
+        let now = Instant::now();
+        let both = [bucket("key"), bucket("other")].concat();
+
+        assert_ok!(buckets.check(&bucket("other"), now));
+        assert_ok!(buckets.check(&bucket("other"), now));
+        assert_err!(buckets.check(&both, now));
+        assert_err!(buckets.check(&both, now));
+
+        // Every denial left "key" alone
+        assert_ok!(buckets.check(&bucket("key"), now));
+        assert_ok!(buckets.check(&bucket("key"), now));
+    }
+}
@@ -0,0 +1,97 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+
+use super::post_delete_poll::AdminError;
+use crate::{middleware::Admin, storage::Storage, telemetry::recent_errors, utils::escape_html};
+
+/// Days of activity shown on the dashboard, today included.
+const ACTIVITY_DAYS: i32 = 14;
+const SHOWN_ERRORS: usize = 50;
+
+#[tracing::instrument(name = "Show admin dashboard", skip_all, fields(username = %admin.username))]
+pub async fn admin_dashboard(
+    admin: Admin,
+    storage: web::Data<dyn Storage>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, AdminError> {
+    let totals = storage
+        .totals()
+        .await
+        .context("failed to count polls, users and suggestions")?;
+    let activity = storage
+        .daily_activity(ACTIVITY_DAYS)
+        .await
+        .context("failed to retrieve the daily activity")?;
+
+    let messages_html = flash_messages
+        .iter()
+        .map(|m| format!("<p><i>{}</i></p>", m.content()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let activity_tr = activity
+        .iter()
+        .map(|d| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                d.day, d.polls, d.users, d.suggestions
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let errors_tr = recent_errors()
+        .latest(SHOWN_ERRORS)
+        .iter()
+        .map(|e| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                e.at.format("%Y-%m-%d %H:%M:%S"),
+                e.level,
+                escape_html(e.span.as_deref().unwrap_or_default()),
+                escape_html(&e.message)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Apoll | Admin</title>
+    </head>
+    <body>
+        {messages_html}
+        <h1>Admin dashboard</h1>
+        <form action="/admin/logout" method="post">
+            <button type="submit">Log out {username}</button>
+        </form>
+        <form action="/admin/polls" method="get">
+            <input type="text" name="q" placeholder="Prompt or poll id" />
+            <button type="submit">Search polls</button>
+        </form>
+        <h2>Totals</h2>
+        <p>Polls: {polls}, users: {users}, suggestions: {suggestions}</p>
+        <h2>Last {ACTIVITY_DAYS} days</h2>
+        <table>
+            <tr><th>Day</th><th>Polls</th><th>Users</th><th>Suggestions</th></tr>
+            {activity_tr}
+        </table>
+        <h2>Recent errors</h2>
+        <table>
+            <tr><th>Time (UTC)</th><th>Level</th><th>Span</th><th>Message</th></tr>
+            {errors_tr}
+        </table>
+    </body>
+</html>"#,
+            username = escape_html(&admin.username),
+            polls = totals.polls,
+            users = totals.users,
+            suggestions = totals.suggestions,
+        )))
+}
@@ -0,0 +1,36 @@
+use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+
+#[tracing::instrument(name = "Show admin login page", skip_all)]
+pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
+    let error_html = flash_messages
+        .iter()
+        .map(|m| format!("<p><i>{}</i></p>", m.content()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Apoll | Admin login</title>
+    </head>
+    <body>
+        {error_html}
+        <h1>Admin login</h1>
+        <form action="/admin/login" method="post">
+        <label for="username">Username
+            <input type="text" name="username" />
+        </label><br>
+        <label for="password">Password
+            <input type="password" name="password" />
+        </label><br>
+        <button type="submit">Log in</button>
+        </form>
+    </body>
+</html>"#
+        ))
+}
@@ -0,0 +1,129 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+
+use super::post_delete_poll::{AdminError, PollPath};
+use crate::{middleware::Admin, storage::Storage, utils::escape_html};
+
+#[tracing::instrument(
+    name = "Show poll as admin"
+    skip_all,
+    fields(username = %admin.username, poll_id = %path.poll_id)
+)]
+pub async fn view_poll(
+    admin: Admin,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<PollPath>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, AdminError> {
+    let poll_id = path.poll_id;
+    let poll = storage
+        .poll_details(&poll_id)
+        .await
+        .context("failed to retrieve the poll")?
+        .ok_or(AdminError::NotFound)?;
+    let members = storage
+        .member_details(&poll_id)
+        .await
+        .context("failed to retrieve the poll members")?;
+    let suggestions = storage
+        .suggestion_details(&poll_id)
+        .await
+        .context("failed to retrieve the poll suggestions")?;
+    let votes = storage
+        .vote_counts(&poll_id)
+        .await
+        .context("failed to count the poll votes")?;
+
+    let messages_html = flash_messages
+        .iter()
+        .map(|m| format!("<p><i>{}</i></p>", m.content()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let members_tr = members
+        .iter()
+        .map(|m| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&m.username),
+                m.role.label(),
+                m.joined_at.format("%Y-%m-%d %H:%M"),
+                status(&m.moderation_reason),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let suggestions_tr = suggestions
+        .iter()
+        .map(|s| {
+            let votes = votes
+                .iter()
+                .find(|(suggestion_id, _)| *suggestion_id == s.suggestion_id)
+                .map(|(_, count)| *count)
+                .unwrap_or_default();
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{votes}</td><td>{}</td></tr>",
+                escape_html(&s.suggestion),
+                escape_html(s.author.as_deref().unwrap_or("a removed member")),
+                status(&s.moderation_reason),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Apoll | Admin poll</title>
+    </head>
+    <body>
+        {messages_html}
+        <h1>{prompt}</h1>
+        <a href="/admin/polls">Back to the polls</a>
+        <ul>
+            <li>Id: {poll_id}</li>
+            <li>Created: {created_at} UTC</li>
+            <li>Invite only: {invite_only}</li>
+            <li>Passphrase: {passphrase}</li>
+            <li>Anti-spam check: {proof_of_work}</li>
+            <li>Own content filter: {content_filter}</li>
+            <li>Pending reports: {reports}</li>
+        </ul>
+        <form action="/admin/polls/{poll_id}/delete" method="post">
+            <button type="submit">Delete poll</button>
+        </form>
+        <h2>Members</h2>
+        <table>
+            <tr><th>Name</th><th>Role</th><th>Joined (UTC)</th><th>Status</th></tr>
+            {members_tr}
+        </table>
+        <h2>Suggestions</h2>
+        <table>
+            <tr><th>Suggestion</th><th>Author</th><th>Votes</th><th>Status</th></tr>
+            {suggestions_tr}
+        </table>
+    </body>
+</html>"#,
+            prompt = escape_html(&poll.prompt),
+            created_at = poll.created_at.format("%Y-%m-%d %H:%M"),
+            invite_only = yes_no(poll.invite_only),
+            passphrase = yes_no(poll.has_passphrase),
+            proof_of_work = yes_no(poll.require_proof_of_work),
+            content_filter = yes_no(poll.has_content_filter),
+            reports = poll.pending_reports,
+        )))
+}
+
+fn status(moderation_reason: &Option<String>) -> String {
+    match moderation_reason {
+        Some(reason) => format!("Hidden, it {}", escape_html(reason)),
+        None => "Visible".to_string(),
+    }
+}
@@ -0,0 +1,85 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+
+use super::post_delete_poll::AdminError;
+use crate::{middleware::Admin, storage::Storage, utils::escape_html};
+
+const SEARCH_LIMIT: i64 = 50;
+
+#[derive(serde::Deserialize)]
+pub struct SearchQuery {
+    /// Part of the prompt or the whole poll id.
+    q: Option<String>,
+}
+
+#[tracing::instrument(
+    name = "Search polls as admin"
+    skip_all,
+    fields(username = %admin.username, q = ?query.q)
+)]
+pub async fn search_polls(
+    admin: Admin,
+    storage: web::Data<dyn Storage>,
+    query: web::Query<SearchQuery>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, AdminError> {
+    let q = query.0.q.unwrap_or_default();
+    let q = q.trim();
+    let polls = storage
+        .search_polls(q, SEARCH_LIMIT)
+        .await
+        .context("failed to search polls")?;
+
+    let messages_html = flash_messages
+        .iter()
+        .map(|m| format!("<p><i>{}</i></p>", m.content()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let polls_tr = polls
+        .iter()
+        .map(|p| {
+            format!(
+                r#"<tr>
+                <td><a href="/admin/polls/{poll_id}">{prompt}</a></td>
+                <td>{created_at}</td>
+                <td>{members}</td>
+                <td>{suggestions}</td>
+            </tr>"#,
+                poll_id = p.poll_id,
+                prompt = escape_html(&p.prompt),
+                created_at = p.created_at.format("%Y-%m-%d %H:%M"),
+                members = p.members,
+                suggestions = p.suggestions,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Apoll | Admin polls</title>
+    </head>
+    <body>
+        {messages_html}
+        <h1>Polls</h1>
+        <a href="/admin">Back to the dashboard</a>
+        <form action="/admin/polls" method="get">
+            <input type="text" name="q" value="{q}" placeholder="Prompt or poll id" />
+            <button type="submit">Search polls</button>
+        </form>
+        <table>
+            <tr><th>Prompt</th><th>Created (UTC)</th><th>Members</th><th>Suggestions</th></tr>
+            {polls_tr}
+        </table>
+    </body>
+</html>"#,
+            q = escape_html(q),
+        )))
+}
@@ -0,0 +1,15 @@
+mod get_dashboard;
+mod get_login;
+mod get_poll;
+mod get_polls;
+mod post_delete_poll;
+mod post_login;
+mod post_logout;
+
+pub use get_dashboard::admin_dashboard;
+pub use get_login::login_form;
+pub use get_poll::view_poll;
+pub use get_polls::search_polls;
+pub use post_delete_poll::delete_poll;
+pub use post_login::log_in;
+pub use post_logout::log_out;
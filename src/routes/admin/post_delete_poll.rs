@@ -0,0 +1,57 @@
+use actix_web::{error::InternalError, web, HttpResponse, ResponseError};
+use actix_web_flash_messages::FlashMessage;
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    middleware::Admin,
+    poll_cache::PollCache,
+    storage::Storage,
+    utils::{flash_message_redirect, redirect},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum AdminError {
+    #[error("Could not find this poll")]
+    NotFound,
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl ResponseError for AdminError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            AdminError::NotFound => StatusCode::NOT_FOUND,
+            AdminError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct PollPath {
+    pub poll_id: Uuid,
+}
+
+#[tracing::instrument(
+    name = "delete poll as admin"
+    skip_all
+    fields(username = %admin.username, poll_id = %path.poll_id)
+)]
+pub async fn delete_poll(
+    admin: Admin,
+    storage: web::Data<dyn Storage>,
+    poll_cache: web::Data<PollCache>,
+    path: web::Path<PollPath>,
+) -> Result<HttpResponse, InternalError<AdminError>> {
+    let polls_uri = "/admin/polls";
+    let prompt = storage
+        .delete_poll(&path.poll_id)
+        .await
+        .map_err(|e| flash_message_redirect(AdminError::Unexpected(e.into()), polls_uri))?
+        .ok_or_else(|| flash_message_redirect(AdminError::NotFound, polls_uri))?;
+    poll_cache.invalidate(&path.poll_id);
+
+    tracing::info!("poll deleted by an admin");
+    FlashMessage::info(format!("Deleted the poll: {prompt}")).send();
+    Ok(redirect(polls_uri))
+}
@@ -0,0 +1,74 @@
+use actix_web::{error::InternalError, web, HttpResponse, ResponseError};
+use reqwest::StatusCode;
+use secrecy::Secret;
+
+use crate::{
+    authentication::verify_passphrase_hash,
+    configuration::AdminSettings,
+    user_session::TypedSession,
+    utils::{flash_message_redirect, redirect},
+};
+
+/// Checked when the username doesn't exist, so that the response takes as
+/// long as for a wrong password.
+const FALLBACK_PASSWORD_HASH: &str = "$argon2id$v=19$m=15000,t=2,p=1$\
+    gZiV/M1gPc22ElAH/Jh1Hw$CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno";
+
+#[derive(thiserror::Error, Debug)]
+pub enum LoginError {
+    #[error("Invalid username or password")]
+    InvalidCredentials,
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl ResponseError for LoginError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            LoginError::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            LoginError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct LoginForm {
+    username: String,
+    password: Secret<String>,
+}
+
+#[tracing::instrument(
+    name = "admin login"
+    skip_all
+    fields(username = %form.username)
+)]
+pub async fn log_in(
+    session: TypedSession,
+    form: web::Form<LoginForm>,
+    admin: web::Data<AdminSettings>,
+) -> Result<HttpResponse, InternalError<LoginError>> {
+    let login_uri = "/admin/login";
+    let LoginForm { username, password } = form.0;
+
+    let account = admin.accounts.iter().find(|a| a.username == username);
+    let expected_hash = account
+        .map(|a| a.password_hash.clone())
+        .unwrap_or_else(|| Secret::new(FALLBACK_PASSWORD_HASH.to_string()));
+    let is_correct = verify_passphrase_hash(expected_hash, password)
+        .await
+        .map_err(|e| flash_message_redirect(LoginError::Unexpected(e), login_uri))?;
+    if account.is_none() || !is_correct {
+        tracing::warn!("failed admin login attempt");
+        return Err(flash_message_redirect(
+            LoginError::InvalidCredentials,
+            login_uri,
+        ));
+    }
+
+    session.renew();
+    session
+        .insert_admin(&username)
+        .map_err(|e| flash_message_redirect(LoginError::Unexpected(e.into()), login_uri))?;
+
+    Ok(redirect("/admin"))
+}
@@ -0,0 +1,12 @@
+use actix_web::HttpResponse;
+use actix_web_flash_messages::FlashMessage;
+
+use crate::{middleware::Admin, user_session::TypedSession, utils::redirect};
+
+/// Only forgets the admin account, the session keeps its poll memberships.
+#[tracing::instrument(name = "admin logout", skip_all, fields(username = %admin.username))]
+pub async fn log_out(admin: Admin, session: TypedSession) -> HttpResponse {
+    session.remove_admin();
+    FlashMessage::info("You have successfully logged out.").send();
+    redirect("/admin/login")
+}
@@ -0,0 +1,179 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use actix_web::{web, HttpResponse};
+
+use crate::storage::Storage;
+
+/// How long a dependency has to answer before it counts as down.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What the readiness probe needs besides the database.
+pub struct Readiness {
+    /// Only checked when the sessions are kept in Redis.
+    redis: Option<redis::Client>,
+    shutting_down: AtomicBool,
+}
+
+impl Readiness {
+    pub fn new(redis_uri: Option<&str>) -> Result<Self, redis::RedisError> {
+        Ok(Self {
+            redis: redis_uri.map(redis::Client::open).transpose()?,
+            shutting_down: AtomicBool::new(false),
+        })
+    }
+
+    /// From now on the instance reports not ready, so that it's taken out of
+    /// the load balancer while the requests in flight finish.
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    async fn ping_redis(redis: &redis::Client) -> Result<(), anyhow::Error> {
+        let mut connection = redis.get_async_connection().await?;
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut connection)
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ReadinessReport {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checks: Option<Checks>,
+}
+
+#[derive(serde::Serialize)]
+struct Checks {
+    database: CheckResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    redis: Option<CheckResult>,
+    migrations: CheckResult,
+}
+
+impl Checks {
+    fn all_up(&self) -> bool {
+        [
+            Some(&self.database),
+            self.redis.as_ref(),
+            Some(&self.migrations),
+        ]
+        .iter()
+        .flatten()
+        .all(|c| c.error.is_none())
+    }
+}
+
+#[derive(serde::Serialize)]
+struct CheckResult {
+    status: &'static str,
+    latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// The process is up and serving requests, its dependencies aren't checked.
+pub async fn liveness_probe() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "alive" }))
+}
+
+/// Whether this instance should receive traffic: the database and Redis, when
+/// it's used, answer, the database has every migration this build knows about
+/// and the server isn't shutting down.
+#[tracing::instrument(name = "Check readiness", skip_all)]
+pub async fn readiness_probe(
+    readiness: web::Data<Readiness>,
+    storage: web::Data<dyn Storage>,
+) -> HttpResponse {
+    if readiness.is_shutting_down() {
+        return HttpResponse::ServiceUnavailable().json(ReadinessReport {
+            status: "shutting_down",
+            checks: None,
+        });
+    }
+
+    let (database, redis, migrations) = tokio::join!(
+        check(async { Ok(storage.ping().await?) }),
+        async {
+            match &readiness.redis {
+                Some(redis) => Some(check(Readiness::ping_redis(redis)).await),
+                None => None,
+            }
+        },
+        check(check_migrations(storage.get_ref())),
+    );
+    let checks = Checks {
+        database,
+        redis,
+        migrations,
+    };
+
+    if checks.all_up() {
+        HttpResponse::Ok().json(ReadinessReport {
+            status: "ready",
+            checks: Some(checks),
+        })
+    } else {
+        HttpResponse::ServiceUnavailable().json(ReadinessReport {
+            status: "not_ready",
+            checks: Some(checks),
+        })
+    }
+}
+
+async fn check(probe: impl Future<Output = Result<(), anyhow::Error>>) -> CheckResult {
+    let start = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, probe).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!(
+            "timed out after {} seconds",
+            CHECK_TIMEOUT.as_secs()
+        )),
+    };
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    match result {
+        Ok(()) => CheckResult {
+            status: "up",
+            latency_ms,
+            error: None,
+        },
+        Err(e) => {
+            tracing::warn!(error = %e, "readiness check failed");
+            CheckResult {
+                status: "down",
+                latency_ms,
+                error: Some(e.to_string()),
+            }
+        }
+    }
+}
+
+/// The database may be ahead of this build during a rolling deploy, but
+/// not behind it.
+async fn check_migrations(storage: &dyn Storage) -> Result<(), anyhow::Error> {
+    let expected = storage
+        .migrator()
+        .iter()
+        .map(|m| m.version)
+        .max()
+        .unwrap_or_default();
+    let applied = storage
+        .applied_migrations()
+        .await?
+        .into_iter()
+        .max()
+        .unwrap_or_default();
+
+    if applied < expected {
+        anyhow::bail!("the database is at migration {applied}, this build expects {expected}");
+    }
+    Ok(())
+}
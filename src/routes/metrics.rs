@@ -0,0 +1,37 @@
+use actix_web::{http::header::AUTHORIZATION, web, HttpRequest, HttpResponse};
+use secrecy::ExposeSecret;
+
+use crate::{
+    configuration::MetricsSettings, metrics::Metrics, middleware::constant_time_eq,
+    storage::Storage,
+};
+
+#[tracing::instrument(name = "Export metrics", skip_all)]
+pub async fn get_metrics(
+    request: HttpRequest,
+    metrics: web::Data<Metrics>,
+    settings: web::Data<MetricsSettings>,
+    storage: web::Data<dyn Storage>,
+) -> HttpResponse {
+    if let Some(token) = &settings.token {
+        let candidate = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if !constant_time_eq(candidate.as_bytes(), token.expose_secret().as_bytes()) {
+            return HttpResponse::Unauthorized().finish();
+        }
+    }
+
+    match metrics.render(&storage.pool_status()) {
+        Ok(text) => HttpResponse::Ok()
+            .content_type(prometheus::TEXT_FORMAT)
+            .body(text),
+        Err(e) => {
+            tracing::error!(error = %e, "failed to encode the metrics");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
@@ -1,10 +1,27 @@
-use actix_web::{http::header::ContentType, web, HttpResponse, ResponseError};
+use actix_web::http::header::{
+    self, CacheControl, CacheDirective, ContentType, ETag, EntityTag, IfNoneMatch,
+};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use actix_web_flash_messages::IncomingFlashMessages;
 use anyhow::Context;
 use reqwest::StatusCode;
-use sqlx::PgPool;
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
-use crate::{middleware::PollInfo, user_session::TypedSession};
+use crate::{
+    domain::{
+        is_well_formed_invite_token, MemberSort, PollRole, ReportReason, ReportTarget,
+        SuggestionSort,
+    },
+    middleware::PollInfo,
+    proof_of_work::ProofOfWork,
+    storage::{Member, OverviewPage, PollOverview, Storage, Suggestion},
+    user_session::TypedSession,
+    utils::escape_html,
+};
+
+/// How many members and how many suggestions a page lists.
+const PAGE_SIZE: i64 = 50;
 
 #[derive(thiserror::Error, Debug)]
 pub enum ShowPollError {
@@ -29,166 +46,370 @@ impl ResponseError for ShowPollError {
     fields(poll_id=tracing::field::Empty)
 )]
 pub async fn show_poll(
-    db_pool: web::Data<PgPool>,
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
     poll_info: PollInfo,
     session: TypedSession,
+    query: web::Query<ShowPollQuery>,
+    flash_messages: IncomingFlashMessages,
+    proof_of_work: web::Data<ProofOfWork>,
 ) -> Result<HttpResponse, ShowPollError> {
-    let PollInfo { poll_id, prompt } = poll_info;
+    let PollInfo {
+        poll_id,
+        prompt,
+        invite_only,
+        require_proof_of_work,
+        ..
+    } = poll_info;
     tracing::Span::current().record("poll_id", &tracing::field::display(&poll_id));
 
+    let user_id = session
+        .get_user_id()
+        .context("failed to retrieve user_id from session store")?;
+    let ShowPollQuery {
+        invite,
+        q,
+        member_sort,
+        members_after,
+        suggestion_sort,
+        suggestions_after,
+    } = query.0;
+    let invite = invite.filter(|token| is_well_formed_invite_token(token));
+    let page = OverviewPage {
+        search: q.unwrap_or_default().trim().to_string(),
+        member_sort,
+        members_after,
+        suggestion_sort,
+        suggestions_after,
+        limit: PAGE_SIZE,
+    };
+    // The version, session user, members and suggestions come in a single
+    // round trip
+    let PollOverview {
+        version,
+        member: session_user,
+        members: poll_users,
+        suggestions,
+        more_members,
+        more_suggestions,
+    } = storage
+        .poll_overview(&poll_id, user_id.as_ref(), &page)
+        .await
+        .context("failed to retrieve the poll's members and suggestions")?;
+    // A poll deleted since it was cached has no version left to tag
+    let etag =
+        version.map(|version| page_etag(&poll_id, version, user_id.as_ref(), req.query_string()));
+    // Flash messages are only shown once, the page has to be sent again
+    let has_messages = flash_messages.iter().next().is_some();
+    if let Some(etag) = etag
+        .as_ref()
+        .filter(|etag| !has_messages && matches_etag(&req, etag))
+    {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(ETag(etag.clone()))
+            .insert_header(revalidate())
+            .insert_header((header::VARY, "Cookie"))
+            .finish());
+    }
+
+    let messages_html = flash_messages
+        .iter()
+        .map(|m| format!("<p><i>{}</i></p>", m.content()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
     let mut user_greeting = String::new();
     let mut suggest_form = String::new();
     let mut join_form = String::new();
-    if let Some(user) = get_session_user(session, &db_pool, &poll_id).await? {
-        user_greeting = format!("<p>Logged in as {}</p>", user.username);
-        suggest_form = format!(
-            r#"<form action="/poll/{poll_id}/suggest" method="post">
+    let mut has_challenge = false;
+    if let Some(user) = &session_user {
+        user_greeting = format!("<p>Logged in as {}</p>", escape_html(&user.username));
+        if user.role.can_manage() {
+            user_greeting.push_str(&format!(
+                r#"<p><a href="/poll/{poll_id}/members">Manage members</a> | <a href="/poll/{poll_id}/invites">Manage invites</a> | <a href="/poll/{poll_id}/moderation">Moderation queue</a> | <a href="/poll/{poll_id}/content_filter">Content filter</a></p>"#
+            ));
+        }
+        if user.role == PollRole::Owner {
+            user_greeting.push_str(&format!(
+                r#"<p><a href="/poll/{poll_id}/export_access">Export access</a></p>"#
+            ));
+        }
+        suggest_form = if user.role.can_suggest() {
+            format!(
+                r#"<form action="/poll/{poll_id}/suggest" method="post">
                 <input type="text" placeholder="Add suggestion" name="suggestion" />
                 <button type="submit">Add Suggestion</button>
             </form>"#
-        );
+            )
+        } else {
+            "<p>You are observing this poll.</p>".to_string()
+        };
     } else {
-        join_form = format!(
-            r#"<form action="/poll/{poll_id}/join" method="post">
+        join_form = match (invite_only, &invite) {
+            (true, None) => "<p>This poll can only be joined with an invite link.</p>".to_string(),
+            (_, invite) => {
+                let invite_input = invite_input(invite.as_deref());
+                let proof_of_work_fields = if proof_of_work.required_for_join(require_proof_of_work)
+                {
+                    has_challenge = true;
+                    proof_of_work.form_fields(&format!("join:{poll_id}"))
+                } else {
+                    String::new()
+                };
+                format!(
+                    r#"<form action="/poll/{poll_id}/join" method="post">
+                {invite_input}
+                {proof_of_work_fields}
                 <input type="text" placeholder="Username" name="username" />
+                <label for="observer">Only observe
+                    <input type="checkbox" name="observer" />
+                </label>
                 <button type="submit">Join poll</button>
             </form>"#
-        )
+                )
+            }
+        };
     }
 
-    // Retrieve users
-    let poll_users = get_poll_users(&db_pool, &poll_id)
-        .await
-        .context("failed to retrieve poll users")?;
+    // Members whose name is held for review aren't listed until it's approved
     let users_li = poll_users
         .iter()
-        .map(|u| format!("<li>{}</li>", u.username))
+        .map(|u| match u.role {
+            PollRole::Participant => format!("<li>{}</li>", escape_html(&u.username)),
+            role => format!("<li>{} ({role})</li>", escape_html(&u.username)),
+        })
         .collect::<Vec<_>>()
         .join("\n");
 
-    // Retrieve suggestions
-    let suggestions = get_suggestions(&db_pool, &poll_id)
-        .await
-        .context("failed to retrieve suggestions")?;
     let suggestions_li = suggestions
         .iter()
-        .map(|s| format!("<li>{s}</li>"))
+        .map(|s| format!("<li>{}</li>", escape_html(&s.suggestion)))
         .collect::<Vec<_>>()
         .join("\n");
 
-    Ok(HttpResponse::Ok()
-        .content_type(ContentType::html())
-        .body(format!(
-            r#"<!DOCTYPE html>
+    let listing_form = listing_form(&poll_id, invite.as_deref(), &page);
+    // The other list stays on the page it's on
+    let more_users_link = match poll_users.last() {
+        Some(last) if more_members => more_link(
+            &poll_id,
+            invite.as_deref(),
+            &OverviewPage {
+                members_after: Some(last.user_id),
+                ..page.clone()
+            },
+            "More users",
+        ),
+        _ => String::new(),
+    };
+    let more_suggestions_link = match suggestions.last() {
+        Some(last) if more_suggestions => more_link(
+            &poll_id,
+            invite.as_deref(),
+            &OverviewPage {
+                suggestions_after: Some(last.suggestion_id),
+                ..page.clone()
+            },
+            "More suggestions",
+        ),
+        _ => String::new(),
+    };
+
+    let report_form = match &session_user {
+        Some(user) if user.role.can_suggest() => {
+            report_form(&poll_id, &user.user_id, &poll_users, &suggestions)
+        }
+        _ => String::new(),
+    };
+
+    let mut response = HttpResponse::Ok();
+    // Challenges are only good for a while and flash messages only once
+    match etag {
+        Some(etag) if !has_messages && !has_challenge => {
+            response
+                .insert_header(ETag(etag))
+                .insert_header(revalidate())
+                .insert_header((header::VARY, "Cookie"));
+        }
+        _ => {
+            response.insert_header(CacheControl(vec![CacheDirective::NoStore]));
+        }
+    }
+
+    Ok(response.content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
 <html lang="en">
     <head>
         <meta http-equiv="content-type" content="text/html; charset=utf-8">
         <title>Login</title>
     </head>
     <body>
+        {messages_html}
         {user_greeting}
         <h1>{prompt}</h1>
         {join_form}
         {suggest_form}
+        {listing_form}
         <h2>Users</h2>
         <ul>
             {users_li}
         </ul>
+        {more_users_link}
         <h2>Suggestions</h2>
         <ul>
             {suggestions_li}
         </ul>
+        {more_suggestions_link}
+        {report_form}
     </body>
-</html>"#
-        )))
+</html>"#,
+        prompt = escape_html(&prompt),
+    )))
 }
 
-#[derive(Debug)]
-struct User {
-    // TODO: remove this when user_id gets used
-    #[allow(dead_code)]
-    user_id: Uuid,
-    username: String,
+/// Weak, the same page can be rendered with other bytes like the order of
+/// its members. It changes with the poll's version, with who's looking and
+/// which page they look at, and with every release so that pages rendered
+/// differently aren't kept.
+fn page_etag(
+    poll_id: &Uuid,
+    version: i64,
+    user_id: Option<&Uuid>,
+    query_string: &str,
+) -> EntityTag {
+    let viewer = user_id.map(Uuid::to_string).unwrap_or_default();
+    let digest = Sha256::digest(format!(
+        "{}:{poll_id}:{version}:{viewer}:{query_string}",
+        env!("CARGO_PKG_VERSION")
+    ));
+    EntityTag::new_weak(hex::encode(&digest[..16]))
 }
 
-#[tracing::instrument(name = "get user from session", skip(session, db_pool))]
-async fn get_session_user(
-    session: TypedSession,
-    db_pool: &PgPool,
-    poll_id: &Uuid,
-) -> Result<Option<User>, anyhow::Error> {
-    let user_id = session
-        .get_user_id()
-        .context("failed to retrieve user_id from session store")?;
+fn matches_etag(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        None => false,
+    }
+}
 
-    let user = match user_id {
-        Some(user_id) => get_user_from_id(db_pool, &user_id, poll_id)
-            .await
-            .context("failed to retrieve user from database")?,
-        None => return Ok(None),
-    };
+/// Browsers keep the page for this session only and check it's current
+/// every time.
+fn revalidate() -> CacheControl {
+    CacheControl(vec![CacheDirective::Private, CacheDirective::NoCache])
+}
 
-    Ok(user)
+fn invite_input(invite: Option<&str>) -> String {
+    invite
+        .map(|token| format!(r#"<input type="hidden" name="invite" value="{token}" />"#))
+        .unwrap_or_default()
 }
 
-#[tracing::instrument(name = "retrieve logged in user info", skip(db_pool))]
-async fn get_user_from_id(
-    db_pool: &PgPool,
-    user_id: &Uuid,
-    poll_id: &Uuid,
-) -> Result<Option<User>, sqlx::Error> {
-    let result = sqlx::query_as!(
-        User,
-        r#"
-        SELECT user_id, username
-        FROM poll_users
-        WHERE poll_id = $1 AND user_id = $2
-        LIMIT 1
-        "#,
-        poll_id,
-        user_id
+/// Searches and sorts both lists, starting them over from their first page.
+fn listing_form(poll_id: &Uuid, invite: Option<&str>, page: &OverviewPage) -> String {
+    let option = |value: &str, label: &str, selected: bool| {
+        let selected = if selected { " selected" } else { "" };
+        format!(r#"<option value="{value}"{selected}>{label}</option>"#)
+    };
+    let member_options = MemberSort::ALL
+        .iter()
+        .map(|s| option(s.as_str(), s.label(), *s == page.member_sort))
+        .collect::<Vec<_>>()
+        .join("");
+    let suggestion_options = SuggestionSort::ALL
+        .iter()
+        .map(|s| option(s.as_str(), s.label(), *s == page.suggestion_sort))
+        .collect::<Vec<_>>()
+        .join("");
+    format!(
+        r#"<form action="/poll/{poll_id}" method="get">
+            {invite_input}
+            <input type="search" name="q" value="{q}" placeholder="Search names and suggestions" />
+            <label>Users by <select name="member_sort">{member_options}</select></label>
+            <label>Suggestions by <select name="suggestion_sort">{suggestion_options}</select></label>
+            <button type="submit">Show</button>
+        </form>"#,
+        invite_input = invite_input(invite),
+        q = escape_html(&page.search),
     )
-    .fetch_optional(db_pool)
-    .await?;
-
-    Ok(result)
 }
 
-#[tracing::instrument(name = "retrieve poll users", skip(db_pool))]
-async fn get_poll_users(db_pool: &PgPool, poll_id: &Uuid) -> Result<Vec<User>, sqlx::Error> {
-    let rows = sqlx::query_as!(
-        User,
-        r#"
-        SELECT user_id, username
-        FROM poll_users
-        WHERE poll_id = $1
-        "#,
-        poll_id
+fn more_link(poll_id: &Uuid, invite: Option<&str>, page: &OverviewPage, label: &str) -> String {
+    let query = ShowPollQuery {
+        invite: invite.map(str::to_string),
+        q: Some(page.search.clone()).filter(|q| !q.is_empty()),
+        member_sort: page.member_sort,
+        members_after: page.members_after,
+        suggestion_sort: page.suggestion_sort,
+        suggestions_after: page.suggestions_after,
+    };
+    let query = serde_urlencoded::to_string(&query).unwrap_or_default();
+    format!(
+        r#"<p><a href="/poll/{poll_id}?{}">{label}</a></p>"#,
+        escape_html(&query)
     )
-    .fetch_all(db_pool)
-    .await?;
-
-    Ok(rows)
 }
 
-#[tracing::instrument(name = "retrieve poll suggestions", skip(db_pool))]
-async fn get_suggestions(db_pool: &PgPool, poll_id: &Uuid) -> Result<Vec<String>, sqlx::Error> {
-    struct Row {
-        suggestion: String,
+/// Lets members flag the suggestions and names of others.
+fn report_form(
+    poll_id: &Uuid,
+    user_id: &Uuid,
+    users: &[Member],
+    suggestions: &[Suggestion],
+) -> String {
+    let suggestion_options = suggestions
+        .iter()
+        .filter(|s| s.creator_id != *user_id)
+        .map(|s| {
+            let target = ReportTarget::Suggestion(s.suggestion_id).form_value();
+            format!(
+                r#"<option value="{target}">Suggestion: {}</option>"#,
+                escape_html(&s.suggestion)
+            )
+        });
+    let member_options = users.iter().filter(|u| u.user_id != *user_id).map(|u| {
+        let target = ReportTarget::Member(u.user_id).form_value();
+        format!(
+            r#"<option value="{target}">Member: {}</option>"#,
+            escape_html(&u.username)
+        )
+    });
+    let target_options = suggestion_options
+        .chain(member_options)
+        .collect::<Vec<_>>()
+        .join("");
+    if target_options.is_empty() {
+        return String::new();
     }
 
-    let rows = sqlx::query_as!(
-        Row,
-        r#"
-        SELECT (suggestion)
-        FROM suggestions
-        WHERE poll_id = $1
-        "#,
-        poll_id
+    let reason_options = ReportReason::ALL
+        .iter()
+        .map(|r| format!(r#"<option value="{}">{}</option>"#, r.as_str(), r.label()))
+        .collect::<Vec<_>>()
+        .join("");
+    format!(
+        r#"<h2>Report</h2>
+        <form action="/poll/{poll_id}/report" method="post">
+            <select name="target">{target_options}</select>
+            <select name="reason">{reason_options}</select>
+            <button type="submit">Report</button>
+        </form>"#
     )
-    .fetch_all(db_pool)
-    .await?;
+}
 
-    let suggestions = rows.into_iter().map(|r| r.suggestion).collect();
-    Ok(suggestions)
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct ShowPollQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    invite: Option<String>,
+    /// Part of the names and suggestions to list.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    q: Option<String>,
+    #[serde(default)]
+    member_sort: MemberSort,
+    /// The last member of the previous page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    members_after: Option<Uuid>,
+    #[serde(default)]
+    suggestion_sort: SuggestionSort,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suggestions_after: Option<Uuid>,
 }
@@ -0,0 +1,140 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use serde_json::Value;
+
+use super::post_content_filter::{ContentFilterError, FilterChoice};
+use crate::{
+    configuration::{ContentFilterSettings, FilterAction},
+    middleware::{require, Authorized, PollInfo},
+    utils::escape_html,
+};
+
+#[tracing::instrument(
+    name = "Show poll content filter page"
+    skip_all,
+    fields(poll_id = %poll_info.poll_id)
+)]
+pub async fn show_content_filter(
+    poll_info: PollInfo,
+    _organizer: Authorized<require::CoOrganizer>,
+    flash_messages: IncomingFlashMessages,
+    content_filter: web::Data<ContentFilterSettings>,
+) -> Result<HttpResponse, ContentFilterError> {
+    let PollInfo {
+        poll_id,
+        prompt,
+        content_filter: poll_settings,
+        ..
+    } = poll_info;
+    let poll_settings = poll_settings.as_ref();
+
+    let messages_html = flash_messages
+        .iter()
+        .map(|m| format!("<p><i>{}</i></p>", m.content()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let site = &content_filter;
+    let word_list_select = choice_select(
+        "word_list",
+        FilterChoice::of(poll_settings, "word_list"),
+        site.word_list.as_ref().map(|f| f.action),
+    );
+    let links_select = choice_select(
+        "links",
+        FilterChoice::of(poll_settings, "links"),
+        site.links.as_ref().map(|f| f.action),
+    );
+    let repeated_characters_select = choice_select(
+        "repeated_characters",
+        FilterChoice::of(poll_settings, "repeated_characters"),
+        site.repeated_characters.as_ref().map(|f| f.action),
+    );
+
+    let words = poll_settings
+        .and_then(|s| s.pointer("/word_list/words"))
+        .and_then(Value::as_array)
+        .map(|words| {
+            words
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+    let max_repeated = poll_settings
+        .and_then(|s| s.pointer("/repeated_characters/max"))
+        .and_then(Value::as_u64)
+        .or_else(|| site.repeated_characters.as_ref().map(|f| f.max as u64))
+        .map(|max| max.to_string())
+        .unwrap_or_default();
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Apoll | Content filter</title>
+    </head>
+    <body>
+        {messages_html}
+        <h1>Content filter of: {prompt}</h1>
+        <a href="/poll/{poll_id}">Back to the poll</a>
+        <p>Suggestions and names caught by a filter set to hold wait in the <a href="/poll/{poll_id}/moderation">moderation queue</a>.</p>
+        <form action="/poll/{poll_id}/content_filter" method="post">
+            <label for="word_list">Blocked words
+                {word_list_select}
+            </label>
+            <label for="words">Words to block, one per line, in place of the site's
+                <textarea name="words">{words}</textarea>
+            </label>
+            <label for="links">Links
+                {links_select}
+            </label>
+            <label for="repeated_characters">Repeated characters
+                {repeated_characters_select}
+            </label>
+            <label for="max_repeated">Most times a character can be repeated
+                <input type="number" min="1" name="max_repeated" value="{max_repeated}" />
+            </label>
+            <button type="submit">Save</button>
+        </form>
+    </body>
+</html>"#,
+            prompt = escape_html(&prompt),
+            words = escape_html(&words),
+        )))
+}
+
+fn choice_select(name: &str, selected: FilterChoice, site_action: Option<FilterAction>) -> String {
+    let site_default = match site_action {
+        Some(FilterAction::Hold) => "hold",
+        Some(FilterAction::Reject) => "reject",
+        None => "off",
+    };
+    let options = [
+        FilterChoice::Default,
+        FilterChoice::Off,
+        FilterChoice::Hold,
+        FilterChoice::Reject,
+    ]
+    .iter()
+    .map(|choice| {
+        let label = match choice {
+            FilterChoice::Default => format!("Site default ({site_default})"),
+            FilterChoice::Off => "Off".to_string(),
+            FilterChoice::Hold => "Hold for review".to_string(),
+            FilterChoice::Reject => "Reject".to_string(),
+        };
+        let selected = if *choice == selected { " selected" } else { "" };
+        format!(
+            r#"<option value="{value}"{selected}>{label}</option>"#,
+            value = choice.as_str()
+        )
+    })
+    .collect::<Vec<_>>()
+    .join("");
+    format!(r#"<select name="{name}">{options}</select>"#)
+}
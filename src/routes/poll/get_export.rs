@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+
+use actix_web::http::header;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    middleware::{AuthorizationError, PollInfo, PollMember},
+    storage::{ExportedSuggestion, PollExport, Storage},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+    #[error(transparent)]
+    Unauthorized(#[from] AuthorizationError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ResponseError for ExportError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            ExportError::Unauthorized(e) => e.status_code(),
+            ExportError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct ExportQuery {
+    format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ExportFormat {
+    Csv,
+    Json,
+    Md,
+}
+
+#[tracing::instrument(
+    name = "Export poll results"
+    skip_all,
+    fields(poll_id = %poll_info.poll_id, format = ?query.format)
+)]
+pub async fn export_poll(
+    poll_info: PollInfo,
+    member: PollMember,
+    storage: web::Data<dyn Storage>,
+    query: web::Query<ExportQuery>,
+) -> Result<HttpResponse, ExportError> {
+    let required = poll_info.export_access.required_role();
+    if member.role < required {
+        return Err(AuthorizationError::InsufficientRole(required).into());
+    }
+
+    let export = storage
+        .poll_export(&poll_info.poll_id)
+        .await
+        .context("failed to retrieve the poll's results")?;
+
+    let (content_type, extension, body) = match query.format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv", to_csv(&export)),
+        ExportFormat::Json => (
+            "application/json",
+            "json",
+            to_json(&poll_info, &export).to_string(),
+        ),
+        ExportFormat::Md => (
+            "text/markdown; charset=utf-8",
+            "md",
+            to_markdown(&poll_info.prompt, &export),
+        ),
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!(
+                r#"attachment; filename="poll-{}.{extension}""#,
+                poll_info.poll_id
+            ),
+        ))
+        .body(body))
+}
+
+/// The suggestions with how many votes each received, most first. Ties
+/// share their rank and list the oldest suggestion first, then by id, so every
+/// backend exports them in the same order.
+fn tally(export: &PollExport) -> Vec<(usize, &ExportedSuggestion, usize)> {
+    let mut votes = HashMap::new();
+    for vote in &export.votes {
+        *votes.entry(vote.suggestion_id).or_insert(0) += 1;
+    }
+    let mut counted = export
+        .suggestions
+        .iter()
+        .map(|s| (s, votes.get(&s.suggestion_id).copied().unwrap_or(0)))
+        .collect::<Vec<_>>();
+    counted.sort_by(|(a, a_votes), (b, b_votes)| {
+        b_votes
+            .cmp(a_votes)
+            .then(a.created_at.cmp(&b.created_at))
+            .then(a.suggestion_id.cmp(&b.suggestion_id))
+    });
+
+    let mut rank = 0;
+    counted
+        .iter()
+        .enumerate()
+        .map(|(i, &(suggestion, votes))| {
+            if i == 0 || counted[i - 1].1 != votes {
+                rank = i + 1;
+            }
+            (rank, suggestion, votes)
+        })
+        .collect()
+}
+
+/// The text of each suggestion by its id, for the votes.
+fn suggestion_texts(export: &PollExport) -> HashMap<Uuid, &str> {
+    export
+        .suggestions
+        .iter()
+        .map(|s| (s.suggestion_id, s.suggestion.as_str()))
+        .collect()
+}
+
+fn timestamp(at: &DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn to_json(poll_info: &PollInfo, export: &PollExport) -> serde_json::Value {
+    let texts = suggestion_texts(export);
+    json!({
+        "poll_id": poll_info.poll_id,
+        "prompt": poll_info.prompt,
+        "participants": export.members.iter().map(|m| json!({
+            "username": m.username,
+            "role": m.role.as_str(),
+            "joined_at": timestamp(&m.joined_at),
+        })).collect::<Vec<_>>(),
+        "suggestions": export.suggestions.iter().map(|s| json!({
+            "suggestion_id": s.suggestion_id,
+            "suggestion": s.suggestion,
+            "author": s.author,
+            "created_at": timestamp(&s.created_at),
+        })).collect::<Vec<_>>(),
+        "votes": export.votes.iter().map(|v| json!({
+            "voter": v.voter,
+            "suggestion_id": v.suggestion_id,
+            "suggestion": texts.get(&v.suggestion_id),
+        })).collect::<Vec<_>>(),
+        "tally": tally(export).into_iter().map(|(rank, s, votes)| json!({
+            "rank": rank,
+            "suggestion_id": s.suggestion_id,
+            "suggestion": s.suggestion,
+            "votes": votes,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// One table after the other, each under a line naming it and separated
+/// by an empty line.
+fn to_csv(export: &PollExport) -> String {
+    let texts = suggestion_texts(export);
+    let mut csv = String::new();
+    let mut table = |name: &str, header: &str, rows: Vec<Vec<String>>| {
+        if !csv.is_empty() {
+            csv.push_str("\r\n");
+        }
+        csv.push_str(&format!("{name}\r\n{header}\r\n"));
+        for row in rows {
+            let row = row.iter().map(|f| csv_field(f)).collect::<Vec<_>>();
+            csv.push_str(&row.join(","));
+            csv.push_str("\r\n");
+        }
+    };
+
+    table(
+        "participants",
+        "username,role,joined_at",
+        export
+            .members
+            .iter()
+            .map(|m| {
+                vec![
+                    m.username.clone(),
+                    m.role.as_str().to_string(),
+                    timestamp(&m.joined_at),
+                ]
+            })
+            .collect(),
+    );
+    table(
+        "suggestions",
+        "suggestion,author,created_at",
+        export
+            .suggestions
+            .iter()
+            .map(|s| {
+                vec![
+                    s.suggestion.clone(),
+                    s.author.clone().unwrap_or_default(),
+                    timestamp(&s.created_at),
+                ]
+            })
+            .collect(),
+    );
+    table(
+        "votes",
+        "voter,suggestion",
+        export
+            .votes
+            .iter()
+            .map(|v| {
+                vec![
+                    v.voter.clone().unwrap_or_default(),
+                    texts
+                        .get(&v.suggestion_id)
+                        .map(|s| s.to_string())
+                        .unwrap_or_default(),
+                ]
+            })
+            .collect(),
+    );
+    table(
+        "tally",
+        "rank,suggestion,votes",
+        tally(export)
+            .into_iter()
+            .map(|(rank, s, votes)| vec![rank.to_string(), s.suggestion.clone(), votes.to_string()])
+            .collect(),
+    );
+    csv
+}
+
+/// Quoted when it has to be. Fields a spreadsheet would take for a formula
+/// are prefixed with a quote so they're shown as they were written.
+fn csv_field(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{field}")
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+fn to_markdown(prompt: &str, export: &PollExport) -> String {
+    let texts = suggestion_texts(export);
+    let mut markdown = format!("# {}\n", markdown_cell(prompt));
+    let mut table = |name: &str, header: &[&str], rows: Vec<Vec<String>>| {
+        markdown.push_str(&format!("\n## {name}\n\n| {} |\n", header.join(" | ")));
+        markdown.push_str(&format!("|{}\n", " --- |".repeat(header.len())));
+        for row in rows {
+            let row = row.iter().map(|c| markdown_cell(c)).collect::<Vec<_>>();
+            markdown.push_str(&format!("| {} |\n", row.join(" | ")));
+        }
+    };
+
+    table(
+        "Tally",
+        &["Rank", "Suggestion", "Votes"],
+        tally(export)
+            .into_iter()
+            .map(|(rank, s, votes)| vec![rank.to_string(), s.suggestion.clone(), votes.to_string()])
+            .collect(),
+    );
+    table(
+        "Suggestions",
+        &["Suggestion", "Author", "Suggested (UTC)"],
+        export
+            .suggestions
+            .iter()
+            .map(|s| {
+                vec![
+                    s.suggestion.clone(),
+                    s.author.clone().unwrap_or_default(),
+                    s.created_at.format("%Y-%m-%d %H:%M").to_string(),
+                ]
+            })
+            .collect(),
+    );
+    table(
+        "Participants",
+        &["Name", "Role", "Joined (UTC)"],
+        export
+            .members
+            .iter()
+            .map(|m| {
+                vec![
+                    m.username.clone(),
+                    m.role.label().to_string(),
+                    m.joined_at.format("%Y-%m-%d %H:%M").to_string(),
+                ]
+            })
+            .collect(),
+    );
+    table(
+        "Votes",
+        &["Voter", "Suggestion"],
+        export
+            .votes
+            .iter()
+            .map(|v| {
+                vec![
+                    v.voter.clone().unwrap_or_default(),
+                    texts
+                        .get(&v.suggestion_id)
+                        .map(|s| s.to_string())
+                        .unwrap_or_default(),
+                ]
+            })
+            .collect(),
+    );
+    markdown
+}
+
+/// Can't end the cell early or turn into markup.
+fn markdown_cell(cell: &str) -> String {
+    let mut escaped = String::with_capacity(cell.len());
+    for c in cell.chars() {
+        match c {
+            '\\' | '|' | '*' | '_' | '`' | '[' | ']' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '<' => escaped.push_str("&lt;"),
+            '\r' | '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use super::{csv_field, markdown_cell, tally};
+    use crate::storage::{ExportedSuggestion, ExportedVote, PollExport};
+
+    #[test]
+    fn csv_fields_are_quoted_and_kept_from_being_formulas() {
+        assert_eq!(csv_field("Pizza"), "Pizza");
+        assert_eq!(csv_field("Pizza, or pasta"), "\"Pizza, or pasta\"");
+        assert_eq!(csv_field("The \"good\" one"), "\"The \"\"good\"\" one\"");
+        assert_eq!(csv_field("=HYPERLINK(1)"), "'=HYPERLINK(1)");
+        assert_eq!(csv_field("=SUM(1,2)"), "\"'=SUM(1,2)\"");
+    }
+
+    #[test]
+    fn markdown_cells_stay_in_their_cell() {
+        assert_eq!(markdown_cell("a | b"), r"a \| b");
+        assert_eq!(markdown_cell("line\nbreak"), "line break");
+        assert_eq!(markdown_cell("<b>*bold*</b>"), r"&lt;b>\*bold\*&lt;/b>");
+    }
+
+    #[test]
+    fn tied_suggestions_share_their_rank_oldest_first() {
+        let now = Utc::now();
+        let suggestions = [("first", 3), ("second", 1), ("third", 2)]
+            .into_iter()
+            .map(|(suggestion, minutes_ago)| ExportedSuggestion {
+                suggestion_id: Uuid::new_v4(),
+                suggestion: suggestion.into(),
+                author: None,
+                created_at: now - Duration::minutes(minutes_ago),
+            })
+            .collect::<Vec<_>>();
+        let votes = [2, 1, 2, 0, 1]
+            .into_iter()
+            .map(|i| ExportedVote {
+                suggestion_id: suggestions[i].suggestion_id,
+                voter: None,
+            })
+            .collect();
+        let export = PollExport {
+            members: Vec::new(),
+            suggestions,
+            votes,
+        };
+
+        let tally = tally(&export)
+            .into_iter()
+            .map(|(rank, s, votes)| (rank, s.suggestion.as_str(), votes))
+            .collect::<Vec<_>>();
+
+        assert_eq!(tally, [(1, "third", 2), (1, "second", 2), (3, "first", 1)]);
+    }
+}
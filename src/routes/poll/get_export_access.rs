@@ -0,0 +1,74 @@
+use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+
+use crate::{
+    domain::ExportAccess,
+    middleware::{require, Authorized, PollInfo},
+    utils::escape_html,
+};
+
+#[tracing::instrument(
+    name = "Show poll export access page"
+    skip_all,
+    fields(poll_id = %poll_info.poll_id)
+)]
+pub async fn show_export_access(
+    poll_info: PollInfo,
+    _owner: Authorized<require::Owner>,
+    flash_messages: IncomingFlashMessages,
+) -> HttpResponse {
+    let PollInfo {
+        poll_id,
+        prompt,
+        export_access,
+        ..
+    } = poll_info;
+
+    let messages_html = flash_messages
+        .iter()
+        .map(|m| format!("<p><i>{}</i></p>", m.content()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let options = ExportAccess::ALL
+        .iter()
+        .map(|access| {
+            let selected = if *access == export_access {
+                " selected"
+            } else {
+                ""
+            };
+            format!(
+                r#"<option value="{value}"{selected}>{label}</option>"#,
+                value = access.as_str(),
+                label = access.label()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Apoll | Export access</title>
+    </head>
+    <body>
+        {messages_html}
+        <h1>Export access of: {prompt}</h1>
+        <a href="/poll/{poll_id}">Back to the poll</a>
+        <p>Download the results as <a href="/poll/{poll_id}/export?format=csv">CSV</a>, <a href="/poll/{poll_id}/export?format=json">JSON</a> or <a href="/poll/{poll_id}/export?format=md">Markdown</a>.</p>
+        <form action="/poll/{poll_id}/export_access" method="post">
+            <label for="export_access">Who can export the results
+                <select name="export_access">{options}</select>
+            </label>
+            <button type="submit">Save</button>
+        </form>
+    </body>
+</html>"#,
+            prompt = escape_html(&prompt),
+        ))
+}
@@ -0,0 +1,109 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::Utc;
+
+use super::post_invite::InviteError;
+use crate::{
+    middleware::{require, Authorized, PollInfo},
+    storage::Storage,
+    utils::escape_html,
+};
+
+#[tracing::instrument(
+    name = "Show poll invites page"
+    skip_all,
+    fields(poll_id = %poll_info.poll_id)
+)]
+pub async fn show_invites(
+    poll_info: PollInfo,
+    _organizer: Authorized<require::CoOrganizer>,
+    storage: web::Data<dyn Storage>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, InviteError> {
+    let PollInfo {
+        poll_id, prompt, ..
+    } = poll_info;
+
+    let invites = storage
+        .invites(&poll_id)
+        .await
+        .context("failed to retrieve poll invites")?;
+
+    let messages_html = flash_messages
+        .iter()
+        .map(|m| format!("<p><i>{}</i></p>", m.content()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let now = Utc::now();
+    let invites_tr = invites
+        .iter()
+        .map(|i| {
+            let status = if i.revoked_at.is_some() {
+                "revoked"
+            } else if i.expires_at <= now {
+                "expired"
+            } else if i.uses >= i.max_uses {
+                "used up"
+            } else {
+                "active"
+            };
+            let revoke_form = if i.revoked_at.is_none() {
+                format!(
+                    r#"<form action="/poll/{poll_id}/invites/{}/revoke" method="post">
+                    <button type="submit">Revoke</button>
+                </form>"#,
+                    i.invite_id
+                )
+            } else {
+                String::new()
+            };
+            format!(
+                r#"<tr>
+                <td><a href="/poll/{poll_id}?invite={token}">/poll/{poll_id}?invite={token}</a></td>
+                <td>{uses}/{max_uses}</td>
+                <td>{expires_at}</td>
+                <td>{status}</td>
+                <td>{revoke_form}</td>
+            </tr>"#,
+                token = i.token,
+                uses = i.uses,
+                max_uses = i.max_uses,
+                expires_at = i.expires_at.format("%Y-%m-%d %H:%M UTC"),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Apoll | Invites</title>
+    </head>
+    <body>
+        {messages_html}
+        <h1>Invites for: {prompt}</h1>
+        <a href="/poll/{poll_id}">Back to the poll</a>
+        <form action="/poll/{poll_id}/invites" method="post">
+            <label for="expires_in_hours">Expires in (hours)
+                <input type="number" name="expires_in_hours" value="24" min="1" max="720" />
+            </label>
+            <label for="max_uses">Maximum uses
+                <input type="number" name="max_uses" value="1" min="1" max="1000" />
+            </label>
+            <button type="submit">Create invite</button>
+        </form>
+        <table>
+            <tr><th>Link</th><th>Uses</th><th>Expires</th><th>Status</th><th></th></tr>
+            {invites_tr}
+        </table>
+    </body>
+</html>"#,
+            prompt = escape_html(&prompt),
+        )))
+}
@@ -0,0 +1,110 @@
+use actix_web::{http::header::ContentType, web, HttpResponse, ResponseError};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use reqwest::StatusCode;
+
+use crate::{
+    domain::PollRole,
+    middleware::{require, Authorized, PollInfo},
+    storage::Storage,
+    utils::escape_html,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ShowMembersError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ResponseError for ShowMembersError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            ShowMembersError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "Show poll members page"
+    skip_all,
+    fields(poll_id = %poll_info.poll_id)
+)]
+pub async fn show_members(
+    poll_info: PollInfo,
+    organizer: Authorized<require::CoOrganizer>,
+    storage: web::Data<dyn Storage>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, ShowMembersError> {
+    let PollInfo {
+        poll_id, prompt, ..
+    } = poll_info;
+
+    let members = storage
+        .members(&poll_id)
+        .await
+        .context("failed to retrieve poll members")?;
+
+    let messages_html = flash_messages
+        .iter()
+        .map(|m| format!("<p><i>{}</i></p>", m.content()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let is_owner = organizer.role == PollRole::Owner;
+    let members_tr = members
+        .iter()
+        .map(|m| {
+            let actions = if is_owner && m.role != PollRole::Owner {
+                let (new_role, label) = match m.role {
+                    PollRole::CoOrganizer => (PollRole::Participant, "Make participant"),
+                    _ => (PollRole::CoOrganizer, "Make co-organizer"),
+                };
+                format!(
+                    r#"<form action="/poll/{poll_id}/members/{user_id}/role" method="post">
+                    <input type="hidden" name="role" value="{new_role}" />
+                    <button type="submit">{label}</button>
+                </form>
+                <form action="/poll/{poll_id}/members/{user_id}/transfer" method="post">
+                    <button type="submit">Transfer ownership</button>
+                </form>"#,
+                    user_id = m.user_id,
+                    new_role = new_role.as_str(),
+                )
+            } else {
+                String::new()
+            };
+            format!(
+                r#"<tr>
+                <td>{username}</td>
+                <td>{role}</td>
+                <td>{actions}</td>
+            </tr>"#,
+                username = escape_html(&m.username),
+                role = m.role,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Apoll | Members</title>
+    </head>
+    <body>
+        {messages_html}
+        <h1>Members of: {prompt}</h1>
+        <a href="/poll/{poll_id}">Back to the poll</a>
+        <table>
+            <tr><th>Username</th><th>Role</th><th></th></tr>
+            {members_tr}
+        </table>
+    </body>
+</html>"#,
+            prompt = escape_html(&prompt),
+        )))
+}
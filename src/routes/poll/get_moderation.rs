@@ -0,0 +1,151 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+
+use super::post_moderation::ModerationError;
+use crate::{
+    domain::ReportReason,
+    middleware::{require, Authorized, PollInfo},
+    storage::{QueuedItem, Storage},
+    utils::escape_html,
+};
+
+#[tracing::instrument(
+    name = "Show poll moderation queue"
+    skip_all,
+    fields(poll_id = %poll_info.poll_id)
+)]
+pub async fn show_moderation_queue(
+    poll_info: PollInfo,
+    _organizer: Authorized<require::CoOrganizer>,
+    storage: web::Data<dyn Storage>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, ModerationError> {
+    let PollInfo {
+        poll_id, prompt, ..
+    } = poll_info;
+
+    let suggestions = storage
+        .queued_suggestions(&poll_id)
+        .await
+        .context("failed to retrieve held and reported suggestions")?;
+    let members = storage
+        .queued_members(&poll_id)
+        .await
+        .context("failed to retrieve held and reported members")?;
+
+    let messages_html = flash_messages
+        .iter()
+        .map(|m| format!("<p><i>{}</i></p>", m.content()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let suggestions_tr = suggestions
+        .iter()
+        .map(|s| {
+            format!(
+                r#"<tr>
+                <td>{suggestion}</td>
+                <td>{status}</td>
+                <td>{reports}</td>
+                <td>
+                    <form action="/poll/{poll_id}/moderation/suggestions/{id}/approve" method="post">
+                        <button type="submit">Approve</button>
+                    </form>
+                    <form action="/poll/{poll_id}/moderation/suggestions/{id}/remove" method="post">
+                        <button type="submit">Remove</button>
+                    </form>
+                </td>
+            </tr>"#,
+                suggestion = escape_html(&s.content),
+                status = s.status(),
+                reports = s.reports(),
+                id = s.id,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let members_tr = members
+        .iter()
+        .map(|m| {
+            format!(
+                r#"<tr>
+                <td>{username}</td>
+                <td>{status}</td>
+                <td>{reports}</td>
+                <td>
+                    <form action="/poll/{poll_id}/moderation/members/{id}/approve" method="post">
+                        <button type="submit">Approve</button>
+                    </form>
+                    <form action="/poll/{poll_id}/moderation/members/{id}/remove" method="post">
+                        <button type="submit">Remove from poll</button>
+                    </form>
+                </td>
+            </tr>"#,
+                username = escape_html(&m.content),
+                status = m.status(),
+                reports = m.reports(),
+                id = m.id,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Apoll | Moderation</title>
+    </head>
+    <body>
+        {messages_html}
+        <h1>Moderation queue of: {prompt}</h1>
+        <a href="/poll/{poll_id}">Back to the poll</a>
+        <h2>Suggestions</h2>
+        <table>
+            <tr><th>Suggestion</th><th>Status</th><th>Pending reports</th><th></th></tr>
+            {suggestions_tr}
+        </table>
+        <h2>Member names</h2>
+        <table>
+            <tr><th>Name</th><th>Status</th><th>Pending reports</th><th></th></tr>
+            {members_tr}
+        </table>
+    </body>
+</html>"#,
+            prompt = escape_html(&prompt),
+        )))
+}
+
+impl QueuedItem {
+    fn status(&self) -> String {
+        match &self.moderation_reason {
+            Some(reason) => format!("Hidden, it {}", escape_html(reason)),
+            None => "Visible".to_string(),
+        }
+    }
+
+    fn reports(&self) -> String {
+        if self.report_reasons.is_empty() {
+            return "None".to_string();
+        }
+        let mut reasons = self
+            .report_reasons
+            .iter()
+            .filter_map(|r| ReportReason::try_from(r.clone()).ok())
+            .collect::<Vec<_>>();
+        let count = reasons.len();
+        reasons.sort_by_key(|r| r.as_str());
+        reasons.dedup();
+        let reasons = reasons
+            .iter()
+            .map(|r| r.label())
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{count}: {reasons}")
+    }
+}
@@ -1,17 +1,27 @@
-use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web::{http::header::ContentType, web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
 
+use crate::proof_of_work::ProofOfWork;
+
 #[tracing::instrument(
     name = "Show new poll page"
     skip_all,
     fields(poll_id=tracing::field::Empty)
 )]
-pub async fn new_poll(flash_messages: IncomingFlashMessages) -> HttpResponse {
+pub async fn new_poll(
+    flash_messages: IncomingFlashMessages,
+    proof_of_work: web::Data<ProofOfWork>,
+) -> HttpResponse {
     let error_html = flash_messages
         .iter()
         .map(|m| format!("<p><i>{}</i></p>", m.content()))
         .collect::<Vec<_>>()
         .join("\n");
+    let proof_of_work_fields = if proof_of_work.required_for_new_poll() {
+        proof_of_work.form_fields("new_poll")
+    } else {
+        String::new()
+    };
 
     HttpResponse::Ok()
         .content_type(ContentType::html())
@@ -32,6 +42,16 @@ pub async fn new_poll(flash_messages: IncomingFlashMessages) -> HttpResponse {
         <label for="prompt">Poll prompt
             <input type="text" name="prompt" />
         </label><br>
+        <label for="invite_only">Invite only
+            <input type="checkbox" name="invite_only" />
+        </label><br>
+        <label for="passphrase">Passphrase (optional)
+            <input type="password" name="passphrase" />
+        </label><br>
+        <label for="require_proof_of_work">Make visitors pass an anti-spam check to join
+            <input type="checkbox" name="require_proof_of_work" />
+        </label><br>
+        {proof_of_work_fields}
         <button type="submit">Create poll</button>
         </form>
     </body>
@@ -1,11 +1,41 @@
 mod get;
+mod get_content_filter;
+mod get_export;
+mod get_export_access;
+mod get_invites;
+mod get_members;
+mod get_moderation;
 mod get_new;
+mod post_content_filter;
+mod post_export_access;
+mod post_invite;
 mod post_join;
+mod post_member_role;
+mod post_moderation;
 mod post_new;
+mod post_report;
+mod post_revoke_invite;
 mod post_suggest;
+mod post_transfer_ownership;
+mod post_unlock;
 
 pub use get::show_poll;
+pub use get_content_filter::show_content_filter;
+pub use get_export::export_poll;
+pub use get_export_access::show_export_access;
+pub use get_invites::show_invites;
+pub use get_members::show_members;
+pub use get_moderation::show_moderation_queue;
 pub use get_new::new_poll;
+pub use post_content_filter::update_content_filter;
+pub use post_export_access::update_export_access;
+pub use post_invite::create_invite;
 pub use post_join::join_poll;
+pub use post_member_role::change_member_role;
+pub use post_moderation::{approve_member, approve_suggestion, remove_member, remove_suggestion};
 pub use post_new::create_poll;
+pub use post_report::report_content;
+pub use post_revoke_invite::revoke_invite;
 pub use post_suggest::suggest_answer;
+pub use post_transfer_ownership::transfer_ownership;
+pub use post_unlock::{unlock_poll, UnlockAttempts};
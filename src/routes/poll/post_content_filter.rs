@@ -0,0 +1,253 @@
+use actix_web::{error::InternalError, web, HttpResponse, ResponseError};
+use actix_web_flash_messages::FlashMessage;
+use reqwest::StatusCode;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    configuration::{ContentFilterSettings, FilterAction},
+    middleware::{require, Authorized, PollInfo},
+    poll_cache::PollCache,
+    storage::Storage,
+    utils::{flash_message_redirect, redirect},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ContentFilterError {
+    #[error("The maximum of repeated characters must be a number above 0")]
+    InvalidMax,
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl ResponseError for ContentFilterError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            ContentFilterError::InvalidMax => StatusCode::BAD_REQUEST,
+            ContentFilterError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// What a poll does with one of the filters.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterChoice {
+    /// Use the site's settings
+    Default,
+    Off,
+    Hold,
+    Reject,
+}
+
+impl FilterChoice {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterChoice::Default => "default",
+            FilterChoice::Off => "off",
+            FilterChoice::Hold => "hold",
+            FilterChoice::Reject => "reject",
+        }
+    }
+
+    /// The choice a poll made for the filter `key`.
+    pub fn of(poll_settings: Option<&Value>, key: &str) -> Self {
+        match poll_settings.and_then(|s| s.get(key)) {
+            None => FilterChoice::Default,
+            Some(Value::Null) => FilterChoice::Off,
+            Some(filter) => match filter.get("action").and_then(Value::as_str) {
+                Some("hold") => FilterChoice::Hold,
+                _ => FilterChoice::Reject,
+            },
+        }
+    }
+
+    fn action(&self) -> Option<FilterAction> {
+        match self {
+            FilterChoice::Hold => Some(FilterAction::Hold),
+            FilterChoice::Reject => Some(FilterAction::Reject),
+            FilterChoice::Default | FilterChoice::Off => None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct ContentFilterForm {
+    word_list: FilterChoice,
+    #[serde(default)]
+    words: String,
+    links: FilterChoice,
+    repeated_characters: FilterChoice,
+    #[serde(default)]
+    max_repeated: String,
+}
+
+#[tracing::instrument(
+    name = "update poll content filter"
+    skip_all
+    fields(poll_id = %poll_info.poll_id, form = ?form.0)
+)]
+pub async fn update_content_filter(
+    poll_info: PollInfo,
+    _organizer: Authorized<require::CoOrganizer>,
+    storage: web::Data<dyn Storage>,
+    poll_cache: web::Data<PollCache>,
+    form: web::Form<ContentFilterForm>,
+    content_filter: web::Data<ContentFilterSettings>,
+) -> Result<HttpResponse, InternalError<ContentFilterError>> {
+    let content_filter_uri = &format!("/poll/{}/content_filter", poll_info.poll_id);
+    let poll_settings =
+        poll_settings(&form.0).map_err(|e| flash_message_redirect(e, content_filter_uri))?;
+
+    // Check that the site and poll settings still make sense together
+    crate::content_filter::merge_settings(&content_filter, poll_settings.as_ref()).map_err(
+        |e| flash_message_redirect(ContentFilterError::Unexpected(e.into()), content_filter_uri),
+    )?;
+
+    storage
+        .set_content_filter(&poll_info.poll_id, poll_settings.as_ref())
+        .await
+        .map_err(|e| {
+            flash_message_redirect(ContentFilterError::Unexpected(e.into()), content_filter_uri)
+        })?;
+    poll_cache.invalidate(&poll_info.poll_id);
+
+    FlashMessage::info("Content filter updated").send();
+    Ok(redirect(content_filter_uri))
+}
+
+/// Only the filters that differ from the site's are stored, so changes to the
+/// site settings still apply to the rest.
+fn poll_settings(form: &ContentFilterForm) -> Result<Option<Value>, ContentFilterError> {
+    let mut settings = Map::new();
+
+    let words = form
+        .words
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>();
+    insert_choice(
+        &mut settings,
+        "word_list",
+        form.word_list,
+        |action| json!({ "words": words, "action": action }),
+    );
+
+    insert_choice(
+        &mut settings,
+        "links",
+        form.links,
+        |action| json!({ "action": action }),
+    );
+
+    let max = match (form.repeated_characters.action(), form.max_repeated.trim()) {
+        (Some(_), max) => Some(
+            max.parse::<usize>()
+                .ok()
+                .filter(|max| *max > 0)
+                .ok_or(ContentFilterError::InvalidMax)?,
+        ),
+        (None, _) => None,
+    };
+    insert_choice(
+        &mut settings,
+        "repeated_characters",
+        form.repeated_characters,
+        |action| json!({ "max": max, "action": action }),
+    );
+
+    Ok((!settings.is_empty()).then_some(Value::Object(settings)))
+}
+
+fn insert_choice(
+    settings: &mut Map<String, Value>,
+    key: &str,
+    choice: FilterChoice,
+    filter: impl FnOnce(FilterAction) -> Value,
+) {
+    match (choice, choice.action()) {
+        (FilterChoice::Default, _) => {}
+        (_, None) => {
+            settings.insert(key.into(), Value::Null);
+        }
+        (_, Some(action)) => {
+            settings.insert(key.into(), filter(action));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::assert_err;
+    use serde_json::json;
+
+    use super::{poll_settings, ContentFilterForm, FilterChoice};
+
+    fn form(
+        word_list: FilterChoice,
+        links: FilterChoice,
+        repeated: FilterChoice,
+    ) -> ContentFilterForm {
+        ContentFilterForm {
+            word_list,
+            words: "pizza, pineapple\nanchovies,".into(),
+            links,
+            repeated_characters: repeated,
+            max_repeated: "3".into(),
+        }
+    }
+
+    #[test]
+    fn defaults_are_not_stored() {
+        let form = form(
+            FilterChoice::Default,
+            FilterChoice::Default,
+            FilterChoice::Default,
+        );
+        assert_eq!(poll_settings(&form).unwrap(), None);
+    }
+
+    #[test]
+    fn choices_become_poll_settings() {
+        let form = form(FilterChoice::Hold, FilterChoice::Off, FilterChoice::Reject);
+        assert_eq!(
+            poll_settings(&form).unwrap(),
+            Some(json!({
+                "word_list": { "words": ["pizza", "pineapple", "anchovies"], "action": "hold" },
+                "links": null,
+                "repeated_characters": { "max": 3, "action": "reject" },
+            }))
+        );
+    }
+
+    #[test]
+    fn repeated_characters_need_a_maximum() {
+        let mut form = form(
+            FilterChoice::Default,
+            FilterChoice::Default,
+            FilterChoice::Hold,
+        );
+        for max in ["", "0", "many"] {
+            form.max_repeated = max.into();
+            assert_err!(poll_settings(&form));
+        }
+    }
+
+    #[test]
+    fn stored_choices_are_read_back() {
+        let settings = json!({ "links": null, "word_list": { "words": [], "action": "hold" } });
+        assert_eq!(
+            FilterChoice::of(Some(&settings), "links"),
+            FilterChoice::Off
+        );
+        assert_eq!(
+            FilterChoice::of(Some(&settings), "word_list"),
+            FilterChoice::Hold
+        );
+        assert_eq!(
+            FilterChoice::of(Some(&settings), "repeated_characters"),
+            FilterChoice::Default
+        );
+        assert_eq!(FilterChoice::of(None, "links"), FilterChoice::Default);
+    }
+}
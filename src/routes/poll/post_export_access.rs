@@ -0,0 +1,55 @@
+use actix_web::{error::InternalError, web, HttpResponse, ResponseError};
+use actix_web_flash_messages::FlashMessage;
+use reqwest::StatusCode;
+
+use crate::{
+    domain::ExportAccess,
+    middleware::{require, Authorized, PollInfo},
+    poll_cache::PollCache,
+    storage::Storage,
+    utils::{flash_message_redirect, redirect},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportAccessError {
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl ResponseError for ExportAccessError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            ExportAccessError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct ExportAccessForm {
+    export_access: ExportAccess,
+}
+
+#[tracing::instrument(
+    name = "update poll export access"
+    skip_all
+    fields(poll_id = %poll_info.poll_id, form = ?form.0)
+)]
+pub async fn update_export_access(
+    poll_info: PollInfo,
+    _owner: Authorized<require::Owner>,
+    storage: web::Data<dyn Storage>,
+    poll_cache: web::Data<PollCache>,
+    form: web::Form<ExportAccessForm>,
+) -> Result<HttpResponse, InternalError<ExportAccessError>> {
+    let export_access_uri = &format!("/poll/{}/export_access", poll_info.poll_id);
+    storage
+        .set_export_access(&poll_info.poll_id, form.export_access)
+        .await
+        .map_err(|e| {
+            flash_message_redirect(ExportAccessError::Unexpected(e.into()), export_access_uri)
+        })?;
+    poll_cache.invalidate(&poll_info.poll_id);
+
+    FlashMessage::info(format!("Export access set to: {}", form.export_access)).send();
+    Ok(redirect(export_access_uri))
+}
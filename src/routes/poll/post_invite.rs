@@ -0,0 +1,71 @@
+use actix_web::{error::InternalError, web, HttpResponse, ResponseError};
+use actix_web_flash_messages::FlashMessage;
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use reqwest::StatusCode;
+use validator::{Validate, ValidationErrors};
+
+use crate::{
+    domain::InviteFormData,
+    middleware::{require, Authorized, PollInfo},
+    storage::{NewInvite, Storage},
+    utils::{flash_message_redirect, redirect},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum InviteError {
+    #[error("{0}")]
+    Validation(ValidationErrors),
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl ResponseError for InviteError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            InviteError::Validation(_) => StatusCode::BAD_REQUEST,
+            InviteError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "create poll invite"
+    skip_all
+    fields(poll_id = %poll_info.poll_id)
+)]
+pub async fn create_invite(
+    poll_info: PollInfo,
+    _organizer: Authorized<require::CoOrganizer>,
+    storage: web::Data<dyn Storage>,
+    form: web::Form<InviteFormData>,
+) -> Result<HttpResponse, InternalError<InviteError>> {
+    let invites_uri = &format!("/poll/{}/invites", poll_info.poll_id);
+    if let Err(e) = form.validate() {
+        return Err(flash_message_redirect(
+            InviteError::Validation(e),
+            invites_uri,
+        ));
+    }
+
+    let invite = NewInvite {
+        token: generate_invite_token(),
+        max_uses: form.max_uses,
+        expires_at: Utc::now() + Duration::hours(i64::from(form.expires_in_hours)),
+    };
+    storage
+        .create_invite(&poll_info.poll_id, &invite)
+        .await
+        .map_err(|e| flash_message_redirect(InviteError::Unexpected(e.into()), invites_uri))?;
+
+    FlashMessage::info("Invite created").send();
+    Ok(redirect(invites_uri))
+}
+
+fn generate_invite_token() -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(32)
+        .collect()
+}
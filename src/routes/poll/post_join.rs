@@ -1,15 +1,45 @@
-use actix_web::{web, HttpResponse, ResponseError};
+use actix_web::{error::InternalError, web, HttpResponse, ResponseError};
+use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
-use reqwest::{header::LOCATION, StatusCode};
-use sqlx::PgPool;
+use reqwest::StatusCode;
 use uuid::Uuid;
+use validator::ValidationError;
 
-use crate::{middleware::PollInfo, user_session::TypedSession};
+use crate::{
+    configuration::ContentFilterSettings,
+    content_filter::{ContentFilter, Verdict},
+    domain::{deserialize_checkbox, ModerationStatus, ParticipantName, PollRole},
+    metrics::Metrics,
+    middleware::PollInfo,
+    proof_of_work::{ProofOfWork, ProofOfWorkError},
+    storage::{NewMember, Storage, StorageError},
+    user_session::TypedSession,
+    utils::{flash_message_redirect, redirect},
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum JoinError {
     #[error("pool does not exist")]
     NotFoundError,
+    #[error("this poll can only be joined with an invite")]
+    InviteRequired,
+    #[error("this invite is invalid, expired or has already been used")]
+    InvalidInvite,
+    #[error(transparent)]
+    ProofOfWork(ProofOfWorkError),
+    #[error("username: {0}")]
+    InvalidName(ValidationError),
+    #[error("The name {name} {reason}.")]
+    FilteredName {
+        name: ParticipantName,
+        reason: String,
+    },
+    #[error("{}", name_taken_message(.name, .taken_by, .alternatives))]
+    NameTaken {
+        name: ParticipantName,
+        taken_by: Option<String>,
+        alternatives: Vec<ParticipantName>,
+    },
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -18,14 +48,64 @@ impl ResponseError for JoinError {
     fn status_code(&self) -> reqwest::StatusCode {
         match self {
             JoinError::NotFoundError => StatusCode::NOT_FOUND,
+            JoinError::InviteRequired => StatusCode::FORBIDDEN,
+            JoinError::InvalidInvite => StatusCode::FORBIDDEN,
+            JoinError::ProofOfWork(_) => StatusCode::BAD_REQUEST,
+            JoinError::InvalidName(_) => StatusCode::BAD_REQUEST,
+            JoinError::FilteredName { .. } => StatusCode::BAD_REQUEST,
+            JoinError::NameTaken { .. } => StatusCode::CONFLICT,
             JoinError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
+fn name_taken_message(
+    name: &ParticipantName,
+    taken_by: &Option<String>,
+    alternatives: &[ParticipantName],
+) -> String {
+    let mut message = match taken_by {
+        Some(taken_by) if taken_by != name.as_ref() => {
+            format!("The name {name} looks too much like {taken_by}, who is already in this poll.")
+        }
+        _ => format!("The name {name} is already taken in this poll."),
+    };
+    if !alternatives.is_empty() {
+        let alternatives = alternatives
+            .iter()
+            .map(|a| a.as_ref())
+            .collect::<Vec<_>>()
+            .join(", ");
+        message.push_str(&format!(" How about: {alternatives}?"));
+    }
+    message
+}
+
+impl JoinError {
+    /// Mistakes the user can fix are flashed on the poll page, the rest are
+    /// returned as is.
+    fn into_response(self, poll_uri: &str) -> InternalError<JoinError> {
+        match self {
+            JoinError::ProofOfWork(_)
+            | JoinError::InvalidName(_)
+            | JoinError::FilteredName { .. }
+            | JoinError::NameTaken { .. } => flash_message_redirect(self, poll_uri),
+            e => {
+                let status = e.status_code();
+                InternalError::new(e, status)
+            }
+        }
+    }
+}
+
 #[derive(serde::Deserialize)]
 pub struct JoinForm {
     username: String,
+    invite: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    observer: bool,
+    pow_challenge: Option<String>,
+    pow_solution: Option<String>,
 }
 
 #[tracing::instrument(
@@ -38,74 +118,168 @@ pub struct JoinForm {
 )]
 pub async fn join_poll(
     form: web::Form<JoinForm>,
-    db_pool: web::Data<PgPool>,
+    storage: web::Data<dyn Storage>,
     poll_info: PollInfo,
     session: TypedSession,
-) -> Result<HttpResponse, JoinError> {
+    proof_of_work: web::Data<ProofOfWork>,
+    content_filter: web::Data<ContentFilterSettings>,
+    metrics: web::Data<Metrics>,
+) -> Result<HttpResponse, InternalError<JoinError>> {
     tracing::Span::current().record("poll_id", &tracing::field::display(&poll_info.poll_id));
     tracing::Span::current().record("user_name", &tracing::field::display(&form.0.username));
+    let poll_uri = &format!("/poll/{}", poll_info.poll_id);
 
     // Reject user if they're already logged in
     if session
         .get_user_id()
-        .map_err(|e| JoinError::UnexpectedError(e.into()))?
+        .map_err(|e| JoinError::UnexpectedError(e.into()).into_response(poll_uri))?
         .is_some()
     {
-        // TODO: add message saying you're already logged in
-        return Ok(HttpResponse::SeeOther()
-            .insert_header((LOCATION, format!("/poll/{}", poll_info.poll_id)))
-            .finish());
+        FlashMessage::info("You are already logged in").send();
+        return Ok(redirect(poll_uri));
+    };
+
+    let invite = match (poll_info.invite_only, form.0.invite) {
+        (true, None) => return Err(JoinError::InviteRequired.into_response(poll_uri)),
+        (true, Some(token)) => Some(token),
+        (false, _) => None,
+    };
+
+    if proof_of_work.required_for_join(poll_info.require_proof_of_work) {
+        proof_of_work
+            .verify(
+                &format!("join:{}", poll_info.poll_id),
+                form.0.pow_challenge.as_deref(),
+                form.0.pow_solution.as_deref(),
+            )
+            .await
+            .map_err(|e| JoinError::ProofOfWork(e).into_response(poll_uri))?;
+    }
+
+    let username = ParticipantName::parse(form.0.username)
+        .map_err(|e| JoinError::InvalidName(e).into_response(poll_uri))?;
+    let content_filter =
+        ContentFilter::for_poll(&content_filter, poll_info.content_filter.as_ref())
+            .context("failed to read the poll's content filter")
+            .map_err(|e| JoinError::UnexpectedError(e).into_response(poll_uri))?;
+    let status = match content_filter.check(username.as_ref()) {
+        Verdict::Allow => ModerationStatus::Visible,
+        Verdict::Hold(reason) => ModerationStatus::Held(reason),
+        Verdict::Reject(reason) => {
+            return Err(JoinError::FilteredName {
+                name: username,
+                reason,
+            }
+            .into_response(poll_uri))
+        }
+    };
+
+    let role = if form.0.observer {
+        PollRole::Observer
+    } else {
+        PollRole::Participant
     };
 
-    let user_id = create_and_insert_user(&db_pool, poll_info.poll_id, form.0.username)
-        .await
-        .context("failed to create and insert user into the poll")?;
+    let user_id = match create_and_insert_user(
+        storage.get_ref(),
+        poll_info.poll_id,
+        &username,
+        role,
+        &status,
+        invite,
+    )
+    .await
+    {
+        Err(JoinError::NameTaken { name, .. }) => {
+            let taken_by = storage
+                .find_lookalike(&poll_info.poll_id, &name.skeleton())
+                .await
+                .context("failed to look for the user with a similar name")
+                .map_err(|e| JoinError::UnexpectedError(e).into_response(poll_uri))?;
+            let alternatives =
+                find_available_alternatives(storage.get_ref(), &poll_info.poll_id, &name)
+                    .await
+                    .context("failed to look for alternative usernames")
+                    .map_err(|e| JoinError::UnexpectedError(e).into_response(poll_uri))?;
+            return Err(JoinError::NameTaken {
+                name,
+                taken_by,
+                alternatives,
+            }
+            .into_response(poll_uri));
+        }
+        result => result.map_err(|e| e.into_response(poll_uri))?,
+    };
+    metrics.poll_joins.inc();
 
     session.renew();
     session
         .insert_user_id(user_id)
-        .context("failed to insert user_id into session store")?;
+        .context("failed to insert user_id into session store")
+        .map_err(|e| JoinError::UnexpectedError(e).into_response(poll_uri))?;
 
-    Ok(HttpResponse::SeeOther()
-        .insert_header((LOCATION, format!("/poll/{}", poll_info.poll_id)))
-        .finish())
+    if let ModerationStatus::Held(reason) = status {
+        FlashMessage::info(format!(
+            "Your name {reason}, the other members will see it once an organizer approves it."
+        ))
+        .send();
+    }
+    Ok(redirect(poll_uri))
 }
 
 #[tracing::instrument(
     name = "create and insert user into poll users"
-    skip(db_pool)
+    skip(storage, invite)
 )]
 async fn create_and_insert_user(
-    db_pool: &PgPool,
+    storage: &dyn Storage,
     poll_id: Uuid,
-    username: String,
-) -> Result<Uuid, sqlx::Error> {
-    let mut transaction = db_pool.begin().await?;
-
-    let user_id = Uuid::new_v4();
-    sqlx::query!(
-        r#"
-        INSERT INTO users (user_id, created_at)
-        VALUES ($1, now())
-        "#,
-        &user_id
-    )
-    .execute(&mut transaction)
-    .await?;
-
-    sqlx::query!(
-        r#"
-        INSERT INTO poll_users (poll_id, user_id, username)
-        VALUES ($1, $2, $3)
-        "#,
+    username: &ParticipantName,
+    role: PollRole,
+    status: &ModerationStatus,
+    invite: Option<String>,
+) -> Result<Uuid, JoinError> {
+    let member = NewMember {
         poll_id,
-        &user_id,
-        username
-    )
-    .execute(&mut transaction)
-    .await?;
+        name: username.clone(),
+        role,
+        status: status.clone(),
+        invite,
+    };
+    match storage.add_member(&member).await {
+        Ok(Some(user_id)) => Ok(user_id),
+        Ok(None) => Err(JoinError::InvalidInvite),
+        Err(StorageError::Conflict) => Err(JoinError::NameTaken {
+            name: member.name,
+            taken_by: None,
+            alternatives: vec![],
+        }),
+        Err(e) => Err(JoinError::UnexpectedError(
+            anyhow::Error::new(e).context("failed to create and insert user into the poll"),
+        )),
+    }
+}
+
+/// The first few alternatives to `name` that don't look like the name of
+/// anybody in the poll.
+#[tracing::instrument(name = "find available alternative usernames", skip(storage))]
+async fn find_available_alternatives(
+    storage: &dyn Storage,
+    poll_id: &Uuid,
+    name: &ParticipantName,
+) -> Result<Vec<ParticipantName>, StorageError> {
+    let candidates = name.alternatives();
+    let candidate_skeletons = candidates.iter().map(|c| c.skeleton()).collect::<Vec<_>>();
 
-    transaction.commit().await?;
+    let taken = storage
+        .taken_skeletons(poll_id, &candidate_skeletons)
+        .await?;
 
-    Ok(user_id)
+    Ok(candidates
+        .into_iter()
+        .zip(candidate_skeletons)
+        .filter(|(_, skeleton)| !taken.contains(skeleton))
+        .map(|(candidate, _)| candidate)
+        .take(3)
+        .collect())
 }
@@ -0,0 +1,70 @@
+use actix_web::{error::InternalError, web, HttpResponse, ResponseError};
+use actix_web_flash_messages::FlashMessage;
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    domain::PollRole,
+    middleware::{require, Authorized, PollInfo},
+    storage::Storage,
+    utils::{flash_message_redirect, redirect},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum MemberError {
+    #[error("The owner's role can only change by transferring ownership")]
+    OwnerRole,
+    #[error("Could not find this member in the poll")]
+    NotFound,
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl ResponseError for MemberError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            MemberError::OwnerRole => StatusCode::BAD_REQUEST,
+            MemberError::NotFound => StatusCode::NOT_FOUND,
+            MemberError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct MemberPath {
+    pub user_id: Uuid,
+}
+
+#[derive(serde::Deserialize)]
+pub struct MemberRoleForm {
+    role: PollRole,
+}
+
+#[tracing::instrument(
+    name = "change poll member role"
+    skip_all
+    fields(poll_id = %poll_info.poll_id, user_id = %path.user_id, role = ?form.role)
+)]
+pub async fn change_member_role(
+    poll_info: PollInfo,
+    _owner: Authorized<require::Owner>,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<MemberPath>,
+    form: web::Form<MemberRoleForm>,
+) -> Result<HttpResponse, InternalError<MemberError>> {
+    let members_uri = &format!("/poll/{}/members", poll_info.poll_id);
+    if form.role == PollRole::Owner {
+        return Err(flash_message_redirect(MemberError::OwnerRole, members_uri));
+    }
+
+    let updated = storage
+        .set_role(&poll_info.poll_id, &path.user_id, form.role)
+        .await
+        .map_err(|e| flash_message_redirect(MemberError::Unexpected(e.into()), members_uri))?;
+    if !updated {
+        return Err(flash_message_redirect(MemberError::NotFound, members_uri));
+    }
+
+    FlashMessage::info(format!("Member is now {}", form.role)).send();
+    Ok(redirect(members_uri))
+}
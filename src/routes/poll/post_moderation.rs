@@ -0,0 +1,153 @@
+use actix_web::{error::InternalError, web, HttpResponse, ResponseError};
+use actix_web_flash_messages::FlashMessage;
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    middleware::{require, Authorized, PollInfo},
+    storage::Storage,
+    utils::{flash_message_redirect, redirect},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ModerationError {
+    #[error("Could not find this item in the moderation queue")]
+    NotFound,
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl ResponseError for ModerationError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            ModerationError::NotFound => StatusCode::NOT_FOUND,
+            ModerationError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct SuggestionPath {
+    suggestion_id: Uuid,
+}
+
+#[derive(serde::Deserialize)]
+pub struct MemberPath {
+    user_id: Uuid,
+}
+
+#[tracing::instrument(
+    name = "approve suggestion"
+    skip_all
+    fields(poll_id = %poll_info.poll_id, suggestion_id = %path.suggestion_id)
+)]
+pub async fn approve_suggestion(
+    poll_info: PollInfo,
+    _organizer: Authorized<require::CoOrganizer>,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<SuggestionPath>,
+) -> Result<HttpResponse, InternalError<ModerationError>> {
+    let moderation_uri = &format!("/poll/{}/moderation", poll_info.poll_id);
+    let approved = storage
+        .approve_suggestion(&poll_info.poll_id, &path.suggestion_id)
+        .await
+        .map_err(|e| {
+            flash_message_redirect(ModerationError::Unexpected(e.into()), moderation_uri)
+        })?;
+    if !approved {
+        return Err(flash_message_redirect(
+            ModerationError::NotFound,
+            moderation_uri,
+        ));
+    }
+
+    FlashMessage::info("Suggestion approved").send();
+    Ok(redirect(moderation_uri))
+}
+
+#[tracing::instrument(
+    name = "remove suggestion"
+    skip_all
+    fields(poll_id = %poll_info.poll_id, suggestion_id = %path.suggestion_id)
+)]
+pub async fn remove_suggestion(
+    poll_info: PollInfo,
+    _organizer: Authorized<require::CoOrganizer>,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<SuggestionPath>,
+) -> Result<HttpResponse, InternalError<ModerationError>> {
+    let moderation_uri = &format!("/poll/{}/moderation", poll_info.poll_id);
+    let removed = storage
+        .remove_suggestion(&poll_info.poll_id, &path.suggestion_id)
+        .await
+        .map_err(|e| {
+            flash_message_redirect(ModerationError::Unexpected(e.into()), moderation_uri)
+        })?;
+    if !removed {
+        return Err(flash_message_redirect(
+            ModerationError::NotFound,
+            moderation_uri,
+        ));
+    }
+
+    FlashMessage::info("Suggestion removed").send();
+    Ok(redirect(moderation_uri))
+}
+
+#[tracing::instrument(
+    name = "approve member name"
+    skip_all
+    fields(poll_id = %poll_info.poll_id, user_id = %path.user_id)
+)]
+pub async fn approve_member(
+    poll_info: PollInfo,
+    _organizer: Authorized<require::CoOrganizer>,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<MemberPath>,
+) -> Result<HttpResponse, InternalError<ModerationError>> {
+    let moderation_uri = &format!("/poll/{}/moderation", poll_info.poll_id);
+    let approved = storage
+        .approve_member(&poll_info.poll_id, &path.user_id)
+        .await
+        .map_err(|e| {
+            flash_message_redirect(ModerationError::Unexpected(e.into()), moderation_uri)
+        })?;
+    if !approved {
+        return Err(flash_message_redirect(
+            ModerationError::NotFound,
+            moderation_uri,
+        ));
+    }
+
+    FlashMessage::info("Member approved").send();
+    Ok(redirect(moderation_uri))
+}
+
+#[tracing::instrument(
+    name = "remove member"
+    skip_all
+    fields(poll_id = %poll_info.poll_id, user_id = %path.user_id)
+)]
+pub async fn remove_member(
+    poll_info: PollInfo,
+    _organizer: Authorized<require::CoOrganizer>,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<MemberPath>,
+) -> Result<HttpResponse, InternalError<ModerationError>> {
+    let moderation_uri = &format!("/poll/{}/moderation", poll_info.poll_id);
+    let removed = storage
+        .remove_member(&poll_info.poll_id, &path.user_id)
+        .await
+        .map_err(|e| {
+            flash_message_redirect(ModerationError::Unexpected(e.into()), moderation_uri)
+        })?;
+    if !removed {
+        return Err(flash_message_redirect(
+            ModerationError::NotFound,
+            moderation_uri,
+        ));
+    }
+
+    FlashMessage::info("Member removed from the poll").send();
+    Ok(redirect(moderation_uri))
+}
@@ -1,15 +1,28 @@
 use actix_web::{error::InternalError, web, HttpResponse, ResponseError};
 use reqwest::{header::LOCATION, StatusCode};
-use sqlx::{PgPool, Postgres, Transaction};
-use uuid::Uuid;
+use secrecy::Secret;
 use validator::{Validate, ValidationErrors};
 
-use crate::{domain::PollFormData, user_session::TypedSession, utils::flash_message_redirect};
+use crate::{
+    authentication::compute_passphrase_hash,
+    configuration::ContentFilterSettings,
+    content_filter::{ContentFilter, Verdict},
+    domain::{ParticipantName, PollFormData},
+    metrics::Metrics,
+    proof_of_work::{ProofOfWork, ProofOfWorkError},
+    storage::{CreatedPoll, NewPoll, Storage},
+    user_session::TypedSession,
+    utils::flash_message_redirect,
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum CreatePollError {
     #[error("{0}")]
     Validation(ValidationErrors),
+    #[error(transparent)]
+    ProofOfWork(#[from] ProofOfWorkError),
+    #[error("The {field} {reason}.")]
+    Filtered { field: &'static str, reason: String },
     #[error("failed to read user session")]
     Session(#[from] serde_json::Error),
     #[error(transparent)]
@@ -20,6 +33,8 @@ impl ResponseError for CreatePollError {
     fn status_code(&self) -> reqwest::StatusCode {
         match self {
             CreatePollError::Validation(_) => StatusCode::BAD_REQUEST,
+            CreatePollError::ProofOfWork(_) => StatusCode::BAD_REQUEST,
+            CreatePollError::Filtered { .. } => StatusCode::BAD_REQUEST,
             CreatePollError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
             CreatePollError::Session(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
@@ -36,108 +51,83 @@ impl ResponseError for CreatePollError {
 )]
 pub async fn create_poll(
     form: web::Form<PollFormData>,
-    db_pool: web::Data<PgPool>,
+    storage: web::Data<dyn Storage>,
     session: TypedSession,
+    proof_of_work: web::Data<ProofOfWork>,
+    content_filter: web::Data<ContentFilterSettings>,
+    metrics: web::Data<Metrics>,
 ) -> Result<HttpResponse, InternalError<CreatePollError>> {
+    if proof_of_work.required_for_new_poll() {
+        proof_of_work
+            .verify(
+                "new_poll",
+                form.pow_challenge.as_deref(),
+                form.pow_solution.as_deref(),
+            )
+            .await
+            .map_err(|e| flash_message_redirect(CreatePollError::ProofOfWork(e), "/"))?;
+    }
     if let Err(e) = form.validate() {
         return Err(flash_message_redirect(CreatePollError::Validation(e), "/"));
     }
+    let username = ParticipantName::parse(form.0.username).map_err(|e| {
+        let mut errors = ValidationErrors::new();
+        errors.add("username", e);
+        flash_message_redirect(CreatePollError::Validation(errors), "/")
+    })?;
+    // A new poll has nobody to review held content yet, so it's rejected
+    let content_filter = ContentFilter::new(&content_filter);
+    for (field, text) in [
+        ("prompt", form.0.prompt.as_str()),
+        ("username", username.as_ref()),
+    ] {
+        if let Verdict::Hold(reason) | Verdict::Reject(reason) = content_filter.check(text) {
+            return Err(flash_message_redirect(
+                CreatePollError::Filtered { field, reason },
+                "/",
+            ));
+        }
+    }
 
-    let mut transaction = db_pool.begin().await.map_err(unexpected)?;
-    // Create new user
-    let user_id = insert_new_user(&mut transaction)
-        .await
-        .map_err(unexpected)?;
-    // Create new poll
-    let poll_id = insert_new_poll(&mut transaction, &user_id, form.0.prompt)
-        .await
-        .map_err(unexpected)?;
-    // Create poll_user instance with new user and poll
-    link_poll_user(&mut transaction, &poll_id, &user_id, form.0.username)
+    let passphrase_hash = match form.0.passphrase {
+        Some(passphrase) => Some(
+            compute_passphrase_hash(Secret::new(passphrase))
+                .await
+                .map_err(|e| flash_message_redirect(CreatePollError::Unexpected(e), "/"))?,
+        ),
+        None => None,
+    };
+    let is_protected = passphrase_hash.is_some();
+
+    // Create the poll along with its creator
+    let CreatedPoll {
+        poll_id,
+        creator_id: user_id,
+    } = storage
+        .create_poll(&NewPoll {
+            prompt: form.0.prompt,
+            creator_name: username,
+            invite_only: form.0.invite_only,
+            passphrase_hash,
+            require_proof_of_work: form.0.require_proof_of_work,
+        })
         .await
-        .map_err(unexpected)?;
-    transaction.commit().await.map_err(unexpected)?;
+        .map_err(|e| flash_message_redirect(CreatePollError::Unexpected(e.into()), "/"))?;
+    metrics.polls_created.inc();
 
     // Log user in
     session.renew();
     session
         .insert_user_id(user_id)
         .map_err(|e| flash_message_redirect(CreatePollError::Session(e), "/"))?;
+    if is_protected {
+        session
+            .insert_unlocked_poll(poll_id)
+            .map_err(|e| flash_message_redirect(CreatePollError::Session(e), "/"))?;
+    }
 
     let response = HttpResponse::SeeOther()
         .insert_header((LOCATION, format!("/poll/{poll_id}")))
         .finish();
     Ok(response)
 }
-
-fn unexpected(e: sqlx::Error) -> InternalError<CreatePollError> {
-    flash_message_redirect(CreatePollError::Unexpected(e.into()), "/")
-}
-
-#[tracing::instrument(name = "Inserting new poll creator in the database", skip_all)]
-async fn insert_new_user(transaction: &mut Transaction<'_, Postgres>) -> Result<Uuid, sqlx::Error> {
-    let user_id = Uuid::new_v4();
-    sqlx::query!(
-        r#"
-        INSERT INTO users (user_id, created_at)
-        VALUES ($1, now())
-        "#,
-        user_id
-    )
-    .execute(transaction)
-    .await?;
-
-    Ok(user_id)
-}
-
-#[tracing::instrument(
-    name = "Inserting poll details in the database",
-    skip_all,
-    fields(creator_id = %creator_id, poll_prompt = %prompt)
-)]
-async fn insert_new_poll(
-    transaction: &mut Transaction<'_, Postgres>,
-    creator_id: &Uuid,
-    prompt: String,
-) -> Result<Uuid, sqlx::Error> {
-    let poll_id = Uuid::new_v4();
-    sqlx::query!(
-        r#"
-        INSERT INTO polls (poll_id, creator_id, prompt, created_at)
-        VALUES ($1, $2, $3, now())
-        "#,
-        poll_id,
-        creator_id,
-        prompt
-    )
-    .execute(transaction)
-    .await?;
-
-    Ok(poll_id)
-}
-
-#[tracing::instrument(
-    name = "Linking poll and its creator in the database",
-    skip_all,
-    fields(poll_id = %poll_id, creator_id = %user_id, user_name = %username)
-)]
-async fn link_poll_user(
-    transaction: &mut Transaction<'_, Postgres>,
-    poll_id: &Uuid,
-    user_id: &Uuid,
-    username: String,
-) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"
-        INSERT INTO poll_users (poll_id, user_id, username)
-        VALUES ($1, $2, $3)
-        "#,
-        poll_id,
-        user_id,
-        username
-    )
-    .execute(transaction)
-    .await?;
-
-    Ok(())
-}
@@ -0,0 +1,80 @@
+use actix_web::{error::InternalError, web, HttpResponse, ResponseError};
+use actix_web_flash_messages::FlashMessage;
+use reqwest::StatusCode;
+
+use crate::{
+    configuration::ModerationSettings,
+    domain::{ReportReason, ReportTarget},
+    middleware::{require, Authorized, PollInfo},
+    storage::{NewReport, ReportOutcome, Storage},
+    utils::{flash_message_redirect, redirect},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReportError {
+    #[error("You can't report yourself")]
+    OwnContent,
+    #[error("Could not find what you reported in this poll")]
+    NotFound,
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl ResponseError for ReportError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            ReportError::OwnContent => StatusCode::BAD_REQUEST,
+            ReportError::NotFound => StatusCode::NOT_FOUND,
+            ReportError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct ReportForm {
+    /// `suggestion:{suggestion_id}` or `member:{user_id}`
+    target: String,
+    reason: ReportReason,
+}
+
+#[tracing::instrument(
+    name = "report content"
+    skip_all
+    fields(poll_id = %poll_info.poll_id, target = %form.target, reason = %form.reason.as_str())
+)]
+pub async fn report_content(
+    poll_info: PollInfo,
+    member: Authorized<require::Participant>,
+    storage: web::Data<dyn Storage>,
+    form: web::Form<ReportForm>,
+    moderation: web::Data<ModerationSettings>,
+) -> Result<HttpResponse, InternalError<ReportError>> {
+    let poll_uri = &format!("/poll/{}", poll_info.poll_id);
+    let target = ReportTarget::parse(&form.target)
+        .ok_or_else(|| flash_message_redirect(ReportError::NotFound, poll_uri))?;
+    let report = NewReport {
+        poll_id: poll_info.poll_id,
+        reporter_id: member.user_id,
+        target,
+        reason: form.reason,
+    };
+    let outcome = storage
+        .file_report(&report, moderation.report_threshold)
+        .await
+        .map_err(|e| {
+            let e = anyhow::Error::new(e).context("failed to file the report");
+            flash_message_redirect(ReportError::Unexpected(e), poll_uri)
+        })?;
+    match outcome {
+        ReportOutcome::Filed => {}
+        ReportOutcome::TargetNotFound => {
+            return Err(flash_message_redirect(ReportError::NotFound, poll_uri))
+        }
+        ReportOutcome::OwnContent => {
+            return Err(flash_message_redirect(ReportError::OwnContent, poll_uri))
+        }
+    }
+
+    FlashMessage::info("Thanks, the organizers will review your report.").send();
+    Ok(redirect(poll_uri))
+}
@@ -0,0 +1,40 @@
+use actix_web::{error::InternalError, web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use uuid::Uuid;
+
+use super::post_invite::InviteError;
+use crate::{
+    middleware::{require, Authorized, PollInfo},
+    storage::Storage,
+    utils::{flash_message_redirect, redirect},
+};
+
+#[derive(serde::Deserialize)]
+pub struct RevokeInvitePath {
+    invite_id: Uuid,
+}
+
+#[tracing::instrument(
+    name = "revoke poll invite"
+    skip_all
+    fields(poll_id = %poll_info.poll_id, invite_id = %path.invite_id)
+)]
+pub async fn revoke_invite(
+    poll_info: PollInfo,
+    _organizer: Authorized<require::CoOrganizer>,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<RevokeInvitePath>,
+) -> Result<HttpResponse, InternalError<InviteError>> {
+    let invites_uri = &format!("/poll/{}/invites", poll_info.poll_id);
+    let revoked = storage
+        .revoke_invite(&poll_info.poll_id, &path.invite_id)
+        .await
+        .map_err(|e| flash_message_redirect(InviteError::Unexpected(e.into()), invites_uri))?;
+
+    if revoked {
+        FlashMessage::info("Invite revoked").send();
+    } else {
+        FlashMessage::warning("Invite was already revoked").send();
+    }
+    Ok(redirect(invites_uri))
+}
@@ -1,18 +1,21 @@
 use actix_web::{error::InternalError, web, HttpResponse, ResponseError};
+use actix_web_flash_messages::FlashMessage;
 use reqwest::StatusCode;
-use sqlx::PgPool;
-use uuid::Uuid;
 
 use crate::{
-    middleware::PollInfo,
-    user_session::TypedSession,
+    configuration::ContentFilterSettings,
+    content_filter::{ContentFilter, Verdict},
+    domain::ModerationStatus,
+    metrics::Metrics,
+    middleware::{require, Authorized, PollInfo},
+    storage::Storage,
     utils::{flash_message_redirect, redirect},
 };
 
 #[derive(thiserror::Error, Debug)]
 pub enum SuggestionError {
-    #[error("You must be logged in to suggest an answer")]
-    Unauthorized,
+    #[error("Your suggestion {0}.")]
+    Filtered(String),
     #[error(transparent)]
     Unexpected(#[from] anyhow::Error),
 }
@@ -20,7 +23,7 @@ pub enum SuggestionError {
 impl ResponseError for SuggestionError {
     fn status_code(&self) -> reqwest::StatusCode {
         match self {
-            SuggestionError::Unauthorized => StatusCode::UNAUTHORIZED,
+            SuggestionError::Filtered(_) => StatusCode::BAD_REQUEST,
             SuggestionError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -38,49 +41,42 @@ pub struct SuggestionForm {
 )]
 pub async fn suggest_answer(
     poll_info: PollInfo,
-    session: TypedSession,
-    db_pool: web::Data<PgPool>,
+    member: Authorized<require::Participant>,
+    storage: web::Data<dyn Storage>,
     form: web::Form<SuggestionForm>,
+    content_filter: web::Data<ContentFilterSettings>,
+    metrics: web::Data<Metrics>,
 ) -> Result<HttpResponse, InternalError<SuggestionError>> {
     let poll_id = poll_info.poll_id;
     tracing::Span::current().record("poll_id", &tracing::field::display(&poll_id));
+    tracing::Span::current().record("user_id", &tracing::field::display(&member.user_id));
 
     let poll_uri = &format!("/poll/{poll_id}");
-    let user_id = session
-        .get_user_id()
-        .map_err(|e| flash_message_redirect(SuggestionError::Unexpected(e.into()), poll_uri))?
-        .ok_or_else(|| flash_message_redirect(SuggestionError::Unauthorized, poll_uri))?;
+    let content_filter =
+        ContentFilter::for_poll(&content_filter, poll_info.content_filter.as_ref())
+            .map_err(|e| flash_message_redirect(SuggestionError::Unexpected(e.into()), poll_uri))?;
+    let status = match content_filter.check(&form.suggestion) {
+        Verdict::Allow => ModerationStatus::Visible,
+        Verdict::Hold(reason) => ModerationStatus::Held(reason),
+        Verdict::Reject(reason) => {
+            return Err(flash_message_redirect(
+                SuggestionError::Filtered(reason),
+                poll_uri,
+            ))
+        }
+    };
 
-    insert_suggestion(&db_pool, &poll_id, &user_id, form.0.suggestion)
+    storage
+        .add_suggestion(&poll_id, &member.user_id, &form.suggestion, &status)
         .await
         .map_err(|e| flash_message_redirect(SuggestionError::Unexpected(e.into()), poll_uri))?;
+    metrics.suggestions.inc();
 
+    if let ModerationStatus::Held(reason) = status {
+        FlashMessage::info(format!(
+            "Your suggestion {reason}, it will show up once an organizer approves it."
+        ))
+        .send();
+    }
     Ok(redirect(poll_uri))
 }
-
-#[tracing::instrument(
-    name = "insert new suggestion"
-    skip(db_pool)
-)]
-async fn insert_suggestion(
-    db_pool: &PgPool,
-    poll_id: &Uuid,
-    user_id: &Uuid,
-    suggestion: String,
-) -> Result<(), sqlx::Error> {
-    let suggestion_id = Uuid::new_v4();
-    sqlx::query!(
-        r#"
-        INSERT INTO suggestions (suggestion_id, poll_id, creator_id, suggestion, created_at)
-        VALUES ($1, $2, $3, $4, now())
-        "#,
-        suggestion_id,
-        poll_id,
-        user_id,
-        suggestion
-    )
-    .execute(db_pool)
-    .await?;
-
-    Ok(())
-}
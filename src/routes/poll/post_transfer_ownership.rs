@@ -0,0 +1,37 @@
+use actix_web::{error::InternalError, web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+
+use super::post_member_role::{MemberError, MemberPath};
+use crate::{
+    middleware::{require, Authorized, PollInfo},
+    storage::Storage,
+    utils::{flash_message_redirect, redirect},
+};
+
+#[tracing::instrument(
+    name = "transfer poll ownership"
+    skip_all
+    fields(poll_id = %poll_info.poll_id, new_owner_id = %path.user_id)
+)]
+pub async fn transfer_ownership(
+    poll_info: PollInfo,
+    owner: Authorized<require::Owner>,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<MemberPath>,
+) -> Result<HttpResponse, InternalError<MemberError>> {
+    let members_uri = &format!("/poll/{}/members", poll_info.poll_id);
+    if path.user_id == owner.user_id {
+        return Err(flash_message_redirect(MemberError::OwnerRole, members_uri));
+    }
+
+    let transferred = storage
+        .transfer_ownership(&poll_info.poll_id, &owner.user_id, &path.user_id)
+        .await
+        .map_err(|e| flash_message_redirect(MemberError::Unexpected(e.into()), members_uri))?;
+    if !transferred {
+        return Err(flash_message_redirect(MemberError::NotFound, members_uri));
+    }
+
+    FlashMessage::info("Ownership transferred, you are now a co-organizer").send();
+    Ok(redirect(members_uri))
+}
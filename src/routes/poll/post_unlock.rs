@@ -0,0 +1,194 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::{
+    error::InternalError, http::header::RETRY_AFTER, web, HttpRequest, HttpResponse, ResponseError,
+};
+use reqwest::StatusCode;
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::{
+    authentication::verify_passphrase_hash,
+    domain::is_well_formed_invite_token,
+    middleware::PollInfo,
+    user_session::TypedSession,
+    utils::{flash_message_redirect, redirect},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum UnlockError {
+    #[error("Incorrect passphrase")]
+    IncorrectPassphrase,
+    #[error("Too many incorrect attempts, try again in {} seconds", .0.as_secs())]
+    TooManyAttempts(Duration),
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl ResponseError for UnlockError {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            UnlockError::IncorrectPassphrase => StatusCode::UNAUTHORIZED,
+            UnlockError::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+            UnlockError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Failed unlock attempts per poll and client address, kept in memory.
+pub struct UnlockAttempts {
+    max_failures: usize,
+    window: Duration,
+    failures: Mutex<HashMap<(Uuid, IpAddr), VecDeque<Instant>>>,
+}
+
+impl Default for UnlockAttempts {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(15 * 60))
+    }
+}
+
+impl UnlockAttempts {
+    pub fn new(max_failures: usize, window: Duration) -> Self {
+        Self {
+            max_failures,
+            window,
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How long the client has to wait before it's allowed to try again, if at all.
+    pub fn retry_after(&self, poll_id: Uuid, ip: IpAddr) -> Option<Duration> {
+        let now = Instant::now();
+        let mut failures = self.failures.lock().unwrap();
+        let attempts = failures.get_mut(&(poll_id, ip))?;
+        self.prune(attempts, now);
+
+        if attempts.len() < self.max_failures {
+            return None;
+        }
+        attempts
+            .front()
+            .map(|oldest| self.window.saturating_sub(now - *oldest))
+    }
+
+    pub fn record_failure(&self, poll_id: Uuid, ip: IpAddr) {
+        let now = Instant::now();
+        let mut failures = self.failures.lock().unwrap();
+        failures.entry((poll_id, ip)).or_default().push_back(now);
+        failures.retain(|_, attempts| {
+            self.prune(attempts, now);
+            !attempts.is_empty()
+        });
+    }
+
+    pub fn clear(&self, poll_id: Uuid, ip: IpAddr) {
+        self.failures.lock().unwrap().remove(&(poll_id, ip));
+    }
+
+    fn prune(&self, attempts: &mut VecDeque<Instant>, now: Instant) {
+        while matches!(attempts.front(), Some(t) if now - *t >= self.window) {
+            attempts.pop_front();
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct UnlockForm {
+    passphrase: Secret<String>,
+    invite: Option<String>,
+}
+
+#[tracing::instrument(
+    name = "unlock protected poll"
+    skip_all
+    fields(poll_id = %poll_info.poll_id)
+)]
+pub async fn unlock_poll(
+    request: HttpRequest,
+    poll_info: PollInfo,
+    session: TypedSession,
+    form: web::Form<UnlockForm>,
+    attempts: web::Data<UnlockAttempts>,
+) -> Result<HttpResponse, InternalError<UnlockError>> {
+    let poll_id = poll_info.poll_id;
+    let poll_uri = &match form.0.invite.filter(|t| is_well_formed_invite_token(t)) {
+        Some(token) => format!("/poll/{poll_id}?invite={token}"),
+        None => format!("/poll/{poll_id}"),
+    };
+
+    let expected_hash = match poll_info.passphrase_hash {
+        Some(hash) => hash,
+        None => return Ok(redirect(poll_uri)),
+    };
+
+    let ip = request
+        .peer_addr()
+        .map(|addr| addr.ip())
+        .unwrap_or(IpAddr::from([0, 0, 0, 0]));
+    if let Some(retry_after) = attempts.retry_after(poll_id, ip) {
+        let e = UnlockError::TooManyAttempts(retry_after);
+        let response = HttpResponse::TooManyRequests()
+            .insert_header((RETRY_AFTER, retry_after.as_secs().max(1).to_string()))
+            .body(e.to_string());
+        return Err(InternalError::from_response(e, response));
+    }
+
+    let is_correct = verify_passphrase_hash(expected_hash, form.0.passphrase)
+        .await
+        .map_err(|e| flash_message_redirect(UnlockError::Unexpected(e), poll_uri))?;
+    if !is_correct {
+        attempts.record_failure(poll_id, ip);
+        return Err(flash_message_redirect(
+            UnlockError::IncorrectPassphrase,
+            poll_uri,
+        ));
+    }
+
+    attempts.clear(poll_id, ip);
+    session
+        .insert_unlocked_poll(poll_id)
+        .map_err(|e| flash_message_redirect(UnlockError::Unexpected(e.into()), poll_uri))?;
+
+    Ok(redirect(poll_uri))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::time::Duration;
+
+    use claim::{assert_none, assert_some};
+    use uuid::Uuid;
+
+    use super::UnlockAttempts;
+
+    #[test]
+    fn attempts_are_limited_per_poll_and_address() {
+        let attempts = UnlockAttempts::new(2, Duration::from_secs(60));
+        let poll_id = Uuid::new_v4();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        attempts.record_failure(poll_id, ip);
+        assert_none!(attempts.retry_after(poll_id, ip));
+        attempts.record_failure(poll_id, ip);
+        assert_some!(attempts.retry_after(poll_id, ip));
+
+        // Other polls and clients are unaffected
+        assert_none!(attempts.retry_after(Uuid::new_v4(), ip));
+        assert_none!(attempts.retry_after(poll_id, IpAddr::from([10, 0, 0, 1])));
+    }
+
+    #[test]
+    fn attempts_are_forgotten_after_the_window() {
+        let attempts = UnlockAttempts::new(1, Duration::ZERO);
+        let poll_id = Uuid::new_v4();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        attempts.record_failure(poll_id, ip);
+        assert_none!(attempts.retry_after(poll_id, ip));
+    }
+}
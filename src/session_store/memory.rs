@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+
+use super::{generate_session_key, SessionState};
+
+/// Sessions kept in the process, for development and tests. They're lost on
+/// restart and every instance has its own.
+#[derive(Clone, Default)]
+pub struct MemorySessionStore {
+    sessions: Arc<Mutex<HashMap<String, StoredSession>>>,
+}
+
+struct StoredSession {
+    state: SessionState,
+    expires_at: Instant,
+}
+
+impl MemorySessionStore {
+    fn store(&self, key: String, state: SessionState, ttl: &time::Duration, now: Instant) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        // Expired sessions would otherwise pile up until the next restart
+        sessions.retain(|_, s| s.expires_at > now);
+        sessions.insert(
+            key,
+            StoredSession {
+                state,
+                expires_at: now + std::time::Duration::try_from(*ttl).unwrap_or_default(),
+            },
+        );
+    }
+
+    fn load_at(&self, key: &SessionKey, now: Instant) -> Option<SessionState> {
+        let sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions
+            .get(key.as_ref())
+            .filter(|s| s.expires_at > now)
+            .map(|s| s.state.clone())
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl SessionStore for MemorySessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        Ok(self.load_at(session_key, Instant::now()))
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &time::Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let key = generate_session_key();
+        self.store(key.clone(), session_state, ttl, Instant::now());
+        SessionKey::try_from(key).map_err(|e| SaveError::Other(e.into()))
+    }
+
+    /// A session that expired in the meantime comes back under a new key.
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &time::Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let now = Instant::now();
+        if self.load_at(&session_key, now).is_none() {
+            return self
+                .save(session_state, ttl)
+                .await
+                .map_err(|e| UpdateError::Other(anyhow::anyhow!(e)));
+        }
+
+        self.store(session_key.as_ref().to_string(), session_state, ttl, now);
+        Ok(session_key)
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(session_key.as_ref());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    use actix_session::storage::{SessionKey, SessionStore};
+
+    use super::MemorySessionStore;
+
+    fn state(value: &str) -> HashMap<String, String> {
+        HashMap::from([("user_id".to_string(), value.to_string())])
+    }
+
+    #[tokio::test]
+    async fn saved_sessions_can_be_loaded_and_updated() {
+        let store = MemorySessionStore::default();
+        let ttl = time::Duration::minutes(5);
+
+        let key = store.save(state("first"), &ttl).await.unwrap();
+        assert_eq!(store.load(&key).await.unwrap(), Some(state("first")));
+
+        let updated = store.update(key, state("second"), &ttl).await.unwrap();
+        assert_eq!(store.load(&updated).await.unwrap(), Some(state("second")));
+
+        store.delete(&updated).await.unwrap();
+        assert_eq!(store.load(&updated).await.unwrap(), None);
+    }
+
+    #[test]
+    fn sessions_expire_after_their_ttl() {
+        let store = MemorySessionStore::default();
+        let now = Instant::now();
+        let key = "key".to_string();
+
+        store.store(
+            key.clone(),
+            state("value"),
+            &time::Duration::seconds(10),
+            now,
+        );
+
+        let key = SessionKey::try_from(key).unwrap();
+        assert!(store.load_at(&key, now + Duration::from_secs(9)).is_some());
+        assert!(store.load_at(&key, now + Duration::from_secs(10)).is_none());
+    }
+}
@@ -0,0 +1,84 @@
+mod memory;
+mod postgres;
+
+use std::collections::HashMap;
+
+use actix_session::storage::{CookieSessionStore, RedisSessionStore};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use secrecy::ExposeSecret;
+use tokio::task::JoinHandle;
+
+use crate::{
+    configuration::{DatabaseSettings, SessionSettings, SessionStoreBackend},
+    metrics::{MeteredSessionStore, Metrics},
+};
+
+pub use memory::MemorySessionStore;
+pub use postgres::PostgresSessionStore;
+
+type SessionState = HashMap<String, String>;
+
+/// The store selected in the configuration wrapped to count its failures,
+/// along with what has to be stopped when the application shuts down.
+pub struct SessionBackend {
+    pub store: MeteredSessionStore,
+    postgres: Option<PostgresSessionStore>,
+    cleanup: Option<JoinHandle<()>>,
+}
+
+impl SessionBackend {
+    /// Expired Postgres sessions are deleted by a task running in the background.
+    pub async fn connect(
+        settings: &SessionSettings,
+        database: &DatabaseSettings,
+        metrics: &Metrics,
+    ) -> Result<Self, anyhow::Error> {
+        let mut postgres = None;
+        let mut cleanup = None;
+        let store = match settings.store {
+            SessionStoreBackend::Redis => MeteredSessionStore::new(
+                RedisSessionStore::new(settings.redis_uri.expose_secret()).await?,
+                metrics,
+            ),
+            SessionStoreBackend::Postgres => {
+                let store = PostgresSessionStore::connect_lazy(database)?;
+                cleanup = Some(store.spawn_cleanup(std::time::Duration::from_secs(
+                    settings.cleanup_interval_seconds,
+                )));
+                postgres = Some(store.clone());
+                MeteredSessionStore::new(store, metrics)
+            }
+            SessionStoreBackend::Cookie => {
+                MeteredSessionStore::new(CookieSessionStore::default(), metrics)
+            }
+            SessionStoreBackend::Memory => {
+                MeteredSessionStore::new(MemorySessionStore::default(), metrics)
+            }
+        };
+
+        Ok(Self {
+            store,
+            postgres,
+            cleanup,
+        })
+    }
+
+    /// Stops the cleanup task and closes the connections of the Postgres store.
+    pub async fn close(&self) {
+        if let Some(cleanup) = &self.cleanup {
+            cleanup.abort();
+        }
+        if let Some(postgres) = &self.postgres {
+            postgres.close().await;
+        }
+    }
+}
+
+/// As long and random as the keys of the Redis store.
+fn generate_session_key() -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(64)
+        .collect()
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use tokio::task::JoinHandle;
+
+use super::{generate_session_key, SessionState};
+use crate::configuration::{DatabaseBackend, DatabaseSettings};
+
+/// Sessions kept in the `sessions` table of the application's database,
+/// they survive restarts without needing Redis.
+#[derive(Clone)]
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// The table comes with the Postgres migrations, so the polls have to be
+    /// stored in Postgres too.
+    pub fn connect_lazy(settings: &DatabaseSettings) -> Result<Self, anyhow::Error> {
+        if settings.backend != DatabaseBackend::Postgres {
+            anyhow::bail!("the postgres session store needs the postgres database backend");
+        }
+
+        Ok(Self::new(
+            PgPoolOptions::new()
+                .connect_timeout(Duration::from_secs(2))
+                .connect_lazy_with(settings.with_db()),
+        ))
+    }
+
+    /// Expired sessions are never loaded, this only keeps the table small.
+    #[tracing::instrument(name = "delete expired sessions", skip(self))]
+    pub async fn delete_expired(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!("DELETE FROM sessions WHERE expires_at <= now()")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    /// Deletes the expired sessions every `period` until the task is aborted.
+    pub fn spawn_cleanup(&self, period: Duration) -> JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                if let Err(e) = store.delete_expired().await {
+                    tracing::warn!(error.cause_chain = ?e, "Failed to delete expired sessions");
+                }
+            }
+        })
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl SessionStore for PostgresSessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        let state = sqlx::query_scalar!(
+            "SELECT state FROM sessions WHERE session_key = $1 AND expires_at > now()",
+            session_key.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| LoadError::Other(e.into()))?;
+
+        state
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| LoadError::Deserialization(e.into()))
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &time::Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let state =
+            serde_json::to_value(&session_state).map_err(|e| SaveError::Serialization(e.into()))?;
+        let key = generate_session_key();
+        sqlx::query!(
+            "INSERT INTO sessions (session_key, state, expires_at) VALUES ($1, $2, $3)",
+            key,
+            state,
+            expires_at(ttl)
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SaveError::Other(e.into()))?;
+
+        SessionKey::try_from(key).map_err(|e| SaveError::Other(e.into()))
+    }
+
+    /// A session that expired in the meantime comes back under a new key.
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &time::Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let state = serde_json::to_value(&session_state)
+            .map_err(|e| UpdateError::Serialization(e.into()))?;
+        let updated = sqlx::query!(
+            r#"
+            UPDATE sessions SET state = $2, expires_at = $3
+            WHERE session_key = $1 AND expires_at > now()
+            "#,
+            session_key.as_ref(),
+            state,
+            expires_at(ttl)
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UpdateError::Other(e.into()))?;
+
+        if updated.rows_affected() == 0 {
+            return self
+                .save(session_state, ttl)
+                .await
+                .map_err(|e| UpdateError::Other(anyhow::anyhow!(e)));
+        }
+        Ok(session_key)
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "DELETE FROM sessions WHERE session_key = $1",
+            session_key.as_ref()
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+fn expires_at(ttl: &time::Duration) -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::seconds(ttl.whole_seconds())
+}
@@ -0,0 +1,183 @@
+use actix_web::cookie::{Cookie, CookieJar, Key};
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+
+use crate::configuration::HmacSecret;
+
+/// How a cookie is protected by its key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Seal {
+    /// Encrypted, like the session cookie.
+    Private,
+    /// Readable but tamper-proof, like the flash messages cookie.
+    Signed,
+}
+
+/// The keys derived from `application.hmac_secrets`: cookies are sealed with
+/// the current one, previous ones only open the cookies sealed before a
+/// rotation so they can be sealed again with the current key.
+pub struct SigningKeys {
+    current: Key,
+    previous: Vec<PreviousKey>,
+    cookies: Vec<(String, Seal)>,
+}
+
+struct PreviousKey {
+    key: Key,
+    accepted_until: Option<DateTime<Utc>>,
+}
+
+impl SigningKeys {
+    pub fn new(secrets: &[HmacSecret]) -> Result<Self, anyhow::Error> {
+        let (current, previous) = secrets
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("application.hmac_secrets is empty"))?;
+
+        Ok(Self {
+            current: key_from(current)?,
+            previous: previous
+                .iter()
+                .map(|secret| {
+                    Ok(PreviousKey {
+                        key: key_from(secret)?,
+                        accepted_until: secret.accepted_until,
+                    })
+                })
+                .collect::<Result<_, anyhow::Error>>()?,
+            cookies: Vec::new(),
+        })
+    }
+
+    /// Reseal the cookie called `name` when it was sealed with a previous key.
+    pub fn with_cookie(mut self, name: impl Into<String>, seal: Seal) -> Self {
+        self.cookies.push((name.into(), seal));
+        self
+    }
+
+    pub fn current(&self) -> &Key {
+        &self.current
+    }
+
+    /// How cookies called `name` are sealed, if they're resealed at all.
+    pub fn seal_of(&self, name: &str) -> Option<Seal> {
+        self.cookies
+            .iter()
+            .find(|(cookie_name, _)| cookie_name == name)
+            .map(|(_, seal)| *seal)
+    }
+
+    /// `cookie` sealed with the current key, if a previous key still accepted
+    /// at `now` opens it. Cookies the current key opens are left alone.
+    pub fn reseal(
+        &self,
+        cookie: &Cookie<'static>,
+        seal: Seal,
+        now: DateTime<Utc>,
+    ) -> Option<Cookie<'static>> {
+        if open(cookie, seal, &self.current).is_some() {
+            return None;
+        }
+
+        let opened = self
+            .previous
+            .iter()
+            .filter(|previous| previous.accepted_until.is_none_or(|until| now < until))
+            .find_map(|previous| open(cookie, seal, &previous.key))?;
+
+        let mut jar = CookieJar::new();
+        match seal {
+            Seal::Private => jar.private_mut(&self.current).add(opened),
+            Seal::Signed => jar.signed_mut(&self.current).add(opened),
+        }
+        jar.get(cookie.name()).cloned()
+    }
+}
+
+fn key_from(secret: &HmacSecret) -> Result<Key, anyhow::Error> {
+    let bytes = secret.secret.expose_secret().as_bytes();
+    if bytes.len() < 64 {
+        anyhow::bail!("HMAC secrets have to be at least 64 bytes long");
+    }
+    Ok(Key::from(bytes))
+}
+
+fn open(cookie: &Cookie<'static>, seal: Seal, key: &Key) -> Option<Cookie<'static>> {
+    let mut jar = CookieJar::new();
+    jar.add_original(cookie.clone());
+    match seal {
+        Seal::Private => jar.private(key).get(cookie.name()),
+        Seal::Signed => jar.signed(key).get(cookie.name()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::cookie::{Cookie, CookieJar, Key};
+    use chrono::{Duration, Utc};
+    use secrecy::Secret;
+
+    use super::{open, Seal, SigningKeys};
+    use crate::configuration::HmacSecret;
+
+    fn secret(c: char) -> String {
+        std::iter::repeat_n(c, 64).collect()
+    }
+
+    fn keys(accepted_until: Option<chrono::DateTime<Utc>>) -> SigningKeys {
+        SigningKeys::new(&[
+            HmacSecret {
+                secret: Secret::new(secret('n')),
+                accepted_until: None,
+            },
+            HmacSecret {
+                secret: Secret::new(secret('o')),
+                accepted_until,
+            },
+        ])
+        .unwrap()
+    }
+
+    fn sealed_with(key: &Key, seal: Seal) -> Cookie<'static> {
+        let mut jar = CookieJar::new();
+        let cookie = Cookie::new("id", "state");
+        match seal {
+            Seal::Private => jar.private_mut(key).add(cookie),
+            Seal::Signed => jar.signed_mut(key).add(cookie),
+        }
+        jar.get("id").cloned().unwrap()
+    }
+
+    #[test]
+    fn cookies_of_a_previous_key_are_resealed_with_the_current_one() {
+        let keys = keys(None);
+        let previous = Key::from(secret('o').as_bytes());
+
+        for seal in [Seal::Private, Seal::Signed] {
+            let resealed = keys
+                .reseal(&sealed_with(&previous, seal), seal, Utc::now())
+                .expect("the cookie wasn't resealed");
+            let opened = open(&resealed, seal, keys.current()).unwrap();
+            assert_eq!(opened.value(), "state");
+        }
+    }
+
+    #[test]
+    fn cookies_of_the_current_key_are_left_alone() {
+        let keys = keys(None);
+        let cookie = sealed_with(keys.current(), Seal::Private);
+
+        assert!(keys.reseal(&cookie, Seal::Private, Utc::now()).is_none());
+    }
+
+    #[test]
+    fn previous_keys_are_not_accepted_after_their_grace_period() {
+        let now = Utc::now();
+        let keys = keys(Some(now));
+        let cookie = sealed_with(&Key::from(secret('o').as_bytes()), Seal::Private);
+
+        assert!(keys
+            .reseal(&cookie, Seal::Private, now - Duration::seconds(1))
+            .is_some());
+        assert!(keys.reseal(&cookie, Seal::Private, now).is_none());
+    }
+}
@@ -1,98 +1,603 @@
 use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
 
-use actix_session::{storage::RedisSessionStore, SessionMiddleware};
-use actix_web::{cookie::Key, dev::Server, web, App, HttpResponse, HttpServer, Responder};
+use actix_session::{SessionLength, SessionMiddleware};
+use actix_web::middleware::{Condition, DefaultHeaders};
+use actix_web::{
+    dev::Server, guard, http::header, web, App, HttpRequest, HttpResponse, HttpServer, Responder,
+};
 use actix_web_flash_messages::{storage::CookieMessageStore, FlashMessagesFramework};
 use actix_web_lab::middleware::from_fn;
-use secrecy::{ExposeSecret, Secret};
-use sqlx::{postgres::PgPoolOptions, PgPool};
+use redis::aio::ConnectionManager;
+use secrecy::ExposeSecret;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
 use tracing_actix_web::TracingLogger;
 
 use crate::{
-    configuration::{DatabaseSettings, Settings},
-    middleware::validate_poll_id,
-    routes::poll::{create_poll, join_poll, new_poll, show_poll, suggest_answer},
+    configuration::{
+        MetricsSettings, SessionSettings, SessionStoreBackend, Settings, ShutdownSettings,
+    },
+    metrics::{MeteredSessionStore, Metrics},
+    middleware::{
+        csrf_protection, limit_admin_logins, limit_joins, limit_poll_creation, limit_suggestions,
+        record_metrics, renew_resealed_session, require_admin, reseal_cookies, validate_poll_id,
+    },
+    poll_cache::PollCache,
+    proof_of_work::ProofOfWork,
+    rate_limit::RateLimiter,
+    routes::admin::{
+        admin_dashboard, delete_poll, log_in, log_out, login_form, search_polls, view_poll,
+    },
+    routes::health::{liveness_probe, readiness_probe, Readiness},
+    routes::metrics::get_metrics,
+    routes::poll::{
+        approve_member, approve_suggestion, change_member_role, create_invite, create_poll,
+        export_poll, join_poll, new_poll, remove_member, remove_suggestion, report_content,
+        revoke_invite, show_content_filter, show_export_access, show_invites, show_members,
+        show_moderation_queue, show_poll, suggest_answer, transfer_ownership, unlock_poll,
+        update_content_filter, update_export_access, UnlockAttempts,
+    },
+    session_store::SessionBackend,
+    signing_keys::{Seal, SigningKeys},
+    storage::{self, Storage},
+    tls::CertificateResolver,
 };
 
+/// The default name of the cookie of `CookieMessageStore`.
+const FLASH_MESSAGES_COOKIE: &str = "_flash";
+
 pub struct Application {
     port: u16,
     server: Server,
+    metrics_port: Option<u16>,
+    metrics_server: Option<Server>,
+    redirect_port: Option<u16>,
+    redirect_server: Option<Server>,
+    tls_reload: Option<JoinHandle<()>>,
+    readiness: web::Data<Readiness>,
+    metrics: web::Data<Metrics>,
+    storage: Arc<dyn Storage>,
+    sessions: SessionBackend,
+    shutdown: ShutdownSettings,
+    shutdown_requested: Arc<Notify>,
+}
+
+/// Shuts the application down as if it received SIGTERM.
+#[derive(Clone)]
+pub struct ShutdownHandle(Arc<Notify>);
+
+impl ShutdownHandle {
+    pub fn shut_down(&self) {
+        self.0.notify_one();
+    }
 }
 
 impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
-        let connection_pool = get_connection_pool(&configuration.database);
+        let storage = storage::connect(&configuration.database);
+        Self::build_with_storage(configuration, storage).await
+    }
+
+    /// Lets tests keep a handle on the storage the application uses, which
+    /// is the only way to reach it in memory.
+    pub async fn build_with_storage(
+        configuration: Settings,
+        storage: Arc<dyn Storage>,
+    ) -> Result<Self, anyhow::Error> {
+        if configuration.database.migrate_on_startup {
+            storage.migrate().await?;
+        }
         let address = format!(
             "{}:{}",
             configuration.application.host, configuration.application.port
         );
         let listener = TcpListener::bind(&address)?;
         let port = listener.local_addr().unwrap().port();
+        let metrics = web::Data::new(Metrics::new()?);
+        let readiness = web::Data::new(Readiness::new(
+            configuration
+                .redis_uri_in_use()
+                .map(|uri| uri.expose_secret().as_str()),
+        )?);
+        let sessions =
+            SessionBackend::connect(&configuration.session, &configuration.database, &metrics)
+                .await?;
+        let shutdown = configuration.shutdown.clone();
+
+        let (metrics_port, metrics_server) = match &configuration.metrics.address {
+            Some(address) => {
+                let listener = TcpListener::bind(address)?;
+                let port = listener.local_addr().unwrap().port();
+                let server = run_metrics_server(
+                    listener,
+                    storage.clone(),
+                    configuration.metrics.clone(),
+                    metrics.clone(),
+                )?;
+                (Some(port), Some(server))
+            }
+            None => (None, None),
+        };
+        let (tls, tls_reload) = match &configuration.application.tls {
+            Some(settings) => {
+                let resolver = Arc::new(CertificateResolver::load(settings)?);
+                (
+                    Some(resolver.server_config()),
+                    Some(resolver.spawn_reload()),
+                )
+            }
+            None => (None, None),
+        };
+        let (redirect_port, redirect_server) = match configuration
+            .application
+            .tls
+            .as_ref()
+            .and_then(|tls| tls.redirect_http_port)
+        {
+            Some(redirect_port) => {
+                let listener =
+                    TcpListener::bind((configuration.application.host.as_str(), redirect_port))?;
+                let redirect_port = listener.local_addr().unwrap().port();
+                (
+                    Some(redirect_port),
+                    Some(run_redirect_server(listener, port)?),
+                )
+            }
+            None => (None, None),
+        };
         let server = run(
             listener,
-            connection_pool,
-            configuration.application.hmac_secret,
-            configuration.redis_uri,
+            tls,
+            storage.clone(),
+            configuration,
+            metrics.clone(),
+            readiness.clone(),
+            sessions.store.clone(),
         )
         .await?;
 
-        Ok(Self { port, server })
+        Ok(Self {
+            port,
+            server,
+            metrics_port,
+            metrics_server,
+            redirect_port,
+            redirect_server,
+            tls_reload,
+            readiness,
+            metrics,
+            storage,
+            sessions,
+            shutdown,
+            shutdown_requested: Arc::new(Notify::new()),
+        })
     }
 
     pub fn port(&self) -> u16 {
         self.port
     }
 
+    /// The port of the metrics listener, if they have one of their own.
+    pub fn metrics_port(&self) -> Option<u16> {
+        self.metrics_port
+    }
+
+    /// The port redirecting plain HTTP to HTTPS, if there is one.
+    pub fn redirect_port(&self) -> Option<u16> {
+        self.redirect_port
+    }
+
+    /// Lets tests and embedders report the instance as shutting down.
+    pub fn readiness(&self) -> web::Data<Readiness> {
+        self.readiness.clone()
+    }
+
+    /// Lets tests and embedders shut the application down without a signal.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle(self.shutdown_requested.clone())
+    }
+
+    /// Serves until SIGTERM or SIGINT, then fails readiness while still
+    /// serving for `shutdown.readiness_delay_seconds`, lets the requests in
+    /// flight finish and closes everything the application holds.
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
-        self.server.await
+        let server_handle = self.server.handle();
+        let metrics_handle = self.metrics_server.as_ref().map(Server::handle);
+        let redirect_handle = self.redirect_server.as_ref().map(Server::handle);
+        let servers = async {
+            tokio::try_join!(
+                self.server,
+                optional_server(self.metrics_server),
+                optional_server(self.redirect_server),
+            )
+            .map(|_| ())
+        };
+        tokio::pin!(servers);
+
+        tokio::select! {
+            result = &mut servers => return result,
+            _ = shutdown_signal() => {}
+            _ = self.shutdown_requested.notified() => {}
+        }
+
+        tracing::info!("Shutting down, readiness now fails");
+        self.readiness.begin_shutdown();
+        let delay = Duration::from_secs(self.shutdown.readiness_delay_seconds);
+        tokio::select! {
+            result = &mut servers => return result,
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        // Waiting for the requests ourselves, a worker stopped by actix can
+        // drop its connections if the listener closed first
+        tracing::info!("Draining the requests in flight");
+        let drain_timeout = Duration::from_secs(self.shutdown.drain_timeout_seconds);
+        let drained = async {
+            server_handle.pause().await;
+            tokio::time::timeout(drain_timeout, requests_finished(&self.metrics)).await
+        };
+        tokio::select! {
+            result = &mut servers => return result,
+            drained = drained => {
+                if drained.is_err() {
+                    tracing::warn!(
+                        requests = self.metrics.requests_in_flight(),
+                        "Requests still in flight after the drain timeout are dropped"
+                    );
+                }
+            }
+        }
+
+        // The other listeners stay open until the application drained. A
+        // graceful stop only resolves once the workers closed what's left of
+        // their connections, the pools can't be closed before that
+        let stopped = async {
+            server_handle.stop(true).await;
+            for handle in [metrics_handle, redirect_handle].into_iter().flatten() {
+                handle.stop(true).await;
+            }
+        };
+        let (result, _) = tokio::join!(servers, stopped);
+
+        if let Some(tls_reload) = &self.tls_reload {
+            tls_reload.abort();
+        }
+        self.sessions.close().await;
+        self.storage.close().await;
+        tracing::info!("Shut down");
+        result
+    }
+}
+
+async fn optional_server(server: Option<Server>) -> Result<(), std::io::Error> {
+    match server {
+        Some(server) => server.await,
+        None => Ok(()),
+    }
+}
+
+async fn requests_finished(metrics: &Metrics) {
+    let mut interval = tokio::time::interval(Duration::from_millis(50));
+    while metrics.requests_in_flight() > 0 {
+        interval.tick().await;
     }
 }
 
-pub fn get_connection_pool(configuration: &DatabaseSettings) -> PgPool {
-    PgPoolOptions::new()
-        .connect_timeout(std::time::Duration::from_secs(2))
-        .connect_lazy_with(configuration.with_db())
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Cookie sessions can only expire with their cookie, the others keep the
+/// cookie for the browser session and expire their state after the TTL.
+fn session_length(settings: &SessionSettings) -> SessionLength {
+    let ttl = Some(time::Duration::minutes(settings.ttl_minutes.into()));
+    match settings.store {
+        SessionStoreBackend::Cookie => SessionLength::Predetermined {
+            max_session_length: ttl,
+        },
+        SessionStoreBackend::Redis
+        | SessionStoreBackend::Postgres
+        | SessionStoreBackend::Memory => SessionLength::BrowserSession { state_ttl: ttl },
+    }
 }
 
 pub async fn run(
     listener: TcpListener,
-    db_pool: PgPool,
-    hmac_secret: Secret<String>,
-    redis_uri: Secret<String>,
+    tls: Option<rustls::ServerConfig>,
+    storage: Arc<dyn Storage>,
+    configuration: Settings,
+    metrics: web::Data<Metrics>,
+    readiness: web::Data<Readiness>,
+    session_store: MeteredSessionStore,
 ) -> Result<Server, anyhow::Error> {
-    let db_pool = web::Data::new(db_pool);
+    let shared_redis_uri = configuration.redis_uri_in_use().cloned();
+    let hsts = configuration
+        .application
+        .tls
+        .as_ref()
+        .and_then(|tls| tls.hsts_max_age_seconds);
+    let Settings {
+        application,
+        session,
+        rate_limit,
+        proof_of_work,
+        content_filter,
+        moderation,
+        admin,
+        metrics: metrics_settings,
+        poll_cache,
+        ..
+    } = configuration;
+    let signing_keys = SigningKeys::new(&application.hmac_secrets)?
+        .with_cookie(session.cookie_name.clone(), Seal::Private)
+        .with_cookie(FLASH_MESSAGES_COOKIE, Seal::Signed);
+    // Challenges are short-lived, a rotation only fails the forms being filled
+    let hmac_secret = application.hmac_secrets[0].secret.clone();
+    let poll_cache = PollCache::new(&poll_cache);
+    // Stops on its own once the storage is closed
+    storage.watch_poll_changes(Box::new({
+        let poll_cache = poll_cache.clone();
+        move |poll_id| match poll_id {
+            Some(poll_id) => poll_cache.invalidate(&poll_id),
+            None => poll_cache.clear(),
+        }
+    }));
+    let poll_cache = web::Data::new(poll_cache);
+    let storage = web::Data::from(storage);
+    let unlock_attempts = web::Data::new(UnlockAttempts::default());
+    let redis = match &shared_redis_uri {
+        Some(redis_uri) => connect_redis(redis_uri.expose_secret()).await,
+        None => None,
+    };
+    let rate_limiter = web::Data::new(RateLimiter::new(
+        rate_limit.namespace.clone(),
+        redis.clone(),
+    ));
+    let rate_limit = web::Data::new(rate_limit);
+    let proof_of_work = web::Data::new(ProofOfWork::new(hmac_secret.clone(), proof_of_work, redis));
+    let content_filter = web::Data::new(content_filter);
+    let moderation = web::Data::new(moderation);
+    let admin = web::Data::new(admin);
+    // Metrics with a listener of their own aren't served to the public
+    let serve_metrics = metrics_settings.address.is_none() && metrics_settings.token.is_some();
+    let metrics_settings = web::Data::new(metrics_settings);
 
-    let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
-    let message_store = CookieMessageStore::builder(secret_key.clone()).build();
+    let secret_key = signing_keys.current().clone();
+    let signing_keys = web::Data::new(signing_keys);
+    let message_store = CookieMessageStore::builder(secret_key.clone())
+        .cookie_name(FLASH_MESSAGES_COOKIE.into())
+        .build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
-    let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
 
     let server = HttpServer::new(move || {
         App::new()
+            .wrap(from_fn(csrf_protection))
             .wrap(message_framework.clone())
-            .wrap(SessionMiddleware::new(
-                redis_store.clone(),
-                secret_key.clone(),
+            .wrap(from_fn(renew_resealed_session))
+            .wrap(
+                SessionMiddleware::builder(session_store.clone(), secret_key.clone())
+                    .cookie_name(session.cookie_name.clone())
+                    .cookie_same_site(session.same_site.into())
+                    .cookie_secure(session.secure)
+                    .session_length(session_length(&session))
+                    .build(),
+            )
+            .wrap(from_fn(reseal_cookies))
+            .wrap(Condition::new(
+                hsts.is_some(),
+                DefaultHeaders::new().add((
+                    header::STRICT_TRANSPORT_SECURITY,
+                    format!("max-age={}", hsts.unwrap_or_default()),
+                )),
             ))
             .wrap(TracingLogger::default())
+            .wrap(from_fn(record_metrics))
             .route("/", web::get().to(new_poll))
-            .route("/new", web::post().to(create_poll))
+            .service(
+                web::resource("/new")
+                    .wrap(from_fn(limit_poll_creation))
+                    .route(web::post().to(create_poll)),
+            )
             .route("/health_check", web::get().to(health_check))
+            .route("/health/live", web::get().to(liveness_probe))
+            .route("/health/ready", web::get().to(readiness_probe))
+            .configure(|cfg| {
+                if serve_metrics {
+                    cfg.route("/metrics", web::get().to(get_metrics));
+                }
+            })
+            // Only attempts to log in are rate limited, the form itself isn't
+            .service(
+                web::resource("/admin/login")
+                    .guard(guard::Post())
+                    .wrap(from_fn(limit_admin_logins))
+                    .route(web::post().to(log_in)),
+            )
+            .route("/admin/login", web::get().to(login_form))
+            .service(
+                web::scope("/admin")
+                    .wrap(from_fn(require_admin))
+                    .route("", web::get().to(admin_dashboard))
+                    .route("/logout", web::post().to(log_out))
+                    .route("/polls", web::get().to(search_polls))
+                    .route("/polls/{poll_id}", web::get().to(view_poll))
+                    .route("/polls/{poll_id}/delete", web::post().to(delete_poll)),
+            )
             .service(
                 web::scope("/poll/{poll_id}")
                     .wrap(from_fn(validate_poll_id))
                     .route("", web::get().to(show_poll))
-                    .route("/join", web::post().to(join_poll))
-                    .route("/suggest", web::post().to(suggest_answer)),
+                    .service(
+                        web::resource("/join")
+                            .wrap(from_fn(limit_joins))
+                            .route(web::post().to(join_poll)),
+                    )
+                    .service(
+                        web::resource("/suggest")
+                            .wrap(from_fn(limit_suggestions))
+                            .route(web::post().to(suggest_answer)),
+                    )
+                    .route("/unlock", web::post().to(unlock_poll))
+                    .route("/export", web::get().to(export_poll))
+                    .route("/export_access", web::get().to(show_export_access))
+                    .route("/export_access", web::post().to(update_export_access))
+                    .route("/invites", web::get().to(show_invites))
+                    .route("/invites", web::post().to(create_invite))
+                    .route("/invites/{invite_id}/revoke", web::post().to(revoke_invite))
+                    .route("/members", web::get().to(show_members))
+                    .route(
+                        "/members/{user_id}/role",
+                        web::post().to(change_member_role),
+                    )
+                    .route(
+                        "/members/{user_id}/transfer",
+                        web::post().to(transfer_ownership),
+                    )
+                    .route("/report", web::post().to(report_content))
+                    .route("/moderation", web::get().to(show_moderation_queue))
+                    .route(
+                        "/moderation/suggestions/{suggestion_id}/approve",
+                        web::post().to(approve_suggestion),
+                    )
+                    .route(
+                        "/moderation/suggestions/{suggestion_id}/remove",
+                        web::post().to(remove_suggestion),
+                    )
+                    .route(
+                        "/moderation/members/{user_id}/approve",
+                        web::post().to(approve_member),
+                    )
+                    .route(
+                        "/moderation/members/{user_id}/remove",
+                        web::post().to(remove_member),
+                    )
+                    .route("/content_filter", web::get().to(show_content_filter))
+                    .route("/content_filter", web::post().to(update_content_filter)),
             )
-            .app_data(db_pool.clone())
+            .app_data(storage.clone())
+            .app_data(poll_cache.clone())
+            .app_data(unlock_attempts.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(rate_limit.clone())
+            .app_data(proof_of_work.clone())
+            .app_data(content_filter.clone())
+            .app_data(moderation.clone())
+            .app_data(admin.clone())
+            .app_data(metrics.clone())
+            .app_data(metrics_settings.clone())
+            .app_data(readiness.clone())
+            .app_data(signing_keys.clone())
+    })
+    // `Application::run_until_stopped` handles the signals and the draining,
+    // the connections still open once it stops the workers are idle
+    .disable_signals()
+    .shutdown_timeout(0);
+    let server = match tls {
+        Some(tls) => server.listen_rustls(listener, tls)?,
+        None => server.listen(listener)?,
+    }
+    .run();
+
+    Ok(server)
+}
+
+/// Serves nothing but `/metrics`, for a listener only the scrapers can reach.
+fn run_metrics_server(
+    listener: TcpListener,
+    storage: Arc<dyn Storage>,
+    settings: MetricsSettings,
+    metrics: web::Data<Metrics>,
+) -> Result<Server, std::io::Error> {
+    let storage = web::Data::from(storage);
+    let settings = web::Data::new(settings);
+    let server = HttpServer::new(move || {
+        App::new()
+            .route("/metrics", web::get().to(get_metrics))
+            .app_data(storage.clone())
+            .app_data(settings.clone())
+            .app_data(metrics.clone())
+    })
+    .disable_signals()
+    .shutdown_timeout(0)
+    .listen(listener)?
+    .run();
+
+    Ok(server)
+}
+
+/// Rate limits and solved challenges are kept in memory instead if Redis
+/// can't be reached.
+async fn connect_redis(redis_uri: &str) -> Option<ConnectionManager> {
+    let redis = match redis::Client::open(redis_uri) {
+        Ok(client) => ConnectionManager::new(client).await,
+        Err(e) => Err(e),
+    };
+    match redis {
+        Ok(redis) => Some(redis),
+        Err(e) => {
+            tracing::warn!(error = %e, "failed to connect to Redis, rate limits and solved challenges are kept in memory");
+            None
+        }
+    }
+}
+
+/// Redirects every request to the same path on the HTTPS listener.
+fn run_redirect_server(listener: TcpListener, https_port: u16) -> Result<Server, std::io::Error> {
+    let server = HttpServer::new(move || {
+        App::new().default_service(web::to(move |req: HttpRequest| {
+            redirect_to_https(req, https_port)
+        }))
     })
+    .disable_signals()
+    .shutdown_timeout(0)
     .listen(listener)?
     .run();
 
     Ok(server)
 }
 
+async fn redirect_to_https(req: HttpRequest, https_port: u16) -> HttpResponse {
+    let info = req.connection_info();
+    // The host without the port of the plain HTTP listener
+    let host = info.host();
+    let host = match host.rsplit_once(':') {
+        Some((name, port))
+            if port.chars().all(|c| c.is_ascii_digit())
+                && (!name.starts_with('[') || name.ends_with(']')) =>
+        {
+            name
+        }
+        _ => host,
+    };
+    let port = match https_port {
+        443 => String::new(),
+        port => format!(":{port}"),
+    };
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+
+    HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, format!("https://{host}{port}{path}")))
+        .finish()
+}
+
 async fn health_check() -> impl Responder {
     HttpResponse::Ok().finish()
 }
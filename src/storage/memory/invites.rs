@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::{InviteRow, MemoryStorage};
+use crate::storage::{Invite, InviteRepository, NewInvite, StorageError};
+
+#[async_trait::async_trait]
+impl InviteRepository for MemoryStorage {
+    #[tracing::instrument(name = "insert new invite", skip(self, invite))]
+    async fn create_invite(&self, poll_id: &Uuid, invite: &NewInvite) -> Result<(), StorageError> {
+        let mut tables = self.tables();
+        if tables.invites.iter().any(|i| i.token == invite.token) {
+            return Err(StorageError::Conflict);
+        }
+        tables.invites.push(InviteRow {
+            invite_id: Uuid::new_v4(),
+            poll_id: *poll_id,
+            token: invite.token.clone(),
+            max_uses: invite.max_uses,
+            uses: 0,
+            expires_at: invite.expires_at,
+            revoked_at: None,
+        });
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "retrieve poll invites", skip(self))]
+    async fn invites(&self, poll_id: &Uuid) -> Result<Vec<Invite>, StorageError> {
+        Ok(self
+            .tables()
+            .invites
+            .iter()
+            .rev()
+            .filter(|i| i.poll_id == *poll_id)
+            .map(|i| Invite {
+                invite_id: i.invite_id,
+                token: i.token.clone(),
+                max_uses: i.max_uses,
+                uses: i.uses,
+                expires_at: i.expires_at,
+                revoked_at: i.revoked_at,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "mark invite as revoked", skip(self))]
+    async fn revoke_invite(&self, poll_id: &Uuid, invite_id: &Uuid) -> Result<bool, StorageError> {
+        let mut tables = self.tables();
+        let invite = tables
+            .invites
+            .iter_mut()
+            .find(|i| i.poll_id == *poll_id && i.invite_id == *invite_id && i.revoked_at.is_none());
+
+        Ok(match invite {
+            Some(invite) => {
+                invite.revoked_at = Some(Utc::now());
+                true
+            }
+            None => false,
+        })
+    }
+
+    #[tracing::instrument(name = "purge expired invites", skip(self))]
+    async fn purge_expired_invites(&self, cutoff: DateTime<Utc>) -> Result<u64, StorageError> {
+        let mut tables = self.tables();
+        let before = tables.invites.len();
+        tables
+            .invites
+            .retain(|i| i.expires_at >= cutoff && i.revoked_at.is_none_or(|r| r >= cutoff));
+
+        Ok((before - tables.invites.len()) as u64)
+    }
+}
@@ -0,0 +1,222 @@
+mod invites;
+mod moderation;
+mod participants;
+mod polls;
+mod suggestions;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+use sqlx::migrate::Migrator;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::{PollChangeHandler, PoolStatus, Storage, StorageError};
+use crate::domain::{ExportAccess, ModerationStatus, PollRole, ReportReason};
+
+/// Nothing to migrate, the tables are created along with the storage.
+pub static MIGRATOR: Migrator = Migrator {
+    migrations: Cow::Borrowed(&[]),
+    ignore_missing: false,
+};
+
+/// Keeps everything in the process, for development and tests. Nothing
+/// survives a restart and nothing is shared between instances.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    tables: Arc<Mutex<Tables>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The rows the same table would have in the SQL backends, for tests
+    /// checking what was left behind.
+    #[cfg(feature = "test-helpers")]
+    pub fn count_rows(&self, table: &str) -> usize {
+        let tables = self.tables();
+        match table {
+            "users" => tables.users.len(),
+            "polls" => tables.polls.len(),
+            "poll_users" => tables.poll_users.len(),
+            "suggestions" => tables.suggestions.len(),
+            "votes" => tables.votes.len(),
+            "poll_invites" => tables.invites.len(),
+            "reports" => tables.reports.len(),
+            other => panic!("there is no {other} table"),
+        }
+    }
+
+    /// Moves when the invite expires and was revoked, tests use it to go
+    /// back in time.
+    #[cfg(feature = "test-helpers")]
+    pub fn set_invite_times(
+        &self,
+        token: &str,
+        expires_at: DateTime<Utc>,
+        revoked_at: Option<DateTime<Utc>>,
+    ) {
+        for invite in self.tables().invites.iter_mut() {
+            if invite.token == token {
+                invite.expires_at = expires_at;
+                invite.revoked_at = revoked_at;
+            }
+        }
+    }
+
+    /// Votes for the suggestion, there's no other way to vote yet.
+    #[cfg(feature = "test-helpers")]
+    pub fn cast_vote(&self, user_id: &Uuid, suggestion_id: &Uuid) {
+        self.tables().votes.insert(*user_id, *suggestion_id);
+    }
+
+    /// Every change is made while holding the lock, which makes it atomic
+    /// the way a transaction is. The lock is never held across an `.await`.
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for MemoryStorage {
+    fn migrator(&self) -> &'static Migrator {
+        &MIGRATOR
+    }
+
+    async fn migrate(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    async fn applied_migrations(&self) -> Result<Vec<i64>, StorageError> {
+        Ok(Vec::new())
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        PoolStatus {
+            connections: 0,
+            idle_connections: 0,
+        }
+    }
+
+    async fn close(&self) {}
+
+    fn watch_poll_changes(&self, _on_change: PollChangeHandler) -> Option<JoinHandle<()>> {
+        None
+    }
+}
+
+/// The rows of the SQL schema, each table kept in insertion order so that
+/// it's also ordered by `created_at`.
+#[derive(Default)]
+struct Tables {
+    users: Vec<UserRow>,
+    polls: Vec<PollRow>,
+    poll_users: Vec<MemberRow>,
+    suggestions: Vec<SuggestionRow>,
+    /// Suggestion each user voted for, a user only votes once.
+    votes: HashMap<Uuid, Uuid>,
+    invites: Vec<InviteRow>,
+    reports: Vec<ReportRow>,
+}
+
+impl Tables {
+    fn member(&self, poll_id: &Uuid, user_id: &Uuid) -> Option<&MemberRow> {
+        self.poll_users
+            .iter()
+            .find(|m| m.poll_id == *poll_id && m.user_id == *user_id)
+    }
+
+    fn member_mut(&mut self, poll_id: &Uuid, user_id: &Uuid) -> Option<&mut MemberRow> {
+        self.poll_users
+            .iter_mut()
+            .find(|m| m.poll_id == *poll_id && m.user_id == *user_id)
+    }
+
+    /// What the triggers of the SQL backends do whenever the members or
+    /// suggestions of the poll change.
+    fn bump_version(&mut self, poll_id: &Uuid) {
+        if let Some(poll) = self.polls.iter_mut().find(|p| p.poll_id == *poll_id) {
+            poll.version += 1;
+        }
+    }
+
+    fn suggestion_mut(
+        &mut self,
+        poll_id: &Uuid,
+        suggestion_id: &Uuid,
+    ) -> Option<&mut SuggestionRow> {
+        self.suggestions
+            .iter_mut()
+            .find(|s| s.poll_id == *poll_id && s.suggestion_id == *suggestion_id)
+    }
+}
+
+struct UserRow {
+    user_id: Uuid,
+    created_at: DateTime<Utc>,
+}
+
+struct PollRow {
+    poll_id: Uuid,
+    creator_id: Uuid,
+    prompt: String,
+    created_at: DateTime<Utc>,
+    invite_only: bool,
+    passphrase_hash: Option<Secret<String>>,
+    require_proof_of_work: bool,
+    content_filter: Option<serde_json::Value>,
+    export_access: ExportAccess,
+    version: i64,
+}
+
+struct MemberRow {
+    poll_id: Uuid,
+    user_id: Uuid,
+    username: String,
+    name_skeleton: String,
+    role: PollRole,
+    status: ModerationStatus,
+}
+
+struct SuggestionRow {
+    suggestion_id: Uuid,
+    poll_id: Uuid,
+    creator_id: Uuid,
+    suggestion: String,
+    created_at: DateTime<Utc>,
+    status: ModerationStatus,
+}
+
+struct InviteRow {
+    invite_id: Uuid,
+    poll_id: Uuid,
+    token: String,
+    max_uses: i32,
+    uses: i32,
+    expires_at: DateTime<Utc>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+struct ReportRow {
+    poll_id: Uuid,
+    reporter_id: Uuid,
+    suggestion_id: Option<Uuid>,
+    reported_user_id: Option<Uuid>,
+    reason: ReportReason,
+    resolved_at: Option<DateTime<Utc>>,
+}
+
+impl ReportRow {
+    fn is_pending(&self) -> bool {
+        self.resolved_at.is_none()
+    }
+}
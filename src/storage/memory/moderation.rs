@@ -0,0 +1,283 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::{MemoryStorage, ReportRow, Tables};
+use crate::{
+    domain::{ModerationStatus, PollRole, ReportTarget},
+    storage::{ModerationRepository, NewReport, QueuedItem, ReportOutcome, StorageError},
+};
+
+#[async_trait::async_trait]
+impl ModerationRepository for MemoryStorage {
+    #[tracing::instrument(name = "file report", skip(self))]
+    async fn file_report(
+        &self,
+        report: &NewReport,
+        threshold: u32,
+    ) -> Result<ReportOutcome, StorageError> {
+        let mut tables = self.tables();
+
+        let author_id = match find_author(&tables, report) {
+            Some(author_id) => author_id,
+            None => return Ok(ReportOutcome::TargetNotFound),
+        };
+        if author_id == report.reporter_id {
+            return Ok(ReportOutcome::OwnContent);
+        }
+
+        let (suggestion_id, reported_user_id) = match report.target {
+            ReportTarget::Suggestion(id) => (Some(id), None),
+            ReportTarget::Member(id) => (None, Some(id)),
+        };
+        let is_target = |r: &ReportRow| {
+            r.poll_id == report.poll_id
+                && r.suggestion_id == suggestion_id
+                && r.reported_user_id == reported_user_id
+                && r.is_pending()
+        };
+
+        // Reporting the same item twice only counts once
+        let already_reported = tables
+            .reports
+            .iter()
+            .any(|r| is_target(r) && r.reporter_id == report.reporter_id);
+        if !already_reported {
+            tables.reports.push(ReportRow {
+                poll_id: report.poll_id,
+                reporter_id: report.reporter_id,
+                suggestion_id,
+                reported_user_id,
+                reason: report.reason,
+                resolved_at: None,
+            });
+        }
+
+        let pending = tables.reports.iter().filter(|r| is_target(r)).count();
+        if pending >= threshold as usize {
+            let reason = format!("was reported by {pending} members");
+            hide_reported(&mut tables, report, reason);
+            tables.bump_version(&report.poll_id);
+        }
+
+        Ok(ReportOutcome::Filed)
+    }
+
+    #[tracing::instrument(name = "retrieve held and reported suggestions", skip(self))]
+    async fn queued_suggestions(&self, poll_id: &Uuid) -> Result<Vec<QueuedItem>, StorageError> {
+        let tables = self.tables();
+
+        Ok(tables
+            .suggestions
+            .iter()
+            .filter(|s| s.poll_id == *poll_id)
+            .filter_map(|s| {
+                queued_item(
+                    &tables,
+                    s.suggestion_id,
+                    &s.suggestion,
+                    &s.status,
+                    |r: &ReportRow| r.suggestion_id == Some(s.suggestion_id),
+                )
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "retrieve held and reported members", skip(self))]
+    async fn queued_members(&self, poll_id: &Uuid) -> Result<Vec<QueuedItem>, StorageError> {
+        let tables = self.tables();
+        let mut members: Vec<_> = tables
+            .poll_users
+            .iter()
+            .filter(|m| m.poll_id == *poll_id)
+            .filter_map(|m| {
+                queued_item(
+                    &tables,
+                    m.user_id,
+                    &m.username,
+                    &m.status,
+                    |r: &ReportRow| r.poll_id == *poll_id && r.reported_user_id == Some(m.user_id),
+                )
+            })
+            .collect();
+        members.sort_by(|a, b| a.content.cmp(&b.content));
+
+        Ok(members)
+    }
+
+    #[tracing::instrument(name = "approve suggestion in the moderation queue", skip(self))]
+    async fn approve_suggestion(
+        &self,
+        poll_id: &Uuid,
+        suggestion_id: &Uuid,
+    ) -> Result<bool, StorageError> {
+        let mut tables = self.tables();
+        let resolved = resolve_reports(&mut tables, |r| {
+            r.poll_id == *poll_id && r.suggestion_id == Some(*suggestion_id)
+        });
+        let approved = match tables.suggestion_mut(poll_id, suggestion_id) {
+            Some(s) if s.status != ModerationStatus::Visible => {
+                s.status = ModerationStatus::Visible;
+                true
+            }
+            _ => false,
+        };
+        if approved {
+            tables.bump_version(poll_id);
+        }
+
+        Ok(resolved > 0 || approved)
+    }
+
+    #[tracing::instrument(name = "delete suggestion in the moderation queue", skip(self))]
+    async fn remove_suggestion(
+        &self,
+        poll_id: &Uuid,
+        suggestion_id: &Uuid,
+    ) -> Result<bool, StorageError> {
+        let mut tables = self.tables();
+        let was_reported = tables
+            .reports
+            .iter()
+            .any(|r| r.poll_id == *poll_id && r.suggestion_id == Some(*suggestion_id));
+        let index = tables.suggestions.iter().position(|s| {
+            s.poll_id == *poll_id
+                && s.suggestion_id == *suggestion_id
+                && (s.status != ModerationStatus::Visible || was_reported)
+        });
+        let index = match index {
+            Some(index) => index,
+            None => return Ok(false),
+        };
+
+        tables.suggestions.remove(index);
+        tables.bump_version(poll_id);
+        tables.votes.retain(|_, s| s != suggestion_id);
+        tables
+            .reports
+            .retain(|r| r.suggestion_id != Some(*suggestion_id));
+
+        Ok(true)
+    }
+
+    #[tracing::instrument(name = "approve member in the moderation queue", skip(self))]
+    async fn approve_member(&self, poll_id: &Uuid, user_id: &Uuid) -> Result<bool, StorageError> {
+        let mut tables = self.tables();
+        let resolved = resolve_reports(&mut tables, |r| {
+            r.poll_id == *poll_id && r.reported_user_id == Some(*user_id)
+        });
+        let approved = match tables.member_mut(poll_id, user_id) {
+            Some(m) if m.status != ModerationStatus::Visible => {
+                m.status = ModerationStatus::Visible;
+                true
+            }
+            _ => false,
+        };
+        if approved {
+            tables.bump_version(poll_id);
+        }
+
+        Ok(resolved > 0 || approved)
+    }
+
+    #[tracing::instrument(name = "delete member in the moderation queue", skip(self))]
+    async fn remove_member(&self, poll_id: &Uuid, user_id: &Uuid) -> Result<bool, StorageError> {
+        let mut tables = self.tables();
+        let is_target =
+            |r: &ReportRow| r.poll_id == *poll_id && r.reported_user_id == Some(*user_id);
+        let was_reported = tables.reports.iter().any(is_target);
+        let index = tables.poll_users.iter().position(|m| {
+            m.poll_id == *poll_id
+                && m.user_id == *user_id
+                && (m.status != ModerationStatus::Visible || was_reported)
+                && m.role != PollRole::Owner
+        });
+        let index = match index {
+            Some(index) => index,
+            None => return Ok(false),
+        };
+
+        tables.poll_users.remove(index);
+        tables.bump_version(poll_id);
+        tables.reports.retain(|r| !is_target(r));
+
+        Ok(true)
+    }
+}
+
+/// Who wrote the reported suggestion, or the reported member themselves.
+/// Items that are already hidden can't be reported.
+fn find_author(tables: &Tables, report: &NewReport) -> Option<Uuid> {
+    match report.target {
+        ReportTarget::Suggestion(suggestion_id) => tables
+            .suggestions
+            .iter()
+            .find(|s| {
+                s.poll_id == report.poll_id
+                    && s.suggestion_id == suggestion_id
+                    && s.status == ModerationStatus::Visible
+            })
+            .map(|s| s.creator_id),
+        ReportTarget::Member(user_id) => tables
+            .member(&report.poll_id, &user_id)
+            .filter(|m| m.status == ModerationStatus::Visible)
+            .map(|m| m.user_id),
+    }
+}
+
+/// The owner's name stays visible, they're the one reviewing the reports.
+fn hide_reported(tables: &mut Tables, report: &NewReport, reason: String) {
+    match report.target {
+        ReportTarget::Suggestion(suggestion_id) => {
+            if let Some(s) = tables.suggestion_mut(&report.poll_id, &suggestion_id) {
+                s.status = ModerationStatus::Held(reason);
+            }
+        }
+        ReportTarget::Member(user_id) => {
+            if let Some(m) = tables.member_mut(&report.poll_id, &user_id) {
+                if m.role != PollRole::Owner {
+                    m.status = ModerationStatus::Held(reason);
+                }
+            }
+        }
+    }
+}
+
+fn resolve_reports(tables: &mut Tables, is_target: impl Fn(&ReportRow) -> bool) -> usize {
+    let now = Utc::now();
+    let mut resolved = 0;
+    for report in tables
+        .reports
+        .iter_mut()
+        .filter(|r| r.is_pending() && is_target(r))
+    {
+        report.resolved_at = Some(now);
+        resolved += 1;
+    }
+    resolved
+}
+
+/// `None` unless the item is held or has pending reports.
+fn queued_item(
+    tables: &Tables,
+    id: Uuid,
+    content: &str,
+    status: &ModerationStatus,
+    is_target: impl Fn(&ReportRow) -> bool,
+) -> Option<QueuedItem> {
+    let report_reasons: Vec<_> = tables
+        .reports
+        .iter()
+        .filter(|r| r.is_pending() && is_target(r))
+        .map(|r| r.reason.as_str().to_string())
+        .collect();
+    if *status == ModerationStatus::Visible && report_reasons.is_empty() {
+        return None;
+    }
+
+    Some(QueuedItem {
+        id,
+        content: content.to_string(),
+        moderation_reason: status.reason().map(str::to_owned),
+        report_reasons,
+    })
+}
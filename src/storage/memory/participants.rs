@@ -0,0 +1,206 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::{MemberRow, MemoryStorage, UserRow};
+use crate::{
+    domain::{ModerationStatus, PollRole},
+    storage::{Member, MemberDetails, NewMember, ParticipantRepository, StorageError},
+};
+
+#[async_trait::async_trait]
+impl ParticipantRepository for MemoryStorage {
+    #[tracing::instrument(name = "insert new poll member", skip(self))]
+    async fn add_member(&self, member: &NewMember) -> Result<Option<Uuid>, StorageError> {
+        let now = Utc::now();
+        let mut tables = self.tables();
+
+        let invite = match &member.invite {
+            Some(token) => {
+                let invite = tables.invites.iter().position(|i| {
+                    i.poll_id == member.poll_id
+                        && i.token == *token
+                        && i.revoked_at.is_none()
+                        && i.expires_at > now
+                        && i.uses < i.max_uses
+                });
+                match invite {
+                    Some(index) => Some(index),
+                    None => return Ok(None),
+                }
+            }
+            None => None,
+        };
+
+        let skeleton = member.name.skeleton();
+        let taken = tables.poll_users.iter().any(|m| {
+            m.poll_id == member.poll_id
+                && (m.username == member.name.as_ref() || m.name_skeleton == skeleton)
+        });
+        if taken {
+            return Err(StorageError::Conflict);
+        }
+
+        // Use up one of the invite's uses
+        if let Some(index) = invite {
+            tables.invites[index].uses += 1;
+        }
+
+        let user_id = Uuid::new_v4();
+        tables.users.push(UserRow {
+            user_id,
+            created_at: now,
+        });
+        tables.poll_users.push(MemberRow {
+            poll_id: member.poll_id,
+            user_id,
+            username: member.name.as_ref().to_string(),
+            name_skeleton: skeleton,
+            role: member.role,
+            status: member.status.clone(),
+        });
+        tables.bump_version(&member.poll_id);
+
+        Ok(Some(user_id))
+    }
+
+    #[tracing::instrument(name = "find poll member", skip(self))]
+    async fn find_member(
+        &self,
+        poll_id: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<Option<Member>, StorageError> {
+        Ok(self
+            .tables()
+            .member(poll_id, user_id)
+            .map(MemberRow::member))
+    }
+
+    #[tracing::instrument(name = "retrieve poll members", skip(self))]
+    async fn members(&self, poll_id: &Uuid) -> Result<Vec<Member>, StorageError> {
+        let mut members: Vec<_> = self
+            .tables()
+            .poll_users
+            .iter()
+            .filter(|m| m.poll_id == *poll_id)
+            .map(MemberRow::member)
+            .collect();
+        members.sort_by(|a, b| a.username.cmp(&b.username));
+
+        Ok(members)
+    }
+
+    #[tracing::instrument(name = "retrieve visible poll members", skip(self))]
+    async fn visible_members(&self, poll_id: &Uuid) -> Result<Vec<Member>, StorageError> {
+        Ok(self
+            .tables()
+            .poll_users
+            .iter()
+            .filter(|m| m.poll_id == *poll_id && m.status == ModerationStatus::Visible)
+            .map(MemberRow::member)
+            .collect())
+    }
+
+    /// Members are added right after their user, so they're already in the
+    /// order they joined.
+    #[tracing::instrument(name = "retrieve all poll members", skip(self))]
+    async fn member_details(&self, poll_id: &Uuid) -> Result<Vec<MemberDetails>, StorageError> {
+        let tables = self.tables();
+
+        Ok(tables
+            .poll_users
+            .iter()
+            .filter(|m| m.poll_id == *poll_id)
+            .filter_map(|m| {
+                let user = tables.users.iter().find(|u| u.user_id == m.user_id)?;
+                Some(MemberDetails {
+                    user_id: m.user_id,
+                    username: m.username.clone(),
+                    role: m.role,
+                    joined_at: user.created_at,
+                    moderation_reason: m.status.reason().map(str::to_owned),
+                })
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "find user with a lookalike name", skip(self))]
+    async fn find_lookalike(
+        &self,
+        poll_id: &Uuid,
+        skeleton: &str,
+    ) -> Result<Option<String>, StorageError> {
+        Ok(self
+            .tables()
+            .poll_users
+            .iter()
+            .find(|m| m.poll_id == *poll_id && m.name_skeleton == skeleton)
+            .map(|m| m.username.clone()))
+    }
+
+    #[tracing::instrument(name = "find taken name skeletons", skip(self))]
+    async fn taken_skeletons(
+        &self,
+        poll_id: &Uuid,
+        skeletons: &[String],
+    ) -> Result<Vec<String>, StorageError> {
+        Ok(self
+            .tables()
+            .poll_users
+            .iter()
+            .filter(|m| m.poll_id == *poll_id && skeletons.contains(&m.name_skeleton))
+            .map(|m| m.name_skeleton.clone())
+            .collect())
+    }
+
+    #[tracing::instrument(name = "update member role", skip(self))]
+    async fn set_role(
+        &self,
+        poll_id: &Uuid,
+        user_id: &Uuid,
+        role: PollRole,
+    ) -> Result<bool, StorageError> {
+        let mut tables = self.tables();
+        match tables.member_mut(poll_id, user_id) {
+            Some(member) if member.role != PollRole::Owner => {
+                member.role = role;
+                tables.bump_version(poll_id);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    #[tracing::instrument(name = "swap poll owner", skip(self))]
+    async fn transfer_ownership(
+        &self,
+        poll_id: &Uuid,
+        current_owner_id: &Uuid,
+        new_owner_id: &Uuid,
+    ) -> Result<bool, StorageError> {
+        let mut tables = self.tables();
+        if tables.member(poll_id, new_owner_id).is_none() {
+            return Ok(false);
+        }
+
+        // Demote first, there can only be one owner at a time
+        if let Some(owner) = tables.member_mut(poll_id, current_owner_id) {
+            owner.role = PollRole::CoOrganizer;
+        }
+        if let Some(member) = tables.member_mut(poll_id, new_owner_id) {
+            member.role = PollRole::Owner;
+        }
+        tables.bump_version(poll_id);
+
+        Ok(true)
+    }
+}
+
+impl MemberRow {
+    pub(super) fn member(&self) -> Member {
+        Member {
+            user_id: self.user_id,
+            username: self.username.clone(),
+            role: self.role,
+        }
+    }
+}
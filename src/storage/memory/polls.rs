@@ -0,0 +1,446 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use super::{MemberRow, MemoryStorage, PollRow, SuggestionRow, UserRow};
+use crate::{
+    domain::{ExportAccess, MemberSort, ModerationStatus, PollRole, SuggestionSort},
+    storage::{
+        CreatedPoll, DailyActivity, ExportedMember, ExportedSuggestion, ExportedVote, NewPoll,
+        OverviewPage, Poll, PollDetails, PollExport, PollOverview, PollRepository, PollSummary,
+        StorageError, Suggestion, Totals,
+    },
+};
+
+#[async_trait::async_trait]
+impl PollRepository for MemoryStorage {
+    #[tracing::instrument(
+        name = "Inserting new poll in memory",
+        skip_all,
+        fields(
+            poll_prompt = %poll.prompt,
+            invite_only = %poll.invite_only,
+            require_proof_of_work = %poll.require_proof_of_work
+        )
+    )]
+    async fn create_poll(&self, poll: &NewPoll) -> Result<CreatedPoll, StorageError> {
+        let now = Utc::now();
+        let mut tables = self.tables();
+
+        let creator_id = Uuid::new_v4();
+        tables.users.push(UserRow {
+            user_id: creator_id,
+            created_at: now,
+        });
+
+        let poll_id = Uuid::new_v4();
+        tables.polls.push(PollRow {
+            poll_id,
+            creator_id,
+            prompt: poll.prompt.clone(),
+            created_at: now,
+            invite_only: poll.invite_only,
+            passphrase_hash: poll.passphrase_hash.clone(),
+            require_proof_of_work: poll.require_proof_of_work,
+            content_filter: None,
+            export_access: ExportAccess::default(),
+            version: 0,
+        });
+
+        tables.poll_users.push(MemberRow {
+            poll_id,
+            user_id: creator_id,
+            username: poll.creator_name.as_ref().to_string(),
+            name_skeleton: poll.creator_name.skeleton(),
+            role: PollRole::Owner,
+            status: ModerationStatus::Visible,
+        });
+
+        Ok(CreatedPoll {
+            poll_id,
+            creator_id,
+        })
+    }
+
+    #[tracing::instrument(name = "find poll in memory", skip(self))]
+    async fn find_poll(&self, poll_id: &Uuid) -> Result<Option<Poll>, StorageError> {
+        Ok(self
+            .tables()
+            .polls
+            .iter()
+            .find(|p| p.poll_id == *poll_id)
+            .map(|p| Poll {
+                poll_id: p.poll_id,
+                creator_id: p.creator_id,
+                prompt: p.prompt.clone(),
+                invite_only: p.invite_only,
+                passphrase_hash: p.passphrase_hash.clone(),
+                require_proof_of_work: p.require_proof_of_work,
+                content_filter: p.content_filter.clone(),
+                export_access: p.export_access,
+            }))
+    }
+
+    /// Sorts the way the SQL backends do, names and suggestions compared
+    /// in lowercase.
+    #[tracing::instrument(name = "retrieve poll overview", skip(self))]
+    async fn poll_overview(
+        &self,
+        poll_id: &Uuid,
+        user_id: Option<&Uuid>,
+        page: &OverviewPage,
+    ) -> Result<PollOverview, StorageError> {
+        let tables = self.tables();
+        let search = page.search.to_lowercase();
+        let mut overview = PollOverview {
+            version: tables
+                .polls
+                .iter()
+                .find(|p| p.poll_id == *poll_id)
+                .map(|p| p.version),
+            ..PollOverview::default()
+        };
+
+        let members = tables
+            .poll_users
+            .iter()
+            .filter(|m| m.poll_id == *poll_id)
+            .filter_map(|m| {
+                let user = tables.users.iter().find(|u| u.user_id == m.user_id)?;
+                Some(MemberEntry {
+                    row: m,
+                    joined_at: user.created_at,
+                    name_key: m.username.to_lowercase(),
+                })
+            })
+            .collect::<Vec<_>>();
+        if let Some(viewer) = user_id.and_then(|id| members.iter().find(|m| m.row.user_id == *id)) {
+            overview.add_member(viewer.row.member(), false, user_id);
+        }
+        let compare = |a: &MemberEntry, b: &MemberEntry| match page.member_sort {
+            MemberSort::Newest => (b.joined_at, b.row.user_id).cmp(&(a.joined_at, a.row.user_id)),
+            MemberSort::Oldest => (a.joined_at, a.row.user_id).cmp(&(b.joined_at, b.row.user_id)),
+            MemberSort::Alphabetical => (&a.name_key, a.joined_at, a.row.user_id).cmp(&(
+                &b.name_key,
+                b.joined_at,
+                b.row.user_id,
+            )),
+        };
+        let cursor = page
+            .members_after
+            .and_then(|id| members.iter().find(|m| m.row.user_id == id));
+        let mut member_page = members
+            .iter()
+            .filter(|m| m.row.status == ModerationStatus::Visible && m.name_key.contains(&search))
+            .filter(|m| cursor.is_none_or(|c| compare(m, c) == Ordering::Greater))
+            .collect::<Vec<_>>();
+        member_page.sort_by(|a, b| compare(a, b));
+        for m in member_page.into_iter().take(page.limit as usize + 1) {
+            overview.add_member(m.row.member(), true, user_id);
+        }
+
+        let suggestions = tables
+            .suggestions
+            .iter()
+            .filter(|s| s.poll_id == *poll_id && s.status == ModerationStatus::Visible)
+            .map(|s| SuggestionEntry {
+                row: s,
+                votes: tables
+                    .votes
+                    .values()
+                    .filter(|id| **id == s.suggestion_id)
+                    .count(),
+                text_key: s.suggestion.to_lowercase(),
+                author_key: tables
+                    .member(poll_id, &s.creator_id)
+                    .filter(|m| m.status == ModerationStatus::Visible)
+                    .map(|m| m.username.to_lowercase())
+                    .unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        let compare = |a: &SuggestionEntry, b: &SuggestionEntry| {
+            let oldest = (a.row.created_at, a.row.suggestion_id)
+                .cmp(&(b.row.created_at, b.row.suggestion_id));
+            match page.suggestion_sort {
+                SuggestionSort::Newest => oldest.reverse(),
+                SuggestionSort::Oldest => oldest,
+                SuggestionSort::MostVotes => b.votes.cmp(&a.votes).then(oldest),
+                SuggestionSort::Alphabetical => a.text_key.cmp(&b.text_key).then(oldest),
+                SuggestionSort::Author => a.author_key.cmp(&b.author_key).then(oldest),
+            }
+        };
+        let cursor = page
+            .suggestions_after
+            .and_then(|id| suggestions.iter().find(|s| s.row.suggestion_id == id));
+        let mut suggestion_page = suggestions
+            .iter()
+            .filter(|s| s.text_key.contains(&search) || s.author_key.contains(&search))
+            .filter(|s| cursor.is_none_or(|c| compare(s, c) == Ordering::Greater))
+            .collect::<Vec<_>>();
+        suggestion_page.sort_by(|a, b| compare(a, b));
+        overview.suggestions = suggestion_page
+            .into_iter()
+            .take(page.limit as usize + 1)
+            .map(|s| Suggestion {
+                suggestion_id: s.row.suggestion_id,
+                creator_id: s.row.creator_id,
+                suggestion: s.row.suggestion.clone(),
+            })
+            .collect();
+
+        Ok(overview.truncate(page.limit))
+    }
+
+    #[tracing::instrument(name = "retrieve poll export", skip(self))]
+    async fn poll_export(&self, poll_id: &Uuid) -> Result<PollExport, StorageError> {
+        let tables = self.tables();
+        let visible_name = |user_id: &Uuid| {
+            tables
+                .member(poll_id, user_id)
+                .filter(|m| m.status == ModerationStatus::Visible)
+                .map(|m| m.username.clone())
+        };
+
+        let members = tables
+            .poll_users
+            .iter()
+            .filter(|m| m.poll_id == *poll_id && m.status == ModerationStatus::Visible)
+            .filter_map(|m| {
+                let user = tables.users.iter().find(|u| u.user_id == m.user_id)?;
+                Some(ExportedMember {
+                    username: m.username.clone(),
+                    role: m.role,
+                    joined_at: user.created_at,
+                })
+            })
+            .collect();
+        let suggestions = tables
+            .suggestions
+            .iter()
+            .filter(|s| s.poll_id == *poll_id && s.status == ModerationStatus::Visible)
+            .map(|s| ExportedSuggestion {
+                suggestion_id: s.suggestion_id,
+                suggestion: s.suggestion.clone(),
+                author: visible_name(&s.creator_id),
+                created_at: s.created_at,
+            })
+            .collect::<Vec<_>>();
+        let mut votes = tables
+            .votes
+            .iter()
+            .filter(|(_, suggestion_id)| {
+                suggestions
+                    .iter()
+                    .any(|s| s.suggestion_id == **suggestion_id)
+            })
+            .map(|(user_id, suggestion_id)| (visible_name(user_id), *user_id, *suggestion_id))
+            .collect::<Vec<_>>();
+        votes.sort_by(|(a, a_id, _), (b, b_id, _)| {
+            (a.is_none(), a, a_id).cmp(&(b.is_none(), b, b_id))
+        });
+
+        Ok(PollExport {
+            members,
+            suggestions,
+            votes: votes
+                .into_iter()
+                .map(|(voter, _, suggestion_id)| ExportedVote {
+                    suggestion_id,
+                    voter,
+                })
+                .collect(),
+        })
+    }
+
+    #[tracing::instrument(name = "store poll content filter", skip(self))]
+    async fn set_content_filter(
+        &self,
+        poll_id: &Uuid,
+        content_filter: Option<&serde_json::Value>,
+    ) -> Result<(), StorageError> {
+        if let Some(poll) = self
+            .tables()
+            .polls
+            .iter_mut()
+            .find(|p| p.poll_id == *poll_id)
+        {
+            poll.content_filter = content_filter.cloned();
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "store poll export access", skip(self))]
+    async fn set_export_access(
+        &self,
+        poll_id: &Uuid,
+        export_access: ExportAccess,
+    ) -> Result<(), StorageError> {
+        if let Some(poll) = self
+            .tables()
+            .polls
+            .iter_mut()
+            .find(|p| p.poll_id == *poll_id)
+        {
+            poll.export_access = export_access;
+        }
+
+        Ok(())
+    }
+
+    /// Removes what the SQL backends cascade to: the poll's members,
+    /// suggestions, votes, invites and reports.
+    #[tracing::instrument(name = "delete poll and its content", skip(self))]
+    async fn delete_poll(&self, poll_id: &Uuid) -> Result<Option<String>, StorageError> {
+        let mut tables = self.tables();
+        let index = match tables.polls.iter().position(|p| p.poll_id == *poll_id) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        let poll = tables.polls.remove(index);
+
+        let members: Vec<_> = tables
+            .poll_users
+            .iter()
+            .filter(|m| m.poll_id == *poll_id)
+            .map(|m| m.user_id)
+            .collect();
+        let suggestions: Vec<_> = tables
+            .suggestions
+            .iter()
+            .filter(|s| s.poll_id == *poll_id)
+            .map(|s| s.suggestion_id)
+            .collect();
+        tables.poll_users.retain(|m| m.poll_id != *poll_id);
+        tables.suggestions.retain(|s| s.poll_id != *poll_id);
+        tables.votes.retain(|_, s| !suggestions.contains(s));
+        tables.invites.retain(|i| i.poll_id != *poll_id);
+        tables.reports.retain(|r| r.poll_id != *poll_id);
+
+        // Users that are still referenced elsewhere stay
+        let tables = &mut *tables;
+        tables.users.retain(|u| {
+            let user_id = &u.user_id;
+            !members.contains(user_id)
+                || tables.poll_users.iter().any(|m| m.user_id == *user_id)
+                || tables.polls.iter().any(|p| p.creator_id == *user_id)
+                || tables.suggestions.iter().any(|s| s.creator_id == *user_id)
+                || tables.votes.contains_key(user_id)
+                || tables
+                    .reports
+                    .iter()
+                    .any(|r| r.reporter_id == *user_id || r.reported_user_id == Some(*user_id))
+        });
+
+        Ok(Some(poll.prompt))
+    }
+
+    #[tracing::instrument(name = "find polls", skip(self))]
+    async fn search_polls(&self, q: &str, limit: i64) -> Result<Vec<PollSummary>, StorageError> {
+        let poll_id = Uuid::parse_str(q).ok();
+        let q = q.to_lowercase();
+        let tables = self.tables();
+
+        Ok(tables
+            .polls
+            .iter()
+            .rev()
+            .filter(|p| Some(p.poll_id) == poll_id || p.prompt.to_lowercase().contains(&q))
+            .take(usize::try_from(limit).unwrap_or_default())
+            .map(|p| PollSummary {
+                poll_id: p.poll_id,
+                prompt: p.prompt.clone(),
+                created_at: p.created_at,
+                members: count(tables.poll_users.iter().filter(|m| m.poll_id == p.poll_id)),
+                suggestions: count(tables.suggestions.iter().filter(|s| s.poll_id == p.poll_id)),
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "retrieve poll details", skip(self))]
+    async fn poll_details(&self, poll_id: &Uuid) -> Result<Option<PollDetails>, StorageError> {
+        let tables = self.tables();
+
+        Ok(tables
+            .polls
+            .iter()
+            .find(|p| p.poll_id == *poll_id)
+            .map(|p| PollDetails {
+                prompt: p.prompt.clone(),
+                created_at: p.created_at,
+                invite_only: p.invite_only,
+                has_passphrase: p.passphrase_hash.is_some(),
+                require_proof_of_work: p.require_proof_of_work,
+                has_content_filter: p.content_filter.is_some(),
+                pending_reports: count(
+                    tables
+                        .reports
+                        .iter()
+                        .filter(|r| r.poll_id == *poll_id && r.is_pending()),
+                ),
+            }))
+    }
+
+    #[tracing::instrument(name = "count polls, users and suggestions", skip(self))]
+    async fn totals(&self) -> Result<Totals, StorageError> {
+        let tables = self.tables();
+
+        Ok(Totals {
+            polls: count(tables.polls.iter()),
+            users: count(tables.users.iter()),
+            suggestions: count(tables.suggestions.iter()),
+        })
+    }
+
+    #[tracing::instrument(name = "retrieve daily activity", skip(self))]
+    async fn daily_activity(&self, days: i32) -> Result<Vec<DailyActivity>, StorageError> {
+        let today = Utc::today().naive_utc();
+        let tables = self.tables();
+
+        Ok((0..days)
+            .map(|i| {
+                let day = today - Duration::days(i64::from(i));
+                DailyActivity {
+                    day,
+                    polls: count(
+                        tables
+                            .polls
+                            .iter()
+                            .filter(|p| p.created_at.date().naive_utc() == day),
+                    ),
+                    users: count(
+                        tables
+                            .users
+                            .iter()
+                            .filter(|u| u.created_at.date().naive_utc() == day),
+                    ),
+                    suggestions: count(
+                        tables
+                            .suggestions
+                            .iter()
+                            .filter(|s| s.created_at.date().naive_utc() == day),
+                    ),
+                }
+            })
+            .collect())
+    }
+}
+
+fn count<T>(rows: impl Iterator<Item = T>) -> i64 {
+    rows.count() as i64
+}
+
+/// A member along with what the poll page sorts them by.
+struct MemberEntry<'a> {
+    row: &'a MemberRow,
+    joined_at: DateTime<Utc>,
+    name_key: String,
+}
+
+struct SuggestionEntry<'a> {
+    row: &'a SuggestionRow,
+    votes: usize,
+    text_key: String,
+    author_key: String,
+}
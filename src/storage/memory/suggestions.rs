@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::{MemoryStorage, SuggestionRow};
+use crate::{
+    domain::ModerationStatus,
+    storage::{StorageError, Suggestion, SuggestionDetails, SuggestionRepository, VoteRepository},
+};
+
+#[async_trait::async_trait]
+impl SuggestionRepository for MemoryStorage {
+    #[tracing::instrument(name = "insert new suggestion", skip(self))]
+    async fn add_suggestion(
+        &self,
+        poll_id: &Uuid,
+        creator_id: &Uuid,
+        suggestion: &str,
+        status: &ModerationStatus,
+    ) -> Result<Uuid, StorageError> {
+        let suggestion_id = Uuid::new_v4();
+        let mut tables = self.tables();
+        tables.suggestions.push(SuggestionRow {
+            suggestion_id,
+            poll_id: *poll_id,
+            creator_id: *creator_id,
+            suggestion: suggestion.to_string(),
+            created_at: Utc::now(),
+            status: status.clone(),
+        });
+        tables.bump_version(poll_id);
+
+        Ok(suggestion_id)
+    }
+
+    #[tracing::instrument(name = "retrieve poll suggestions", skip(self))]
+    async fn visible_suggestions(&self, poll_id: &Uuid) -> Result<Vec<Suggestion>, StorageError> {
+        Ok(self
+            .tables()
+            .suggestions
+            .iter()
+            .filter(|s| s.poll_id == *poll_id && s.status == ModerationStatus::Visible)
+            .map(|s| Suggestion {
+                suggestion_id: s.suggestion_id,
+                creator_id: s.creator_id,
+                suggestion: s.suggestion.clone(),
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "retrieve all poll suggestions", skip(self))]
+    async fn suggestion_details(
+        &self,
+        poll_id: &Uuid,
+    ) -> Result<Vec<SuggestionDetails>, StorageError> {
+        let tables = self.tables();
+
+        Ok(tables
+            .suggestions
+            .iter()
+            .filter(|s| s.poll_id == *poll_id)
+            .map(|s| SuggestionDetails {
+                suggestion_id: s.suggestion_id,
+                suggestion: s.suggestion.clone(),
+                author: tables
+                    .member(poll_id, &s.creator_id)
+                    .map(|m| m.username.clone()),
+                moderation_reason: s.status.reason().map(str::to_owned),
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl VoteRepository for MemoryStorage {
+    #[tracing::instrument(name = "count poll votes", skip(self))]
+    async fn vote_counts(&self, poll_id: &Uuid) -> Result<Vec<(Uuid, i64)>, StorageError> {
+        let tables = self.tables();
+        let mut counts: HashMap<Uuid, i64> = HashMap::new();
+        for suggestion_id in tables.votes.values() {
+            let in_poll = tables
+                .suggestions
+                .iter()
+                .any(|s| s.suggestion_id == *suggestion_id && s.poll_id == *poll_id);
+            if in_poll {
+                *counts.entry(*suggestion_id).or_default() += 1;
+            }
+        }
+
+        Ok(counts.into_iter().collect())
+    }
+}
@@ -0,0 +1,502 @@
+mod memory;
+mod postgres;
+mod sqlite;
+
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use secrecy::Secret;
+use sqlx::migrate::Migrator;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::{
+    configuration::{DatabaseBackend, DatabaseSettings},
+    domain::{
+        ExportAccess, MemberSort, ModerationStatus, ParticipantName, PollRole, ReportReason,
+        ReportTarget, SuggestionSort,
+    },
+};
+
+pub use memory::MemoryStorage;
+pub use postgres::PostgresStorage;
+pub use sqlite::SqliteStorage;
+
+#[derive(thiserror::Error, Debug)]
+pub enum StorageError {
+    /// A row with the same unique key is already there.
+    #[error("conflicts with an existing row")]
+    Conflict,
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for StorageError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            // Unique violations of Postgres, then of SQLite's UNIQUE and PRIMARY KEY
+            sqlx::Error::Database(db_error)
+                if matches!(db_error.code().as_deref(), Some("23505" | "2067" | "1555")) =>
+            {
+                StorageError::Conflict
+            }
+            _ => StorageError::Unexpected(e.into()),
+        }
+    }
+}
+
+/// The backend selected in the configuration, the pool connects lazily.
+pub fn connect(settings: &DatabaseSettings) -> Arc<dyn Storage> {
+    match settings.backend {
+        DatabaseBackend::Postgres => Arc::new(PostgresStorage::connect_lazy(settings)),
+        DatabaseBackend::Sqlite => Arc::new(SqliteStorage::connect_lazy(settings)),
+        DatabaseBackend::Memory => Arc::new(MemoryStorage::new()),
+    }
+}
+
+/// Everything the application keeps in its database.
+#[async_trait::async_trait]
+pub trait Storage:
+    PollRepository
+    + ParticipantRepository
+    + SuggestionRepository
+    + VoteRepository
+    + InviteRepository
+    + ModerationRepository
+    + Send
+    + Sync
+{
+    /// The migrations this build expects the database to have.
+    fn migrator(&self) -> &'static Migrator;
+
+    async fn migrate(&self) -> Result<(), StorageError>;
+
+    /// Versions of the migrations that were applied successfully.
+    async fn applied_migrations(&self) -> Result<Vec<i64>, StorageError>;
+
+    /// Runs a trivial query to check the database answers.
+    async fn ping(&self) -> Result<(), StorageError>;
+
+    fn pool_status(&self) -> PoolStatus;
+
+    /// Waits for the connections in use to be released and closes them all.
+    async fn close(&self);
+
+    /// Calls `on_change` for the polls changed through any connection to the
+    /// database, other instances included, until the storage is closed.
+    /// Backends a single instance uses have nothing to watch.
+    fn watch_poll_changes(&self, on_change: PollChangeHandler) -> Option<JoinHandle<()>>;
+}
+
+/// Called with the id of a poll that changed or was deleted, or with `None`
+/// when changes may have been missed.
+pub type PollChangeHandler = Box<dyn Fn(Option<Uuid>) + Send + Sync>;
+
+pub struct PoolStatus {
+    pub connections: u32,
+    pub idle_connections: usize,
+}
+
+#[async_trait::async_trait]
+pub trait PollRepository {
+    /// Creates the poll along with its creator, who becomes its owner.
+    async fn create_poll(&self, poll: &NewPoll) -> Result<CreatedPoll, StorageError>;
+
+    async fn find_poll(&self, poll_id: &Uuid) -> Result<Option<Poll>, StorageError>;
+
+    /// What the poll's page shows in a single round trip: a page of the
+    /// members and one of the suggestions that aren't held for review, along
+    /// with the member `user_id` is whether their name is held or not and
+    /// the poll's version.
+    async fn poll_overview(
+        &self,
+        poll_id: &Uuid,
+        user_id: Option<&Uuid>,
+        page: &OverviewPage,
+    ) -> Result<PollOverview, StorageError>;
+
+    /// The results the poll is exported with, leaving out what is held for
+    /// review: members in the order they joined, suggestions oldest first
+    /// and every vote for them.
+    async fn poll_export(&self, poll_id: &Uuid) -> Result<PollExport, StorageError>;
+
+    async fn set_content_filter(
+        &self,
+        poll_id: &Uuid,
+        content_filter: Option<&serde_json::Value>,
+    ) -> Result<(), StorageError>;
+
+    async fn set_export_access(
+        &self,
+        poll_id: &Uuid,
+        export_access: ExportAccess,
+    ) -> Result<(), StorageError>;
+
+    /// Deletes the poll with everything in it, the users that only existed
+    /// in this poll go too. Returns the prompt of the deleted poll.
+    async fn delete_poll(&self, poll_id: &Uuid) -> Result<Option<String>, StorageError>;
+
+    /// The newest polls whose id is `q` or whose prompt contains it, ignoring
+    /// case. An empty search returns the newest polls.
+    async fn search_polls(&self, q: &str, limit: i64) -> Result<Vec<PollSummary>, StorageError>;
+
+    async fn poll_details(&self, poll_id: &Uuid) -> Result<Option<PollDetails>, StorageError>;
+
+    async fn totals(&self) -> Result<Totals, StorageError>;
+
+    /// What was created on each of the last `days` days, newest first, days
+    /// without activity included.
+    async fn daily_activity(&self, days: i32) -> Result<Vec<DailyActivity>, StorageError>;
+}
+
+#[async_trait::async_trait]
+pub trait ParticipantRepository {
+    /// Adds a new user to the poll, using up one of the invite's uses if
+    /// there is one. Returns `None` if the invite can't be used anymore and
+    /// [`StorageError::Conflict`] if the name looks like a member's.
+    async fn add_member(&self, member: &NewMember) -> Result<Option<Uuid>, StorageError>;
+
+    async fn find_member(
+        &self,
+        poll_id: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<Option<Member>, StorageError>;
+
+    /// Every member of the poll, ordered by name.
+    async fn members(&self, poll_id: &Uuid) -> Result<Vec<Member>, StorageError>;
+
+    /// Members whose name isn't held for review.
+    async fn visible_members(&self, poll_id: &Uuid) -> Result<Vec<Member>, StorageError>;
+
+    /// Every member with when they joined, ordered by that.
+    async fn member_details(&self, poll_id: &Uuid) -> Result<Vec<MemberDetails>, StorageError>;
+
+    /// The name of the member whose name has this skeleton, if any.
+    async fn find_lookalike(
+        &self,
+        poll_id: &Uuid,
+        skeleton: &str,
+    ) -> Result<Option<String>, StorageError>;
+
+    /// Which of `skeletons` belong to names of members.
+    async fn taken_skeletons(
+        &self,
+        poll_id: &Uuid,
+        skeletons: &[String],
+    ) -> Result<Vec<String>, StorageError>;
+
+    /// Owners are left untouched, their role only changes through a transfer.
+    async fn set_role(
+        &self,
+        poll_id: &Uuid,
+        user_id: &Uuid,
+        role: PollRole,
+    ) -> Result<bool, StorageError>;
+
+    /// The current owner steps down to co-organizer, returns `false` if the
+    /// new owner isn't a member of the poll.
+    async fn transfer_ownership(
+        &self,
+        poll_id: &Uuid,
+        current_owner_id: &Uuid,
+        new_owner_id: &Uuid,
+    ) -> Result<bool, StorageError>;
+}
+
+#[async_trait::async_trait]
+pub trait SuggestionRepository {
+    async fn add_suggestion(
+        &self,
+        poll_id: &Uuid,
+        creator_id: &Uuid,
+        suggestion: &str,
+        status: &ModerationStatus,
+    ) -> Result<Uuid, StorageError>;
+
+    /// Suggestions that aren't held for review.
+    async fn visible_suggestions(&self, poll_id: &Uuid) -> Result<Vec<Suggestion>, StorageError>;
+
+    /// Every suggestion, held ones included, oldest first.
+    async fn suggestion_details(
+        &self,
+        poll_id: &Uuid,
+    ) -> Result<Vec<SuggestionDetails>, StorageError>;
+}
+
+#[async_trait::async_trait]
+pub trait VoteRepository {
+    /// How many votes each suggestion of the poll that has any received.
+    async fn vote_counts(&self, poll_id: &Uuid) -> Result<Vec<(Uuid, i64)>, StorageError>;
+}
+
+#[async_trait::async_trait]
+pub trait InviteRepository {
+    async fn create_invite(&self, poll_id: &Uuid, invite: &NewInvite) -> Result<(), StorageError>;
+
+    /// The invites of the poll, newest first.
+    async fn invites(&self, poll_id: &Uuid) -> Result<Vec<Invite>, StorageError>;
+
+    /// Returns `false` if the invite was already revoked.
+    async fn revoke_invite(&self, poll_id: &Uuid, invite_id: &Uuid) -> Result<bool, StorageError>;
+
+    /// Deletes the invites that expired or were revoked before `cutoff`.
+    async fn purge_expired_invites(&self, cutoff: DateTime<Utc>) -> Result<u64, StorageError>;
+}
+
+#[async_trait::async_trait]
+pub trait ModerationRepository {
+    /// Stores the report and hides the reported item once `threshold`
+    /// members reported it. Reporting the same item twice only counts once.
+    async fn file_report(
+        &self,
+        report: &NewReport,
+        threshold: u32,
+    ) -> Result<ReportOutcome, StorageError>;
+
+    /// Suggestions that are held or have pending reports, oldest first.
+    async fn queued_suggestions(&self, poll_id: &Uuid) -> Result<Vec<QueuedItem>, StorageError>;
+
+    /// Members whose name is held or has pending reports, ordered by name.
+    async fn queued_members(&self, poll_id: &Uuid) -> Result<Vec<QueuedItem>, StorageError>;
+
+    /// Makes a held suggestion visible and closes the reports against it.
+    async fn approve_suggestion(
+        &self,
+        poll_id: &Uuid,
+        suggestion_id: &Uuid,
+    ) -> Result<bool, StorageError>;
+
+    /// Deletes a suggestion that is held or was reported, along with its
+    /// votes and reports.
+    async fn remove_suggestion(
+        &self,
+        poll_id: &Uuid,
+        suggestion_id: &Uuid,
+    ) -> Result<bool, StorageError>;
+
+    /// Makes a held name visible and closes the reports against it.
+    async fn approve_member(&self, poll_id: &Uuid, user_id: &Uuid) -> Result<bool, StorageError>;
+
+    /// Removes a member whose name is held or was reported from the poll.
+    /// Owners are never removed.
+    async fn remove_member(&self, poll_id: &Uuid, user_id: &Uuid) -> Result<bool, StorageError>;
+}
+
+pub struct NewPoll {
+    pub prompt: String,
+    pub creator_name: ParticipantName,
+    pub invite_only: bool,
+    pub passphrase_hash: Option<Secret<String>>,
+    pub require_proof_of_work: bool,
+}
+
+pub struct CreatedPoll {
+    pub poll_id: Uuid,
+    pub creator_id: Uuid,
+}
+
+pub struct Poll {
+    pub poll_id: Uuid,
+    pub creator_id: Uuid,
+    pub prompt: String,
+    pub invite_only: bool,
+    pub passphrase_hash: Option<Secret<String>>,
+    pub require_proof_of_work: bool,
+    pub content_filter: Option<serde_json::Value>,
+    pub export_access: ExportAccess,
+}
+
+pub struct PollSummary {
+    pub poll_id: Uuid,
+    pub prompt: String,
+    pub created_at: DateTime<Utc>,
+    pub members: i64,
+    pub suggestions: i64,
+}
+
+/// Which members and suggestions the poll's page lists. The pages are
+/// keyset paginated: the next one starts after the last item of the
+/// previous, the way it is sorted now.
+#[derive(Debug, Default, Clone)]
+pub struct OverviewPage {
+    /// Only the names and suggestions containing it, ignoring case.
+    pub search: String,
+    pub member_sort: MemberSort,
+    pub members_after: Option<Uuid>,
+    pub suggestion_sort: SuggestionSort,
+    pub suggestions_after: Option<Uuid>,
+    /// How many members and how many suggestions at most.
+    pub limit: i64,
+}
+
+#[derive(Default)]
+pub struct PollOverview {
+    /// Bumped whenever the members or suggestions of the poll change, `None`
+    /// if there is no such poll.
+    pub version: Option<i64>,
+    /// The member whose id was given, `None` if they aren't one.
+    pub member: Option<Member>,
+    pub members: Vec<Member>,
+    pub suggestions: Vec<Suggestion>,
+    /// Whether there are members after this page.
+    pub more_members: bool,
+    pub more_suggestions: bool,
+}
+
+impl PollOverview {
+    fn add_member(&mut self, member: Member, listed: bool, user_id: Option<&Uuid>) {
+        if Some(&member.user_id) == user_id {
+            self.member = Some(member.clone());
+        }
+        if listed {
+            self.members.push(member);
+        }
+    }
+
+    /// The backends fetch one more of each than `limit` to know if there is
+    /// another page.
+    fn truncate(mut self, limit: i64) -> Self {
+        let limit = usize::try_from(limit).unwrap_or_default();
+        self.more_members = self.members.len() > limit;
+        self.members.truncate(limit);
+        self.more_suggestions = self.suggestions.len() > limit;
+        self.suggestions.truncate(limit);
+        self
+    }
+}
+
+pub struct PollExport {
+    pub members: Vec<ExportedMember>,
+    pub suggestions: Vec<ExportedSuggestion>,
+    pub votes: Vec<ExportedVote>,
+}
+
+pub struct ExportedMember {
+    pub username: String,
+    pub role: PollRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+pub struct ExportedSuggestion {
+    pub suggestion_id: Uuid,
+    pub suggestion: String,
+    /// `None` once the author left the poll or while their name is held.
+    pub author: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct ExportedVote {
+    pub suggestion_id: Uuid,
+    /// `None` once the voter left the poll or while their name is held.
+    pub voter: Option<String>,
+}
+
+pub struct PollDetails {
+    pub prompt: String,
+    pub created_at: DateTime<Utc>,
+    pub invite_only: bool,
+    pub has_passphrase: bool,
+    pub require_proof_of_work: bool,
+    pub has_content_filter: bool,
+    pub pending_reports: i64,
+}
+
+pub struct Totals {
+    pub polls: i64,
+    pub users: i64,
+    pub suggestions: i64,
+}
+
+pub struct DailyActivity {
+    pub day: NaiveDate,
+    pub polls: i64,
+    pub users: i64,
+    pub suggestions: i64,
+}
+
+#[derive(Debug)]
+pub struct NewMember {
+    pub poll_id: Uuid,
+    pub name: ParticipantName,
+    pub role: PollRole,
+    pub status: ModerationStatus,
+    pub invite: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: PollRole,
+}
+
+pub struct MemberDetails {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: PollRole,
+    pub joined_at: DateTime<Utc>,
+    pub moderation_reason: Option<String>,
+}
+
+pub struct Suggestion {
+    pub suggestion_id: Uuid,
+    pub creator_id: Uuid,
+    pub suggestion: String,
+}
+
+pub struct SuggestionDetails {
+    pub suggestion_id: Uuid,
+    pub suggestion: String,
+    /// `None` once the author was removed from the poll.
+    pub author: Option<String>,
+    pub moderation_reason: Option<String>,
+}
+
+pub struct NewInvite {
+    pub token: String,
+    pub max_uses: i32,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct Invite {
+    pub invite_id: Uuid,
+    pub token: String,
+    pub max_uses: i32,
+    pub uses: i32,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub struct NewReport {
+    pub poll_id: Uuid,
+    pub reporter_id: Uuid,
+    pub target: ReportTarget,
+    pub reason: ReportReason,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ReportOutcome {
+    Filed,
+    /// The target isn't in the poll or is already hidden.
+    TargetNotFound,
+    /// Members can't report their own suggestions or name.
+    OwnContent,
+}
+
+/// A suggestion or name that is hidden or was reported.
+pub struct QueuedItem {
+    pub id: Uuid,
+    pub content: String,
+    pub moderation_reason: Option<String>,
+    pub report_reasons: Vec<String>,
+}
+
+fn parse_role(role: String) -> Result<PollRole, StorageError> {
+    PollRole::try_from(role).map_err(|e| StorageError::Unexpected(anyhow::Error::msg(e)))
+}
+
+fn parse_export_access(export_access: String) -> Result<ExportAccess, StorageError> {
+    ExportAccess::try_from(export_access)
+        .map_err(|e| StorageError::Unexpected(anyhow::Error::msg(e)))
+}
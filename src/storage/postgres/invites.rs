@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::PostgresStorage;
+use crate::storage::{Invite, InviteRepository, NewInvite, StorageError};
+
+#[async_trait::async_trait]
+impl InviteRepository for PostgresStorage {
+    #[tracing::instrument(name = "insert new invite", skip(self, invite))]
+    async fn create_invite(&self, poll_id: &Uuid, invite: &NewInvite) -> Result<(), StorageError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO poll_invites (invite_id, poll_id, token, max_uses, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, now(), $5)
+            "#,
+            Uuid::new_v4(),
+            poll_id,
+            invite.token,
+            invite.max_uses,
+            invite.expires_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "retrieve poll invites", skip(self))]
+    async fn invites(&self, poll_id: &Uuid) -> Result<Vec<Invite>, StorageError> {
+        Ok(sqlx::query_as!(
+            Invite,
+            r#"
+            SELECT invite_id, token, max_uses, uses, expires_at, revoked_at
+            FROM poll_invites
+            WHERE poll_id = $1
+            ORDER BY created_at DESC
+            "#,
+            poll_id
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    #[tracing::instrument(name = "mark invite as revoked", skip(self))]
+    async fn revoke_invite(&self, poll_id: &Uuid, invite_id: &Uuid) -> Result<bool, StorageError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE poll_invites
+            SET revoked_at = now()
+            WHERE poll_id = $1 AND invite_id = $2 AND revoked_at IS NULL
+            "#,
+            poll_id,
+            invite_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    #[tracing::instrument(name = "purge expired invites", skip(self))]
+    async fn purge_expired_invites(&self, cutoff: DateTime<Utc>) -> Result<u64, StorageError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM poll_invites
+            WHERE expires_at < $1 OR revoked_at < $1
+            "#,
+            cutoff
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,125 @@
+mod invites;
+mod moderation;
+mod participants;
+mod polls;
+mod suggestions;
+
+use std::convert::Infallible;
+use std::time::Duration;
+
+use sqlx::{
+    migrate::Migrator,
+    postgres::{PgListener, PgPoolOptions},
+    PgPool,
+};
+use tokio::task::JoinHandle;
+
+use super::{PollChangeHandler, PoolStatus, Storage, StorageError};
+use crate::configuration::DatabaseSettings;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Notified by a trigger with the id of every poll updated or deleted.
+const POLL_CHANGES_CHANNEL: &str = "poll_changed";
+
+#[derive(Clone)]
+pub struct PostgresStorage {
+    pool: PgPool,
+}
+
+impl PostgresStorage {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn connect_lazy(settings: &DatabaseSettings) -> Self {
+        Self::new(
+            PgPoolOptions::new()
+                .connect_timeout(std::time::Duration::from_secs(2))
+                .connect_lazy_with(settings.with_db()),
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for PostgresStorage {
+    fn migrator(&self) -> &'static Migrator {
+        &MIGRATOR
+    }
+
+    async fn migrate(&self) -> Result<(), StorageError> {
+        MIGRATOR
+            .run(&self.pool)
+            .await
+            .map_err(|e| StorageError::Unexpected(e.into()))
+    }
+
+    async fn applied_migrations(&self) -> Result<Vec<i64>, StorageError> {
+        let has_table: bool =
+            sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+                .fetch_one(&self.pool)
+                .await?;
+        if !has_table {
+            return Ok(Vec::new());
+        }
+
+        Ok(
+            sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        PoolStatus {
+            connections: self.pool.size(),
+            idle_connections: self.pool.num_idle(),
+        }
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    fn watch_poll_changes(&self, on_change: PollChangeHandler) -> Option<JoinHandle<()>> {
+        let pool = self.pool.clone();
+        Some(tokio::spawn(async move {
+            loop {
+                match listen_for_poll_changes(&pool, &on_change).await {
+                    Err(sqlx::Error::PoolClosed) => return,
+                    Err(e) => tracing::warn!(
+                        error.cause_chain = ?e,
+                        "Stopped listening for poll changes, trying again"
+                    ),
+                    Ok(never) => match never {},
+                }
+                on_change(None);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }))
+    }
+}
+
+async fn listen_for_poll_changes(
+    pool: &PgPool,
+    on_change: &PollChangeHandler,
+) -> Result<Infallible, sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(POLL_CHANGES_CHANNEL).await?;
+    // Changes made before listening were missed
+    on_change(None);
+
+    loop {
+        match listener.try_recv().await? {
+            Some(notification) => on_change(notification.payload().parse().ok()),
+            // The listener reconnects on the next call, the notifications
+            // sent in between are lost
+            None => on_change(None),
+        }
+    }
+}
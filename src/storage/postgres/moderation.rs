@@ -0,0 +1,335 @@
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use super::PostgresStorage;
+use crate::{
+    domain::ReportTarget,
+    storage::{ModerationRepository, NewReport, QueuedItem, ReportOutcome, StorageError},
+};
+
+#[async_trait::async_trait]
+impl ModerationRepository for PostgresStorage {
+    #[tracing::instrument(name = "file report", skip(self))]
+    async fn file_report(
+        &self,
+        report: &NewReport,
+        threshold: u32,
+    ) -> Result<ReportOutcome, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let author_id = match find_author(&mut transaction, report).await? {
+            Some(author_id) => author_id,
+            None => return Ok(ReportOutcome::TargetNotFound),
+        };
+        if author_id == report.reporter_id {
+            return Ok(ReportOutcome::OwnContent);
+        }
+
+        let (suggestion_id, reported_user_id) = match report.target {
+            ReportTarget::Suggestion(id) => (Some(id), None),
+            ReportTarget::Member(id) => (None, Some(id)),
+        };
+        sqlx::query!(
+            r#"
+            INSERT INTO reports (
+                report_id, poll_id, reporter_id, suggestion_id, reported_user_id, reason,
+                created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, now())
+            ON CONFLICT DO NOTHING
+            "#,
+            Uuid::new_v4(),
+            report.poll_id,
+            report.reporter_id,
+            suggestion_id,
+            reported_user_id,
+            report.reason.as_str()
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        let pending = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM reports
+            WHERE poll_id = $1
+              AND (suggestion_id = $2 OR reported_user_id = $3)
+              AND resolved_at IS NULL
+            "#,
+            report.poll_id,
+            suggestion_id,
+            reported_user_id
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .count;
+
+        if pending >= i64::from(threshold) {
+            let reason = format!("was reported by {pending} members");
+            hide_reported(&mut transaction, report, &reason).await?;
+        }
+
+        transaction.commit().await?;
+        Ok(ReportOutcome::Filed)
+    }
+
+    #[tracing::instrument(name = "retrieve held and reported suggestions", skip(self))]
+    async fn queued_suggestions(&self, poll_id: &Uuid) -> Result<Vec<QueuedItem>, StorageError> {
+        Ok(sqlx::query_as!(
+            QueuedItem,
+            r#"
+            SELECT s.suggestion_id AS id, s.suggestion AS content,
+                   s.moderation_reason,
+                   array_remove(array_agg(r.reason), NULL) AS "report_reasons!"
+            FROM suggestions s
+            LEFT JOIN reports r ON r.suggestion_id = s.suggestion_id AND r.resolved_at IS NULL
+            WHERE s.poll_id = $1
+            GROUP BY s.suggestion_id
+            HAVING s.moderation_status = 'held' OR COUNT(r.report_id) > 0
+            ORDER BY s.created_at
+            "#,
+            poll_id
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    #[tracing::instrument(name = "retrieve held and reported members", skip(self))]
+    async fn queued_members(&self, poll_id: &Uuid) -> Result<Vec<QueuedItem>, StorageError> {
+        Ok(sqlx::query_as!(
+            QueuedItem,
+            r#"
+            SELECT u.user_id AS id, u.username AS content,
+                   u.moderation_reason,
+                   array_remove(array_agg(r.reason), NULL) AS "report_reasons!"
+            FROM poll_users u
+            LEFT JOIN reports r
+                ON r.poll_id = u.poll_id AND r.reported_user_id = u.user_id
+                AND r.resolved_at IS NULL
+            WHERE u.poll_id = $1
+            GROUP BY u.poll_id, u.user_id
+            HAVING u.moderation_status = 'held' OR COUNT(r.report_id) > 0
+            ORDER BY u.username
+            "#,
+            poll_id
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    #[tracing::instrument(name = "approve suggestion in the moderation queue", skip(self))]
+    async fn approve_suggestion(
+        &self,
+        poll_id: &Uuid,
+        suggestion_id: &Uuid,
+    ) -> Result<bool, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+        let resolved = sqlx::query!(
+            r#"
+            UPDATE reports
+            SET resolved_at = now()
+            WHERE poll_id = $1 AND suggestion_id = $2 AND resolved_at IS NULL
+            "#,
+            poll_id,
+            suggestion_id
+        )
+        .execute(&mut transaction)
+        .await?;
+        let approved = sqlx::query!(
+            r#"
+            UPDATE suggestions
+            SET moderation_status = 'visible', moderation_reason = NULL
+            WHERE poll_id = $1 AND suggestion_id = $2 AND moderation_status = 'held'
+            "#,
+            poll_id,
+            suggestion_id
+        )
+        .execute(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+
+        Ok(resolved.rows_affected() > 0 || approved.rows_affected() == 1)
+    }
+
+    #[tracing::instrument(name = "delete suggestion in the moderation queue", skip(self))]
+    async fn remove_suggestion(
+        &self,
+        poll_id: &Uuid,
+        suggestion_id: &Uuid,
+    ) -> Result<bool, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+        let reports = sqlx::query!(
+            r#"
+            DELETE FROM reports
+            WHERE poll_id = $1 AND suggestion_id = $2
+            "#,
+            poll_id,
+            suggestion_id
+        )
+        .execute(&mut transaction)
+        .await?;
+        sqlx::query!(
+            r#"
+            DELETE FROM votes
+            WHERE suggestion_id = $1
+            "#,
+            suggestion_id
+        )
+        .execute(&mut transaction)
+        .await?;
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM suggestions
+            WHERE poll_id = $1 AND suggestion_id = $2 AND (moderation_status = 'held' OR $3)
+            "#,
+            poll_id,
+            suggestion_id,
+            reports.rows_affected() > 0
+        )
+        .execute(&mut transaction)
+        .await?;
+        if result.rows_affected() == 0 {
+            transaction.rollback().await?;
+            return Ok(false);
+        }
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    #[tracing::instrument(name = "approve member in the moderation queue", skip(self))]
+    async fn approve_member(&self, poll_id: &Uuid, user_id: &Uuid) -> Result<bool, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+        let resolved = sqlx::query!(
+            r#"
+            UPDATE reports
+            SET resolved_at = now()
+            WHERE poll_id = $1 AND reported_user_id = $2 AND resolved_at IS NULL
+            "#,
+            poll_id,
+            user_id
+        )
+        .execute(&mut transaction)
+        .await?;
+        let approved = sqlx::query!(
+            r#"
+            UPDATE poll_users
+            SET moderation_status = 'visible', moderation_reason = NULL
+            WHERE poll_id = $1 AND user_id = $2 AND moderation_status = 'held'
+            "#,
+            poll_id,
+            user_id
+        )
+        .execute(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+
+        Ok(resolved.rows_affected() > 0 || approved.rows_affected() == 1)
+    }
+
+    #[tracing::instrument(name = "delete member in the moderation queue", skip(self))]
+    async fn remove_member(&self, poll_id: &Uuid, user_id: &Uuid) -> Result<bool, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+        let reports = sqlx::query!(
+            r#"
+            DELETE FROM reports
+            WHERE poll_id = $1 AND reported_user_id = $2
+            "#,
+            poll_id,
+            user_id
+        )
+        .execute(&mut transaction)
+        .await?;
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM poll_users
+            WHERE poll_id = $1
+              AND user_id = $2
+              AND (moderation_status = 'held' OR $3)
+              AND role <> 'owner'
+            "#,
+            poll_id,
+            user_id,
+            reports.rows_affected() > 0
+        )
+        .execute(&mut transaction)
+        .await?;
+        if result.rows_affected() == 0 {
+            transaction.rollback().await?;
+            return Ok(false);
+        }
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+}
+
+/// Who wrote the reported suggestion, or the reported member themselves.
+/// Items that are already hidden can't be reported.
+async fn find_author(
+    transaction: &mut Transaction<'_, Postgres>,
+    report: &NewReport,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    match report.target {
+        ReportTarget::Suggestion(suggestion_id) => Ok(sqlx::query!(
+            r#"
+            SELECT creator_id
+            FROM suggestions
+            WHERE poll_id = $1 AND suggestion_id = $2 AND moderation_status = 'visible'
+            "#,
+            report.poll_id,
+            suggestion_id
+        )
+        .fetch_optional(transaction)
+        .await?
+        .map(|r| r.creator_id)),
+        ReportTarget::Member(user_id) => Ok(sqlx::query!(
+            r#"
+            SELECT user_id
+            FROM poll_users
+            WHERE poll_id = $1 AND user_id = $2 AND moderation_status = 'visible'
+            "#,
+            report.poll_id,
+            user_id
+        )
+        .fetch_optional(transaction)
+        .await?
+        .map(|r| r.user_id)),
+    }
+}
+
+/// The owner's name stays visible, they're the one reviewing the reports.
+async fn hide_reported(
+    transaction: &mut Transaction<'_, Postgres>,
+    report: &NewReport,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    match report.target {
+        ReportTarget::Suggestion(suggestion_id) => sqlx::query!(
+            r#"
+            UPDATE suggestions
+            SET moderation_status = 'held', moderation_reason = $3
+            WHERE poll_id = $1 AND suggestion_id = $2
+            "#,
+            report.poll_id,
+            suggestion_id,
+            reason
+        )
+        .execute(transaction)
+        .await
+        .map(|_| ()),
+        ReportTarget::Member(user_id) => sqlx::query!(
+            r#"
+            UPDATE poll_users
+            SET moderation_status = 'held', moderation_reason = $3
+            WHERE poll_id = $1 AND user_id = $2 AND role <> 'owner'
+            "#,
+            report.poll_id,
+            user_id,
+            reason
+        )
+        .execute(transaction)
+        .await
+        .map(|_| ()),
+    }
+}
@@ -0,0 +1,283 @@
+use uuid::Uuid;
+
+use super::PostgresStorage;
+use crate::{
+    domain::PollRole,
+    storage::{parse_role, Member, MemberDetails, NewMember, ParticipantRepository, StorageError},
+};
+
+#[async_trait::async_trait]
+impl ParticipantRepository for PostgresStorage {
+    #[tracing::instrument(name = "insert new poll member", skip(self))]
+    async fn add_member(&self, member: &NewMember) -> Result<Option<Uuid>, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+
+        if let Some(token) = &member.invite {
+            // Use up one of the invite's uses
+            let consumed = sqlx::query!(
+                r#"
+                UPDATE poll_invites
+                SET uses = uses + 1
+                WHERE poll_id = $1
+                  AND token = $2
+                  AND revoked_at IS NULL
+                  AND expires_at > now()
+                  AND uses < max_uses
+                "#,
+                member.poll_id,
+                token
+            )
+            .execute(&mut transaction)
+            .await?;
+            if consumed.rows_affected() != 1 {
+                return Ok(None);
+            }
+        }
+
+        let user_id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO users (user_id, created_at)
+            VALUES ($1, now())
+            "#,
+            &user_id
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO poll_users (
+                poll_id, user_id, username, name_skeleton, role, moderation_status,
+                moderation_reason
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            member.poll_id,
+            &user_id,
+            member.name.as_ref(),
+            member.name.skeleton(),
+            member.role.as_str(),
+            member.status.as_str(),
+            member.status.reason()
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+        Ok(Some(user_id))
+    }
+
+    #[tracing::instrument(name = "find poll member", skip(self))]
+    async fn find_member(
+        &self,
+        poll_id: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<Option<Member>, StorageError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT user_id, username, role
+            FROM poll_users
+            WHERE poll_id = $1 AND user_id = $2
+            "#,
+            poll_id,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|r| {
+            Ok(Member {
+                user_id: r.user_id,
+                username: r.username,
+                role: parse_role(r.role)?,
+            })
+        })
+        .transpose()
+    }
+
+    #[tracing::instrument(name = "retrieve poll members", skip(self))]
+    async fn members(&self, poll_id: &Uuid) -> Result<Vec<Member>, StorageError> {
+        sqlx::query!(
+            r#"
+            SELECT user_id, username, role
+            FROM poll_users
+            WHERE poll_id = $1
+            ORDER BY username
+            "#,
+            poll_id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| {
+            Ok(Member {
+                user_id: r.user_id,
+                username: r.username,
+                role: parse_role(r.role)?,
+            })
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "retrieve visible poll members", skip(self))]
+    async fn visible_members(&self, poll_id: &Uuid) -> Result<Vec<Member>, StorageError> {
+        sqlx::query!(
+            r#"
+            SELECT user_id, username, role
+            FROM poll_users
+            WHERE poll_id = $1 AND moderation_status = 'visible'
+            "#,
+            poll_id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| {
+            Ok(Member {
+                user_id: r.user_id,
+                username: r.username,
+                role: parse_role(r.role)?,
+            })
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "retrieve all poll members", skip(self))]
+    async fn member_details(&self, poll_id: &Uuid) -> Result<Vec<MemberDetails>, StorageError> {
+        sqlx::query!(
+            r#"
+            SELECT pu.user_id, pu.username, pu.role, u.created_at AS joined_at,
+                   pu.moderation_reason
+            FROM poll_users pu
+            JOIN users u ON u.user_id = pu.user_id
+            WHERE pu.poll_id = $1
+            ORDER BY u.created_at
+            "#,
+            poll_id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| {
+            Ok(MemberDetails {
+                user_id: r.user_id,
+                username: r.username,
+                role: parse_role(r.role)?,
+                joined_at: r.joined_at,
+                moderation_reason: r.moderation_reason,
+            })
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "find user with a lookalike name", skip(self))]
+    async fn find_lookalike(
+        &self,
+        poll_id: &Uuid,
+        skeleton: &str,
+    ) -> Result<Option<String>, StorageError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT username
+            FROM poll_users
+            WHERE poll_id = $1 AND name_skeleton = $2
+            "#,
+            poll_id,
+            skeleton
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| r.username))
+    }
+
+    #[tracing::instrument(name = "find taken name skeletons", skip(self))]
+    async fn taken_skeletons(
+        &self,
+        poll_id: &Uuid,
+        skeletons: &[String],
+    ) -> Result<Vec<String>, StorageError> {
+        Ok(sqlx::query!(
+            r#"
+            SELECT name_skeleton
+            FROM poll_users
+            WHERE poll_id = $1 AND name_skeleton = ANY($2)
+            "#,
+            poll_id,
+            skeletons
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| r.name_skeleton)
+        .collect())
+    }
+
+    #[tracing::instrument(name = "update member role", skip(self))]
+    async fn set_role(
+        &self,
+        poll_id: &Uuid,
+        user_id: &Uuid,
+        role: PollRole,
+    ) -> Result<bool, StorageError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE poll_users
+            SET role = $3
+            WHERE poll_id = $1 AND user_id = $2 AND role <> 'owner'
+            "#,
+            poll_id,
+            user_id,
+            role.as_str()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    #[tracing::instrument(name = "swap poll owner", skip(self))]
+    async fn transfer_ownership(
+        &self,
+        poll_id: &Uuid,
+        current_owner_id: &Uuid,
+        new_owner_id: &Uuid,
+    ) -> Result<bool, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Demote first, there can only be one owner at a time
+        sqlx::query!(
+            r#"
+            UPDATE poll_users
+            SET role = $3
+            WHERE poll_id = $1 AND user_id = $2
+            "#,
+            poll_id,
+            current_owner_id,
+            PollRole::CoOrganizer.as_str()
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE poll_users
+            SET role = $3
+            WHERE poll_id = $1 AND user_id = $2
+            "#,
+            poll_id,
+            new_owner_id,
+            PollRole::Owner.as_str()
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        transaction.commit().await?;
+        Ok(true)
+    }
+}
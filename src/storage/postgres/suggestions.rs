@@ -0,0 +1,97 @@
+use uuid::Uuid;
+
+use super::PostgresStorage;
+use crate::{
+    domain::ModerationStatus,
+    storage::{StorageError, Suggestion, SuggestionDetails, SuggestionRepository, VoteRepository},
+};
+
+#[async_trait::async_trait]
+impl SuggestionRepository for PostgresStorage {
+    #[tracing::instrument(name = "insert new suggestion", skip(self))]
+    async fn add_suggestion(
+        &self,
+        poll_id: &Uuid,
+        creator_id: &Uuid,
+        suggestion: &str,
+        status: &ModerationStatus,
+    ) -> Result<Uuid, StorageError> {
+        let suggestion_id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO suggestions (
+                suggestion_id, poll_id, creator_id, suggestion, moderation_status,
+                moderation_reason, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, now())
+            "#,
+            suggestion_id,
+            poll_id,
+            creator_id,
+            suggestion,
+            status.as_str(),
+            status.reason()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(suggestion_id)
+    }
+
+    #[tracing::instrument(name = "retrieve poll suggestions", skip(self))]
+    async fn visible_suggestions(&self, poll_id: &Uuid) -> Result<Vec<Suggestion>, StorageError> {
+        Ok(sqlx::query_as!(
+            Suggestion,
+            r#"
+            SELECT suggestion_id, creator_id, suggestion
+            FROM suggestions
+            WHERE poll_id = $1 AND moderation_status = 'visible'
+            "#,
+            poll_id
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    #[tracing::instrument(name = "retrieve all poll suggestions", skip(self))]
+    async fn suggestion_details(
+        &self,
+        poll_id: &Uuid,
+    ) -> Result<Vec<SuggestionDetails>, StorageError> {
+        Ok(sqlx::query_as!(
+            SuggestionDetails,
+            r#"
+            SELECT s.suggestion_id, s.suggestion, pu.username AS "author?", s.moderation_reason
+            FROM suggestions s
+            LEFT JOIN poll_users pu ON pu.poll_id = s.poll_id AND pu.user_id = s.creator_id
+            WHERE s.poll_id = $1
+            ORDER BY s.created_at
+            "#,
+            poll_id
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+}
+
+#[async_trait::async_trait]
+impl VoteRepository for PostgresStorage {
+    #[tracing::instrument(name = "count poll votes", skip(self))]
+    async fn vote_counts(&self, poll_id: &Uuid) -> Result<Vec<(Uuid, i64)>, StorageError> {
+        Ok(sqlx::query!(
+            r#"
+            SELECT v.suggestion_id, COUNT(*) AS "votes!"
+            FROM votes v
+            JOIN suggestions s ON s.suggestion_id = v.suggestion_id
+            WHERE s.poll_id = $1
+            GROUP BY v.suggestion_id
+            "#,
+            poll_id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| (r.suggestion_id, r.votes))
+        .collect())
+    }
+}
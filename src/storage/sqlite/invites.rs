@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::SqliteStorage;
+use crate::storage::{Invite, InviteRepository, NewInvite, StorageError};
+
+#[async_trait::async_trait]
+impl InviteRepository for SqliteStorage {
+    #[tracing::instrument(name = "insert new invite", skip(self, invite))]
+    async fn create_invite(&self, poll_id: &Uuid, invite: &NewInvite) -> Result<(), StorageError> {
+        sqlx::query(
+            r#"
+            INSERT INTO poll_invites (invite_id, poll_id, token, max_uses, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(poll_id)
+        .bind(&invite.token)
+        .bind(invite.max_uses)
+        .bind(Utc::now())
+        .bind(invite.expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "retrieve poll invites", skip(self))]
+    async fn invites(&self, poll_id: &Uuid) -> Result<Vec<Invite>, StorageError> {
+        let rows: Vec<(Uuid, String, i32, i32, DateTime<Utc>, Option<DateTime<Utc>>)> =
+            sqlx::query_as(
+                r#"
+                SELECT invite_id, token, max_uses, uses, expires_at, revoked_at
+                FROM poll_invites
+                WHERE poll_id = $1
+                ORDER BY created_at DESC
+                "#,
+            )
+            .bind(poll_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(invite_id, token, max_uses, uses, expires_at, revoked_at)| Invite {
+                    invite_id,
+                    token,
+                    max_uses,
+                    uses,
+                    expires_at,
+                    revoked_at,
+                },
+            )
+            .collect())
+    }
+
+    #[tracing::instrument(name = "mark invite as revoked", skip(self))]
+    async fn revoke_invite(&self, poll_id: &Uuid, invite_id: &Uuid) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE poll_invites
+            SET revoked_at = $3
+            WHERE poll_id = $1 AND invite_id = $2 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(poll_id)
+        .bind(invite_id)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    #[tracing::instrument(name = "purge expired invites", skip(self))]
+    async fn purge_expired_invites(&self, cutoff: DateTime<Utc>) -> Result<u64, StorageError> {
+        let result =
+            sqlx::query("DELETE FROM poll_invites WHERE expires_at < $1 OR revoked_at < $1")
+                .bind(cutoff)
+                .execute(&self.pool)
+                .await?;
+
+        Ok(result.rows_affected())
+    }
+}
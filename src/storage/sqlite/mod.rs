@@ -0,0 +1,85 @@
+mod invites;
+mod moderation;
+mod participants;
+mod polls;
+mod suggestions;
+
+use sqlx::{migrate::Migrator, sqlite::SqlitePoolOptions, SqlitePool};
+use tokio::task::JoinHandle;
+
+use super::{PollChangeHandler, PoolStatus, Storage, StorageError};
+use crate::configuration::DatabaseSettings;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+#[derive(Clone)]
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// SQLite lets one writer in at a time, a single connection keeps two
+    /// transactions from both waiting to upgrade their read lock.
+    pub fn connect_lazy(settings: &DatabaseSettings) -> Self {
+        Self::new(
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .connect_lazy_with(settings.sqlite_options()),
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for SqliteStorage {
+    fn migrator(&self) -> &'static Migrator {
+        &MIGRATOR
+    }
+
+    async fn migrate(&self) -> Result<(), StorageError> {
+        MIGRATOR
+            .run(&self.pool)
+            .await
+            .map_err(|e| StorageError::Unexpected(e.into()))
+    }
+
+    async fn applied_migrations(&self) -> Result<Vec<i64>, StorageError> {
+        let has_table: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        if !has_table {
+            return Ok(Vec::new());
+        }
+
+        Ok(
+            sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        PoolStatus {
+            connections: self.pool.size(),
+            idle_connections: self.pool.num_idle(),
+        }
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    fn watch_poll_changes(&self, _on_change: PollChangeHandler) -> Option<JoinHandle<()>> {
+        None
+    }
+}
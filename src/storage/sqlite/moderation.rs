@@ -0,0 +1,331 @@
+use chrono::Utc;
+use sqlx::{Sqlite, Transaction};
+use uuid::Uuid;
+
+use super::SqliteStorage;
+use crate::{
+    domain::ReportTarget,
+    storage::{ModerationRepository, NewReport, QueuedItem, ReportOutcome, StorageError},
+};
+
+#[async_trait::async_trait]
+impl ModerationRepository for SqliteStorage {
+    #[tracing::instrument(name = "file report", skip(self))]
+    async fn file_report(
+        &self,
+        report: &NewReport,
+        threshold: u32,
+    ) -> Result<ReportOutcome, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let author_id = match find_author(&mut transaction, report).await? {
+            Some(author_id) => author_id,
+            None => return Ok(ReportOutcome::TargetNotFound),
+        };
+        if author_id == report.reporter_id {
+            return Ok(ReportOutcome::OwnContent);
+        }
+
+        let (suggestion_id, reported_user_id) = match report.target {
+            ReportTarget::Suggestion(id) => (Some(id), None),
+            ReportTarget::Member(id) => (None, Some(id)),
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO reports (
+                report_id, poll_id, reporter_id, suggestion_id, reported_user_id, reason,
+                created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(report.poll_id)
+        .bind(report.reporter_id)
+        .bind(suggestion_id)
+        .bind(reported_user_id)
+        .bind(report.reason.as_str())
+        .bind(Utc::now())
+        .execute(&mut transaction)
+        .await?;
+
+        let pending: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM reports
+            WHERE poll_id = $1
+              AND (suggestion_id = $2 OR reported_user_id = $3)
+              AND resolved_at IS NULL
+            "#,
+        )
+        .bind(report.poll_id)
+        .bind(suggestion_id)
+        .bind(reported_user_id)
+        .fetch_one(&mut transaction)
+        .await?;
+
+        if pending >= i64::from(threshold) {
+            let reason = format!("was reported by {pending} members");
+            hide_reported(&mut transaction, report, &reason).await?;
+        }
+
+        transaction.commit().await?;
+        Ok(ReportOutcome::Filed)
+    }
+
+    #[tracing::instrument(name = "retrieve held and reported suggestions", skip(self))]
+    async fn queued_suggestions(&self, poll_id: &Uuid) -> Result<Vec<QueuedItem>, StorageError> {
+        let rows: Vec<(Uuid, String, Option<String>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT s.suggestion_id, s.suggestion, s.moderation_reason, group_concat(r.reason)
+            FROM suggestions s
+            LEFT JOIN reports r ON r.suggestion_id = s.suggestion_id AND r.resolved_at IS NULL
+            WHERE s.poll_id = $1
+            GROUP BY s.suggestion_id
+            HAVING s.moderation_status = 'held' OR COUNT(r.report_id) > 0
+            ORDER BY s.created_at
+            "#,
+        )
+        .bind(poll_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(queued_item).collect())
+    }
+
+    #[tracing::instrument(name = "retrieve held and reported members", skip(self))]
+    async fn queued_members(&self, poll_id: &Uuid) -> Result<Vec<QueuedItem>, StorageError> {
+        let rows: Vec<(Uuid, String, Option<String>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT u.user_id, u.username, u.moderation_reason, group_concat(r.reason)
+            FROM poll_users u
+            LEFT JOIN reports r
+                ON r.poll_id = u.poll_id AND r.reported_user_id = u.user_id
+                AND r.resolved_at IS NULL
+            WHERE u.poll_id = $1
+            GROUP BY u.poll_id, u.user_id
+            HAVING u.moderation_status = 'held' OR COUNT(r.report_id) > 0
+            ORDER BY u.username
+            "#,
+        )
+        .bind(poll_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(queued_item).collect())
+    }
+
+    #[tracing::instrument(name = "approve suggestion in the moderation queue", skip(self))]
+    async fn approve_suggestion(
+        &self,
+        poll_id: &Uuid,
+        suggestion_id: &Uuid,
+    ) -> Result<bool, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+        let resolved = sqlx::query(
+            r#"
+            UPDATE reports
+            SET resolved_at = $3
+            WHERE poll_id = $1 AND suggestion_id = $2 AND resolved_at IS NULL
+            "#,
+        )
+        .bind(poll_id)
+        .bind(suggestion_id)
+        .bind(Utc::now())
+        .execute(&mut transaction)
+        .await?;
+        let approved = sqlx::query(
+            r#"
+            UPDATE suggestions
+            SET moderation_status = 'visible', moderation_reason = NULL
+            WHERE poll_id = $1 AND suggestion_id = $2 AND moderation_status = 'held'
+            "#,
+        )
+        .bind(poll_id)
+        .bind(suggestion_id)
+        .execute(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+
+        Ok(resolved.rows_affected() > 0 || approved.rows_affected() == 1)
+    }
+
+    #[tracing::instrument(name = "delete suggestion in the moderation queue", skip(self))]
+    async fn remove_suggestion(
+        &self,
+        poll_id: &Uuid,
+        suggestion_id: &Uuid,
+    ) -> Result<bool, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+        let reports = sqlx::query("DELETE FROM reports WHERE poll_id = $1 AND suggestion_id = $2")
+            .bind(poll_id)
+            .bind(suggestion_id)
+            .execute(&mut transaction)
+            .await?;
+        sqlx::query("DELETE FROM votes WHERE suggestion_id = $1")
+            .bind(suggestion_id)
+            .execute(&mut transaction)
+            .await?;
+        let result = sqlx::query(
+            r#"
+            DELETE FROM suggestions
+            WHERE poll_id = $1 AND suggestion_id = $2 AND (moderation_status = 'held' OR $3)
+            "#,
+        )
+        .bind(poll_id)
+        .bind(suggestion_id)
+        .bind(reports.rows_affected() > 0)
+        .execute(&mut transaction)
+        .await?;
+        if result.rows_affected() == 0 {
+            transaction.rollback().await?;
+            return Ok(false);
+        }
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    #[tracing::instrument(name = "approve member in the moderation queue", skip(self))]
+    async fn approve_member(&self, poll_id: &Uuid, user_id: &Uuid) -> Result<bool, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+        let resolved = sqlx::query(
+            r#"
+            UPDATE reports
+            SET resolved_at = $3
+            WHERE poll_id = $1 AND reported_user_id = $2 AND resolved_at IS NULL
+            "#,
+        )
+        .bind(poll_id)
+        .bind(user_id)
+        .bind(Utc::now())
+        .execute(&mut transaction)
+        .await?;
+        let approved = sqlx::query(
+            r#"
+            UPDATE poll_users
+            SET moderation_status = 'visible', moderation_reason = NULL
+            WHERE poll_id = $1 AND user_id = $2 AND moderation_status = 'held'
+            "#,
+        )
+        .bind(poll_id)
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+
+        Ok(resolved.rows_affected() > 0 || approved.rows_affected() == 1)
+    }
+
+    #[tracing::instrument(name = "delete member in the moderation queue", skip(self))]
+    async fn remove_member(&self, poll_id: &Uuid, user_id: &Uuid) -> Result<bool, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+        let reports =
+            sqlx::query("DELETE FROM reports WHERE poll_id = $1 AND reported_user_id = $2")
+                .bind(poll_id)
+                .bind(user_id)
+                .execute(&mut transaction)
+                .await?;
+        let result = sqlx::query(
+            r#"
+            DELETE FROM poll_users
+            WHERE poll_id = $1
+              AND user_id = $2
+              AND (moderation_status = 'held' OR $3)
+              AND role <> 'owner'
+            "#,
+        )
+        .bind(poll_id)
+        .bind(user_id)
+        .bind(reports.rows_affected() > 0)
+        .execute(&mut transaction)
+        .await?;
+        if result.rows_affected() == 0 {
+            transaction.rollback().await?;
+            return Ok(false);
+        }
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+}
+
+/// Report reasons come back comma separated, none of them contain a comma.
+fn queued_item(
+    (id, content, moderation_reason, reasons): (Uuid, String, Option<String>, Option<String>),
+) -> QueuedItem {
+    QueuedItem {
+        id,
+        content,
+        moderation_reason,
+        report_reasons: reasons
+            .map(|r| r.split(',').map(str::to_owned).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Who wrote the reported suggestion, or the reported member themselves.
+/// Items that are already hidden can't be reported.
+async fn find_author(
+    transaction: &mut Transaction<'_, Sqlite>,
+    report: &NewReport,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    match report.target {
+        ReportTarget::Suggestion(suggestion_id) => {
+            sqlx::query_scalar(
+                r#"
+                SELECT creator_id
+                FROM suggestions
+                WHERE poll_id = $1 AND suggestion_id = $2 AND moderation_status = 'visible'
+                "#,
+            )
+            .bind(report.poll_id)
+            .bind(suggestion_id)
+            .fetch_optional(transaction)
+            .await
+        }
+        ReportTarget::Member(user_id) => {
+            sqlx::query_scalar(
+                r#"
+                SELECT user_id
+                FROM poll_users
+                WHERE poll_id = $1 AND user_id = $2 AND moderation_status = 'visible'
+                "#,
+            )
+            .bind(report.poll_id)
+            .bind(user_id)
+            .fetch_optional(transaction)
+            .await
+        }
+    }
+}
+
+/// The owner's name stays visible, they're the one reviewing the reports.
+async fn hide_reported(
+    transaction: &mut Transaction<'_, Sqlite>,
+    report: &NewReport,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    let query = match report.target {
+        ReportTarget::Suggestion(suggestion_id) => sqlx::query(
+            r#"
+            UPDATE suggestions
+            SET moderation_status = 'held', moderation_reason = $3
+            WHERE poll_id = $1 AND suggestion_id = $2
+            "#,
+        )
+        .bind(report.poll_id)
+        .bind(suggestion_id),
+        ReportTarget::Member(user_id) => sqlx::query(
+            r#"
+            UPDATE poll_users
+            SET moderation_status = 'held', moderation_reason = $3
+            WHERE poll_id = $1 AND user_id = $2 AND role <> 'owner'
+            "#,
+        )
+        .bind(report.poll_id)
+        .bind(user_id),
+    };
+    query.bind(reason).execute(transaction).await.map(|_| ())
+}
@@ -0,0 +1,253 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::SqliteStorage;
+use crate::{
+    domain::PollRole,
+    storage::{parse_role, Member, MemberDetails, NewMember, ParticipantRepository, StorageError},
+};
+
+#[async_trait::async_trait]
+impl ParticipantRepository for SqliteStorage {
+    #[tracing::instrument(name = "insert new poll member", skip(self))]
+    async fn add_member(&self, member: &NewMember) -> Result<Option<Uuid>, StorageError> {
+        let now = Utc::now();
+        let mut transaction = self.pool.begin().await?;
+
+        if let Some(token) = &member.invite {
+            // Use up one of the invite's uses
+            let consumed = sqlx::query(
+                r#"
+                UPDATE poll_invites
+                SET uses = uses + 1
+                WHERE poll_id = $1
+                  AND token = $2
+                  AND revoked_at IS NULL
+                  AND expires_at > $3
+                  AND uses < max_uses
+                "#,
+            )
+            .bind(member.poll_id)
+            .bind(token)
+            .bind(now)
+            .execute(&mut transaction)
+            .await?;
+            if consumed.rows_affected() != 1 {
+                return Ok(None);
+            }
+        }
+
+        let user_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO users (user_id, created_at) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(now)
+            .execute(&mut transaction)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO poll_users (
+                poll_id, user_id, username, name_skeleton, role, moderation_status,
+                moderation_reason
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(member.poll_id)
+        .bind(user_id)
+        .bind(member.name.as_ref())
+        .bind(member.name.skeleton())
+        .bind(member.role.as_str())
+        .bind(member.status.as_str())
+        .bind(member.status.reason())
+        .execute(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+        Ok(Some(user_id))
+    }
+
+    #[tracing::instrument(name = "find poll member", skip(self))]
+    async fn find_member(
+        &self,
+        poll_id: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<Option<Member>, StorageError> {
+        let row: Option<(Uuid, String, String)> = sqlx::query_as(
+            r#"
+            SELECT user_id, username, role
+            FROM poll_users
+            WHERE poll_id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(poll_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(member).transpose()
+    }
+
+    #[tracing::instrument(name = "retrieve poll members", skip(self))]
+    async fn members(&self, poll_id: &Uuid) -> Result<Vec<Member>, StorageError> {
+        let rows: Vec<(Uuid, String, String)> = sqlx::query_as(
+            r#"
+            SELECT user_id, username, role
+            FROM poll_users
+            WHERE poll_id = $1
+            ORDER BY username
+            "#,
+        )
+        .bind(poll_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(member).collect()
+    }
+
+    #[tracing::instrument(name = "retrieve visible poll members", skip(self))]
+    async fn visible_members(&self, poll_id: &Uuid) -> Result<Vec<Member>, StorageError> {
+        let rows: Vec<(Uuid, String, String)> = sqlx::query_as(
+            r#"
+            SELECT user_id, username, role
+            FROM poll_users
+            WHERE poll_id = $1 AND moderation_status = 'visible'
+            "#,
+        )
+        .bind(poll_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(member).collect()
+    }
+
+    #[tracing::instrument(name = "retrieve all poll members", skip(self))]
+    async fn member_details(&self, poll_id: &Uuid) -> Result<Vec<MemberDetails>, StorageError> {
+        let rows: Vec<(Uuid, String, String, DateTime<Utc>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT pu.user_id, pu.username, pu.role, u.created_at, pu.moderation_reason
+            FROM poll_users pu
+            JOIN users u ON u.user_id = pu.user_id
+            WHERE pu.poll_id = $1
+            ORDER BY u.created_at
+            "#,
+        )
+        .bind(poll_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(user_id, username, role, joined_at, moderation_reason)| {
+                Ok(MemberDetails {
+                    user_id,
+                    username,
+                    role: parse_role(role)?,
+                    joined_at,
+                    moderation_reason,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "find user with a lookalike name", skip(self))]
+    async fn find_lookalike(
+        &self,
+        poll_id: &Uuid,
+        skeleton: &str,
+    ) -> Result<Option<String>, StorageError> {
+        Ok(sqlx::query_scalar(
+            r#"
+            SELECT username
+            FROM poll_users
+            WHERE poll_id = $1 AND name_skeleton = $2
+            "#,
+        )
+        .bind(poll_id)
+        .bind(skeleton)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    /// SQLite has no arrays to bind, the poll's skeletons are filtered here.
+    #[tracing::instrument(name = "find taken name skeletons", skip(self))]
+    async fn taken_skeletons(
+        &self,
+        poll_id: &Uuid,
+        skeletons: &[String],
+    ) -> Result<Vec<String>, StorageError> {
+        let taken: Vec<String> =
+            sqlx::query_scalar("SELECT name_skeleton FROM poll_users WHERE poll_id = $1")
+                .bind(poll_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(taken
+            .into_iter()
+            .filter(|skeleton| skeletons.contains(skeleton))
+            .collect())
+    }
+
+    #[tracing::instrument(name = "update member role", skip(self))]
+    async fn set_role(
+        &self,
+        poll_id: &Uuid,
+        user_id: &Uuid,
+        role: PollRole,
+    ) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE poll_users
+            SET role = $3
+            WHERE poll_id = $1 AND user_id = $2 AND role <> 'owner'
+            "#,
+        )
+        .bind(poll_id)
+        .bind(user_id)
+        .bind(role.as_str())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    #[tracing::instrument(name = "swap poll owner", skip(self))]
+    async fn transfer_ownership(
+        &self,
+        poll_id: &Uuid,
+        current_owner_id: &Uuid,
+        new_owner_id: &Uuid,
+    ) -> Result<bool, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Demote first, there can only be one owner at a time
+        sqlx::query("UPDATE poll_users SET role = $3 WHERE poll_id = $1 AND user_id = $2")
+            .bind(poll_id)
+            .bind(current_owner_id)
+            .bind(PollRole::CoOrganizer.as_str())
+            .execute(&mut transaction)
+            .await?;
+
+        let result =
+            sqlx::query("UPDATE poll_users SET role = $3 WHERE poll_id = $1 AND user_id = $2")
+                .bind(poll_id)
+                .bind(new_owner_id)
+                .bind(PollRole::Owner.as_str())
+                .execute(&mut transaction)
+                .await?;
+
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        transaction.commit().await?;
+        Ok(true)
+    }
+}
+
+fn member((user_id, username, role): (Uuid, String, String)) -> Result<Member, StorageError> {
+    Ok(Member {
+        user_id,
+        username,
+        role: parse_role(role)?,
+    })
+}
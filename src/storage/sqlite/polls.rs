@@ -0,0 +1,556 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
+
+use super::SqliteStorage;
+use crate::{
+    domain::{ExportAccess, PollRole},
+    storage::{
+        parse_export_access, parse_role, CreatedPoll, DailyActivity, ExportedMember,
+        ExportedSuggestion, ExportedVote, Member, NewPoll, OverviewPage, Poll, PollDetails,
+        PollExport, PollOverview, PollRepository, PollSummary, StorageError, Suggestion, Totals,
+    },
+};
+
+#[async_trait::async_trait]
+impl PollRepository for SqliteStorage {
+    #[tracing::instrument(
+        name = "Inserting new poll in the database",
+        skip_all,
+        fields(
+            poll_prompt = %poll.prompt,
+            invite_only = %poll.invite_only,
+            require_proof_of_work = %poll.require_proof_of_work
+        )
+    )]
+    async fn create_poll(&self, poll: &NewPoll) -> Result<CreatedPoll, StorageError> {
+        let now = Utc::now();
+        let mut transaction = self.pool.begin().await?;
+
+        let creator_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO users (user_id, created_at) VALUES ($1, $2)")
+            .bind(creator_id)
+            .bind(now)
+            .execute(&mut transaction)
+            .await?;
+
+        let poll_id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO polls (
+                poll_id, creator_id, prompt, invite_only, passphrase_hash, require_proof_of_work,
+                created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(poll_id)
+        .bind(creator_id)
+        .bind(&poll.prompt)
+        .bind(poll.invite_only)
+        .bind(poll.passphrase_hash.as_ref().map(|h| h.expose_secret()))
+        .bind(poll.require_proof_of_work)
+        .bind(now)
+        .execute(&mut transaction)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO poll_users (poll_id, user_id, username, name_skeleton, role)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(poll_id)
+        .bind(creator_id)
+        .bind(poll.creator_name.as_ref())
+        .bind(poll.creator_name.skeleton())
+        .bind(PollRole::Owner.as_str())
+        .execute(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+        Ok(CreatedPoll {
+            poll_id,
+            creator_id,
+        })
+    }
+
+    #[tracing::instrument(name = "find poll in database", skip(self))]
+    async fn find_poll(&self, poll_id: &Uuid) -> Result<Option<Poll>, StorageError> {
+        let row: Option<PollRow> = sqlx::query_as(
+            r#"
+            SELECT poll_id, creator_id, prompt, invite_only, passphrase_hash,
+                   require_proof_of_work, content_filter, export_access
+            FROM polls
+            WHERE poll_id = $1
+            "#,
+        )
+        .bind(poll_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|r| {
+            let content_filter = r
+                .content_filter
+                .map(|f| serde_json::from_str(&f))
+                .transpose()
+                .map_err(|e| StorageError::Unexpected(e.into()))?;
+            Ok(Poll {
+                poll_id: r.poll_id,
+                creator_id: r.creator_id,
+                prompt: r.prompt,
+                invite_only: r.invite_only,
+                passphrase_hash: r.passphrase_hash.map(Secret::new),
+                require_proof_of_work: r.require_proof_of_work,
+                content_filter,
+                export_access: parse_export_access(r.export_access)?,
+            })
+        })
+        .transpose()
+    }
+
+    /// Members, suggestions and the version come in one query, telling them
+    /// apart by `kind`.
+    #[tracing::instrument(name = "retrieve poll overview", skip(self))]
+    async fn poll_overview(
+        &self,
+        poll_id: &Uuid,
+        user_id: Option<&Uuid>,
+        page: &OverviewPage,
+    ) -> Result<PollOverview, StorageError> {
+        let rows: Vec<(String, Uuid, String, Option<String>, Option<Uuid>)> = sqlx::query_as(
+            r#"
+                WITH member_rows AS (
+                    SELECT pu.user_id, pu.username, pu.role,
+                           pu.moderation_status = 'visible' AS visible, u.created_at,
+                           lower(pu.username) AS name_key
+                    FROM poll_users pu
+                    JOIN users u ON u.user_id = pu.user_id
+                    WHERE pu.poll_id = $1
+                ),
+                member_cursor AS (
+                    SELECT * FROM member_rows WHERE user_id = $5
+                ),
+                member_page AS (
+                    SELECT m.user_id, m.username, m.role, ROW_NUMBER() OVER (
+                        ORDER BY
+                            CASE WHEN $4 = 'alphabetical' THEN m.name_key END,
+                            CASE WHEN $4 = 'newest' THEN m.created_at END DESC,
+                            CASE WHEN $4 = 'newest' THEN m.user_id END DESC,
+                            m.created_at, m.user_id
+                    ) AS position
+                    FROM member_rows m
+                    LEFT JOIN member_cursor c ON TRUE
+                    WHERE m.visible
+                      AND instr(m.name_key, lower($3)) > 0
+                      AND (c.user_id IS NULL OR CASE $4
+                          WHEN 'newest' THEN (m.created_at, m.user_id) < (c.created_at, c.user_id)
+                          WHEN 'alphabetical' THEN (m.name_key, m.created_at, m.user_id)
+                              > (c.name_key, c.created_at, c.user_id)
+                          ELSE (m.created_at, m.user_id) > (c.created_at, c.user_id)
+                      END)
+                    ORDER BY position
+                    LIMIT $8
+                ),
+                suggestion_rows AS (
+                    SELECT s.suggestion_id, s.creator_id, s.suggestion, s.created_at,
+                           lower(s.suggestion) AS text_key,
+                           COALESCE(lower(pu.username), '') AS author_key,
+                           (SELECT COUNT(*) FROM votes v
+                            WHERE v.suggestion_id = s.suggestion_id) AS votes
+                    FROM suggestions s
+                    LEFT JOIN poll_users pu
+                        ON pu.poll_id = s.poll_id AND pu.user_id = s.creator_id
+                        AND pu.moderation_status = 'visible'
+                    WHERE s.poll_id = $1 AND s.moderation_status = 'visible'
+                ),
+                suggestion_cursor AS (
+                    SELECT * FROM suggestion_rows WHERE suggestion_id = $7
+                ),
+                suggestion_page AS (
+                    SELECT s.suggestion_id, s.creator_id, s.suggestion, ROW_NUMBER() OVER (
+                        ORDER BY
+                            CASE WHEN $6 = 'most_votes' THEN s.votes END DESC,
+                            CASE WHEN $6 = 'alphabetical' THEN s.text_key END,
+                            CASE WHEN $6 = 'author' THEN s.author_key END,
+                            CASE WHEN $6 = 'newest' THEN s.created_at END DESC,
+                            CASE WHEN $6 = 'newest' THEN s.suggestion_id END DESC,
+                            s.created_at, s.suggestion_id
+                    ) AS position
+                    FROM suggestion_rows s
+                    LEFT JOIN suggestion_cursor c ON TRUE
+                    WHERE (instr(s.text_key, lower($3)) > 0 OR instr(s.author_key, lower($3)) > 0)
+                      AND (c.suggestion_id IS NULL OR CASE $6
+                          WHEN 'newest' THEN (s.created_at, s.suggestion_id)
+                              < (c.created_at, c.suggestion_id)
+                          WHEN 'most_votes' THEN s.votes < c.votes OR (s.votes = c.votes
+                              AND (s.created_at, s.suggestion_id) > (c.created_at, c.suggestion_id))
+                          WHEN 'alphabetical' THEN (s.text_key, s.created_at, s.suggestion_id)
+                              > (c.text_key, c.created_at, c.suggestion_id)
+                          WHEN 'author' THEN (s.author_key, s.created_at, s.suggestion_id)
+                              > (c.author_key, c.created_at, c.suggestion_id)
+                          ELSE (s.created_at, s.suggestion_id) > (c.created_at, c.suggestion_id)
+                      END)
+                    ORDER BY position
+                    LIMIT $8
+                )
+                SELECT kind, id, text, role, creator_id
+                FROM (
+                    SELECT 'member' AS kind, user_id AS id, username AS text, role,
+                           NULL AS creator_id, position
+                    FROM member_page
+                    UNION ALL
+                    SELECT 'viewer', user_id, username, role, NULL, 0
+                    FROM member_rows
+                    WHERE user_id = $2
+                    UNION ALL
+                    SELECT 'suggestion', suggestion_id, suggestion, NULL, creator_id, position
+                    FROM suggestion_page
+                    UNION ALL
+                    SELECT 'version', poll_id, CAST(version AS TEXT), NULL, NULL, 0
+                    FROM polls
+                    WHERE poll_id = $1
+                )
+                ORDER BY kind, position
+                "#,
+        )
+        .bind(poll_id)
+        .bind(user_id)
+        .bind(&page.search)
+        .bind(page.member_sort.as_str())
+        .bind(page.members_after)
+        .bind(page.suggestion_sort.as_str())
+        .bind(page.suggestions_after)
+        .bind(page.limit + 1)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut overview = PollOverview::default();
+        for (kind, id, text, role, creator_id) in rows {
+            match (kind.as_str(), role, creator_id) {
+                (kind @ ("member" | "viewer"), Some(role), _) => overview.add_member(
+                    Member {
+                        user_id: id,
+                        username: text,
+                        role: parse_role(role)?,
+                    },
+                    kind == "member",
+                    user_id,
+                ),
+                ("suggestion", _, Some(creator_id)) => overview.suggestions.push(Suggestion {
+                    suggestion_id: id,
+                    creator_id,
+                    suggestion: text,
+                }),
+                ("version", _, _) => {
+                    overview.version = Some(text.parse().map_err(|e| {
+                        StorageError::Unexpected(anyhow::anyhow!("invalid poll version: {e}"))
+                    })?)
+                }
+                (kind, _, _) => {
+                    return Err(StorageError::Unexpected(anyhow::anyhow!(
+                        "unexpected {kind} row in the poll overview"
+                    )))
+                }
+            }
+        }
+
+        Ok(overview.truncate(page.limit))
+    }
+
+    #[tracing::instrument(name = "retrieve poll export", skip(self))]
+    async fn poll_export(&self, poll_id: &Uuid) -> Result<PollExport, StorageError> {
+        let members: Vec<(String, String, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+            SELECT pu.username, pu.role, u.created_at
+            FROM poll_users pu
+            JOIN users u ON u.user_id = pu.user_id
+            WHERE pu.poll_id = $1 AND pu.moderation_status = 'visible'
+            ORDER BY u.created_at, pu.user_id
+            "#,
+        )
+        .bind(poll_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let members = members
+            .into_iter()
+            .map(|(username, role, joined_at)| {
+                Ok(ExportedMember {
+                    username,
+                    role: parse_role(role)?,
+                    joined_at,
+                })
+            })
+            .collect::<Result<_, StorageError>>()?;
+
+        let suggestions: Vec<(Uuid, String, Option<String>, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+            SELECT s.suggestion_id, s.suggestion, pu.username, s.created_at
+            FROM suggestions s
+            LEFT JOIN poll_users pu
+                ON pu.poll_id = s.poll_id AND pu.user_id = s.creator_id
+                AND pu.moderation_status = 'visible'
+            WHERE s.poll_id = $1 AND s.moderation_status = 'visible'
+            ORDER BY s.created_at, s.suggestion_id
+            "#,
+        )
+        .bind(poll_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let votes: Vec<(Uuid, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT v.suggestion_id, pu.username
+            FROM votes v
+            JOIN suggestions s ON s.suggestion_id = v.suggestion_id
+            LEFT JOIN poll_users pu
+                ON pu.poll_id = s.poll_id AND pu.user_id = v.user_id
+                AND pu.moderation_status = 'visible'
+            WHERE s.poll_id = $1 AND s.moderation_status = 'visible'
+            ORDER BY pu.username IS NULL, pu.username, v.user_id
+            "#,
+        )
+        .bind(poll_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(PollExport {
+            members,
+            suggestions: suggestions
+                .into_iter()
+                .map(
+                    |(suggestion_id, suggestion, author, created_at)| ExportedSuggestion {
+                        suggestion_id,
+                        suggestion,
+                        author,
+                        created_at,
+                    },
+                )
+                .collect(),
+            votes: votes
+                .into_iter()
+                .map(|(suggestion_id, voter)| ExportedVote {
+                    suggestion_id,
+                    voter,
+                })
+                .collect(),
+        })
+    }
+
+    #[tracing::instrument(name = "store poll content filter", skip(self))]
+    async fn set_content_filter(
+        &self,
+        poll_id: &Uuid,
+        content_filter: Option<&serde_json::Value>,
+    ) -> Result<(), StorageError> {
+        sqlx::query("UPDATE polls SET content_filter = $2 WHERE poll_id = $1")
+            .bind(poll_id)
+            .bind(content_filter.map(|f| f.to_string()))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "store poll export access", skip(self))]
+    async fn set_export_access(
+        &self,
+        poll_id: &Uuid,
+        export_access: ExportAccess,
+    ) -> Result<(), StorageError> {
+        sqlx::query("UPDATE polls SET export_access = $2 WHERE poll_id = $1")
+            .bind(poll_id)
+            .bind(export_access.as_str())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The database cascades to the poll's members, suggestions, votes,
+    /// invites and reports.
+    #[tracing::instrument(name = "delete poll and its content", skip(self))]
+    async fn delete_poll(&self, poll_id: &Uuid) -> Result<Option<String>, StorageError> {
+        let mut transaction = self.pool.begin().await?;
+        let members: Vec<Uuid> =
+            sqlx::query_scalar("SELECT user_id FROM poll_users WHERE poll_id = $1")
+                .bind(poll_id)
+                .fetch_all(&mut transaction)
+                .await?;
+        let prompt: Option<String> =
+            sqlx::query_scalar("DELETE FROM polls WHERE poll_id = $1 RETURNING prompt")
+                .bind(poll_id)
+                .fetch_optional(&mut transaction)
+                .await?;
+        if prompt.is_none() {
+            return Ok(None);
+        }
+
+        for user_id in members {
+            sqlx::query(
+                r#"
+                DELETE FROM users
+                WHERE user_id = $1
+                  AND NOT EXISTS (SELECT 1 FROM poll_users pu WHERE pu.user_id = $1)
+                  AND NOT EXISTS (SELECT 1 FROM polls p WHERE p.creator_id = $1)
+                  AND NOT EXISTS (SELECT 1 FROM suggestions s WHERE s.creator_id = $1)
+                  AND NOT EXISTS (SELECT 1 FROM votes v WHERE v.user_id = $1)
+                  AND NOT EXISTS (
+                      SELECT 1 FROM reports r
+                      WHERE r.reporter_id = $1 OR r.reported_user_id = $1
+                  )
+                "#,
+            )
+            .bind(user_id)
+            .execute(&mut transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+
+        Ok(prompt)
+    }
+
+    #[tracing::instrument(name = "find polls", skip(self))]
+    async fn search_polls(&self, q: &str, limit: i64) -> Result<Vec<PollSummary>, StorageError> {
+        let poll_id = Uuid::parse_str(q).ok();
+        let rows: Vec<(Uuid, String, DateTime<Utc>, i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT p.poll_id, p.prompt, p.created_at,
+                   (SELECT COUNT(*) FROM poll_users u WHERE u.poll_id = p.poll_id),
+                   (SELECT COUNT(*) FROM suggestions s WHERE s.poll_id = p.poll_id)
+            FROM polls p
+            WHERE p.poll_id = $1 OR instr(lower(p.prompt), lower($2)) > 0
+            ORDER BY p.created_at DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(poll_id)
+        .bind(q)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(poll_id, prompt, created_at, members, suggestions)| PollSummary {
+                    poll_id,
+                    prompt,
+                    created_at,
+                    members,
+                    suggestions,
+                },
+            )
+            .collect())
+    }
+
+    #[tracing::instrument(name = "retrieve poll details", skip(self))]
+    async fn poll_details(&self, poll_id: &Uuid) -> Result<Option<PollDetails>, StorageError> {
+        let row: Option<(String, DateTime<Utc>, bool, bool, bool, bool, i64)> = sqlx::query_as(
+            r#"
+            SELECT prompt, created_at, invite_only, require_proof_of_work,
+                   passphrase_hash IS NOT NULL,
+                   content_filter IS NOT NULL,
+                   (SELECT COUNT(*) FROM reports r
+                    WHERE r.poll_id = p.poll_id AND r.resolved_at IS NULL)
+            FROM polls p
+            WHERE poll_id = $1
+            "#,
+        )
+        .bind(poll_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(
+            |(
+                prompt,
+                created_at,
+                invite_only,
+                require_proof_of_work,
+                has_passphrase,
+                has_content_filter,
+                pending_reports,
+            )| PollDetails {
+                prompt,
+                created_at,
+                invite_only,
+                has_passphrase,
+                require_proof_of_work,
+                has_content_filter,
+                pending_reports,
+            },
+        ))
+    }
+
+    #[tracing::instrument(name = "count polls, users and suggestions", skip(self))]
+    async fn totals(&self) -> Result<Totals, StorageError> {
+        let (polls, users, suggestions) = sqlx::query_as(
+            r#"
+            SELECT (SELECT COUNT(*) FROM polls),
+                   (SELECT COUNT(*) FROM users),
+                   (SELECT COUNT(*) FROM suggestions)
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(Totals {
+            polls,
+            users,
+            suggestions,
+        })
+    }
+
+    /// Times are stored as `YYYY-MM-DD hh:mm:ss`, the day is their first ten
+    /// characters.
+    #[tracing::instrument(name = "retrieve daily activity", skip(self))]
+    async fn daily_activity(&self, days: i32) -> Result<Vec<DailyActivity>, StorageError> {
+        let today = Utc::today().naive_utc();
+        let first_day = today - Duration::days(i64::from(days) - 1);
+        let rows: Vec<(String, NaiveDate, i64)> = sqlx::query_as(
+            r#"
+            SELECT 'polls', substr(created_at, 1, 10) AS day, COUNT(*) FROM polls
+            WHERE created_at >= $1 GROUP BY day
+            UNION ALL
+            SELECT 'users', substr(created_at, 1, 10) AS day, COUNT(*) FROM users
+            WHERE created_at >= $1 GROUP BY day
+            UNION ALL
+            SELECT 'suggestions', substr(created_at, 1, 10) AS day, COUNT(*) FROM suggestions
+            WHERE created_at >= $1 GROUP BY day
+            "#,
+        )
+        .bind(first_day)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok((0..days)
+            .map(|i| {
+                let day = today - Duration::days(i64::from(i));
+                let count = |table: &str| {
+                    rows.iter()
+                        .find(|(t, d, _)| t == table && *d == day)
+                        .map(|(_, _, count)| *count)
+                        .unwrap_or_default()
+                };
+                DailyActivity {
+                    day,
+                    polls: count("polls"),
+                    users: count("users"),
+                    suggestions: count("suggestions"),
+                }
+            })
+            .collect())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct PollRow {
+    poll_id: Uuid,
+    creator_id: Uuid,
+    prompt: String,
+    invite_only: bool,
+    passphrase_hash: Option<String>,
+    require_proof_of_work: bool,
+    content_filter: Option<String>,
+    export_access: String,
+}
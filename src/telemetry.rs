@@ -1,15 +1,37 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use opentelemetry::sdk::propagation::TraceContextPropagator;
+use opentelemetry::sdk::trace::{self as sdktrace, Sampler, Tracer, TracerProvider};
+use opentelemetry::sdk::Resource;
+use opentelemetry::trace::{TraceError, TracerProvider as _};
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::{SpanExporterBuilder, WithExportConfig};
+use tokio::task::JoinHandle;
+use tracing::field::{Field, Visit};
 use tracing::subscriber::set_global_default;
-use tracing::Subscriber;
+use tracing::{Event, Level, Subscriber};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
 use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
 
-/// Compose multiple layers into a `tracing`'s subscriber.
+use crate::configuration::OtlpSettings;
+
+static RECENT_ERRORS: Lazy<RecentErrors> = Lazy::new(|| RecentErrors::new(200));
+
+/// Compose multiple layers into a `tracing`'s subscriber, spans are also
+/// exported through `tracer` when there is one.
 pub fn get_subscriber<Sink>(
     name: String,
     env_filter: String,
     sink: Sink,
+    tracer: Option<Tracer>,
 ) -> impl Subscriber + Sync + Send
 where
     Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
@@ -21,6 +43,8 @@ where
         .with(env_filter)
         .with(JsonStorageLayer)
         .with(formatting_layer)
+        .with(RecentErrorsLayer)
+        .with(tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)))
 }
 
 /// Register a subscriber as global default to process span data.
@@ -29,3 +53,260 @@ pub fn init_subscriber(subscriber: impl Subscriber + Sync + Send) {
     LogTracer::init().expect("failed to setup logger");
     set_global_default(subscriber).expect("failed to set subscriber");
 }
+
+/// Exports spans to an OpenTelemetry collector over OTLP/HTTP, and continues
+/// the traces of requests that come with a W3C `traceparent` header.
+/// Call `global::shutdown_tracer_provider` before exiting to send what's left.
+pub fn install_otlp_exporter(
+    service_name: &'static str,
+    settings: &OtlpSettings,
+) -> Result<Tracer, TraceError> {
+    let provider = otlp_tracer_provider(service_name, settings)?;
+    let tracer = provider.tracer(service_name);
+    global::set_tracer_provider(provider);
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(tracer)
+}
+
+fn otlp_tracer_provider(
+    service_name: &'static str,
+    settings: &OtlpSettings,
+) -> Result<TracerProvider, TraceError> {
+    let exporter = SpanExporterBuilder::from(
+        opentelemetry_otlp::new_exporter()
+            .http()
+            .with_endpoint(&settings.endpoint),
+    )
+    .build_span_exporter()?;
+    let config = sdktrace::config()
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            settings.sample_ratio,
+        ))))
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            service_name,
+        )]));
+
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry::runtime::Tokio)
+        .with_config(config)
+        .build())
+}
+
+/// Run a CPU intensive closure on the blocking thread pool, without losing the current span.
+pub fn spawn_blocking_with_tracing<F, R>(f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let current_span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || current_span.in_scope(f))
+}
+
+/// The warnings and errors logged since the process started, shown on the
+/// admin dashboard.
+pub fn recent_errors() -> &'static RecentErrors {
+    &RECENT_ERRORS
+}
+
+#[derive(Clone, Debug)]
+pub struct ErrorEvent {
+    pub at: DateTime<Utc>,
+    pub level: Level,
+    /// The span the event happened in, usually the handler's.
+    pub span: Option<String>,
+    pub message: String,
+}
+
+/// Keeps the latest events in memory, the oldest are dropped once it's full.
+pub struct RecentErrors {
+    capacity: usize,
+    events: Mutex<VecDeque<ErrorEvent>>,
+}
+
+impl RecentErrors {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn push(&self, event: ErrorEvent) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// The newest events first.
+    pub fn latest(&self, limit: usize) -> Vec<ErrorEvent> {
+        let events = self.events.lock().unwrap();
+        events.iter().rev().take(limit).cloned().collect()
+    }
+}
+
+/// Copies every warning and error into `recent_errors()`.
+struct RecentErrorsLayer;
+
+impl<S> Layer<S> for RecentErrorsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let level = *event.metadata().level();
+        if level > Level::WARN {
+            return;
+        }
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        recent_errors().push(ErrorEvent {
+            at: Utc::now(),
+            level,
+            span: ctx.event_span(event).map(|span| span.name().to_string()),
+            message: visitor.message,
+        });
+    }
+}
+
+/// Formats an event as its message followed by its other fields.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.message);
+            write!(self.message, "{value:?}{fields}").unwrap();
+        } else {
+            write!(self.message, " {}={value:?}", field.name()).unwrap();
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &format_args!("{value}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::Utc;
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::sdk::propagation::TraceContextPropagator;
+    use opentelemetry::trace::TracerProvider as _;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tracing::Level;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+
+    use super::{otlp_tracer_provider, ErrorEvent, RecentErrors};
+    use crate::configuration::OtlpSettings;
+
+    fn event(message: &str) -> ErrorEvent {
+        ErrorEvent {
+            at: Utc::now(),
+            level: Level::ERROR,
+            span: None,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn oldest_events_are_dropped_when_full() {
+        let errors = RecentErrors::new(2);
+        errors.push(event("first"));
+        errors.push(event("second"));
+        errors.push(event("third"));
+
+        let messages = errors
+            .latest(10)
+            .into_iter()
+            .map(|e| e.message)
+            .collect::<Vec<_>>();
+        assert_eq!(messages, ["third", "second"]);
+    }
+
+    /// A collector that accepts every export and hands over the request bodies.
+    async fn spawn_collector() -> (String, mpsc::UnboundedReceiver<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buffer = [0; 4096];
+                let body = loop {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_ascii_lowercase();
+                    if let Some(header_end) = text.find("\r\n\r\n") {
+                        let length = text[..header_end]
+                            .lines()
+                            .find_map(|l| l.strip_prefix("content-length:"))
+                            .map(|l| l.trim().parse::<usize>().unwrap())
+                            .unwrap_or_default();
+                        if request.len() >= header_end + 4 + length || read == 0 {
+                            break request[header_end + 4..].to_vec();
+                        }
+                    }
+                };
+                sender.send(body).unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                    .await
+                    .unwrap();
+            }
+        });
+
+        (endpoint, receiver)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sampled_spans_are_exported_with_the_caller_as_parent() {
+        let (endpoint, mut exports) = spawn_collector().await;
+        // Traces starting here are never sampled, only the caller's are kept
+        let settings = OtlpSettings {
+            endpoint,
+            sample_ratio: 0.0,
+        };
+        let provider = otlp_tracer_provider("apoll", &settings).unwrap();
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("apoll"));
+        let subscriber = Registry::default().with(layer);
+
+        let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+        let carrier = HashMap::from([(
+            "traceparent".to_string(),
+            format!("00-{trace_id}-00f067aa0ba902b7-01"),
+        )]);
+        let parent = TraceContextPropagator::new().extract(&carrier);
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("find poll in database");
+            span.set_parent(parent);
+            span.in_scope(|| {});
+            tracing::info_span!("unsampled span").in_scope(|| {});
+        });
+        // Dropping the provider flushes the batch
+        tokio::task::spawn_blocking(move || drop(provider))
+            .await
+            .unwrap();
+
+        let mut body = Vec::new();
+        while let Ok(export) = exports.try_recv() {
+            body.extend(export);
+        }
+        let contains = |needle: &[u8]| body.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"find poll in database"));
+        assert!(contains(&hex::decode(trace_id).unwrap()));
+        assert!(!contains(b"unsampled span"));
+    }
+}
@@ -0,0 +1,147 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::Context;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::{self, CertifiedKey};
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use tokio::task::JoinHandle;
+
+use crate::configuration::TlsSettings;
+
+/// Hands out the certificate last loaded from `tls.certificate_path` and
+/// `tls.key_path`, so that new connections use a renewed certificate while
+/// the ones already open keep theirs.
+pub struct CertificateResolver {
+    settings: TlsSettings,
+    certified_key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl CertificateResolver {
+    pub fn load(settings: &TlsSettings) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            settings: settings.clone(),
+            certified_key: RwLock::new(Arc::new(load_certified_key(settings)?)),
+        })
+    }
+
+    pub fn server_config(self: &Arc<Self>) -> ServerConfig {
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone())
+    }
+
+    /// Loads the files again whenever the contents of either of them changed.
+    /// A pair that doesn't load keeps the previous certificate in use.
+    pub fn spawn_reload(self: &Arc<Self>) -> JoinHandle<()> {
+        let resolver = self.clone();
+        let period = Duration::from_secs(self.settings.reload_interval_seconds);
+        let mut contents = resolver.contents();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            // The first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let now_contents = resolver.contents();
+                if now_contents == contents {
+                    continue;
+                }
+                match load_certified_key(&resolver.settings) {
+                    Ok(certified_key) => {
+                        *resolver.certified_key.write().unwrap() = Arc::new(certified_key);
+                        contents = now_contents;
+                        tracing::info!("Reloaded the TLS certificate");
+                    }
+                    Err(e) => tracing::warn!(
+                        error.cause_chain = ?e,
+                        "Failed to reload the TLS certificate, the previous one is still used"
+                    ),
+                }
+            }
+        })
+    }
+
+    fn contents(&self) -> [Option<Vec<u8>>; 2] {
+        [&self.settings.certificate_path, &self.settings.key_path]
+            .map(|path| std::fs::read(path).ok())
+    }
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.certified_key.read().unwrap().clone())
+    }
+}
+
+pub fn load_certified_key(settings: &TlsSettings) -> Result<CertifiedKey, anyhow::Error> {
+    let certificates = rustls_pemfile::certs(&mut open(&settings.certificate_path)?)
+        .with_context(|| format!("failed to read `{}`", settings.certificate_path))?;
+    if certificates.is_empty() {
+        anyhow::bail!("`{}` has no certificate", settings.certificate_path);
+    }
+
+    let key = private_key(&mut open(&settings.key_path)?)
+        .with_context(|| format!("failed to read `{}`", settings.key_path))?
+        .ok_or_else(|| anyhow::anyhow!("`{}` has no private key", settings.key_path))?;
+    let key = sign::any_supported_type(&PrivateKey(key))
+        .map_err(|_| anyhow::anyhow!("`{}` isn't a supported key", settings.key_path))?;
+
+    Ok(CertifiedKey::new(
+        certificates.into_iter().map(Certificate).collect(),
+        key,
+    ))
+}
+
+fn open(path: &str) -> Result<BufReader<File>, anyhow::Error> {
+    let file = File::open(path).with_context(|| format!("failed to open `{path}`"))?;
+    Ok(BufReader::new(file))
+}
+
+fn private_key(reader: &mut BufReader<File>) -> Result<Option<Vec<u8>>, std::io::Error> {
+    while let Some(item) = rustls_pemfile::read_one(reader)? {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(Some(key)),
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::load_certified_key;
+    use crate::configuration::TlsSettings;
+
+    fn settings(certificate: &str, key: &str) -> TlsSettings {
+        TlsSettings {
+            certificate_path: format!("tests/fixtures/tls/{certificate}"),
+            key_path: format!("tests/fixtures/tls/{key}"),
+            reload_interval_seconds: 60,
+            redirect_http_port: None,
+            hsts_max_age_seconds: None,
+        }
+    }
+
+    #[test]
+    fn a_pem_certificate_and_key_are_loaded() {
+        let certified_key =
+            load_certified_key(&settings("first.cert.pem", "first.key.pem")).unwrap();
+
+        assert_eq!(certified_key.cert.len(), 1);
+    }
+
+    #[test]
+    fn a_key_file_without_a_key_is_rejected() {
+        let error = load_certified_key(&settings("first.cert.pem", "first.cert.pem"))
+            .err()
+            .unwrap();
+
+        assert!(error.to_string().contains("has no private key"));
+    }
+}
@@ -8,6 +8,10 @@ pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
+    const UNLOCKED_POLLS_KEY: &'static str = "unlocked_polls";
+    const CSRF_TOKEN_KEY: &'static str = "csrf_token";
+    const CLIENT_ID_KEY: &'static str = "client_id";
+    const ADMIN_KEY: &'static str = "admin";
 
     pub fn renew(&self) {
         self.0.renew();
@@ -21,6 +25,58 @@ impl TypedSession {
         self.0.get(Self::USER_ID_KEY)
     }
 
+    /// Remember that the passphrase of a poll was entered correctly in this session.
+    pub fn insert_unlocked_poll(&self, poll_id: Uuid) -> Result<(), serde_json::Error> {
+        let mut unlocked = self.get_unlocked_polls()?;
+        if !unlocked.contains(&poll_id) {
+            unlocked.push(poll_id);
+        }
+        self.0.insert(Self::UNLOCKED_POLLS_KEY, unlocked)
+    }
+
+    pub fn is_poll_unlocked(&self, poll_id: &Uuid) -> Result<bool, serde_json::Error> {
+        Ok(self.get_unlocked_polls()?.contains(poll_id))
+    }
+
+    fn get_unlocked_polls(&self) -> Result<Vec<Uuid>, serde_json::Error> {
+        Ok(self
+            .0
+            .get::<Vec<Uuid>>(Self::UNLOCKED_POLLS_KEY)?
+            .unwrap_or_default())
+    }
+
+    pub fn insert_csrf_token(&self, token: &str) -> Result<(), serde_json::Error> {
+        self.0.insert(Self::CSRF_TOKEN_KEY, token)
+    }
+
+    pub fn get_csrf_token(&self) -> Result<Option<String>, serde_json::Error> {
+        self.0.get(Self::CSRF_TOKEN_KEY)
+    }
+
+    /// A random id that identifies the session to the rate limiter, unlike
+    /// the user id it exists before joining a poll.
+    pub fn get_or_insert_client_id(&self) -> Result<Uuid, serde_json::Error> {
+        if let Some(client_id) = self.0.get(Self::CLIENT_ID_KEY)? {
+            return Ok(client_id);
+        }
+        let client_id = Uuid::new_v4();
+        self.0.insert(Self::CLIENT_ID_KEY, client_id)?;
+        Ok(client_id)
+    }
+
+    /// Remember the admin account that logged in, next to any poll membership.
+    pub fn insert_admin(&self, username: &str) -> Result<(), serde_json::Error> {
+        self.0.insert(Self::ADMIN_KEY, username)
+    }
+
+    pub fn get_admin(&self) -> Result<Option<String>, serde_json::Error> {
+        self.0.get(Self::ADMIN_KEY)
+    }
+
+    pub fn remove_admin(&self) {
+        self.0.remove(Self::ADMIN_KEY);
+    }
+
     pub fn log_out(&self) {
         self.0.purge();
     }
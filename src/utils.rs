@@ -12,3 +12,20 @@ pub fn flash_message_redirect<E: std::error::Error>(e: E, location: &str) -> Int
     FlashMessage::error(e.to_string()).send();
     InternalError::from_response(e, redirect(location))
 }
+
+/// Makes text safe to put in HTML. Everything users supply has to go through
+/// it before it's rendered, validated or not.
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,243 @@
+use apoll::authentication::compute_passphrase_hash;
+use apoll::configuration::{ActionLimits, AdminAccount, BucketSettings, Settings};
+use secrecy::Secret;
+
+use crate::helpers::{location_string, new_api_client, TestApp};
+
+const USERNAME: &str = "operator";
+const PASSWORD: &str = "correct horse battery staple";
+
+/// An app with a single admin account.
+async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
+    let password_hash = compute_passphrase_hash(Secret::new(PASSWORD.to_string()))
+        .await
+        .unwrap();
+    TestApp::with_configuration(|c| {
+        c.admin.accounts = vec![AdminAccount {
+            username: USERNAME.to_string(),
+            password_hash,
+        }];
+        configure(c);
+    })
+    .await
+}
+
+async fn log_in(app: &TestApp, client: &reqwest::Client, password: &str) -> reqwest::Response {
+    app.post_form(
+        client,
+        "/admin/login",
+        &serde_json::json!({ "username": USERNAME, "password": password }),
+    )
+    .await
+}
+
+/// A client logged in as the admin.
+async fn admin_client(app: &TestApp) -> reqwest::Client {
+    let client = new_api_client();
+    let response = log_in(app, &client, PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(location_string(response), "/admin");
+    client
+}
+
+async fn get_page(app: &TestApp, client: &reqwest::Client, path: &str) -> String {
+    client
+        .get(app.endpoint(path))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap()
+}
+
+async fn count(app: &TestApp, table: &str) -> i64 {
+    app.db_pool.count_rows(table).await
+}
+
+#[tokio::test]
+async fn visitors_are_sent_to_the_login_form() {
+    let app = spawn_app_with(|_| {}).await;
+    let poll_id = app.post_create_poll("Lunch?", "owner").await;
+
+    for path in ["/admin", "/admin/polls", &format!("/admin/polls/{poll_id}")] {
+        let response = app.api_client.get(app.endpoint(path)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 303);
+        assert_eq!(location_string(response), "/admin/login");
+    }
+
+    let response = app
+        .post_form(
+            &app.api_client,
+            &format!("/admin/polls/{poll_id}/delete"),
+            &serde_json::json!({}),
+        )
+        .await;
+    assert_eq!(location_string(response), "/admin/login");
+    assert_eq!(count(&app, "polls").await, 1);
+}
+
+#[tokio::test]
+async fn wrong_credentials_are_rejected() {
+    let app = spawn_app_with(|_| {}).await;
+    let client = new_api_client();
+
+    let response = log_in(&app, &client, "wrong password").await;
+    assert_eq!(location_string(response), "/admin/login");
+    let html = get_page(&app, &client, "/admin/login").await;
+    assert!(html.contains("Invalid username or password"));
+
+    let response = app
+        .post_form(
+            &client,
+            "/admin/login",
+            &serde_json::json!({ "username": "nobody", "password": PASSWORD }),
+        )
+        .await;
+    assert_eq!(location_string(response), "/admin/login");
+
+    let response = client.get(app.endpoint("/admin")).send().await.unwrap();
+    assert_eq!(location_string(response), "/admin/login");
+}
+
+#[tokio::test]
+async fn login_attempts_are_rate_limited() {
+    let app = spawn_app_with(|c| {
+        c.rate_limit.admin_login = ActionLimits {
+            per_ip: Some(BucketSettings {
+                capacity: 1,
+                refill_per_minute: 1,
+            }),
+            ..Default::default()
+        }
+    })
+    .await;
+    let client = new_api_client();
+
+    log_in(&app, &client, "wrong password").await;
+    let response = log_in(&app, &client, PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    // The form itself is still served
+    let response = client
+        .get(app.endpoint("/admin/login"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn dashboard_shows_totals_and_recent_errors() {
+    let app = spawn_app_with(|_| {}).await;
+    app.post_create_poll("Lunch?", "owner").await;
+    let client = admin_client(&app).await;
+
+    // A form posted without its CSRF token is logged as a warning
+    new_api_client()
+        .post(app.endpoint("/new"))
+        .form(&serde_json::json!({ "username": "owner", "prompt": "<b>Dinner?</b>" }))
+        .send()
+        .await
+        .unwrap();
+
+    let html = get_page(&app, &client, "/admin").await;
+    assert!(html.contains("Polls: 1, users: 1, suggestions: 0"));
+    assert!(html.contains("rejected request that failed the CSRF check"));
+}
+
+#[tokio::test]
+async fn polls_can_be_searched_by_prompt_and_id() {
+    let app = spawn_app_with(|_| {}).await;
+    let lunch = app
+        .post_create_poll("Where do we get lunch?", "owner")
+        .await;
+    app.post_create_poll("Movie night", "owner").await;
+    let client = admin_client(&app).await;
+
+    let html = get_page(&app, &client, "/admin/polls?q=LUNCH").await;
+    assert!(html.contains(&format!(r#"<a href="/admin/polls/{lunch}">"#)));
+    assert!(!html.contains("Movie night"));
+
+    let html = get_page(&app, &client, &format!("/admin/polls?q={lunch}")).await;
+    assert!(html.contains("Where do we get lunch?"));
+    assert!(!html.contains("Movie night"));
+
+    let html = get_page(&app, &client, &format!("/admin/polls/{lunch}")).await;
+    assert!(html.contains("<h1>Where do we get lunch?</h1>"));
+    assert!(html.contains("owner"));
+}
+
+#[tokio::test]
+async fn deleting_a_poll_removes_everything_in_it() {
+    let app = spawn_app_with(|_| {}).await;
+    let poll_id = app
+        .create_poll(&serde_json::json!({ "username": "owner", "prompt": "Lunch?" }))
+        .await;
+    let participant = new_api_client();
+    app.post_form(
+        &participant,
+        &format!("/poll/{poll_id}/join"),
+        &serde_json::json!({ "username": "participant" }),
+    )
+    .await;
+    app.post_suggestion(&poll_id, &serde_json::json!({ "suggestion": "Pizza" }))
+        .await;
+    let suggestion_id = app.storage.suggestion_details(&poll_id).await.unwrap()[0].suggestion_id;
+    app.post_form(
+        &participant,
+        &format!("/poll/{poll_id}/report"),
+        &serde_json::json!({ "target": format!("suggestion:{suggestion_id}"), "reason": "spam" }),
+    )
+    .await;
+    assert_eq!(count(&app, "reports").await, 1);
+
+    // Another poll is left alone
+    app.post_create_poll("Movie night", "someone").await;
+
+    let client = admin_client(&app).await;
+    let response = app
+        .post_form(
+            &client,
+            &format!("/admin/polls/{poll_id}/delete"),
+            &serde_json::json!({}),
+        )
+        .await;
+    assert_eq!(location_string(response), "/admin/polls");
+    let html = get_page(&app, &client, "/admin/polls").await;
+    assert!(html.contains("Deleted the poll: Lunch?"));
+
+    for table in ["suggestions", "reports", "votes", "poll_invites"] {
+        assert_eq!(count(&app, table).await, 0, "{table} were left behind");
+    }
+    assert_eq!(count(&app, "polls").await, 1);
+    assert_eq!(count(&app, "poll_users").await, 1);
+    assert_eq!(count(&app, "users").await, 1);
+
+    let response = app.get_poll_page(&poll_id.to_string()).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn logging_out_keeps_the_rest_of_the_session() {
+    let app = spawn_app_with(|_| {}).await;
+    let client = admin_client(&app).await;
+    let response = app
+        .post_form(
+            &client,
+            "/new",
+            &serde_json::json!({ "username": "owner", "prompt": "Lunch?" }),
+        )
+        .await;
+    let poll_uri = location_string(response);
+
+    let response = app
+        .post_form(&client, "/admin/logout", &serde_json::json!({}))
+        .await;
+    assert_eq!(location_string(response), "/admin/login");
+    let response = client.get(app.endpoint("/admin")).send().await.unwrap();
+    assert_eq!(location_string(response), "/admin/login");
+
+    let html = get_page(&app, &client, &poll_uri).await;
+    assert!(html.contains("<li>owner (owner)</li>"));
+}
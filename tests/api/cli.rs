@@ -0,0 +1,92 @@
+use apoll::{
+    cli::{migrate, pending_migrations, purge_expired_invites},
+    storage::{self, NewInvite},
+};
+use chrono::{Duration, Utc};
+
+use crate::helpers::{database_exists, drop_database, new_database_settings, TestApp};
+
+#[tokio::test]
+async fn migrate_creates_the_database_and_applies_every_migration() {
+    let settings = new_database_settings();
+
+    let planned = migrate(&settings, true).await.unwrap();
+    let embedded = storage::connect(&settings).migrator().iter().count();
+    assert_eq!(planned.len(), embedded);
+
+    let applied = migrate(&settings, false).await.unwrap();
+    assert_eq!(applied, planned);
+    assert!(migrate(&settings, false).await.unwrap().is_empty());
+
+    drop_database(&settings).await;
+}
+
+#[tokio::test]
+async fn dry_run_does_not_create_the_database() {
+    let settings = new_database_settings();
+
+    migrate(&settings, true).await.unwrap();
+
+    assert!(!database_exists(&settings).await);
+}
+
+#[tokio::test]
+async fn the_application_migrates_on_startup() {
+    let app = TestApp::new().await;
+
+    assert!(pending_migrations(app.storage.as_ref())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn purge_removes_only_invites_past_the_grace_period() {
+    let app = TestApp::new().await;
+    let poll_id = app
+        .create_poll(&serde_json::json!({
+            "username": "creator",
+            "prompt": "Secret question?",
+            "invite_only": "on",
+        }))
+        .await;
+
+    let now = Utc::now();
+    let invites = [
+        ("working", now + Duration::days(1), None),
+        ("just-expired", now - Duration::days(1), None),
+        ("long-expired", now - Duration::days(30), None),
+        (
+            "long-revoked",
+            now + Duration::days(1),
+            Some(now - Duration::days(30)),
+        ),
+    ];
+    for (token, expires_at, revoked_at) in invites {
+        let invite = NewInvite {
+            token: token.to_string(),
+            max_uses: 1,
+            expires_at,
+        };
+        app.storage.create_invite(&poll_id, &invite).await.unwrap();
+        app.db_pool
+            .set_invite_times(token, expires_at, revoked_at)
+            .await;
+    }
+
+    let deleted = purge_expired_invites(app.storage.as_ref(), Duration::days(7))
+        .await
+        .unwrap();
+    assert_eq!(deleted, 2);
+
+    let mut left: Vec<String> = app
+        .storage
+        .invites(&poll_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|i| i.token)
+        .collect();
+    left.sort();
+    assert_eq!(left, ["just-expired", "working"]);
+}
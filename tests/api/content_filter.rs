@@ -0,0 +1,288 @@
+use apoll::configuration::{FilterAction, WordListSettings};
+use uuid::Uuid;
+
+use crate::helpers::{location_string, new_api_client, TestApp};
+
+async fn spawn_app() -> TestApp {
+    TestApp::with_configuration(|c| {
+        c.content_filter.word_list = Some(WordListSettings {
+            words: vec!["spam".into()],
+            action: FilterAction::Reject,
+        });
+    })
+    .await
+}
+
+/// A poll owned by `api_client` and a participant to post as.
+async fn poll_with_participant(app: &TestApp) -> (Uuid, reqwest::Client) {
+    let poll_id = app
+        .create_poll(&serde_json::json!({ "username": "owner", "prompt": "Lunch?" }))
+        .await;
+    let participant = new_api_client();
+    let response = app
+        .post_form(
+            &participant,
+            &format!("/poll/{poll_id}/join"),
+            &serde_json::json!({ "username": "participant" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+    (poll_id, participant)
+}
+
+async fn get_page(app: &TestApp, client: &reqwest::Client, path: &str) -> String {
+    client
+        .get(app.endpoint(path))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap()
+}
+
+async fn held_id(app: &TestApp, poll_id: &Uuid, suggestion: &str) -> Uuid {
+    app.storage
+        .queued_suggestions(poll_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|s| s.content == suggestion && s.moderation_reason.is_some())
+        .expect("suggestion was not held")
+        .id
+}
+
+#[tokio::test]
+async fn new_poll_with_a_blocked_word_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_form(
+            &app.api_client,
+            "/new",
+            &serde_json::json!({ "username": "owner", "prompt": "Buy $p4m now" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(location_string(response), "/");
+
+    let html = get_page(&app, &app.api_client, "/").await;
+    assert!(html.contains("The prompt contains a blocked word."));
+}
+
+#[tokio::test]
+async fn suggestion_over_the_repeated_characters_limit_is_rejected() {
+    let app = spawn_app().await;
+    let (poll_id, participant) = poll_with_participant(&app).await;
+
+    app.post_form(
+        &participant,
+        &format!("/poll/{poll_id}/suggest"),
+        &serde_json::json!({ "suggestion": "Pizzaaaaaaaa" }),
+    )
+    .await;
+
+    let html = get_page(&app, &participant, &format!("/poll/{poll_id}")).await;
+    assert!(html.contains("Your suggestion repeats a character more than 5 times in a row."));
+    assert!(!html.contains("Pizzaaaaaaaa"));
+}
+
+#[tokio::test]
+async fn held_suggestion_is_shown_once_approved() {
+    let app = spawn_app().await;
+    let (poll_id, participant) = poll_with_participant(&app).await;
+    let suggestion = "Order from pizza.example.com";
+
+    app.post_form(
+        &participant,
+        &format!("/poll/{poll_id}/suggest"),
+        &serde_json::json!({ "suggestion": suggestion }),
+    )
+    .await;
+    let html = get_page(&app, &participant, &format!("/poll/{poll_id}")).await;
+    assert!(html.contains(
+        "Your suggestion contains a link, it will show up once an organizer approves it."
+    ));
+    assert!(!html.contains(suggestion));
+
+    // The owner finds it in the moderation queue
+    let html = get_page(
+        &app,
+        &app.api_client,
+        &format!("/poll/{poll_id}/moderation"),
+    )
+    .await;
+    assert!(html.contains(suggestion));
+    assert!(html.contains("contains a link"));
+
+    let suggestion_id = held_id(&app, &poll_id, suggestion).await;
+    let response = app
+        .post_form(
+            &app.api_client,
+            &format!("/poll/{poll_id}/moderation/suggestions/{suggestion_id}/approve"),
+            &serde_json::json!({}),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let html = get_page(&app, &participant, &format!("/poll/{poll_id}")).await;
+    assert!(html.contains(suggestion));
+}
+
+#[tokio::test]
+async fn held_suggestion_is_escaped_in_the_moderation_queue() {
+    let app = spawn_app().await;
+    let (poll_id, participant) = poll_with_participant(&app).await;
+
+    app.post_form(
+        &participant,
+        &format!("/poll/{poll_id}/suggest"),
+        &serde_json::json!({ "suggestion": "<script>alert(1)</script> pizza.example.com" }),
+    )
+    .await;
+
+    let html = get_page(
+        &app,
+        &app.api_client,
+        &format!("/poll/{poll_id}/moderation"),
+    )
+    .await;
+    assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt; pizza.example.com"));
+    assert!(!html.contains("<script>"));
+}
+
+#[tokio::test]
+async fn held_suggestion_can_be_removed() {
+    let app = spawn_app().await;
+    let (poll_id, participant) = poll_with_participant(&app).await;
+    let suggestion = "www.example.org";
+
+    app.post_form(
+        &participant,
+        &format!("/poll/{poll_id}/suggest"),
+        &serde_json::json!({ "suggestion": suggestion }),
+    )
+    .await;
+    let suggestion_id = held_id(&app, &poll_id, suggestion).await;
+
+    app.post_form(
+        &app.api_client,
+        &format!("/poll/{poll_id}/moderation/suggestions/{suggestion_id}/remove"),
+        &serde_json::json!({}),
+    )
+    .await;
+
+    let html = get_page(
+        &app,
+        &app.api_client,
+        &format!("/poll/{poll_id}/moderation"),
+    )
+    .await;
+    assert!(html.contains("Suggestion removed"));
+    assert!(!html.contains(suggestion));
+}
+
+#[tokio::test]
+async fn participants_cannot_moderate() {
+    let app = spawn_app().await;
+    let (poll_id, participant) = poll_with_participant(&app).await;
+
+    for path in ["moderation", "content_filter"] {
+        let response = participant
+            .get(app.endpoint(&format!("/poll/{poll_id}/{path}")))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 403);
+    }
+
+    let response = app
+        .post_form(
+            &participant,
+            &format!("/poll/{poll_id}/content_filter"),
+            &serde_json::json!({
+                "word_list": "off",
+                "links": "off",
+                "repeated_characters": "off",
+            }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn poll_can_hold_names_with_its_own_word_list() {
+    let app = spawn_app().await;
+    let poll_id = app
+        .create_poll(&serde_json::json!({ "username": "owner", "prompt": "Lunch?" }))
+        .await;
+
+    let response = app
+        .post_form(
+            &app.api_client,
+            &format!("/poll/{poll_id}/content_filter"),
+            &serde_json::json!({
+                "word_list": "hold",
+                "words": "troll\nspam",
+                "links": "default",
+                "repeated_characters": "default",
+            }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let visitor = new_api_client();
+    app.post_form(
+        &visitor,
+        &format!("/poll/{poll_id}/join"),
+        &serde_json::json!({ "username": "Tr0ll" }),
+    )
+    .await;
+    let html = get_page(&app, &visitor, &format!("/poll/{poll_id}")).await;
+    assert!(html.contains("Your name contains a blocked word, the other members will see it"));
+    assert!(!html.contains("<li>Tr0ll</li>"));
+
+    let user_id = app.user_id_of(&poll_id, "Tr0ll").await;
+    app.post_form(
+        &app.api_client,
+        &format!("/poll/{poll_id}/moderation/members/{user_id}/approve"),
+        &serde_json::json!({}),
+    )
+    .await;
+    let html = get_page(&app, &visitor, &format!("/poll/{poll_id}")).await;
+    assert!(html.contains("<li>Tr0ll</li>"));
+}
+
+#[tokio::test]
+async fn poll_can_turn_a_filter_off() {
+    let app = spawn_app().await;
+    let (poll_id, participant) = poll_with_participant(&app).await;
+
+    app.post_form(
+        &app.api_client,
+        &format!("/poll/{poll_id}/content_filter"),
+        &serde_json::json!({
+            "word_list": "default",
+            "links": "off",
+            "repeated_characters": "default",
+        }),
+    )
+    .await;
+    let html = get_page(
+        &app,
+        &app.api_client,
+        &format!("/poll/{poll_id}/content_filter"),
+    )
+    .await;
+    assert!(html.contains(r#"<option value="off" selected>Off</option>"#));
+
+    let suggestion = "https://example.com/menu";
+    app.post_form(
+        &participant,
+        &format!("/poll/{poll_id}/suggest"),
+        &serde_json::json!({ "suggestion": suggestion }),
+    )
+    .await;
+    let html = get_page(&app, &participant, &format!("/poll/{poll_id}")).await;
+    assert!(html.contains(suggestion));
+}
@@ -0,0 +1,136 @@
+use crate::helpers::{extract_csrf_token, new_api_client, TestApp};
+
+fn poll_body() -> serde_json::Value {
+    serde_json::json!({
+        "username": "username",
+        "prompt": "Is this a good prompt?",
+    })
+}
+
+#[tokio::test]
+async fn every_post_form_carries_the_token() {
+    let app = TestApp::new().await;
+
+    let poll_id = app.post_create_poll("Test question?", "testuser").await;
+    let token = app.csrf_token(&app.api_client).await;
+    let text = app
+        .get_poll_page(&poll_id.to_string())
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(extract_csrf_token(&text), Some(token));
+}
+
+#[tokio::test]
+async fn post_without_token_is_rejected() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .api_client
+        .post(app.endpoint("/new"))
+        .form(&poll_body())
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("the form is missing its CSRF token"));
+}
+
+#[tokio::test]
+async fn post_with_token_of_another_session_is_rejected() {
+    let app = TestApp::new().await;
+
+    // Make sure the client has a session of its own
+    app.csrf_token(&app.api_client).await;
+    let mut body = poll_body();
+    body["csrf_token"] = app.csrf_token(&new_api_client()).await.into();
+
+    let response = app
+        .api_client
+        .post(app.endpoint("/new"))
+        .form(&body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("the form's CSRF token is invalid or expired"));
+}
+
+#[tokio::test]
+async fn token_can_be_sent_as_a_header() {
+    let app = TestApp::new().await;
+
+    let token = app.csrf_token(&app.api_client).await;
+    let response = app
+        .api_client
+        .post(app.endpoint("/new"))
+        .header("X-CSRF-Token", token)
+        .form(&poll_body())
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 303);
+}
+
+#[tokio::test]
+async fn post_from_another_origin_is_rejected() {
+    let app = TestApp::new().await;
+
+    let mut body = poll_body();
+    body["csrf_token"] = app.csrf_token(&app.api_client).await.into();
+    let test_cases = vec![
+        ("Origin", "https://evil.example".to_string()),
+        ("Origin", "null".to_string()),
+        ("Referer", "https://evil.example/apoll".to_string()),
+    ];
+
+    for (header, value) in test_cases {
+        let response = app
+            .api_client
+            .post(app.endpoint("/new"))
+            .header(header, &value)
+            .form(&body)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.status().as_u16(),
+            403,
+            "request with {header}: {value} was let through"
+        );
+    }
+}
+
+#[tokio::test]
+async fn post_from_same_origin_is_accepted() {
+    let app = TestApp::new().await;
+
+    let mut body = poll_body();
+    body["csrf_token"] = app.csrf_token(&app.api_client).await.into();
+
+    let response = app
+        .api_client
+        .post(app.endpoint("/new"))
+        .header("Origin", &app.address)
+        .header("Referer", app.endpoint("/"))
+        .form(&body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 303);
+}
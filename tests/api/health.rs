@@ -0,0 +1,82 @@
+use apoll::configuration::{DatabaseBackend, SessionStoreBackend};
+
+use crate::helpers::TestApp;
+
+async fn get_json(app: &TestApp, path: &str) -> (u16, serde_json::Value) {
+    let response = app
+        .api_client
+        .get(app.endpoint(path))
+        .send()
+        .await
+        .expect("failed to execute request");
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn liveness_does_not_depend_on_anything() {
+    let app = TestApp::new().await;
+
+    let (status, body) = get_json(&app, "/health/live").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "alive");
+}
+
+#[tokio::test]
+async fn ready_when_every_dependency_is_up() {
+    let app = TestApp::new().await;
+
+    let (status, body) = get_json(&app, "/health/ready").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["status"], "ready");
+    let mut dependencies = vec!["database", "migrations"];
+    if app.redis_in_use {
+        dependencies.push("redis");
+    }
+    for dependency in dependencies {
+        assert_eq!(body["checks"][dependency]["status"], "up");
+        assert!(body["checks"][dependency]["latency_ms"].is_number());
+    }
+}
+
+#[tokio::test]
+async fn redis_is_not_checked_when_sessions_are_kept_in_memory() {
+    let app = TestApp::with_configuration(|c| c.session.store = SessionStoreBackend::Memory).await;
+
+    let (status, body) = get_json(&app, "/health/ready").await;
+    assert_eq!(status, 200, "{body}");
+    assert!(body["checks"].get("redis").is_none());
+}
+
+#[tokio::test]
+async fn not_ready_with_pending_migrations() {
+    let app = TestApp::new().await;
+    // Memory storage has no migrations to miss
+    if app.database.backend == DatabaseBackend::Memory {
+        return;
+    }
+    app.db_pool.forget_last_migration().await;
+
+    let (status, body) = get_json(&app, "/health/ready").await;
+    assert_eq!(status, 503);
+    assert_eq!(body["status"], "not_ready");
+    assert_eq!(body["checks"]["database"]["status"], "up");
+    assert_eq!(body["checks"]["migrations"]["status"], "down");
+    assert!(body["checks"]["migrations"]["error"]
+        .as_str()
+        .unwrap()
+        .contains("this build expects"));
+}
+
+#[tokio::test]
+async fn not_ready_while_shutting_down() {
+    let app = TestApp::new().await;
+    app.readiness.begin_shutdown();
+
+    let (status, body) = get_json(&app, "/health/ready").await;
+    assert_eq!(status, 503);
+    assert_eq!(body["status"], "shutting_down");
+
+    let (status, _) = get_json(&app, "/health/live").await;
+    assert_eq!(status, 200);
+}
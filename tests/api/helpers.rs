@@ -1,12 +1,21 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use fake::{faker::name::en::FirstName, Fake};
 use once_cell::sync::Lazy;
 use reqwest::Response;
-use sqlx::{Connection, Executor, PgConnection, PgPool};
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, Executor, PgConnection, PgPool, SqlitePool};
 use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
 use tracing::info;
 use uuid::Uuid;
 
-use apoll::configuration::{DatabaseSettings, Settings};
-use apoll::startup::Application;
+use apoll::configuration::{DatabaseBackend, DatabaseSettings, Environment, Settings};
+use apoll::domain::ParticipantName;
+use apoll::routes::health::Readiness;
+use apoll::startup::{Application, ShutdownHandle};
+use apoll::storage::{self, MemoryStorage, NewPoll, Storage};
 use apoll::telemetry::{get_subscriber, init_subscriber};
 
 // Only initialize tracing once
@@ -15,131 +24,165 @@ static TRACING: Lazy<()> = Lazy::new(|| {
     let subscriber_name = "test".to_string();
 
     if std::env::var("TEST_LOG").is_ok() {
-        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::stdout);
+        let subscriber =
+            get_subscriber(subscriber_name, default_filter_level, std::io::stdout, None);
         init_subscriber(subscriber);
     } else {
-        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink);
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink, None);
         init_subscriber(subscriber);
     }
 });
 
 pub struct TestApp {
     pub address: String,
-    pub db_name: String,
-    pub db_pool: PgPool,
+    /// Where `/metrics` is served when it has a listener of its own.
+    pub metrics_address: Option<String>,
+    /// Where plain HTTP is redirected to HTTPS, when TLS is configured.
+    pub redirect_address: Option<String>,
+    pub database: DatabaseSettings,
+    /// Whether the readiness probe checks Redis.
+    pub redis_in_use: bool,
+    pub storage: Arc<dyn Storage>,
+    /// For the few checks the storage has no method for.
+    pub db_pool: TestDatabase,
     pub api_client: reqwest::Client,
+    pub readiness: actix_web::web::Data<Readiness>,
+    pub shutdown: ShutdownHandle,
+    /// Resolves once the application shut down.
+    pub server: JoinHandle<Result<(), std::io::Error>>,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
+        Self::with_configuration(|_| {}).await
+    }
+
+    /// Spawn the application after `configure` had a chance to change its settings.
+    pub async fn with_configuration(configure: impl FnOnce(&mut Settings)) -> Self {
         // Start tracing
         Lazy::force(&TRACING);
 
         // Assign a random name and port to the application
         let configuration = {
-            let mut c = Settings::new().expect("failed to read configuration");
-            c.database.database_name = Uuid::new_v4().to_string();
+            let mut c = test_settings();
+            c.database = new_database_settings();
             c.application.port = 0;
+            // The application applies its embedded migrations to the new database
+            c.database.migrate_on_startup = true;
+            // Keep the rate limits of tests running at the same time apart
+            c.rate_limit.namespace = format!("rate_limit:{}", c.database.database_name);
+            configure(&mut c);
             c
         };
 
-        // Create and migrate the database
+        // Create the database, the application shares its storage with the test
         let db_pool = TestApp::configure_database(&configuration.database).await;
+        let storage = match &db_pool {
+            TestDatabase::Memory(memory) => Arc::new(memory.clone()),
+            _ => storage::connect(&configuration.database),
+        };
+        let redis_in_use = configuration.redis_uri_in_use().is_some();
 
         // Create API client
-        let api_client = reqwest::Client::builder()
-            .redirect(reqwest::redirect::Policy::none())
-            .cookie_store(true)
-            .build()
-            .unwrap();
+        let api_client = new_api_client();
 
         // Run the server
-        let application = Application::build(configuration.clone())
+        let application = Application::build_with_storage(configuration.clone(), storage.clone())
             .await
             .expect("failed to build application");
         let application_port = application.port();
-        let _ = tokio::spawn(application.run_until_stopped());
+        let readiness = application.readiness();
+        let metrics_address = application
+            .metrics_port()
+            .map(|port| format!("http://localhost:{port}"));
+        let redirect_address = application
+            .redirect_port()
+            .map(|port| format!("http://localhost:{port}"));
+        let scheme = match configuration.application.tls {
+            Some(_) => "https",
+            None => "http",
+        };
+        let shutdown = application.shutdown_handle();
+        let server = tokio::spawn(application.run_until_stopped());
 
         TestApp {
-            address: format!("http://localhost:{}", application_port),
-            db_name: configuration.database.database_name,
+            address: format!("{}://localhost:{}", scheme, application_port),
+            metrics_address,
+            redirect_address,
+            database: configuration.database,
+            redis_in_use,
+            storage,
             db_pool,
             api_client,
+            readiness,
+            shutdown,
+            server,
         }
     }
 
-    async fn configure_database(config: &DatabaseSettings) -> PgPool {
-        // Create the database
-        let mut connection = PgConnection::connect_with(&config.without_db())
-            .await
-            .expect("failed to connect to Postgres");
-        connection
-            .execute(&*format!(r#"CREATE DATABASE "{}";"#, config.database_name))
-            .await
-            .expect("failed to create database");
-
-        // Migrate the database
-        let db_pool = PgPool::connect_with(config.with_db())
-            .await
-            .expect("failed to connect to Postgres");
-        sqlx::migrate!("./migrations")
-            .run(&db_pool)
-            .await
-            .expect("failed to migrate the database");
+    async fn configure_database(config: &DatabaseSettings) -> TestDatabase {
+        match config.backend {
+            DatabaseBackend::Postgres => {
+                let mut connection = PgConnection::connect_with(&config.without_db())
+                    .await
+                    .expect("failed to connect to Postgres");
+                connection
+                    .execute(&*format!(r#"CREATE DATABASE "{}";"#, config.database_name))
+                    .await
+                    .expect("failed to create database");
 
-        db_pool
+                TestDatabase::Postgres(
+                    PgPool::connect_with(config.with_db())
+                        .await
+                        .expect("failed to connect to Postgres"),
+                )
+            }
+            DatabaseBackend::Sqlite => TestDatabase::Sqlite(
+                SqlitePool::connect_with(config.sqlite_options())
+                    .await
+                    .expect("failed to open the SQLite database"),
+            ),
+            DatabaseBackend::Memory => TestDatabase::Memory(MemoryStorage::new()),
+        }
     }
 
     pub fn endpoint(&self, s: &str) -> String {
         format!("{}{}", &self.address, s)
     }
 
-    pub async fn post_create_user(&self) -> Uuid {
-        let user_id = Uuid::new_v4();
-        sqlx::query!(
-            r#"
-            INSERT INTO users (user_id, created_at)
-            VALUES ($1, now())
-            "#,
-            user_id
-        )
-        .execute(&self.db_pool)
-        .await
-        .expect("failed to create new user");
+    pub async fn post_create_poll(&self, prompt: &str, username: &str) -> Uuid {
+        self.storage
+            .create_poll(&NewPoll {
+                prompt: prompt.to_string(),
+                creator_name: ParticipantName::parse(username.to_string())
+                    .expect("invalid creator username"),
+                invite_only: false,
+                passphrase_hash: None,
+                require_proof_of_work: false,
+            })
+            .await
+            .expect("failed to create poll")
+            .poll_id
+    }
 
-        user_id
+    pub async fn user_id_of(&self, poll_id: &Uuid, username: &str) -> Uuid {
+        self.storage
+            .members(poll_id)
+            .await
+            .expect("failed to retrieve poll members")
+            .into_iter()
+            .find(|m| m.username == username)
+            .expect("failed to find user")
+            .user_id
     }
 
-    pub async fn post_create_poll(&self, prompt: &str, username: &str) -> Uuid {
-        let user_id = self.post_create_user().await;
-        let poll_id = Uuid::new_v4();
-        sqlx::query!(
-            r#"
-            INSERT INTO polls (poll_id, creator_id, prompt, created_at)
-            VALUES ($1, $2, $3, now())
-            "#,
-            poll_id,
-            user_id,
-            prompt
-        )
-        .execute(&self.db_pool)
-        .await
-        .expect("failed to create poll");
-
-        sqlx::query!(
-            r#"
-            INSERT INTO poll_users (poll_id, user_id, username)
-            VALUES ($1, $2, $3)
-            "#,
-            poll_id,
-            user_id,
-            username
-        )
-        .execute(&self.db_pool)
-        .await
-        .expect("failed to insert poll_users junction table entry");
-
-        poll_id
+    /// Create a poll through the `/new` form, logging `api_client` in as its creator.
+    pub async fn create_poll<Body: serde::Serialize>(&self, body: &Body) -> Uuid {
+        let response = self.post_form(&self.api_client, "/new", body).await;
+        assert_eq!(response.status().as_u16(), 303);
+
+        let location = location_string(response);
+        Uuid::parse_str(location.trim_start_matches("/poll/")).expect("invalid poll_id")
     }
 
     pub async fn get_poll_page(&self, poll_id: &str) -> reqwest::Response {
@@ -155,12 +198,8 @@ impl TestApp {
         poll_id: &Uuid,
         body: &Body,
     ) -> reqwest::Response {
-        self.api_client
-            .post(&self.endpoint(&format!("/poll/{poll_id}/join")))
-            .form(body)
-            .send()
+        self.post_form(&self.api_client, &format!("/poll/{poll_id}/join"), body)
             .await
-            .expect("could not send join request")
     }
 
     pub async fn post_suggestion<Body: serde::Serialize>(
@@ -168,9 +207,41 @@ impl TestApp {
         poll_id: &Uuid,
         body: &Body,
     ) -> reqwest::Response {
-        self.api_client
-            .post(self.endpoint(&format!("/poll/{poll_id}/suggest")))
-            .form(body)
+        self.post_form(&self.api_client, &format!("/poll/{poll_id}/suggest"), body)
+            .await
+    }
+
+    /// The CSRF token of `client`'s session, read from the new poll form.
+    pub async fn csrf_token(&self, client: &reqwest::Client) -> String {
+        let html = client
+            .get(self.endpoint("/"))
+            .send()
+            .await
+            .expect("failed to execute request")
+            .text()
+            .await
+            .unwrap();
+        extract_csrf_token(&html).expect("no CSRF token in the new poll form")
+    }
+
+    /// Submit a form the way a browser would, with `client`'s CSRF token.
+    pub async fn post_form<Body: serde::Serialize>(
+        &self,
+        client: &reqwest::Client,
+        path: &str,
+        body: &Body,
+    ) -> reqwest::Response {
+        let token = self.csrf_token(client).await;
+        let mut form = match serde_json::to_value(body).expect("invalid form body") {
+            serde_json::Value::Object(form) => form,
+            serde_json::Value::Null => serde_json::Map::new(),
+            _ => panic!("form body must be an object"),
+        };
+        form.insert("csrf_token".into(), token.into());
+
+        client
+            .post(self.endpoint(path))
+            .form(&form)
             .send()
             .await
             .expect("failed to execute request")
@@ -180,30 +251,12 @@ impl TestApp {
 impl Drop for TestApp {
     fn drop(&mut self) {
         let (tx, rx) = std::sync::mpsc::channel();
-        let db_name = self.db_name.clone();
+        let database = self.database.clone();
 
         std::thread::spawn(move || {
             let rt = Runtime::new().unwrap();
             rt.block_on(async {
-                let config = Settings::new().expect("Failed to read configuration");
-                let mut conn = PgConnection::connect_with(&config.database.without_db())
-                    .await
-                    .expect("Failed to connect to Postgres");
-
-                conn.execute(&*format!(
-                    "SELECT pg_terminate_backend(pg_stat_activity.pid)
-                    FROM pg_stat_activity
-                    WHERE datname = '{}'
-                      AND pid <> pg_backend_pid();",
-                    db_name
-                ))
-                .await
-                .expect("Failed to disconnect other sessions");
-
-                conn.execute(&*format!("DROP DATABASE \"{}\";", db_name))
-                    .await
-                    .unwrap_or_else(|_| panic!("Failed to drop temporary database: {}", db_name));
-                info!("Dropped database: {db_name}");
+                drop_database(&database).await;
                 let _ = tx.send(());
             })
         });
@@ -213,6 +266,218 @@ impl Drop for TestApp {
     }
 }
 
+/// A connection to the test's database next to the application's storage.
+pub enum TestDatabase {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+    Memory(MemoryStorage),
+}
+
+impl TestDatabase {
+    pub async fn count_rows(&self, table: &str) -> i64 {
+        let query = format!("SELECT COUNT(*) FROM {table}");
+        match self {
+            TestDatabase::Postgres(pool) => sqlx::query_scalar(&query).fetch_one(pool).await,
+            TestDatabase::Sqlite(pool) => sqlx::query_scalar(&query).fetch_one(pool).await,
+            TestDatabase::Memory(memory) => Ok(memory.count_rows(table) as i64),
+        }
+        .expect("failed to count rows")
+    }
+
+    /// Makes the database look like it missed the newest migration, memory
+    /// has no migrations to miss.
+    pub async fn forget_last_migration(&self) {
+        let query = "DELETE FROM _sqlx_migrations \
+            WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)";
+        match self {
+            TestDatabase::Postgres(pool) => sqlx::query(query).execute(pool).await.map(|_| ()),
+            TestDatabase::Sqlite(pool) => sqlx::query(query).execute(pool).await.map(|_| ()),
+            TestDatabase::Memory(_) => panic!("memory storage has no migrations"),
+        }
+        .expect("failed to delete the migration");
+    }
+
+    /// Moves when the invite expires and was revoked, e.g. into the past.
+    pub async fn set_invite_times(
+        &self,
+        token: &str,
+        expires_at: DateTime<Utc>,
+        revoked_at: Option<DateTime<Utc>>,
+    ) {
+        let query = "UPDATE poll_invites SET expires_at = $1, revoked_at = $2 WHERE token = $3";
+        match self {
+            TestDatabase::Postgres(pool) => sqlx::query(query)
+                .bind(expires_at)
+                .bind(revoked_at)
+                .bind(token)
+                .execute(pool)
+                .await
+                .map(|_| ()),
+            TestDatabase::Sqlite(pool) => sqlx::query(query)
+                .bind(expires_at)
+                .bind(revoked_at)
+                .bind(token)
+                .execute(pool)
+                .await
+                .map(|_| ()),
+            TestDatabase::Memory(memory) => {
+                memory.set_invite_times(token, expires_at, revoked_at);
+                Ok(())
+            }
+        }
+        .expect("failed to update the invite");
+    }
+
+    /// Votes straight in the database, the application has no way to vote.
+    pub async fn cast_vote(&self, user_id: &Uuid, suggestion_id: &Uuid) {
+        let query = "INSERT INTO votes (user_id, suggestion_id) VALUES ($1, $2)";
+        match self {
+            TestDatabase::Postgres(pool) => sqlx::query(query)
+                .bind(user_id)
+                .bind(suggestion_id)
+                .execute(pool)
+                .await
+                .map(|_| ()),
+            TestDatabase::Sqlite(pool) => sqlx::query(query)
+                .bind(user_id)
+                .bind(suggestion_id)
+                .execute(pool)
+                .await
+                .map(|_| ()),
+            TestDatabase::Memory(memory) => {
+                memory.cast_vote(user_id, suggestion_id);
+                Ok(())
+            }
+        }
+        .expect("failed to insert the vote");
+    }
+}
+
+/// The configured database settings, pointed at a database of its own that
+/// doesn't exist yet.
+pub fn new_database_settings() -> DatabaseSettings {
+    let mut settings = test_settings().database;
+    let name = Uuid::new_v4().to_string();
+    settings.sqlite_path = std::env::temp_dir()
+        .join(format!("apoll-{name}.sqlite"))
+        .to_string_lossy()
+        .into_owned();
+    settings.database_name = name;
+    settings
+}
+
+pub async fn database_exists(settings: &DatabaseSettings) -> bool {
+    match settings.backend {
+        DatabaseBackend::Postgres => {
+            let mut connection = PgConnection::connect_with(&settings.without_db())
+                .await
+                .expect("failed to connect to Postgres");
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)")
+                .bind(&settings.database_name)
+                .fetch_one(&mut connection)
+                .await
+                .expect("failed to look up the database")
+        }
+        DatabaseBackend::Sqlite => std::path::Path::new(&settings.sqlite_path).exists(),
+        DatabaseBackend::Memory => false,
+    }
+}
+
+pub async fn drop_database(settings: &DatabaseSettings) {
+    match settings.backend {
+        DatabaseBackend::Postgres => {
+            let mut conn = PgConnection::connect_with(&settings.without_db())
+                .await
+                .expect("Failed to connect to Postgres");
+            conn.execute(&*format!(
+                r#"DROP DATABASE IF EXISTS "{}" WITH (FORCE);"#,
+                settings.database_name
+            ))
+            .await
+            .unwrap_or_else(|_| {
+                panic!(
+                    "Failed to drop temporary database: {}",
+                    settings.database_name
+                )
+            });
+        }
+        DatabaseBackend::Sqlite => {
+            // The write-ahead log and its index sit next to the database
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{suffix}", settings.sqlite_path));
+            }
+        }
+        DatabaseBackend::Memory => {}
+    }
+    info!("Dropped database: {}", settings.database_name);
+}
+
+/// The `test` environment keeps everything in memory, `APP_ENVIRONMENT` runs
+/// the tests against the services of another one.
+pub fn test_settings() -> Settings {
+    let environment = match std::env::var("APP_ENVIRONMENT") {
+        Ok(environment) => environment
+            .try_into()
+            .expect("failed to parse APP_ENVIRONMENT"),
+        Err(_) => Environment::Test,
+    };
+    Settings::for_environment(environment).expect("failed to read configuration")
+}
+
+/// A random first name that is also a valid participant name.
+pub fn fake_username() -> String {
+    loop {
+        let name: String = FirstName().fake();
+        if name.len() >= 3 {
+            return name;
+        }
+    }
+}
+
+/// A client with its own cookie store, acting as a separate visitor.
+pub fn new_api_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap()
+}
+
+pub fn extract_csrf_token(html: &str) -> Option<String> {
+    extract_input_value(html, "csrf_token")
+}
+
+/// The value of the first input named `name` in the page.
+pub fn extract_input_value(html: &str, name: &str) -> Option<String> {
+    let attributes = format!(r#"name="{name}" value=""#);
+    let start = html.find(&attributes)? + attributes.len();
+    let end = html[start..].find('"')? + start;
+    Some(html[start..end].to_string())
+}
+
+/// Does the work a browser would for a proof-of-work challenge, the way the
+/// solver script does.
+pub fn solve(challenge: &str) -> u64 {
+    let difficulty = challenge
+        .split(':')
+        .nth(1)
+        .and_then(|d| d.parse::<u32>().ok())
+        .expect("malformed challenge");
+    (0..)
+        .find(|n: &u64| {
+            let digest = Sha256::digest(format!("{challenge}:{n}").as_bytes());
+            let mut bits = 0;
+            for byte in digest {
+                bits += byte.leading_zeros();
+                if byte != 0 {
+                    break;
+                }
+            }
+            bits >= difficulty
+        })
+        .unwrap()
+}
+
 pub fn location_string(res: Response) -> String {
     res.headers()
         .get("location")
@@ -1,2 +1,15 @@
+mod admin;
+mod cli;
+mod content_filter;
+mod csrf;
+mod health;
 mod helpers;
+mod metrics;
 mod poll;
+mod poll_cache;
+mod proof_of_work;
+mod rate_limit;
+mod session;
+mod shutdown;
+mod signing_keys;
+mod tls;
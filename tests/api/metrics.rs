@@ -0,0 +1,102 @@
+use secrecy::Secret;
+
+use crate::helpers::TestApp;
+
+const TOKEN: &str = "scraper-token";
+
+async fn spawn_app_with_token() -> TestApp {
+    TestApp::with_configuration(|c| c.metrics.token = Some(Secret::new(TOKEN.to_string()))).await
+}
+
+async fn scrape(app: &TestApp, base: &str) -> String {
+    let response = app
+        .api_client
+        .get(format!("{base}/metrics"))
+        .bearer_auth(TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    response.text().await.unwrap()
+}
+
+#[tokio::test]
+async fn metrics_are_not_served_unless_configured() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .api_client
+        .get(app.endpoint("/metrics"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn metrics_require_the_token() {
+    let app = spawn_app_with_token().await;
+
+    let response = app
+        .api_client
+        .get(app.endpoint("/metrics"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .api_client
+        .get(app.endpoint("/metrics"))
+        .bearer_auth("wrong-token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn requests_and_domain_events_are_counted() {
+    let app = spawn_app_with_token().await;
+    let poll_id = app
+        .create_poll(&serde_json::json!({ "username": "owner", "prompt": "Lunch?" }))
+        .await;
+    app.post_suggestion(&poll_id, &serde_json::json!({ "suggestion": "Pizza" }))
+        .await;
+
+    let text = scrape(&app, &app.address).await;
+    assert!(text.contains(
+        r#"apoll_http_requests_total{method="POST",route="/poll/{poll_id}/suggest",status="303"} 1"#
+    ));
+    assert!(
+        text.contains(r#"apoll_http_request_duration_seconds_count{method="POST",route="/new"} 1"#)
+    );
+    assert!(text.contains("apoll_polls_created_total 1"));
+    assert!(text.contains("apoll_poll_joins_total 0"));
+    assert!(text.contains("apoll_suggestions_total 1"));
+    assert!(text.contains("apoll_db_pool_connections"));
+    assert!(text.contains(r#"apoll_session_store_errors_total{operation="load"} 0"#));
+}
+
+#[tokio::test]
+async fn metrics_can_have_a_listener_of_their_own() {
+    let app = TestApp::with_configuration(|c| {
+        c.metrics.address = Some("127.0.0.1:0".to_string());
+        c.metrics.token = Some(Secret::new(TOKEN.to_string()));
+    })
+    .await;
+
+    // Not on the public listener
+    let response = app
+        .api_client
+        .get(app.endpoint("/metrics"))
+        .bearer_auth(TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    let metrics_address = app.metrics_address.clone().unwrap();
+    let text = scrape(&app, &metrics_address).await;
+    assert!(text.contains("apoll_polls_created_total 0"));
+}
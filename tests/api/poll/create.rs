@@ -11,13 +11,7 @@ async fn create_poll_returns_200_with_valid_form_data() {
         "prompt": "Is this a good prompt?",
     });
 
-    let response = app
-        .api_client
-        .post(app.endpoint("/new"))
-        .form(&body)
-        .send()
-        .await
-        .expect("failed to execute request");
+    let response = app.post_form(&app.api_client, "/new", &body).await;
 
     // Response was redirected
     assert_eq!(response.status().as_u16(), 303);
@@ -42,13 +36,7 @@ async fn create_poll_should_display_message_on_invalid_data() {
         "prompt": ""
     });
 
-    let response = app
-        .api_client
-        .post(app.endpoint("/new"))
-        .form(&body)
-        .send()
-        .await
-        .expect("failed to execute request");
+    let response = app.post_form(&app.api_client, "/new", &body).await;
 
     // Should be redirected to the create poll page
     assert_eq!(response.status().as_u16(), 303);
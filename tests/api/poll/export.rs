@@ -0,0 +1,225 @@
+use apoll::domain::{ExportAccess, ModerationStatus};
+use uuid::Uuid;
+
+use crate::helpers::{new_api_client, TestApp};
+
+async fn get_export(
+    app: &TestApp,
+    client: &reqwest::Client,
+    poll_id: &Uuid,
+    format: &str,
+) -> reqwest::Response {
+    client
+        .get(app.endpoint(&format!("/poll/{poll_id}/export?format={format}")))
+        .send()
+        .await
+        .expect("failed to send get request")
+}
+
+/// A poll created by `api_client` as "owner", where "alice" suggested
+/// "Tacos" and "Pizza" and the owner voted for "Pizza".
+async fn poll_with_results(app: &TestApp) -> (Uuid, reqwest::Client) {
+    let poll_id = app
+        .create_poll(&serde_json::json!({ "username": "owner", "prompt": "Lunch?" }))
+        .await;
+    let alice = new_api_client();
+    app.post_form(
+        &alice,
+        &format!("/poll/{poll_id}/join"),
+        &serde_json::json!({ "username": "alice" }),
+    )
+    .await;
+    for suggestion in ["Tacos", "Pizza"] {
+        app.post_form(
+            &alice,
+            &format!("/poll/{poll_id}/suggest"),
+            &serde_json::json!({ "suggestion": suggestion }),
+        )
+        .await;
+    }
+    let pizza_id = app
+        .storage
+        .visible_suggestions(&poll_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|s| s.suggestion == "Pizza")
+        .unwrap()
+        .suggestion_id;
+    let owner_id = app.user_id_of(&poll_id, "owner").await;
+    app.db_pool.cast_vote(&owner_id, &pizza_id).await;
+    (poll_id, alice)
+}
+
+#[tokio::test]
+async fn results_are_exported_as_json() {
+    let app = TestApp::new().await;
+    let (poll_id, alice) = poll_with_results(&app).await;
+
+    let response = get_export(&app, &alice, &poll_id, "json").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers().get("Content-Disposition").unwrap(),
+        &format!(r#"attachment; filename="poll-{poll_id}.json""#)
+    );
+    let export: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(export["prompt"], "Lunch?");
+    let participants = export["participants"].as_array().unwrap();
+    assert_eq!(participants[0]["username"], "owner");
+    assert_eq!(participants[0]["role"], "owner");
+    assert_eq!(participants[1]["username"], "alice");
+    let suggestions = export["suggestions"].as_array().unwrap();
+    assert_eq!(suggestions[0]["suggestion"], "Tacos");
+    assert_eq!(suggestions[0]["author"], "alice");
+    assert!(suggestions[0]["created_at"].is_string());
+    assert_eq!(export["votes"][0]["voter"], "owner");
+    assert_eq!(export["votes"][0]["suggestion"], "Pizza");
+    assert_eq!(export["tally"][0]["suggestion"], "Pizza");
+    assert_eq!(export["tally"][0]["votes"], 1);
+    assert_eq!(export["tally"][1]["suggestion"], "Tacos");
+    assert_eq!(export["tally"][1]["rank"], 2);
+}
+
+#[tokio::test]
+async fn results_are_exported_as_csv_and_markdown() {
+    let app = TestApp::new().await;
+    let (poll_id, alice) = poll_with_results(&app).await;
+
+    let response = get_export(&app, &alice, &poll_id, "csv").await;
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "text/csv; charset=utf-8"
+    );
+    let csv = response.text().await.unwrap();
+    assert!(csv.starts_with("participants\r\nusername,role,joined_at\r\nowner,owner,"));
+    assert!(csv.contains("votes\r\nvoter,suggestion\r\nowner,Pizza\r\n"));
+    assert!(csv.contains("tally\r\nrank,suggestion,votes\r\n1,Pizza,1\r\n2,Tacos,0\r\n"));
+
+    let response = get_export(&app, &alice, &poll_id, "md").await;
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "text/markdown; charset=utf-8"
+    );
+    let markdown = response.text().await.unwrap();
+    assert!(markdown.starts_with("# Lunch?\n"));
+    assert!(markdown.contains("| 1 | Pizza | 1 |\n| 2 | Tacos | 0 |\n"));
+    assert!(markdown.contains("| owner | Pizza |"));
+}
+
+#[tokio::test]
+async fn only_participants_can_export_open_polls() {
+    let app = TestApp::new().await;
+    let poll_id = app.post_create_poll("Lunch?", "owner").await;
+
+    let response = get_export(&app, &new_api_client(), &poll_id, "json").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let observer = new_api_client();
+    app.post_form(
+        &observer,
+        &format!("/poll/{poll_id}/join"),
+        &serde_json::json!({ "username": "watcher", "observer": "on" }),
+    )
+    .await;
+    let response = get_export(&app, &observer, &poll_id, "json").await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+async fn post_export_access(
+    app: &TestApp,
+    client: &reqwest::Client,
+    poll_id: &Uuid,
+    export_access: &str,
+) -> reqwest::Response {
+    app.post_form(
+        client,
+        &format!("/poll/{poll_id}/export_access"),
+        &serde_json::json!({ "export_access": export_access }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn every_participant_can_export_by_default() {
+    let app = TestApp::new().await;
+    let (poll_id, alice) = poll_with_results(&app).await;
+
+    let response = get_export(&app, &alice, &poll_id, "json").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn only_the_owner_can_export_once_they_keep_the_results() {
+    let app = TestApp::new().await;
+    let (poll_id, alice) = poll_with_results(&app).await;
+
+    let response = post_export_access(&app, &app.api_client, &poll_id, "owner").await;
+    assert_eq!(response.status().as_u16(), 303);
+    let page = app
+        .api_client
+        .get(app.endpoint(&format!("/poll/{poll_id}/export_access")))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains(r#"<option value="owner" selected>"#));
+
+    let response = get_export(&app, &alice, &poll_id, "json").await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = get_export(&app, &app.api_client, &poll_id, "json").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    post_export_access(&app, &app.api_client, &poll_id, "participants").await;
+    let response = get_export(&app, &alice, &poll_id, "json").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn only_the_owner_can_change_who_exports() {
+    let app = TestApp::new().await;
+    let (poll_id, alice) = poll_with_results(&app).await;
+
+    let response = post_export_access(&app, &alice, &poll_id, "owner").await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let poll = app.storage.find_poll(&poll_id).await.unwrap().unwrap();
+    assert_eq!(poll.export_access, ExportAccess::Participants);
+}
+
+#[tokio::test]
+async fn held_suggestions_are_left_out_of_the_export() {
+    let app = TestApp::new().await;
+    let (poll_id, alice) = poll_with_results(&app).await;
+    let owner_id = app.user_id_of(&poll_id, "owner").await;
+    app.storage
+        .add_suggestion(
+            &poll_id,
+            &owner_id,
+            "Held back",
+            &ModerationStatus::Held("matched a filter".into()),
+        )
+        .await
+        .unwrap();
+
+    let csv = get_export(&app, &alice, &poll_id, "csv")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert!(!csv.contains("Held back"));
+}
+
+#[tokio::test]
+async fn export_rejects_an_unknown_format() {
+    let app = TestApp::new().await;
+    let (poll_id, alice) = poll_with_results(&app).await;
+
+    let response = get_export(&app, &alice, &poll_id, "xlsx").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -1,10 +1,9 @@
-use fake::{
-    faker::{lorem::en::Sentence, name::en::FirstName},
-    Fake,
-};
+use apoll::domain::{ModerationStatus, ParticipantName, PollRole};
+use apoll::storage::NewMember;
+use fake::{faker::lorem::en::Sentence, Fake};
 use uuid::Uuid;
 
-use crate::helpers::TestApp;
+use crate::helpers::{fake_username, new_api_client, TestApp};
 
 #[tokio::test]
 async fn page_should_return_404_if_path_is_invalid_uuid() {
@@ -30,7 +29,7 @@ async fn page_should_return_404_if_poll_doesnt_exist() {
 async fn page_should_return_200_if_path_is_a_valid_poll() {
     let app = TestApp::new().await;
 
-    let username: String = FirstName().fake();
+    let username = fake_username();
     let poll_id = app.post_create_poll("Poll question?", &username).await;
 
     let response = app.get_poll_page(&poll_id.to_string()).await;
@@ -43,7 +42,7 @@ async fn page_should_display_creator_and_prompt() {
     let app = TestApp::new().await;
 
     let prompt: String = Sentence(1..3).fake();
-    let username: String = FirstName().fake();
+    let username = fake_username();
     let poll_id = app.post_create_poll(&prompt, &username).await;
 
     let response_text = app
@@ -56,3 +55,312 @@ async fn page_should_display_creator_and_prompt() {
     assert!(response_text.contains(&username));
     assert!(response_text.contains(&prompt));
 }
+
+async fn get_poll_page_if_none_match(
+    app: &TestApp,
+    poll_id: &Uuid,
+    etag: &str,
+) -> reqwest::Response {
+    app.api_client
+        .get(app.endpoint(&format!("/poll/{poll_id}")))
+        .header("If-None-Match", etag)
+        .send()
+        .await
+        .expect("failed to send get request")
+}
+
+fn etag_of(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get("ETag")
+        .expect("no ETag on the page")
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn page_is_sent_with_a_weak_etag_to_revalidate() {
+    let app = TestApp::new().await;
+    let poll_id = app.post_create_poll("prompt", &fake_username()).await;
+
+    let response = app.get_poll_page(&poll_id.to_string()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(etag_of(&response).starts_with("W/\""));
+    let headers = response.headers();
+    assert_eq!(headers.get("Cache-Control").unwrap(), "private, no-cache");
+    assert_eq!(headers.get("Vary").unwrap(), "Cookie");
+}
+
+#[tokio::test]
+async fn page_returns_304_if_the_etag_matches() {
+    let app = TestApp::new().await;
+    let poll_id = app.post_create_poll("prompt", &fake_username()).await;
+    let etag = etag_of(&app.get_poll_page(&poll_id.to_string()).await);
+
+    let response = get_poll_page_if_none_match(&app, &poll_id, &etag).await;
+
+    assert_eq!(response.status().as_u16(), 304);
+    assert_eq!(etag_of(&response), etag);
+    assert!(response.text().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn page_returns_200_with_a_new_etag_after_the_poll_changed() {
+    let app = TestApp::new().await;
+    let poll_id = app.post_create_poll("prompt", &fake_username()).await;
+    app.join_poll(&poll_id, &serde_json::json!({"username": "newuser"}))
+        .await;
+    let etag = etag_of(&app.get_poll_page(&poll_id.to_string()).await);
+
+    app.post_suggestion(&poll_id, &serde_json::json!({"suggestion": "pizza"}))
+        .await;
+    // The redirect after the suggestion left no message behind
+    let response = get_poll_page_if_none_match(&app, &poll_id, &etag).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_ne!(etag_of(&response), etag);
+    assert!(response.text().await.unwrap().contains("pizza"));
+}
+
+#[tokio::test]
+async fn page_has_another_etag_for_another_session_user() {
+    let app = TestApp::new().await;
+    let poll_id = app.post_create_poll("prompt", &fake_username()).await;
+    let anonymous = etag_of(&app.get_poll_page(&poll_id.to_string()).await);
+
+    app.join_poll(&poll_id, &serde_json::json!({"username": "newuser"}))
+        .await;
+    let response = app.get_poll_page(&poll_id.to_string()).await;
+    let member = etag_of(&response);
+
+    assert_ne!(anonymous, member);
+    let other = new_api_client()
+        .get(app.endpoint(&format!("/poll/{poll_id}")))
+        .header("If-None-Match", &member)
+        .send()
+        .await
+        .expect("failed to send get request");
+    assert_eq!(other.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn pages_with_a_challenge_are_not_cached() {
+    let app = TestApp::new().await;
+    let poll_id = app
+        .create_poll(&serde_json::json!({
+            "username": "owner",
+            "prompt": "Question?",
+            "require_proof_of_work": "on",
+        }))
+        .await;
+
+    let response = new_api_client()
+        .get(app.endpoint(&format!("/poll/{poll_id}")))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.headers().get("Cache-Control").unwrap(), "no-store");
+    assert!(response.headers().get("ETag").is_none());
+}
+
+async fn add_suggestions(app: &TestApp, poll_id: &Uuid, creator_id: &Uuid, suggestions: &[&str]) {
+    for suggestion in suggestions {
+        app.storage
+            .add_suggestion(poll_id, creator_id, suggestion, &ModerationStatus::Visible)
+            .await
+            .expect("failed to add suggestion");
+    }
+}
+
+async fn join_as(app: &TestApp, poll_id: &Uuid, username: &str) -> Uuid {
+    let body = serde_json::json!({ "username": username });
+    app.post_form(&new_api_client(), &format!("/poll/{poll_id}/join"), &body)
+        .await;
+    app.user_id_of(poll_id, username).await
+}
+
+async fn poll_page_with(app: &TestApp, poll_id: &Uuid, query: &str) -> String {
+    app.api_client
+        .get(app.endpoint(&format!("/poll/{poll_id}?{query}")))
+        .send()
+        .await
+        .expect("failed to send get request")
+        .text()
+        .await
+        .unwrap()
+}
+
+/// Whether the page lists `items` in this order.
+fn lists_in_order(html: &str, items: &[&str]) -> bool {
+    let positions = items
+        .iter()
+        .map(|item| html.find(&format!("<li>{item}")))
+        .collect::<Option<Vec<_>>>();
+    positions.is_some_and(|p| p.windows(2).all(|w| w[0] < w[1]))
+}
+
+fn more_link(html: &str, label: &str) -> Option<String> {
+    let end = html.find(&format!("\">{label}</a>"))?;
+    let start = html[..end].rfind("href=\"")? + "href=\"".len();
+    Some(html[start..end].replace("&amp;", "&"))
+}
+
+#[tokio::test]
+async fn prompt_and_suggestions_are_escaped() {
+    let app = TestApp::new().await;
+    let poll_id = app
+        .create_poll(&serde_json::json!({ "username": "owner", "prompt": "<b>Lunch</b>?" }))
+        .await;
+    let alice_id = join_as(&app, &poll_id, "alice").await;
+    add_suggestions(&app, &poll_id, &alice_id, &["<script>alert(1)</script>"]).await;
+
+    let html = poll_page_with(&app, &poll_id, "").await;
+
+    assert!(html.contains("<h1>&lt;b&gt;Lunch&lt;/b&gt;?</h1>"));
+    assert!(html.contains("<li>&lt;script&gt;alert(1)&lt;/script&gt;</li>"));
+    assert!(html.contains("Suggestion: &lt;script&gt;alert(1)&lt;/script&gt;</option>"));
+    assert!(!html.contains("<script>"));
+}
+
+#[tokio::test]
+async fn page_lists_suggestions_a_page_at_a_time() {
+    let app = TestApp::new().await;
+    let poll_id = app.post_create_poll("prompt", "owner").await;
+    let owner_id = app.user_id_of(&poll_id, "owner").await;
+    let suggestions = (0..51).map(|i| format!("idea {i:02}")).collect::<Vec<_>>();
+    let suggestions = suggestions.iter().map(String::as_str).collect::<Vec<_>>();
+    add_suggestions(&app, &poll_id, &owner_id, &suggestions).await;
+
+    let html = poll_page_with(&app, &poll_id, "suggestion_sort=oldest").await;
+    assert!(lists_in_order(&html, &suggestions[..50]));
+    assert!(!html.contains("idea 50"));
+
+    let next = more_link(&html, "More suggestions").expect("no link to the next page");
+    let html = app
+        .api_client
+        .get(app.endpoint(&next))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("<li>idea 50</li>"));
+    assert!(!html.contains("idea 49"));
+    assert!(html.contains("<li>owner"));
+    assert!(more_link(&html, "More suggestions").is_none());
+}
+
+#[tokio::test]
+async fn suggestions_can_be_sorted() {
+    let app = TestApp::new().await;
+    let poll_id = app.post_create_poll("prompt", "owner").await;
+    let owner_id = app.user_id_of(&poll_id, "owner").await;
+    let alice_id = join_as(&app, &poll_id, "alice").await;
+    add_suggestions(&app, &poll_id, &owner_id, &["Banana", "cherry"]).await;
+    add_suggestions(&app, &poll_id, &alice_id, &["apple"]).await;
+    let apple_id = app
+        .storage
+        .visible_suggestions(&poll_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|s| s.suggestion == "apple")
+        .unwrap()
+        .suggestion_id;
+    app.db_pool.cast_vote(&owner_id, &apple_id).await;
+
+    let sorted = [
+        ("oldest", ["Banana", "cherry", "apple"]),
+        ("newest", ["apple", "cherry", "Banana"]),
+        ("most_votes", ["apple", "Banana", "cherry"]),
+        ("alphabetical", ["apple", "Banana", "cherry"]),
+        ("author", ["apple", "Banana", "cherry"]),
+    ];
+    for (sort, order) in sorted {
+        let html = poll_page_with(&app, &poll_id, &format!("suggestion_sort={sort}")).await;
+        assert!(lists_in_order(&html, &order), "wrong order for {sort}");
+    }
+}
+
+#[tokio::test]
+async fn members_can_be_sorted_and_paged_through() {
+    let app = TestApp::new().await;
+    let poll_id = app.post_create_poll("prompt", "owner").await;
+    join_as(&app, &poll_id, "zed").await;
+    join_as(&app, &poll_id, "bob").await;
+
+    let html = poll_page_with(&app, &poll_id, "member_sort=newest").await;
+    assert!(lists_in_order(&html, &["bob", "zed", "owner"]));
+    let html = poll_page_with(&app, &poll_id, "member_sort=alphabetical").await;
+    assert!(lists_in_order(&html, &["bob", "owner", "zed"]));
+
+    let bob_id = app.user_id_of(&poll_id, "bob").await;
+    let query = format!("member_sort=alphabetical&members_after={bob_id}");
+    let html = poll_page_with(&app, &poll_id, &query).await;
+    assert!(lists_in_order(&html, &["owner", "zed"]));
+    assert!(!html.contains("<li>bob"));
+}
+
+#[tokio::test]
+async fn search_filters_members_and_suggestions() {
+    let app = TestApp::new().await;
+    let poll_id = app.post_create_poll("prompt", "owner").await;
+    let owner_id = app.user_id_of(&poll_id, "owner").await;
+    let alice_id = join_as(&app, &poll_id, "alice").await;
+    add_suggestions(&app, &poll_id, &owner_id, &["Pizza place", "Sushi bar"]).await;
+    add_suggestions(&app, &poll_id, &alice_id, &["Tacos"]).await;
+
+    let html = poll_page_with(&app, &poll_id, "q=PIZZA").await;
+    assert!(html.contains("<li>Pizza place</li>"));
+    assert!(!html.contains("Sushi bar"));
+    assert!(!html.contains("<li>alice"));
+    assert!(html.contains(r#"value="PIZZA""#));
+
+    // Suggestions are found by their author's name too
+    let html = poll_page_with(&app, &poll_id, "q=alice").await;
+    assert!(html.contains("<li>alice"));
+    assert!(html.contains("<li>Tacos</li>"));
+    assert!(!html.contains("Pizza place"));
+}
+
+#[tokio::test]
+async fn search_does_not_find_names_held_for_review() {
+    let app = TestApp::new().await;
+    let poll_id = app.post_create_poll("prompt", "owner").await;
+    let mallory_id = app
+        .storage
+        .add_member(&NewMember {
+            poll_id,
+            name: ParticipantName::parse("mallory".into()).unwrap(),
+            role: PollRole::Participant,
+            status: ModerationStatus::Held("matched a filter".into()),
+            invite: None,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    add_suggestions(&app, &poll_id, &mallory_id, &["Tacos"]).await;
+
+    let html = poll_page_with(&app, &poll_id, "q=mallory").await;
+    assert!(!html.contains("mallory</li>"));
+    assert!(!html.contains("<li>Tacos</li>"));
+}
+
+#[tokio::test]
+async fn page_rejects_an_unknown_sort() {
+    let app = TestApp::new().await;
+    let poll_id = app.post_create_poll("prompt", "owner").await;
+
+    let response = app
+        .api_client
+        .get(app.endpoint(&format!("/poll/{poll_id}?suggestion_sort=random")))
+        .send()
+        .await
+        .expect("failed to send get request");
+
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,180 @@
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::helpers::{fake_username, location_string, new_api_client, TestApp};
+
+async fn create_invite_only_poll(app: &TestApp) -> Uuid {
+    app.create_poll(&serde_json::json!({
+        "username": "creator",
+        "prompt": "Secret question?",
+        "invite_only": "on",
+    }))
+    .await
+}
+
+async fn create_invite(app: &TestApp, poll_id: &Uuid, max_uses: u32) -> String {
+    let response = app
+        .post_form(
+            &app.api_client,
+            &format!("/poll/{poll_id}/invites"),
+            &serde_json::json!({ "expires_in_hours": 24, "max_uses": max_uses }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let text = app
+        .api_client
+        .get(app.endpoint(&format!("/poll/{poll_id}/invites")))
+        .send()
+        .await
+        .expect("failed to execute request")
+        .text()
+        .await
+        .unwrap();
+    let start = text.find("?invite=").expect("no invite on the page") + "?invite=".len();
+    text[start..start + 32].to_string()
+}
+
+async fn join_as_guest(app: &TestApp, poll_id: &Uuid, invite: Option<&str>) -> reqwest::Response {
+    let mut body = serde_json::json!({ "username": fake_username() });
+    if let Some(invite) = invite {
+        body["invite"] = invite.into();
+    }
+
+    app.post_form(&new_api_client(), &format!("/poll/{poll_id}/join"), &body)
+        .await
+}
+
+#[tokio::test]
+async fn invite_only_poll_rejects_join_without_invite() {
+    let app = TestApp::new().await;
+    let poll_id = create_invite_only_poll(&app).await;
+
+    let response = join_as_guest(&app, &poll_id, None).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn invite_only_poll_can_be_joined_with_a_valid_invite() {
+    let app = TestApp::new().await;
+    let poll_id = create_invite_only_poll(&app).await;
+    let invite = create_invite(&app, &poll_id, 1).await;
+
+    let response = join_as_guest(&app, &poll_id, Some(&invite)).await;
+
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(location_string(response), format!("/poll/{poll_id}"));
+}
+
+#[tokio::test]
+async fn invite_cannot_be_used_more_than_max_uses() {
+    let app = TestApp::new().await;
+    let poll_id = create_invite_only_poll(&app).await;
+    let invite = create_invite(&app, &poll_id, 1).await;
+
+    let response = join_as_guest(&app, &poll_id, Some(&invite)).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let response = join_as_guest(&app, &poll_id, Some(&invite)).await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn expired_invite_is_rejected() {
+    let app = TestApp::new().await;
+    let poll_id = create_invite_only_poll(&app).await;
+    let invite = create_invite(&app, &poll_id, 5).await;
+
+    app.db_pool
+        .set_invite_times(&invite, Utc::now() - Duration::minutes(1), None)
+        .await;
+
+    let response = join_as_guest(&app, &poll_id, Some(&invite)).await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn revoked_invite_is_rejected() {
+    let app = TestApp::new().await;
+    let poll_id = create_invite_only_poll(&app).await;
+    let invite = create_invite(&app, &poll_id, 5).await;
+
+    let invite_id = app
+        .storage
+        .invites(&poll_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|i| i.token == invite)
+        .unwrap()
+        .invite_id;
+
+    let response = app
+        .post_form(
+            &app.api_client,
+            &format!("/poll/{poll_id}/invites/{invite_id}/revoke"),
+            &serde_json::json!({}),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let text = app
+        .api_client
+        .get(app.endpoint(&format!("/poll/{poll_id}/invites")))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(text.contains("revoked"));
+
+    let response = join_as_guest(&app, &poll_id, Some(&invite)).await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn only_organizers_can_manage_invites() {
+    let app = TestApp::new().await;
+    let poll_id = create_invite_only_poll(&app).await;
+
+    // Visitors need to join first
+    let stranger = new_api_client();
+    let response = stranger
+        .get(app.endpoint(&format!("/poll/{poll_id}/invites")))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Participants aren't allowed to
+    let invite = create_invite(&app, &poll_id, 1).await;
+    let participant = new_api_client();
+    app.post_form(
+        &participant,
+        &format!("/poll/{poll_id}/join"),
+        &serde_json::json!({ "username": "participant", "invite": invite }),
+    )
+    .await;
+    let response = app
+        .post_form(
+            &participant,
+            &format!("/poll/{poll_id}/invites"),
+            &serde_json::json!({ "expires_in_hours": 24, "max_uses": 1 }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn polls_are_open_to_everyone_by_default() {
+    let app = TestApp::new().await;
+    let poll_id = app
+        .create_poll(&serde_json::json!({ "username": "creator", "prompt": "Open question?" }))
+        .await;
+
+    let response = join_as_guest(&app, &poll_id, None).await;
+
+    assert_eq!(response.status().as_u16(), 303);
+}
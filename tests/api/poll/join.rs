@@ -1,7 +1,6 @@
-use fake::{faker::name::en::FirstName, Fake};
 use uuid::Uuid;
 
-use crate::helpers::{location_string, TestApp};
+use crate::helpers::{fake_username, location_string, new_api_client, TestApp};
 
 #[tokio::test]
 async fn post_join_should_return_200_ok() {
@@ -9,7 +8,7 @@ async fn post_join_should_return_200_ok() {
 
     let poll_id = app.post_create_poll("Test question?", "TestUser").await;
 
-    let username: String = FirstName().fake();
+    let username = fake_username();
     let body = serde_json::json!({ "username": &username });
 
     let response = app.join_poll(&poll_id, &body).await;
@@ -22,7 +21,7 @@ async fn post_join_should_return_200_ok() {
 async fn join_should_return_404_if_poll_id_does_not_exist() {
     let app = TestApp::new().await;
 
-    let username: String = FirstName().fake();
+    let username = fake_username();
     let body = serde_json::json!({ "username": &username });
 
     let poll_id = Uuid::new_v4();
@@ -36,7 +35,7 @@ async fn joined_user_should_appear_in_the_poll_page() {
     let app = TestApp::new().await;
 
     let poll_id = app.post_create_poll("Test Question", "testuser").await;
-    let username: String = FirstName().fake();
+    let username = fake_username();
     let body = serde_json::json!({ "username": &username });
 
     // Join poll
@@ -61,7 +60,7 @@ async fn joined_user_should_be_rejected_if_they_try_to_join_again() {
     let app = TestApp::new().await;
 
     let poll_id = app.post_create_poll("Test Question", "testuser").await;
-    let username: String = FirstName().fake();
+    let username = fake_username();
     let body = serde_json::json!({ "username": &username });
 
     // Join poll
@@ -89,3 +88,130 @@ async fn joined_user_should_be_rejected_if_they_try_to_join_again() {
     // Assert greeting is displayed
     assert!(text.contains(&format!("<p>Logged in as {username}</p>")))
 }
+
+#[tokio::test]
+async fn taken_username_is_reported_with_alternatives() {
+    let app = TestApp::new().await;
+
+    let poll_id = app.post_create_poll("Test Question", "testuser").await;
+    let body = serde_json::json!({ "username": "testuser" });
+
+    let response = app.join_poll(&poll_id, &body).await;
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(location_string(response), format!("/poll/{poll_id}"));
+
+    let text = app
+        .get_poll_page(&poll_id.to_string())
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(text.contains("The name testuser is already taken in this poll."));
+    assert!(text.contains("How about: testuser2, testuser_2, testuser3?"));
+    // The user wasn't logged in
+    assert!(!text.contains("Logged in as"));
+}
+
+#[tokio::test]
+async fn alternatives_skip_names_that_are_also_taken() {
+    let app = TestApp::new().await;
+
+    let poll_id = app.post_create_poll("Test Question", "testuser").await;
+    let response = app
+        .post_form(
+            &new_api_client(),
+            &format!("/poll/{poll_id}/join"),
+            &serde_json::json!({ "username": "testuser2" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    app.join_poll(&poll_id, &serde_json::json!({ "username": "testuser" }))
+        .await;
+
+    let text = app
+        .get_poll_page(&poll_id.to_string())
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(text.contains("How about: testuser_2, testuser3, testuser_3?"));
+}
+
+#[tokio::test]
+async fn invalid_username_is_reported() {
+    let app = TestApp::new().await;
+
+    let poll_id = app.post_create_poll("Test Question", "testuser").await;
+    let test_cases = vec![
+        ("ab", "username: length is invalid."),
+        (
+            "<b>user</b>",
+            "username: first character must be a letter or a number.",
+        ),
+        (
+            "user name",
+            "username: only letters, numbers and underscores are allowed.",
+        ),
+    ];
+
+    for (username, message) in test_cases {
+        let response = app
+            .join_poll(&poll_id, &serde_json::json!({ "username": username }))
+            .await;
+        assert_eq!(response.status().as_u16(), 303);
+
+        let text = app
+            .get_poll_page(&poll_id.to_string())
+            .await
+            .text()
+            .await
+            .unwrap();
+        assert!(
+            text.contains(&format!("<p><i>{message}</i></p>")),
+            "missing message for username: {username}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn unicode_username_can_join() {
+    let app = TestApp::new().await;
+
+    let poll_id = app.post_create_poll("Test Question", "testuser").await;
+    // "Zoë" with a combining diaeresis is stored in its composed form
+    let response = app
+        .join_poll(&poll_id, &serde_json::json!({ "username": "Zoe\u{308}" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let text = app
+        .get_poll_page(&poll_id.to_string())
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(text.contains("Logged in as Zo\u{eb}"));
+}
+
+#[tokio::test]
+async fn lookalike_username_is_rejected() {
+    let app = TestApp::new().await;
+
+    let poll_id = app.post_create_poll("Test Question", "alice").await;
+    // "аlice" starting with a Cyrillic а
+    let response = app
+        .join_poll(&poll_id, &serde_json::json!({ "username": "\u{430}lice" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let text = app
+        .get_poll_page(&poll_id.to_string())
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(text
+        .contains("The name \u{430}lice looks too much like alice, who is already in this poll."));
+    assert!(!text.contains("Logged in as"));
+}
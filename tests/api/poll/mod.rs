@@ -1,4 +1,9 @@
 mod create;
+mod export;
 mod get;
+mod invite;
 mod join;
+mod passphrase;
+mod report;
+mod roles;
 mod suggest;
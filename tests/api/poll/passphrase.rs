@@ -0,0 +1,128 @@
+use uuid::Uuid;
+
+use crate::helpers::{location_string, new_api_client, TestApp};
+
+async fn create_protected_poll(app: &TestApp) -> Uuid {
+    app.create_poll(&serde_json::json!({
+        "username": "creator",
+        "prompt": "Salary bands?",
+        "passphrase": "open sesame",
+    }))
+    .await
+}
+
+async fn unlock(
+    app: &TestApp,
+    client: &reqwest::Client,
+    poll_id: &Uuid,
+    passphrase: &str,
+) -> reqwest::Response {
+    app.post_form(
+        client,
+        &format!("/poll/{poll_id}/unlock"),
+        &serde_json::json!({ "passphrase": passphrase }),
+    )
+    .await
+}
+
+async fn get_page(app: &TestApp, client: &reqwest::Client, poll_id: &Uuid) -> reqwest::Response {
+    client
+        .get(app.endpoint(&format!("/poll/{poll_id}")))
+        .send()
+        .await
+        .expect("failed to execute request")
+}
+
+#[tokio::test]
+async fn protected_poll_asks_visitors_for_the_passphrase() {
+    let app = TestApp::new().await;
+    let poll_id = create_protected_poll(&app).await;
+
+    let response = get_page(&app, &new_api_client(), &poll_id).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    let text = response.text().await.unwrap();
+    assert!(text.contains(r#"name="passphrase""#));
+    assert!(!text.contains("Salary bands?"));
+}
+
+#[tokio::test]
+async fn creator_can_see_their_protected_poll() {
+    let app = TestApp::new().await;
+    let poll_id = create_protected_poll(&app).await;
+
+    let response = app.get_poll_page(&poll_id.to_string()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Salary bands?"));
+}
+
+#[tokio::test]
+async fn correct_passphrase_unlocks_the_poll_for_the_session() {
+    let app = TestApp::new().await;
+    let poll_id = create_protected_poll(&app).await;
+    let client = new_api_client();
+
+    let response = unlock(&app, &client, &poll_id, "open sesame").await;
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(location_string(response), format!("/poll/{poll_id}"));
+
+    let response = get_page(&app, &client, &poll_id).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Salary bands?"));
+
+    // Other sessions are still locked out
+    let response = get_page(&app, &new_api_client(), &poll_id).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn incorrect_passphrase_is_reported() {
+    let app = TestApp::new().await;
+    let poll_id = create_protected_poll(&app).await;
+    let client = new_api_client();
+
+    let response = unlock(&app, &client, &poll_id, "wrong guess").await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let response = get_page(&app, &client, &poll_id).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("<p><i>Incorrect passphrase</i></p>"));
+}
+
+#[tokio::test]
+async fn locked_poll_cannot_be_joined() {
+    let app = TestApp::new().await;
+    let poll_id = create_protected_poll(&app).await;
+
+    let response = app
+        .post_form(
+            &new_api_client(),
+            &format!("/poll/{poll_id}/join"),
+            &serde_json::json!({ "username": "intruder" }),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn repeated_incorrect_attempts_are_rate_limited() {
+    let app = TestApp::new().await;
+    let poll_id = create_protected_poll(&app).await;
+    let client = new_api_client();
+
+    for _ in 0..5 {
+        let response = unlock(&app, &client, &poll_id, "wrong guess").await;
+        assert_eq!(response.status().as_u16(), 303);
+    }
+
+    // Even the right passphrase is refused until the window has passed
+    let response = unlock(&app, &client, &poll_id, "open sesame").await;
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().get("retry-after").is_some());
+}
@@ -0,0 +1,191 @@
+use apoll::domain::PollRole;
+use uuid::Uuid;
+
+use crate::helpers::{new_api_client, TestApp};
+
+async fn spawn_app() -> TestApp {
+    TestApp::with_configuration(|c| c.moderation.report_threshold = 2).await
+}
+
+/// A poll owned by `api_client` with a few participants, the first of which
+/// made a suggestion.
+async fn poll_with_participants(app: &TestApp, count: usize) -> (Uuid, Vec<reqwest::Client>, Uuid) {
+    let poll_id = app
+        .create_poll(&serde_json::json!({ "username": "owner", "prompt": "Lunch?" }))
+        .await;
+    let mut participants = Vec::new();
+    for i in 0..count {
+        let participant = new_api_client();
+        let response = app
+            .post_form(
+                &participant,
+                &format!("/poll/{poll_id}/join"),
+                &serde_json::json!({ "username": format!("participant{i}") }),
+            )
+            .await;
+        assert_eq!(response.status().as_u16(), 303);
+        participants.push(participant);
+    }
+
+    app.post_form(
+        &participants[0],
+        &format!("/poll/{poll_id}/suggest"),
+        &serde_json::json!({ "suggestion": "Pineapple pizza" }),
+    )
+    .await;
+    let suggestion_id = app.storage.suggestion_details(&poll_id).await.unwrap()[0].suggestion_id;
+
+    (poll_id, participants, suggestion_id)
+}
+
+async fn report(
+    app: &TestApp,
+    client: &reqwest::Client,
+    poll_id: &Uuid,
+    target: &str,
+) -> reqwest::Response {
+    app.post_form(
+        client,
+        &format!("/poll/{poll_id}/report"),
+        &serde_json::json!({ "target": target, "reason": "offensive" }),
+    )
+    .await
+}
+
+async fn get_page(app: &TestApp, client: &reqwest::Client, path: &str) -> String {
+    client
+        .get(app.endpoint(path))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn suggestion_is_hidden_once_reported_by_enough_members() {
+    let app = spawn_app().await;
+    let (poll_id, participants, suggestion_id) = poll_with_participants(&app, 3).await;
+    let target = format!("suggestion:{suggestion_id}");
+    let poll_uri = format!("/poll/{poll_id}");
+
+    let response = report(&app, &participants[1], &poll_id, &target).await;
+    assert_eq!(response.status().as_u16(), 303);
+    let html = get_page(&app, &participants[1], &poll_uri).await;
+    assert!(html.contains("Thanks, the organizers will review your report."));
+    assert!(html.contains("<li>Pineapple pizza</li>"));
+
+    // Reporting twice only counts once
+    report(&app, &participants[1], &poll_id, &target).await;
+    let html = get_page(&app, &participants[1], &poll_uri).await;
+    assert!(html.contains("<li>Pineapple pizza</li>"));
+
+    report(&app, &participants[2], &poll_id, &target).await;
+    let html = get_page(&app, &participants[1], &poll_uri).await;
+    assert!(!html.contains("<li>Pineapple pizza</li>"));
+
+    let html = get_page(&app, &app.api_client, &format!("{poll_uri}/moderation")).await;
+    assert!(html.contains("Pineapple pizza"));
+    assert!(html.contains("Hidden, it was reported by 2 members"));
+    assert!(html.contains("2: Offensive"));
+}
+
+#[tokio::test]
+async fn reports_below_the_threshold_can_be_dismissed() {
+    let app = spawn_app().await;
+    let (poll_id, participants, suggestion_id) = poll_with_participants(&app, 2).await;
+
+    report(
+        &app,
+        &participants[1],
+        &poll_id,
+        &format!("suggestion:{suggestion_id}"),
+    )
+    .await;
+    let moderation_uri = format!("/poll/{poll_id}/moderation");
+    let html = get_page(&app, &app.api_client, &moderation_uri).await;
+    assert!(html.contains("Pineapple pizza"));
+    assert!(html.contains("1: Offensive"));
+
+    let response = app
+        .post_form(
+            &app.api_client,
+            &format!("{moderation_uri}/suggestions/{suggestion_id}/approve"),
+            &serde_json::json!({}),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let html = get_page(&app, &app.api_client, &moderation_uri).await;
+    assert!(html.contains("Suggestion approved"));
+    assert!(!html.contains("Pineapple pizza"));
+    let html = get_page(&app, &participants[1], &format!("/poll/{poll_id}")).await;
+    assert!(html.contains("<li>Pineapple pizza</li>"));
+}
+
+#[tokio::test]
+async fn reported_member_can_be_removed() {
+    let app = spawn_app().await;
+    let (poll_id, participants, _) = poll_with_participants(&app, 2).await;
+    let user_id = app.user_id_of(&poll_id, "participant0").await;
+
+    report(
+        &app,
+        &participants[1],
+        &poll_id,
+        &format!("member:{user_id}"),
+    )
+    .await;
+    let response = app
+        .post_form(
+            &app.api_client,
+            &format!("/poll/{poll_id}/moderation/members/{user_id}/remove"),
+            &serde_json::json!({}),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let html = get_page(&app, &participants[1], &format!("/poll/{poll_id}")).await;
+    assert!(!html.contains("<li>participant0</li>"));
+}
+
+#[tokio::test]
+async fn members_cannot_report_themselves() {
+    let app = spawn_app().await;
+    let (poll_id, participants, suggestion_id) = poll_with_participants(&app, 1).await;
+
+    report(
+        &app,
+        &participants[0],
+        &poll_id,
+        &format!("suggestion:{suggestion_id}"),
+    )
+    .await;
+    let html = get_page(&app, &participants[0], &format!("/poll/{poll_id}")).await;
+    assert!(html.contains("You can't report yourself"));
+
+    assert_eq!(app.db_pool.count_rows("reports").await, 0);
+}
+
+#[tokio::test]
+async fn owner_is_never_hidden_by_reports() {
+    let app = spawn_app().await;
+    let (poll_id, participants, _) = poll_with_participants(&app, 2).await;
+    let owner_id = app
+        .storage
+        .members(&poll_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|m| m.role == PollRole::Owner)
+        .unwrap()
+        .user_id;
+
+    for participant in &participants {
+        report(&app, participant, &poll_id, &format!("member:{owner_id}")).await;
+    }
+
+    let html = get_page(&app, &participants[0], &format!("/poll/{poll_id}")).await;
+    assert!(html.contains("<li>owner (owner)</li>"));
+}
@@ -0,0 +1,242 @@
+use apoll::domain::PollRole;
+use uuid::Uuid;
+
+use crate::helpers::{new_api_client, TestApp};
+
+async fn join(
+    app: &TestApp,
+    client: &reqwest::Client,
+    poll_id: &Uuid,
+    body: serde_json::Value,
+) -> reqwest::Response {
+    app.post_form(client, &format!("/poll/{poll_id}/join"), &body)
+        .await
+}
+
+async fn user_id_of(app: &TestApp, poll_id: &Uuid, username: &str) -> Uuid {
+    app.user_id_of(poll_id, username).await
+}
+
+async fn create_poll(app: &TestApp) -> Uuid {
+    app.create_poll(&serde_json::json!({ "username": "owner", "prompt": "Team retro?" }))
+        .await
+}
+
+#[tokio::test]
+async fn poll_creator_is_its_owner() {
+    let app = TestApp::new().await;
+    let poll_id = create_poll(&app).await;
+
+    let text = app
+        .get_poll_page(&poll_id.to_string())
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert!(text.contains("<li>owner (owner)</li>"));
+    assert!(text.contains(&format!("/poll/{poll_id}/members")));
+}
+
+#[tokio::test]
+async fn observers_can_see_the_poll_but_not_suggest() {
+    let app = TestApp::new().await;
+    let poll_id = create_poll(&app).await;
+    let observer = new_api_client();
+
+    let response = join(
+        &app,
+        &observer,
+        &poll_id,
+        serde_json::json!({ "username": "watcher", "observer": "on" }),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let text = observer
+        .get(app.endpoint(&format!("/poll/{poll_id}")))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(text.contains("<li>watcher (observer)</li>"));
+    assert!(text.contains("You are observing this poll."));
+
+    let response = app
+        .post_form(
+            &observer,
+            &format!("/poll/{poll_id}/suggest"),
+            &serde_json::json!({ "suggestion": "sneaky" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn participants_cannot_manage_members() {
+    let app = TestApp::new().await;
+    let poll_id = create_poll(&app).await;
+    let participant = new_api_client();
+    join(
+        &app,
+        &participant,
+        &poll_id,
+        serde_json::json!({ "username": "participant" }),
+    )
+    .await;
+
+    let response = participant
+        .get(app.endpoint(&format!("/poll/{poll_id}/members")))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+
+    let owner_id = user_id_of(&app, &poll_id, "owner").await;
+    let response = app
+        .post_form(
+            &participant,
+            &format!("/poll/{poll_id}/members/{owner_id}/transfer"),
+            &serde_json::json!({}),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn owner_can_promote_a_co_organizer() {
+    let app = TestApp::new().await;
+    let poll_id = create_poll(&app).await;
+    let helper = new_api_client();
+    join(
+        &app,
+        &helper,
+        &poll_id,
+        serde_json::json!({ "username": "helper" }),
+    )
+    .await;
+    let helper_id = user_id_of(&app, &poll_id, "helper").await;
+
+    let response = app
+        .post_form(
+            &app.api_client,
+            &format!("/poll/{poll_id}/members/{helper_id}/role"),
+            &serde_json::json!({ "role": "co_organizer" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    // Co-organizers can manage invites and see members
+    let response = helper
+        .get(app.endpoint(&format!("/poll/{poll_id}/members")))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("co-organizer"));
+
+    let response = app
+        .post_form(
+            &helper,
+            &format!("/poll/{poll_id}/invites"),
+            &serde_json::json!({ "expires_in_hours": 24, "max_uses": 1 }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    // But they can't change roles themselves
+    let owner_id = user_id_of(&app, &poll_id, "owner").await;
+    let response = app
+        .post_form(
+            &helper,
+            &format!("/poll/{poll_id}/members/{owner_id}/role"),
+            &serde_json::json!({ "role": "observer" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn owner_role_cannot_be_granted_directly() {
+    let app = TestApp::new().await;
+    let poll_id = create_poll(&app).await;
+    join(
+        &app,
+        &new_api_client(),
+        &poll_id,
+        serde_json::json!({ "username": "helper" }),
+    )
+    .await;
+    let helper_id = user_id_of(&app, &poll_id, "helper").await;
+
+    app.post_form(
+        &app.api_client,
+        &format!("/poll/{poll_id}/members/{helper_id}/role"),
+        &serde_json::json!({ "role": "owner" }),
+    )
+    .await;
+
+    let helper = app
+        .storage
+        .find_member(&poll_id, &helper_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(helper.role, PollRole::Participant);
+}
+
+#[tokio::test]
+async fn owner_can_transfer_ownership() {
+    let app = TestApp::new().await;
+    let poll_id = create_poll(&app).await;
+    let successor = new_api_client();
+    join(
+        &app,
+        &successor,
+        &poll_id,
+        serde_json::json!({ "username": "successor" }),
+    )
+    .await;
+    let successor_id = user_id_of(&app, &poll_id, "successor").await;
+    let owner_id = user_id_of(&app, &poll_id, "owner").await;
+
+    let response = app
+        .post_form(
+            &app.api_client,
+            &format!("/poll/{poll_id}/members/{successor_id}/transfer"),
+            &serde_json::json!({}),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let text = app
+        .get_poll_page(&poll_id.to_string())
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(text.contains("<li>successor (owner)</li>"));
+    assert!(text.contains("<li>owner (co-organizer)</li>"));
+
+    // The previous owner lost their owner privileges
+    let response = app
+        .post_form(
+            &app.api_client,
+            &format!("/poll/{poll_id}/members/{successor_id}/role"),
+            &serde_json::json!({ "role": "participant" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // While the new owner gained them
+    let response = app
+        .post_form(
+            &successor,
+            &format!("/poll/{poll_id}/members/{owner_id}/role"),
+            &serde_json::json!({ "role": "participant" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+}
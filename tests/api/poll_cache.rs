@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use apoll::configuration::DatabaseBackend;
+
+use crate::helpers::{test_settings, TestApp};
+
+async fn poll_page_status(app: &TestApp, poll_id: &uuid::Uuid) -> u16 {
+    app.get_poll_page(&poll_id.to_string())
+        .await
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn cached_polls_are_looked_up_again_after_the_ttl() {
+    let app = TestApp::with_configuration(|c| c.poll_cache.ttl_seconds = 1).await;
+    let poll_id = app.post_create_poll("Lunch?", "owner").await;
+    assert_eq!(poll_page_status(&app, &poll_id).await, 200);
+
+    // Deleted behind the application's back
+    app.storage.delete_poll(&poll_id).await.unwrap();
+    if app.database.backend != DatabaseBackend::Postgres {
+        // Nothing tells the other backends
+        assert_eq!(poll_page_status(&app, &poll_id).await, 200);
+    }
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(poll_page_status(&app, &poll_id).await, 404);
+}
+
+#[tokio::test]
+async fn polls_changed_by_another_instance_are_forgotten() {
+    // Instances only tell each other through Postgres
+    if test_settings().database.backend != DatabaseBackend::Postgres {
+        return;
+    }
+    let app = TestApp::with_configuration(|c| c.poll_cache.ttl_seconds = 3600).await;
+    let poll_id = app.post_create_poll("Lunch?", "owner").await;
+    assert_eq!(poll_page_status(&app, &poll_id).await, 200);
+
+    app.storage.delete_poll(&poll_id).await.unwrap();
+
+    let mut status = 200;
+    for _ in 0..50 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        status = poll_page_status(&app, &poll_id).await;
+        if status == 404 {
+            break;
+        }
+    }
+    assert_eq!(
+        status, 404,
+        "the deleted poll was still served from the cache"
+    );
+}
@@ -0,0 +1,132 @@
+use crate::helpers::{extract_input_value, location_string, new_api_client, solve, TestApp};
+
+async fn spawn_app(required_for_new_poll: bool) -> TestApp {
+    TestApp::with_configuration(|c| {
+        c.proof_of_work.difficulty = 4;
+        c.proof_of_work.required_for_new_poll = required_for_new_poll;
+    })
+    .await
+}
+
+async fn solved_fields(app: &TestApp, path: &str) -> serde_json::Value {
+    let html = app
+        .api_client
+        .get(app.endpoint(path))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let challenge = extract_input_value(&html, "pow_challenge").expect("no challenge in the form");
+    serde_json::json!({
+        "pow_solution": solve(&challenge).to_string(),
+        "pow_challenge": challenge,
+    })
+}
+
+#[tokio::test]
+async fn new_poll_requires_a_solved_challenge_when_configured() {
+    let app = spawn_app(true).await;
+    let mut body = serde_json::json!({
+        "username": "username",
+        "prompt": "Is this a good prompt?",
+    });
+
+    let response = app.post_form(&app.api_client, "/new", &body).await;
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(location_string(response), "/");
+    let html = app
+        .api_client
+        .get(app.endpoint("/"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("The anti-spam check didn't run"));
+
+    let solved = solved_fields(&app, "/").await;
+    body["pow_challenge"] = solved["pow_challenge"].clone();
+    body["pow_solution"] = solved["pow_solution"].clone();
+    let response = app.post_form(&app.api_client, "/new", &body).await;
+    assert_eq!(response.status().as_u16(), 303);
+    assert!(location_string(response).starts_with("/poll/"));
+}
+
+#[tokio::test]
+async fn solved_challenge_can_only_be_used_once() {
+    let app = spawn_app(true).await;
+    let mut body = solved_fields(&app, "/").await;
+    body["username"] = "username".into();
+    body["prompt"] = "Is this a good prompt?".into();
+
+    let response = app.post_form(&app.api_client, "/new", &body).await;
+    assert!(location_string(response).starts_with("/poll/"));
+    let response = app.post_form(&app.api_client, "/new", &body).await;
+    assert_eq!(location_string(response), "/");
+}
+
+#[tokio::test]
+async fn new_poll_form_has_no_challenge_by_default() {
+    let app = spawn_app(false).await;
+
+    let html = app
+        .api_client
+        .get(app.endpoint("/"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(!html.contains("pow_challenge"));
+}
+
+#[tokio::test]
+async fn polls_can_require_a_solved_challenge_to_join() {
+    let app = spawn_app(false).await;
+    let poll_id = app
+        .create_poll(&serde_json::json!({
+            "username": "owner",
+            "prompt": "Question?",
+            "require_proof_of_work": "on",
+        }))
+        .await;
+    let visitor = new_api_client();
+    let join_path = format!("/poll/{poll_id}/join");
+    let poll_page = |client: &reqwest::Client| {
+        let url = app.endpoint(&format!("/poll/{poll_id}"));
+        let request = client.get(url).send();
+        async move { request.await.unwrap().text().await.unwrap() }
+    };
+
+    let body = serde_json::json!({ "username": "visitor" });
+    let response = app.post_form(&visitor, &join_path, &body).await;
+    assert_eq!(response.status().as_u16(), 303);
+    let html = poll_page(&visitor).await;
+    assert!(html.contains("The anti-spam check didn't run"));
+    assert!(!html.contains("Logged in as"));
+
+    let challenge = extract_input_value(&html, "pow_challenge").expect("no challenge in the form");
+    let body = serde_json::json!({
+        "username": "visitor",
+        "pow_solution": solve(&challenge).to_string(),
+        "pow_challenge": challenge,
+    });
+    app.post_form(&visitor, &join_path, &body).await;
+    assert!(poll_page(&visitor).await.contains("Logged in as visitor"));
+
+    // Polls that don't ask for it can still be joined without
+    let other_poll_id = app.post_create_poll("Question?", "owner").await;
+    let response = app
+        .post_form(
+            &new_api_client(),
+            &format!("/poll/{other_poll_id}/join"),
+            &serde_json::json!({ "username": "visitor" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(location_string(response), format!("/poll/{other_poll_id}"));
+}
@@ -0,0 +1,121 @@
+use apoll::configuration::{ActionLimits, BucketSettings};
+
+use crate::helpers::{new_api_client, TestApp};
+
+const ONE_PER_MINUTE: BucketSettings = BucketSettings {
+    capacity: 1,
+    refill_per_minute: 1,
+};
+
+fn poll_body() -> serde_json::Value {
+    serde_json::json!({
+        "username": "username",
+        "prompt": "Is this a good prompt?",
+    })
+}
+
+#[tokio::test]
+async fn poll_creation_is_limited_per_session() {
+    let app = TestApp::with_configuration(|c| {
+        c.rate_limit.create_poll = ActionLimits {
+            per_session: Some(ONE_PER_MINUTE),
+            ..Default::default()
+        }
+    })
+    .await;
+
+    let response = app.post_form(&app.api_client, "/new", &poll_body()).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let response = app.post_form(&app.api_client, "/new", &poll_body()).await;
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(response.headers()["Retry-After"], "60");
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("Too many requests, try again in 60 seconds"));
+
+    // Another session still has its own bucket
+    let response = app.post_form(&new_api_client(), "/new", &poll_body()).await;
+    assert_eq!(response.status().as_u16(), 303);
+}
+
+#[tokio::test]
+async fn poll_creation_is_limited_per_ip() {
+    let app = TestApp::with_configuration(|c| {
+        c.rate_limit.create_poll = ActionLimits {
+            per_ip: Some(ONE_PER_MINUTE),
+            ..Default::default()
+        }
+    })
+    .await;
+
+    let response = app.post_form(&app.api_client, "/new", &poll_body()).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    // A new session doesn't help when it comes from the same address
+    let response = app.post_form(&new_api_client(), "/new", &poll_body()).await;
+    assert_eq!(response.status().as_u16(), 429);
+}
+
+#[tokio::test]
+async fn joins_are_limited_per_poll() {
+    let app = TestApp::with_configuration(|c| {
+        c.rate_limit.join_poll = ActionLimits {
+            per_poll: Some(ONE_PER_MINUTE),
+            ..Default::default()
+        }
+    })
+    .await;
+
+    let poll_id = app.post_create_poll("Test question?", "testuser").await;
+    let other_poll_id = app.post_create_poll("Other question?", "testuser").await;
+    let join = |poll_id, username: &'static str| {
+        let app = &app;
+        async move {
+            app.post_form(
+                &new_api_client(),
+                &format!("/poll/{poll_id}/join"),
+                &serde_json::json!({ "username": username }),
+            )
+            .await
+            .status()
+            .as_u16()
+        }
+    };
+
+    assert_eq!(join(poll_id, "first").await, 303);
+    assert_eq!(join(poll_id, "second").await, 429);
+    assert_eq!(join(other_poll_id, "second").await, 303);
+}
+
+#[tokio::test]
+async fn suggestions_are_limited_per_session() {
+    let app = TestApp::with_configuration(|c| {
+        c.rate_limit.suggest = ActionLimits {
+            per_session: Some(ONE_PER_MINUTE),
+            ..Default::default()
+        }
+    })
+    .await;
+
+    let poll_id = app
+        .create_poll(&serde_json::json!({ "username": "owner", "prompt": "Question?" }))
+        .await;
+    let suggestion = |text: &str| serde_json::json!({ "suggestion": text });
+
+    let response = app.post_suggestion(&poll_id, &suggestion("first")).await;
+    assert_eq!(response.status().as_u16(), 303);
+    let response = app.post_suggestion(&poll_id, &suggestion("second")).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    let text = app
+        .get_poll_page(&poll_id.to_string())
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(text.contains("first"));
+    assert!(!text.contains("second"));
+}
@@ -0,0 +1,73 @@
+use apoll::configuration::{DatabaseBackend, SameSiteSetting, SessionStoreBackend, Settings};
+
+use crate::helpers::{test_settings, TestApp};
+
+/// Joining a poll and then suggesting only works if the session was kept.
+async fn assert_session_is_kept(app: &TestApp) {
+    let poll_id = app.post_create_poll("prompt", "creator").await;
+    let response = app
+        .join_poll(&poll_id, &serde_json::json!({"username": "newuser"}))
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let suggestion = "kept in the session";
+    let response = app
+        .post_suggestion(&poll_id, &serde_json::json!({ "suggestion": suggestion }))
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let text = app
+        .get_poll_page(&poll_id.to_string())
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(text.contains(suggestion));
+}
+
+#[tokio::test]
+async fn cookie_sessions_keep_members_logged_in() {
+    let app = TestApp::with_configuration(|c| c.session.store = SessionStoreBackend::Cookie).await;
+
+    assert_session_is_kept(&app).await;
+}
+
+#[tokio::test]
+async fn postgres_sessions_keep_members_logged_in() {
+    // The table only exists in the Postgres database
+    if test_settings().database.backend != DatabaseBackend::Postgres {
+        return;
+    }
+    let app =
+        TestApp::with_configuration(|c| c.session.store = SessionStoreBackend::Postgres).await;
+
+    assert_session_is_kept(&app).await;
+    assert_eq!(app.db_pool.count_rows("sessions").await, 1);
+}
+
+#[tokio::test]
+async fn the_session_cookie_follows_the_configuration() {
+    let configure = |c: &mut Settings| {
+        c.session.cookie_name = "apoll_session".into();
+        c.session.same_site = SameSiteSetting::Strict;
+        c.session.secure = false;
+    };
+    let app = TestApp::with_configuration(configure).await;
+
+    let response = app
+        .api_client
+        .get(app.endpoint("/"))
+        .send()
+        .await
+        .expect("failed to execute request");
+
+    let cookie = response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find(|value| value.starts_with("apoll_session="))
+        .expect("no session cookie was set");
+    assert!(cookie.contains("SameSite=Strict"), "{cookie}");
+    assert!(!cookie.contains("Secure"), "{cookie}");
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use apoll::configuration::DatabaseBackend;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::helpers::TestApp;
+
+async fn get_status(app: &TestApp, path: &str) -> Result<u16, reqwest::Error> {
+    app.api_client
+        .get(app.endpoint(path))
+        .send()
+        .await
+        .map(|response| response.status().as_u16())
+}
+
+#[tokio::test]
+async fn shutting_down_fails_readiness_before_closing_everything() {
+    let mut app = TestApp::with_configuration(|c| c.shutdown.readiness_delay_seconds = 1).await;
+
+    app.shutdown.shut_down();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Still serving while load balancers notice
+    assert_eq!(get_status(&app, "/health/ready").await.unwrap(), 503);
+    assert_eq!(get_status(&app, "/health/live").await.unwrap(), 200);
+
+    let result = tokio::time::timeout(Duration::from_secs(10), &mut app.server)
+        .await
+        .expect("the application didn't shut down")
+        .unwrap();
+    assert!(result.is_ok());
+    assert!(get_status(&app, "/health/live").await.is_err());
+    if app.database.backend != DatabaseBackend::Memory {
+        assert!(app.storage.ping().await.is_err());
+    }
+}
+
+#[tokio::test]
+async fn requests_in_flight_are_answered_before_shutting_down() {
+    let app = TestApp::new().await;
+    let address = app.address.trim_start_matches("http://");
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    // The request is in flight as long as its body is still coming
+    stream
+        .write_all(
+            b"POST /new HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+              Content-Type: application/x-www-form-urlencoded\r\nContent-Length: 12\r\n\r\n\
+              prompt=",
+        )
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    app.shutdown.shut_down();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    stream.write_all(b"Lunch").await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 "), "{response}");
+}
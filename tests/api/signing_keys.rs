@@ -0,0 +1,81 @@
+use actix_web::cookie::{Cookie, CookieJar, Key};
+use apoll::configuration::{HmacSecret, SessionStoreBackend, Settings};
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::helpers::{extract_csrf_token, new_api_client, TestApp};
+
+const NEW_SECRET: &str = "a-brand-new-secret-key-that-replaced-the-previous-one-after-a-leak";
+
+/// Rotate the secret of the test settings, the cookies keep their sessions.
+fn rotate_secret(c: &mut Settings, accepted_until: Option<DateTime<Utc>>) -> Key {
+    c.session.store = SessionStoreBackend::Cookie;
+    let mut previous = c.application.hmac_secrets[0].clone();
+    previous.accepted_until = accepted_until;
+    let previous_key = Key::from(previous.secret.expose_secret().as_bytes());
+    c.application.hmac_secrets = vec![
+        HmacSecret {
+            secret: Secret::new(NEW_SECRET.into()),
+            accepted_until: None,
+        },
+        previous,
+    ];
+    previous_key
+}
+
+/// A cookie session holding `csrf_token`, encrypted with `key`.
+fn session_cookie(key: &Key, csrf_token: &str) -> String {
+    let state = serde_json::json!({ "csrf_token": serde_json::to_string(csrf_token).unwrap() });
+    let mut jar = CookieJar::new();
+    jar.private_mut(key)
+        .add(Cookie::new("id", state.to_string()));
+    jar.get("id").unwrap().encoded().to_string()
+}
+
+async fn get_form(app: &TestApp, cookie: &str) -> reqwest::Response {
+    new_api_client()
+        .get(app.endpoint("/"))
+        .header("Cookie", cookie)
+        .send()
+        .await
+        .expect("failed to execute request")
+}
+
+#[tokio::test]
+async fn sessions_signed_with_a_previous_secret_are_kept_and_resigned() {
+    let mut previous_key = None;
+    let app = TestApp::with_configuration(|c| previous_key = Some(rotate_secret(c, None))).await;
+    let cookie = session_cookie(&previous_key.unwrap(), "previous-token");
+
+    let response = get_form(&app, &cookie).await;
+
+    let set_cookie = response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find(|value| value.starts_with("id="))
+        .expect("the session cookie wasn't sent back")
+        .to_string();
+    let html = response.text().await.unwrap();
+    assert_eq!(extract_csrf_token(&html).as_deref(), Some("previous-token"));
+
+    let mut jar = CookieJar::new();
+    jar.add_original(Cookie::parse_encoded(set_cookie).unwrap().into_owned());
+    let new_key = Key::from(NEW_SECRET.as_bytes());
+    assert!(jar.private(&new_key).get("id").is_some());
+}
+
+#[tokio::test]
+async fn previous_secrets_are_rejected_after_their_grace_period() {
+    let mut previous_key = None;
+    let app = TestApp::with_configuration(|c| {
+        previous_key = Some(rotate_secret(c, Some(Utc::now())));
+    })
+    .await;
+    let cookie = session_cookie(&previous_key.unwrap(), "previous-token");
+
+    let html = get_form(&app, &cookie).await.text().await.unwrap();
+
+    assert_ne!(extract_csrf_token(&html).as_deref(), Some("previous-token"));
+}
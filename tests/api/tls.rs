@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use apoll::configuration::TlsSettings;
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+const FIXTURES: &str = "tests/fixtures/tls";
+
+/// A copy of the `first` certificate and key that the test can replace.
+fn certificate_files() -> (PathBuf, PathBuf) {
+    let directory = std::env::temp_dir().join(format!("apoll-tls-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let certificate = directory.join("cert.pem");
+    let key = directory.join("key.pem");
+    install("first", &certificate, &key);
+    (certificate, key)
+}
+
+fn install(fixture: &str, certificate: &Path, key: &Path) {
+    std::fs::copy(format!("{FIXTURES}/{fixture}.cert.pem"), certificate).unwrap();
+    std::fs::copy(format!("{FIXTURES}/{fixture}.key.pem"), key).unwrap();
+}
+
+async fn spawn_app(
+    (certificate, key): &(PathBuf, PathBuf),
+    configure: impl FnOnce(&mut TlsSettings),
+) -> TestApp {
+    let mut tls = TlsSettings {
+        certificate_path: certificate.to_string_lossy().into(),
+        key_path: key.to_string_lossy().into(),
+        reload_interval_seconds: 1,
+        redirect_http_port: None,
+        hsts_max_age_seconds: None,
+    };
+    configure(&mut tls);
+    TestApp::with_configuration(|c| c.application.tls = Some(tls)).await
+}
+
+/// A client that only trusts the certificate of `fixture`.
+fn client_trusting(fixture: &str) -> reqwest::Client {
+    let pem = std::fs::read(format!("{FIXTURES}/{fixture}.cert.pem")).unwrap();
+    reqwest::Client::builder()
+        .tls_built_in_root_certs(false)
+        .add_root_certificate(reqwest::Certificate::from_pem(&pem).unwrap())
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn the_application_is_served_over_https() {
+    let app = spawn_app(&certificate_files(), |_| {}).await;
+    assert!(app.address.starts_with("https://"));
+
+    let response = client_trusting("first")
+        .get(app.endpoint("/health_check"))
+        .send()
+        .await
+        .expect("failed to execute request");
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .headers()
+        .get("Strict-Transport-Security")
+        .is_none());
+}
+
+#[tokio::test]
+async fn a_replaced_certificate_is_served_without_a_restart() {
+    let files = certificate_files();
+    let app = spawn_app(&files, |_| {}).await;
+
+    install("second", &files.0, &files.1);
+
+    let mut reloaded = false;
+    for _ in 0..50 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let response = client_trusting("second")
+            .get(app.endpoint("/health_check"))
+            .send()
+            .await;
+        if response.is_ok() {
+            reloaded = true;
+            break;
+        }
+    }
+    assert!(reloaded, "the new certificate was never served");
+    assert!(client_trusting("first")
+        .get(app.endpoint("/health_check"))
+        .send()
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn plain_http_is_redirected_to_https() {
+    let app = spawn_app(&certificate_files(), |tls| tls.redirect_http_port = Some(0)).await;
+    let redirect_address = app.redirect_address.clone().unwrap();
+
+    let response = app
+        .api_client
+        .get(format!("{redirect_address}/poll/abc?sort=newest"))
+        .send()
+        .await
+        .expect("failed to execute request");
+
+    assert_eq!(response.status().as_u16(), 308);
+    assert_eq!(
+        response.headers().get("Location").unwrap(),
+        &app.endpoint("/poll/abc?sort=newest")
+    );
+}
+
+#[tokio::test]
+async fn hsts_is_sent_when_configured() {
+    let app = spawn_app(&certificate_files(), |tls| {
+        tls.hsts_max_age_seconds = Some(31536000)
+    })
+    .await;
+
+    let response = client_trusting("first")
+        .get(app.endpoint("/health_check"))
+        .send()
+        .await
+        .expect("failed to execute request");
+
+    assert_eq!(
+        response.headers().get("Strict-Transport-Security").unwrap(),
+        "max-age=31536000"
+    );
+}